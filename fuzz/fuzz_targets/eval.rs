@@ -0,0 +1,15 @@
+//! Feeds arbitrary UTF-8 to the engine's entry points, which must return
+//! rather than panic. Run with `cargo fuzz run eval` from the repository
+//! root.
+
+#![no_main]
+
+use calculator::{calculate_tuple, eval, format_expr, is_incomplete, Context};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: &str| {
+    let _ = eval(input);
+    let _ = calculate_tuple(input, &Context::default());
+    let _ = format_expr(input);
+    let _ = is_incomplete(input);
+});
@@ -0,0 +1,483 @@
+//! Expression syntax tree produced by the parser.
+//!
+//! The tree is public so that other crates can analyze or rewrite
+//! expressions without reimplementing the parser. Read-only analyses
+//! implement [`Visitor`]; rewrites implement [`Fold`].
+//!
+//! Nodes hold their children behind [`Arc`], so trees can share subtrees
+//! and cross threads: a parser [`Arena`](crate::parser::Arena) hands out
+//! the nodes it kept from the last input for the parts of a line an edit
+//! did not touch, and cloning a tree is cheap.
+
+use std::borrow::Borrow;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// A parsed calculator expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// A numeric literal.
+    Number(f64),
+    /// A reference to a named variable such as `x`.
+    Variable(Name),
+    /// A binary operation such as `5 + 3`.
+    Binary {
+        op: BinaryOp,
+        lhs: Arc<Expr>,
+        rhs: Arc<Expr>,
+    },
+    /// A negation such as `-(5 + 3)` or `-sqrt(4)`. It applies after powers,
+    /// so `-2^2` is `-(2^2)`; otherwise a minus written on a plain number,
+    /// as in `-5`, is part of the number instead.
+    Negate(Arc<Expr>),
+    /// A call to a built-in function such as `ulps(a, b)`.
+    Call { name: Name, args: Vec<Expr> },
+    /// A value with a unit, such as `20 °C`.
+    Quantity { value: Arc<Expr>, unit: String },
+    /// Several values passed as one function argument, such as the values
+    /// in `wavg(90, 80; 1, 2)`.
+    List(Vec<Expr>),
+}
+
+impl Expr {
+    /// Builds a binary node from its operator and operands.
+    pub fn binary(op: BinaryOp, lhs: Expr, rhs: Expr) -> Self {
+        Expr::Binary {
+            op,
+            lhs: Arc::new(lhs),
+            rhs: Arc::new(rhs),
+        }
+    }
+
+    /// Builds a negation node.
+    pub fn negate(operand: Expr) -> Self {
+        Expr::Negate(Arc::new(operand))
+    }
+
+    /// Builds a variable node.
+    pub fn variable(name: &str) -> Self {
+        Expr::Variable(Name::new(name))
+    }
+
+    /// Builds a function call node.
+    pub fn call(name: &str, args: Vec<Expr>) -> Self {
+        Expr::Call {
+            name: Name::new(name),
+            args,
+        }
+    }
+
+    /// Builds a quantity node from its value and unit name.
+    pub fn quantity(value: Expr, unit: &str) -> Self {
+        Expr::Quantity {
+            value: Arc::new(value),
+            unit: unit.to_string(),
+        }
+    }
+
+    /// Number of nodes in the tree, a rough measure of complexity.
+    pub fn node_count(&self) -> usize {
+        struct Counter(usize);
+
+        impl Visitor for Counter {
+            fn visit_expr(&mut self, expr: &Expr) {
+                self.0 += 1;
+                walk_expr(self, expr);
+            }
+        }
+
+        let mut counter = Counter(0);
+        counter.visit_expr(self);
+        counter.0
+    }
+
+    /// Names of the variables referenced by the expression, in order of
+    /// first appearance.
+    pub fn variables(&self) -> Vec<String> {
+        struct Names(Vec<String>);
+
+        impl Visitor for Names {
+            fn visit_variable(&mut self, name: &str) {
+                if !self.0.iter().any(|known| known == name) {
+                    self.0.push(name.to_string());
+                }
+            }
+        }
+
+        let mut names = Names(Vec::new());
+        names.visit_expr(self);
+        names.0
+    }
+
+    /// Length of the longest path from the root to a leaf.
+    pub fn depth(&self) -> usize {
+        match self {
+            Expr::Number(_) | Expr::Variable(_) => 1,
+            Expr::Binary { lhs, rhs, .. } => 1 + lhs.depth().max(rhs.depth()),
+            Expr::Negate(operand) => 1 + operand.depth(),
+            Expr::Call { args, .. } => 1 + args.iter().map(Expr::depth).max().unwrap_or(0),
+            Expr::Quantity { value, .. } => 1 + value.depth(),
+            Expr::List(items) => 1 + items.iter().map(Expr::depth).max().unwrap_or(0),
+        }
+    }
+}
+
+/// The name of a variable or function.
+///
+/// Cloning a name only counts a reference, so the names of a tree are
+/// shared between its copies, and a parser [`Arena`](crate::parser::Arena)
+/// hands out the names it read in the last input instead of copying them.
+/// A name reads as the `str` it holds.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Name(Arc<str>);
+
+impl Name {
+    /// The name with the text `name`.
+    pub fn new(name: &str) -> Self {
+        Name(Arc::from(name))
+    }
+
+    /// The text of the name.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for Name {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for Name {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for Name {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for Name {
+    fn from(name: &str) -> Self {
+        Name::new(name)
+    }
+}
+
+impl From<String> for Name {
+    fn from(name: String) -> Self {
+        Name::new(&name)
+    }
+}
+
+impl PartialEq<str> for Name {
+    fn eq(&self, other: &str) -> bool {
+        *self.0 == *other
+    }
+}
+
+impl PartialEq<&str> for Name {
+    fn eq(&self, other: &&str) -> bool {
+        *self.0 == **other
+    }
+}
+
+impl fmt::Debug for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
+
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// The arithmetic operators understood by the calculator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    /// Remainder of a floored division, with the sign of the divisor.
+    Mod,
+    /// Raising to a power; groups right to left.
+    Pow,
+}
+
+impl BinaryOp {
+    /// Maps an operator character to its operator, if it is one.
+    pub fn from_symbol(c: char) -> Option<Self> {
+        match c {
+            '+' => Some(BinaryOp::Add),
+            '-' => Some(BinaryOp::Sub),
+            '*' => Some(BinaryOp::Mul),
+            '/' => Some(BinaryOp::Div),
+            '%' => Some(BinaryOp::Mod),
+            '^' => Some(BinaryOp::Pow),
+            _ => None,
+        }
+    }
+
+    /// Binding strength; higher binds tighter.
+    pub fn precedence(self) -> u8 {
+        match self {
+            BinaryOp::Add | BinaryOp::Sub => 1,
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod => 2,
+            BinaryOp::Pow => 3,
+        }
+    }
+
+    /// Whether a chain of this operator groups from the right, as powers do:
+    /// `2^3^2` is `2^(3^2)`.
+    pub fn right_associative(self) -> bool {
+        self == BinaryOp::Pow
+    }
+
+    /// The character used to write this operator.
+    pub fn symbol(self) -> char {
+        match self {
+            BinaryOp::Add => '+',
+            BinaryOp::Sub => '-',
+            BinaryOp::Mul => '*',
+            BinaryOp::Div => '/',
+            BinaryOp::Mod => '%',
+            BinaryOp::Pow => '^',
+        }
+    }
+}
+
+impl fmt::Display for BinaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.symbol())
+    }
+}
+
+/// Read-only traversal of an expression tree.
+///
+/// Every method has a default implementation that keeps walking, so an
+/// implementor only overrides the nodes it cares about. Call
+/// [`walk_expr`] from an override to continue into the children.
+pub trait Visitor {
+    fn visit_expr(&mut self, expr: &Expr) {
+        walk_expr(self, expr);
+    }
+
+    fn visit_number(&mut self, _value: f64) {}
+
+    fn visit_variable(&mut self, _name: &str) {}
+
+    fn visit_binary(&mut self, _op: BinaryOp, lhs: &Expr, rhs: &Expr) {
+        self.visit_expr(lhs);
+        self.visit_expr(rhs);
+    }
+
+    fn visit_negate(&mut self, operand: &Expr) {
+        self.visit_expr(operand);
+    }
+
+    fn visit_call(&mut self, _name: &str, args: &[Expr]) {
+        for arg in args {
+            self.visit_expr(arg);
+        }
+    }
+
+    fn visit_quantity(&mut self, value: &Expr, _unit: &str) {
+        self.visit_expr(value);
+    }
+
+    fn visit_list(&mut self, items: &[Expr]) {
+        for item in items {
+            self.visit_expr(item);
+        }
+    }
+}
+
+/// Dispatches `expr` to the matching `visit_*` method of `visitor`.
+pub fn walk_expr<V: Visitor + ?Sized>(visitor: &mut V, expr: &Expr) {
+    match expr {
+        Expr::Number(value) => visitor.visit_number(*value),
+        Expr::Variable(name) => visitor.visit_variable(name),
+        Expr::Binary { op, lhs, rhs } => visitor.visit_binary(*op, lhs, rhs),
+        Expr::Negate(operand) => visitor.visit_negate(operand),
+        Expr::Call { name, args } => visitor.visit_call(name, args),
+        Expr::Quantity { value, unit } => visitor.visit_quantity(value, unit),
+        Expr::List(items) => visitor.visit_list(items),
+    }
+}
+
+/// Owning, bottom-up rewrite of an expression tree.
+///
+/// The defaults rebuild the tree unchanged; override a `fold_*` method to
+/// replace the nodes of that kind. Children are folded before their parent
+/// sees them.
+pub trait Fold {
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        fold_children(self, expr)
+    }
+
+    fn fold_number(&mut self, value: f64) -> Expr {
+        Expr::Number(value)
+    }
+
+    fn fold_variable(&mut self, name: Name) -> Expr {
+        Expr::Variable(name)
+    }
+
+    fn fold_binary(&mut self, op: BinaryOp, lhs: Expr, rhs: Expr) -> Expr {
+        Expr::binary(op, lhs, rhs)
+    }
+
+    fn fold_negate(&mut self, operand: Expr) -> Expr {
+        Expr::negate(operand)
+    }
+
+    fn fold_call(&mut self, name: Name, args: Vec<Expr>) -> Expr {
+        Expr::Call { name, args }
+    }
+
+    fn fold_quantity(&mut self, value: Expr, unit: String) -> Expr {
+        Expr::Quantity {
+            value: Arc::new(value),
+            unit,
+        }
+    }
+
+    fn fold_list(&mut self, items: Vec<Expr>) -> Expr {
+        Expr::List(items)
+    }
+}
+
+/// Folds the children of `expr` and hands the result to the matching
+/// `fold_*` method of `folder`. A child shared with another tree is
+/// copied first, so the other tree is left as it was.
+pub fn fold_children<F: Fold + ?Sized>(folder: &mut F, expr: Expr) -> Expr {
+    match expr {
+        Expr::Number(value) => folder.fold_number(value),
+        Expr::Variable(name) => folder.fold_variable(name),
+        Expr::Binary { op, lhs, rhs } => {
+            let lhs = folder.fold_expr(Arc::unwrap_or_clone(lhs));
+            let rhs = folder.fold_expr(Arc::unwrap_or_clone(rhs));
+            folder.fold_binary(op, lhs, rhs)
+        }
+        Expr::Negate(operand) => {
+            let operand = folder.fold_expr(Arc::unwrap_or_clone(operand));
+            folder.fold_negate(operand)
+        }
+        Expr::Call { name, args } => {
+            let args = args.into_iter().map(|arg| folder.fold_expr(arg)).collect();
+            folder.fold_call(name, args)
+        }
+        Expr::Quantity { value, unit } => {
+            let value = folder.fold_expr(Arc::unwrap_or_clone(value));
+            folder.fold_quantity(value, unit)
+        }
+        Expr::List(items) => {
+            let items = items.into_iter().map(|item| folder.fold_expr(item)).collect();
+            folder.fold_list(items)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Expr {
+        Expr::binary(BinaryOp::Mul, Expr::Number(5.0), Expr::Number(3.0))
+    }
+
+    #[test]
+    fn test_visitor_collects_numbers() {
+        struct Numbers(Vec<f64>);
+
+        impl Visitor for Numbers {
+            fn visit_number(&mut self, value: f64) {
+                self.0.push(value);
+            }
+        }
+
+        let mut numbers = Numbers(Vec::new());
+        numbers.visit_expr(&sample());
+        assert_eq!(numbers.0, vec![5.0, 3.0]);
+    }
+
+    #[test]
+    fn test_fold_rewrites_operators() {
+        struct MulToAdd;
+
+        impl Fold for MulToAdd {
+            fn fold_binary(&mut self, op: BinaryOp, lhs: Expr, rhs: Expr) -> Expr {
+                let op = if op == BinaryOp::Mul { BinaryOp::Add } else { op };
+                Expr::binary(op, lhs, rhs)
+            }
+        }
+
+        let folded = MulToAdd.fold_expr(sample());
+        assert_eq!(folded, Expr::binary(BinaryOp::Add, Expr::Number(5.0), Expr::Number(3.0)));
+    }
+
+    #[test]
+    fn test_variables_lists_each_name_once() {
+        let expr = Expr::binary(
+            BinaryOp::Add,
+            Expr::variable("x"),
+            Expr::binary(BinaryOp::Mul, Expr::variable("y"), Expr::variable("x")),
+        );
+        assert_eq!(expr.variables(), vec!["x".to_string(), "y".to_string()]);
+        assert!(sample().variables().is_empty());
+    }
+
+    #[test]
+    fn test_complexity_metrics() {
+        assert_eq!(Expr::Number(1.0).node_count(), 1);
+        assert_eq!(sample().node_count(), 3);
+        assert_eq!(sample().depth(), 2);
+
+        let call = Expr::call("ulps", vec![sample(), Expr::Number(1.0)]);
+        assert_eq!(call.node_count(), 5);
+        assert_eq!(call.depth(), 3);
+    }
+
+    #[test]
+    fn test_names_read_as_text() {
+        let (first, second) = (Name::new("rate"), Name::from("rate".to_string()));
+        assert_eq!(first, second);
+        assert!(std::ptr::eq(first.as_str(), first.clone().as_str()));
+        assert_eq!(first, "rate");
+        assert_ne!(Name::new("rates"), first);
+        assert_eq!(format!("{} {:?}", first, Expr::Variable(first.clone())), "rate Variable(\"rate\")");
+    }
+
+    #[test]
+    fn test_fold_leaves_shared_children() {
+        struct Unchanged;
+
+        impl Fold for Unchanged {}
+
+        let shared = Arc::new(sample());
+        let expr = Expr::Negate(shared.clone());
+        let folded = Unchanged.fold_expr(expr.clone());
+        assert_eq!(folded, expr);
+        assert_eq!(*shared, sample());
+    }
+
+    #[test]
+    fn test_trees_cross_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+
+        assert_send_sync::<Expr>();
+        assert_send_sync::<Name>();
+        let expr = Expr::call("sqrt", vec![sample()]);
+        let moved = expr.clone();
+        assert_eq!(std::thread::spawn(move || moved).join().unwrap(), expr);
+    }
+}
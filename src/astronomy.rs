@@ -0,0 +1,39 @@
+//! Astronomy helpers: sidereal time and light-time distances. Times are
+//! given as Julian dates; see [`crate::dates`].
+//!
+//! Only compiled in with the `astronomy` feature, which is on by default.
+
+/// Kilometres in a light year (Julian year of 365.25 days).
+pub const LIGHT_YEAR_KM: f64 = 9_460_730_472_580.8;
+/// Kilometres in an astronomical unit.
+pub const AU_KM: f64 = 149_597_870.7;
+
+// Julian date of 2000 January 1.5, the J2000.0 epoch.
+const J2000: f64 = 2_451_545.0;
+
+/// Greenwich mean sidereal time in hours at Julian date `jd`.
+pub fn greenwich_sidereal_time(jd: f64) -> f64 {
+    let days = jd - J2000;
+    let centuries = days / 36_525.0;
+    let degrees = 280.460_618_37 + 360.985_647_366_29 * days + 0.000_387_933 * centuries * centuries
+        - centuries * centuries * centuries / 38_710_000.0;
+    degrees.rem_euclid(360.0) / 15.0
+}
+
+/// Local mean sidereal time in hours at east `longitude` in degrees.
+pub fn local_sidereal_time(jd: f64, longitude: f64) -> f64 {
+    (greenwich_sidereal_time(jd) + longitude / 15.0).rem_euclid(24.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sidereal_time() {
+        // Meeus example 12.a: 1987 April 10, 0h UT
+        let gmst = greenwich_sidereal_time(2_446_895.5);
+        assert!((gmst - 13.1795).abs() < 1e-4, "{}", gmst);
+        assert!((local_sidereal_time(2_446_895.5, -90.0) - 7.1795).abs() < 1e-4);
+    }
+}
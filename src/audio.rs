@@ -0,0 +1,86 @@
+//! Audio and music math: decibels, note frequencies in twelve-tone equal
+//! temperament tuned to A4 = 440 Hz, and tempo-synced delay times.
+
+fn positive(value: f64, what: &str) -> Result<f64, String> {
+    if value > 0.0 && value.is_finite() {
+        Ok(value)
+    } else {
+        Err(format!("{} must be positive", what))
+    }
+}
+
+/// Decibels of an amplitude (voltage or sound pressure) ratio.
+pub fn ratio_to_db(ratio: f64) -> Result<f64, String> {
+    Ok(20.0 * positive(ratio, "Ratio")?.log10())
+}
+
+/// The amplitude ratio of a gain in decibels.
+pub fn db_to_ratio(db: f64) -> f64 {
+    10f64.powf(db / 20.0)
+}
+
+/// Frequency in Hz of a note such as `A4`, `C#5`, `Bb3` or `C-1`.
+pub fn note_frequency(note: &str) -> Result<f64, String> {
+    let invalid = || format!("'{}' is not a note; write a letter, an optional # or b, and an octave, e.g. C#4", note);
+    let mut chars = note.chars();
+    let semitone: i32 = match chars.next().map(|c| c.to_ascii_uppercase()) {
+        Some('C') => 0,
+        Some('D') => 2,
+        Some('E') => 4,
+        Some('F') => 5,
+        Some('G') => 7,
+        Some('A') => 9,
+        Some('B') => 11,
+        _ => return Err(invalid()),
+    };
+    let rest = chars.as_str();
+    let octave_start = rest.find(|c: char| c.is_ascii_digit() || c == '-').ok_or_else(invalid)?;
+    let accidental = rest[..octave_start].chars().try_fold(0, |shift, c| match c {
+        '#' | '♯' => Ok(shift + 1),
+        'b' | '♭' => Ok(shift - 1),
+        _ => Err(invalid()),
+    })?;
+    let octave: i32 = rest[octave_start..].parse().map_err(|_| invalid())?;
+
+    // MIDI note 69 is A4; in floating point, as the octave can be any i32
+    let midi = (octave as f64 + 1.0) * 12.0 + (semitone + accidental) as f64;
+    Ok(440.0 * 2f64.powf((midi - 69.0) / 12.0))
+}
+
+/// The interval from `f1` to `f2` in cents; 100 cents is a semitone.
+pub fn cents(f1: f64, f2: f64) -> Result<f64, String> {
+    Ok(1200.0 * (positive(f2, "Frequency")? / positive(f1, "Frequency")?).log2())
+}
+
+/// Milliseconds per beat at `bpm` beats per minute; also the BPM for a
+/// beat of that many milliseconds, as the conversion is its own inverse.
+pub fn beat_ms(bpm: f64) -> Result<f64, String> {
+    Ok(60_000.0 / positive(bpm, "Tempo")?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decibels() {
+        assert_eq!(ratio_to_db(10.0), Ok(20.0));
+        assert_eq!(db_to_ratio(-20.0), 0.1);
+        assert!((ratio_to_db(2.0).unwrap() - 6.0206).abs() < 1e-4);
+        assert_eq!(ratio_to_db(0.0), Err("Ratio must be positive".to_string()));
+    }
+
+    #[test]
+    fn test_notes() {
+        assert_eq!(note_frequency("A4"), Ok(440.0));
+        assert_eq!(note_frequency("a5"), Ok(880.0));
+        assert!((note_frequency("C4").unwrap() - 261.6256).abs() < 1e-4);
+        assert_eq!(note_frequency("C#4"), note_frequency("Db4"));
+        assert!((note_frequency("C-1").unwrap() - 8.1758).abs() < 1e-4);
+        assert!(note_frequency("H4").is_err());
+        assert!(note_frequency("Ax4").is_err());
+        assert!(note_frequency("A").is_err());
+        assert_eq!(cents(440.0, 880.0), Ok(1200.0));
+        assert_eq!(beat_ms(120.0), Ok(500.0));
+    }
+}
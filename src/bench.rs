@@ -0,0 +1,276 @@
+//! Standard workloads timed on the machine the calculator runs on, so
+//! packagers can compare builds and releases.
+//!
+//! Each workload is run a fixed number of rounds; the report gives the
+//! total time and the time per round, and the allocations per round where
+//! the program counts them with [`CountingAllocator`].
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fmt;
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::ast::{BinaryOp, Expr};
+use crate::{calculate_promoted, calculate_quantity, calculate_tuple, calculate_with, inspect_report, sub_results};
+use crate::eval::{evaluate, evaluate_number, Context, OverflowMode};
+use crate::normalize::normalize;
+use crate::parser::parse;
+use crate::units::has_units;
+
+/// Rounds each workload runs for unless told otherwise.
+pub const DEFAULT_ROUNDS: usize = 10_000;
+
+// Long enough that reading it, not setting up, is what takes the time
+const PARSE_INPUT: &str = "wavg(90, 80, 70, 60, 50, 40, 30, 20; 1, 2, 3, 4, 5, 6, 7, 8) * ulps(0.1 + 0.2, 0.3)";
+
+// Sums of this many terms are what the evaluation workload walks
+const EVAL_TERMS: usize = 1024;
+
+// Overflows a float in a product, a power and a quotient, so each is
+// calculated again with big numbers
+const BIGNUM_INPUT: &str = "1e300 * 1e300 * 7 ^ 900 / 3 - 2 ^ 1100 % 97";
+
+// A day's worth of calculations of every kind, calculated one after another
+const BATCH_INPUTS: &[&str] = &[
+    "5+3",
+    "1.5e3 * 2",
+    "0.1 + 0.2",
+    "x * 2",
+    "x / 3",
+    "1e100 * 1e-100",
+    "ulps(0.1 + 0.2, 0.3)",
+    "approx_eq(x, 2.5000001, 1e-6)",
+    "wavg(90, 80, 70; 1, 2, 1)",
+    "sum(1, 2, 3, 4) - avg(5, 6, 7)",
+    "20 °C + 10 K",
+    "5' 3 1/2\" + 2' 7/8\"",
+    "pow(x, 2) + 1",
+    "5 / 0",
+    "y + 1",
+];
+
+struct Workload {
+    name: &'static str,
+    description: &'static str,
+    run: fn(usize, &Context),
+}
+
+const WORKLOADS: &[Workload] = &[
+    Workload {
+        name: "parse",
+        description: "normalize and parse a long call",
+        run: |rounds, ctx| {
+            for _ in 0..rounds {
+                let _ = black_box(parse(&normalize(black_box(PARSE_INPUT), ctx)));
+            }
+        },
+    },
+    Workload {
+        name: "evaluate",
+        description: "evaluate a parsed sum of 1024 terms",
+        run: |rounds, ctx| {
+            let sum = sum_of(0, EVAL_TERMS);
+            for _ in 0..rounds {
+                let _ = black_box(evaluate(black_box(&sum), ctx));
+            }
+        },
+    },
+    // The same sum past the arithmetic fast path, to show what it saves
+    Workload {
+        name: "generic",
+        description: "evaluate the same sum the general way",
+        run: |rounds, ctx| {
+            let sum = sum_of(0, EVAL_TERMS);
+            for _ in 0..rounds {
+                let sum = black_box(&sum);
+                let _ = black_box(if has_units(sum) { evaluate(sum, ctx) } else { evaluate_number(sum, ctx) });
+            }
+        },
+    },
+    // What the window asks of each keystroke, parsing in an arena as the
+    // window does; the two inputs take turns so every round is an edit
+    Workload {
+        name: "preview",
+        description: "preview edits to a long line",
+        run: |rounds, ctx| {
+            let mut ctx = ctx.clone();
+            ctx.arena = Some(Default::default());
+            let ctx = &ctx;
+            let edited = format!("{} + 1", PARSE_INPUT);
+            for round in 0..rounds {
+                let input = if round % 2 == 0 { PARSE_INPUT } else { edited.as_str() };
+                let _ = black_box(calculate_tuple(black_box(input), ctx));
+                let _ = black_box(calculate_quantity(black_box(input), ctx));
+                let _ = black_box(inspect_report(black_box(input), ctx));
+                let _ = black_box(sub_results(black_box(input), ctx));
+            }
+        },
+    },
+    Workload {
+        name: "bignum",
+        description: "promote an overflowing calculation to big numbers",
+        run: |rounds, ctx| {
+            let mut ctx = ctx.clone();
+            ctx.overflow = OverflowMode::Promote;
+            for _ in 0..rounds {
+                let _ = black_box(calculate_promoted(black_box(BIGNUM_INPUT), &ctx));
+            }
+        },
+    },
+    Workload {
+        name: "batch",
+        description: "calculate 15 mixed inputs from text",
+        run: |rounds, ctx| {
+            for _ in 0..rounds {
+                for input in BATCH_INPUTS {
+                    let _ = black_box(calculate_with(black_box(input), ctx));
+                }
+            }
+        },
+    },
+];
+
+// The terms from `first` up to `last` added as a balanced tree, so walking
+// it does not recurse deeper than a real input would; every term but the
+// first is a number and that one is `x`
+fn sum_of(first: usize, last: usize) -> Expr {
+    match last - first {
+        1 if first == 0 => Expr::variable("x"),
+        1 => Expr::Number(first as f64),
+        terms => {
+            let middle = first + terms / 2;
+            Expr::binary(BinaryOp::Add, sum_of(first, middle), sum_of(middle, last))
+        }
+    }
+}
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// The system allocator, counting the allocations made through it. A
+/// program that installs it as its `#[global_allocator]`, as the
+/// `calculator` binary does, gets allocation counts in its reports.
+pub struct CountingAllocator;
+
+// SAFETY: every call is passed on to the system allocator unchanged
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+/// How long one workload took.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Timing {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub rounds: usize,
+    pub total: Duration,
+    /// Allocations made in all rounds, when they were counted.
+    pub allocations: Option<usize>,
+}
+
+impl Timing {
+    /// The time one round took, on average.
+    pub fn per_round(&self) -> Duration {
+        self.total / self.rounds.max(1) as u32
+    }
+
+    /// The allocations one round made, on average, when they were counted.
+    pub fn allocations_per_round(&self) -> Option<f64> {
+        self.allocations.map(|allocations| allocations as f64 / self.rounds.max(1) as f64)
+    }
+}
+
+/// Timings of a full benchmark run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub timings: Vec<Timing>,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for timing in &self.timings {
+            write!(
+                f,
+                "{:<9} {:>8} rounds {:>12.3?} total {:>12.3?}/round",
+                timing.name,
+                timing.rounds,
+                timing.total,
+                timing.per_round()
+            )?;
+            if let Some(allocations) = timing.allocations_per_round() {
+                write!(f, " {:>9.1} allocs/round", allocations)?;
+            }
+            writeln!(f, "  ({})", timing.description)?;
+        }
+        let total: Duration = self.timings.iter().map(|timing| timing.total).sum();
+        write!(f, "\n{} workloads in {:.3?}", self.timings.len(), total)
+    }
+}
+
+/// Runs every workload for `rounds` rounds.
+pub fn run(rounds: usize) -> Report {
+    let mut ctx = Context::new();
+    ctx.set("x", 2.5);
+    // Setting up allocates, so the count is still zero only when the
+    // counting allocator is not installed
+    let counted = ALLOCATIONS.load(Ordering::Relaxed) > 0;
+
+    let timings = WORKLOADS
+        .iter()
+        .map(|workload| {
+            let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+            let start = Instant::now();
+            (workload.run)(rounds, &ctx);
+            let total = start.elapsed();
+            Timing {
+                name: workload.name,
+                description: workload.description,
+                rounds,
+                total,
+                allocations: counted.then(|| ALLOCATIONS.load(Ordering::Relaxed) - allocations),
+            }
+        })
+        .collect();
+    Report { timings }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bench_runs_every_workload() {
+        assert!(parse(&normalize(PARSE_INPUT, &Context::new())).is_ok());
+        let mut ctx = Context::new();
+        ctx.set("x", 0.5);
+        assert_eq!(evaluate(&sum_of(0, EVAL_TERMS), &ctx), Ok(523_776.5));
+        assert_eq!(evaluate_number(&sum_of(0, EVAL_TERMS), &ctx), Ok(523_776.5));
+        ctx.overflow = OverflowMode::Promote;
+        assert!(calculate_promoted(BIGNUM_INPUT, &ctx).is_some_and(|outcome| outcome.is_ok()));
+        let report = run(2);
+        let names: Vec<&str> = report.timings.iter().map(|timing| timing.name).collect();
+        assert_eq!(names, ["parse", "evaluate", "generic", "preview", "bignum", "batch"]);
+        assert!(report.timings.iter().all(|timing| timing.rounds == 2));
+        assert!(report.to_string().contains("\n6 workloads in "));
+        // Tests run with the system allocator, which counts nothing
+        assert!(report.timings.iter().all(|timing| timing.allocations.is_none()));
+        assert!(!report.to_string().contains("allocs/round"));
+    }
+}
@@ -0,0 +1,262 @@
+//! Arbitrary-precision arithmetic for results an f64 cannot hold, used when
+//! the overflow setting is [`OverflowMode::Promote`]: `1e308 * 10` is
+//! calculated again as big decimals instead of failing.
+//!
+//! Sums, differences, products and remainders are exact; quotients and
+//! whole-number powers keep 100 significant digits. Functions and other
+//! powers are still calculated as floats, so they work where their
+//! arguments and results fit in one; of the functions whose results
+//! overflow, `sum` and `pow` are calculated again like the operators, and
+//! `exp` to the precision of a float.
+//!
+//! [`OverflowMode::Promote`]: crate::OverflowMode::Promote
+
+use bigdecimal::num_bigint::BigInt;
+use bigdecimal::{BigDecimal, One, RoundingMode, ToPrimitive, Zero};
+
+use crate::ast::{BinaryOp, Expr};
+use crate::eval::{evaluate_number, Context, OVERFLOW};
+use crate::format::{self, format_number};
+use crate::normalize::DecimalSeparator;
+
+// The largest power of ten a big result may reach, which keeps a chain of
+// powers from growing past what can be stored or printed
+const MAX_MAGNITUDE: i64 = 1_000_000_000;
+
+// ln 10 to more digits than a float holds, so the whole number of tens in
+// e^x is right for any x a big result can reach
+const LN_10: &str = "2.30258509299404568401799145468436420760110148862877";
+
+const TOO_LARGE: &str = "Result is too large or too small even for big numbers";
+
+// Significant digits shown of a big result
+const SHOWN_DIGITS: u64 = 30;
+
+// Digits in each whole number of a big result written for input, which
+// a float holds exactly
+const GROUP_DIGITS: usize = 15;
+
+/// Evaluates `expr` with big decimals under the settings and profile of
+/// `ctx`.
+pub fn evaluate(expr: &Expr, ctx: &Context) -> Result<BigDecimal, String> {
+    match expr {
+        Expr::Number(n) => from_f64(*n),
+        Expr::Negate(operand) => Ok(-evaluate(operand, ctx)?),
+        Expr::Binary { op, lhs, rhs } => apply(*op, evaluate(lhs, ctx)?, evaluate(rhs, ctx)?, ctx),
+        Expr::Call { name, args } => match evaluate_number(expr, ctx) {
+            Err(err) if err == OVERFLOW => call(name, args, ctx),
+            value => from_f64(value?),
+        },
+        Expr::Variable(_) => from_f64(evaluate_number(expr, ctx)?),
+        Expr::Quantity { .. } | Expr::List(_) => Err("Big numbers cannot have units or lists".to_string()),
+    }
+}
+
+/// Formats a big result like [`format_number`] does a float: plain for
+/// everyday magnitudes, scientific notation beyond them, with up to 30
+/// significant digits.
+pub fn format(value: &BigDecimal) -> String {
+    let value = value.with_prec(SHOWN_DIGITS).normalized();
+    if value.is_zero() {
+        return "0".to_string();
+    }
+    if (-6..16).contains(&value.order_of_magnitude()) {
+        value.to_plain_string()
+    } else {
+        value.to_scientific_notation()
+    }
+}
+
+/// The text to put in the input or on the clipboard for a big result. A
+/// value within the range of a float is written as the float nearest it.
+/// Any other is written as whole numbers of up to 15 digits, which floats
+/// hold exactly, scaled by a power of ten, as in
+/// `(135829852904938 * 1e15 + 584927735142836) * 10 ^ 302`; the text
+/// overflows a float, so with big numbers on it is calculated as big
+/// numbers again and reads back as exactly the value [`format`] shows.
+pub fn format_for_input(value: &BigDecimal, separator: DecimalSeparator) -> String {
+    let value = value.with_prec(SHOWN_DIGITS).normalized();
+    if let Some(float) = value.to_f64().filter(|float| float.is_normal() || value.is_zero()) {
+        return format::format_for_input(float, separator);
+    }
+    let (digits, scale) = value.as_bigint_and_exponent();
+    let digits = digits.to_string();
+    let (sign, digits) = match digits.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", digits.as_str()),
+    };
+    // Groups of 15 digits from the right, each shifted past the next
+    let first = match digits.len() % GROUP_DIGITS {
+        0 => GROUP_DIGITS,
+        length => length,
+    };
+    let mut whole = digits[..first].to_string();
+    for start in (first..digits.len()).step_by(GROUP_DIGITS) {
+        let group = digits[start..start + GROUP_DIGITS].trim_start_matches('0');
+        whole = format!("({} * 1e{}", whole, GROUP_DIGITS);
+        whole += &if group.is_empty() { ")".to_string() } else { format!(" + {})", group) };
+    }
+    match scale {
+        0 => format!("{}{}", sign, whole),
+        scale if scale > 0 => format!("{}{} / 10 ^ {}", sign, whole, scale),
+        scale => format!("{}{} * 10 ^ {}", sign, whole, -scale),
+    }
+}
+
+// The decimal an f64 is written as, so `0.1` is 0.1 and not the binary
+// fraction nearest it
+fn from_f64(value: f64) -> Result<BigDecimal, String> {
+    if !value.is_finite() {
+        return Err(format!("{} is not a number big numbers can hold", format_number(value)));
+    }
+    format_number(value).parse().map_err(|_| format!("Cannot read {} as a big number", value))
+}
+
+// A function whose float result overflowed
+fn call(name: &str, args: &[Expr], ctx: &Context) -> Result<BigDecimal, String> {
+    match (name, args) {
+        ("pow", [base, exponent]) => apply(BinaryOp::Pow, evaluate(base, ctx)?, evaluate(exponent, ctx)?, ctx),
+        ("sum", args) => args.iter().flat_map(list_items).try_fold(BigDecimal::zero(), |total, item| {
+            apply(BinaryOp::Add, total, evaluate(item, ctx)?, ctx)
+        }),
+        ("exp", [x]) => exp(&evaluate(x, ctx)?),
+        _ => Err(format!("{}() cannot be calculated with big numbers", name)),
+    }
+}
+
+// The items of a list argument, or the argument itself
+fn list_items(arg: &Expr) -> &[Expr] {
+    match arg {
+        Expr::List(items) => items,
+        other => std::slice::from_ref(other),
+    }
+}
+
+// e^x as e^r * 10^k, where k is the whole number of times ln 10 goes into
+// x; the remainder r is small enough that e^r is as precise as a float
+fn exp(x: &BigDecimal) -> Result<BigDecimal, String> {
+    let ln_10: BigDecimal = LN_10.parse().expect("ln 10 is a decimal");
+    let whole = (x / &ln_10).with_scale_round(0, RoundingMode::Floor);
+    let whole = whole.to_i64().filter(|whole| whole.abs() <= MAX_MAGNITUDE).ok_or(TOO_LARGE)?;
+    let rest = (x - &ln_10 * BigDecimal::from(whole)).to_f64().ok_or(TOO_LARGE)?;
+    Ok(from_f64(rest.exp())? * BigDecimal::new(BigInt::one(), -whole))
+}
+
+fn apply(op: BinaryOp, a: BigDecimal, b: BigDecimal, ctx: &Context) -> Result<BigDecimal, String> {
+    if !ctx.profile().allows_operator(op) {
+        return Err(ctx.profile().forbids(&format!("The {} operator", op)));
+    }
+    let result = match op {
+        BinaryOp::Add => a + b,
+        BinaryOp::Sub => a - b,
+        BinaryOp::Mul => a * b,
+        BinaryOp::Div if b.is_zero() => return Err("Division by zero".to_string()),
+        BinaryOp::Div => a / b,
+        BinaryOp::Mod if b.is_zero() => return Err("Modulo by zero".to_string()),
+        // Floored, as for floats
+        BinaryOp::Mod => {
+            let remainder = &a % &b;
+            if !remainder.is_zero() && remainder.sign() != b.sign() {
+                remainder + b
+            } else {
+                remainder
+            }
+        }
+        BinaryOp::Pow => power(&a, &b)?,
+    };
+    if !result.is_zero() && result.order_of_magnitude().abs() > MAX_MAGNITUDE {
+        return Err(TOO_LARGE.to_string());
+    }
+    Ok(result)
+}
+
+fn power(base: &BigDecimal, exponent: &BigDecimal) -> Result<BigDecimal, String> {
+    let Some(exponent) = exponent.is_integer().then(|| exponent.to_i64()).flatten() else {
+        // Other powers are worked out as floats, when they fit
+        return match (base.to_f64(), exponent.to_f64()) {
+            (Some(base), Some(exponent)) if base.powf(exponent).is_finite() => from_f64(base.powf(exponent)),
+            _ => Err("Big numbers can only be raised to whole-number powers".to_string()),
+        };
+    };
+    if exponent == 0 || base.is_one() {
+        return Ok(BigDecimal::one());
+    }
+    if base.is_zero() {
+        return if exponent < 0 { Err("Division by zero".to_string()) } else { Ok(BigDecimal::zero()) };
+    }
+    // The result's magnitude grows with the exponent; one past the limit
+    // is refused before it is worked out
+    let magnitude = (base.order_of_magnitude().abs() + 1).saturating_mul(exponent.abs());
+    if magnitude > MAX_MAGNITUDE.saturating_mul(2) {
+        return Err(TOO_LARGE.to_string());
+    }
+    Ok(base.powi(exponent))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    fn big(input: &str) -> Result<String, String> {
+        evaluate(&parse(input).unwrap(), &Context::new()).map(|value| format(&value))
+    }
+
+    #[test]
+    fn test_big_arithmetic() {
+        assert_eq!(big("1e308 * 10"), Ok("1e309".to_string()));
+        assert_eq!(big("1e308 * 1e308 - 1e300 * 1e300 * 1e16"), Ok("0".to_string()));
+        assert_eq!(big("0.1 + 0.2"), Ok("0.3".to_string()));
+        assert_eq!(big("2 ^ 1100"), Ok("1.35829852904938584927735142836e331".to_string()));
+        assert_eq!(big("1 / 3"), Ok("0.333333333333333333333333333333".to_string()));
+        assert_eq!(big("-7 % 3"), Ok("2".to_string()));
+        assert_eq!(big("1e-320 / 1e10"), Ok("1e-330".to_string()));
+        assert_eq!(big("-2 ^ 2"), Ok("-4".to_string()));
+        assert_eq!(big("2 ^ 0.5"), Ok("1.4142135623730951".to_string()));
+    }
+
+    #[test]
+    fn test_format_for_input_round_trips() {
+        let mut ctx = Context::new();
+        ctx.overflow = crate::OverflowMode::Promote;
+        for input in ["1e308 * 10", "2e300 * 1e8", "2 ^ 1100", "-(7 ^ 900)", "1e300 * 1e300 / 3", "1 / (1e300 * 1e300 * 3)", "1e308 * 1e308 / 1e300 / 7"] {
+            let value = evaluate(&parse(input).unwrap(), &ctx).unwrap();
+            for separator in DecimalSeparator::ALL {
+                let mut ctx = ctx.clone();
+                ctx.decimal_separator = separator;
+                let text = format_for_input(&value, separator);
+                let read = match crate::calculate_promoted(&text, &ctx) {
+                    Some(read) => read,
+                    None => crate::calculate_with(&text, &ctx).map(format_number),
+                };
+                assert_eq!(read, Ok(format(&value)), "{} written as {}", input, text);
+            }
+        }
+        assert_eq!(format_for_input(&"1e309".parse().unwrap(), DecimalSeparator::Point), "1 * 10 ^ 309");
+        assert_eq!(format_for_input(&"-2.5".parse().unwrap(), DecimalSeparator::Comma), "-2,5");
+        assert_eq!(format_for_input(&"0.333333333333333333333333333333".parse().unwrap(), DecimalSeparator::Point), "0.3333333333333333");
+        let big = evaluate(&parse("2 ^ 1100").unwrap(), &ctx).unwrap();
+        assert_eq!(
+            format_for_input(&big, DecimalSeparator::Point),
+            "(135829852904938 * 1e15 + 584927735142836) * 10 ^ 302"
+        );
+    }
+
+    #[cfg(feature = "scientific")]
+    #[test]
+    fn test_big_functions() {
+        assert_eq!(big("pow(10, 400)"), Ok("1e400".to_string()));
+        assert_eq!(big("sum(1e308, 1e308, 1)"), Ok("2e308".to_string()));
+        assert!(big("exp(1000)").is_ok_and(|value| value.starts_with("1.97007111401704") && value.ends_with("e434")));
+        assert_eq!(big("exp(1e12)"), Err(TOO_LARGE.to_string()));
+        assert_eq!(big("ratio(10000)"), Err("ratio() cannot be calculated with big numbers".to_string()));
+    }
+
+    #[test]
+    fn test_big_errors() {
+        assert_eq!(big("5 / (1 - 1)"), Err("Division by zero".to_string()));
+        assert_eq!(big("(1e300 * 1e300) ^ 0.5"), Err("Big numbers can only be raised to whole-number powers".to_string()));
+        assert_eq!(big("10 ^ 1e9 ^ 2"), Err("Result is too large or too small even for big numbers".to_string()));
+        assert_eq!(big("y * 2"), Err("Unknown variable 'y'".to_string()));
+    }
+}
@@ -0,0 +1,51 @@
+//! Small-business arithmetic: break-even points, contribution margins, and
+//! markup and margin, which are both profit in percent but of different
+//! bases: markup of the cost, margin of the price.
+
+/// Units to sell before the sales cover `fixed` costs, when each unit
+/// sells for `price` and costs `varcost` to make.
+pub fn breakeven(fixed: f64, price: f64, varcost: f64) -> Result<f64, String> {
+    let margin = contribution_margin(price, varcost);
+    if margin <= 0.0 {
+        return Err("The price must be above the variable cost to ever break even".to_string());
+    }
+    Ok(fixed / margin)
+}
+
+/// What each unit sold leaves toward the fixed costs.
+pub fn contribution_margin(price: f64, varcost: f64) -> f64 {
+    price - varcost
+}
+
+/// The margin in percent that a markup in percent gives: a 25% markup on
+/// cost is a 20% margin on the price.
+pub fn markup_to_margin(markup: f64) -> Result<f64, String> {
+    if markup <= -100.0 {
+        return Err("A markup must be above -100%".to_string());
+    }
+    Ok(markup / (100.0 + markup) * 100.0)
+}
+
+/// The markup in percent that gives a margin in percent.
+pub fn margin_to_markup(margin: f64) -> Result<f64, String> {
+    if margin >= 100.0 {
+        return Err("A margin must be under 100%".to_string());
+    }
+    Ok(margin / (100.0 - margin) * 100.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breakeven_and_margins() {
+        assert_eq!(breakeven(10_000.0, 25.0, 15.0), Ok(1000.0));
+        assert!(breakeven(10_000.0, 15.0, 15.0).is_err());
+        assert_eq!(contribution_margin(25.0, 15.0), 10.0);
+        assert_eq!(markup_to_margin(25.0), Ok(20.0));
+        assert_eq!(margin_to_markup(20.0), Ok(25.0));
+        assert!(margin_to_markup(100.0).is_err());
+        assert!(markup_to_margin(-100.0).is_err());
+    }
+}
@@ -0,0 +1,81 @@
+//! A searchable list of the built-in functions, grouped by pack and drawn
+//! from the function registry.
+
+use eframe::egui;
+
+use calculator::functions::{self, Function, Pack};
+use calculator::Context;
+
+#[derive(Default)]
+pub struct Catalog {
+    pub open: bool,
+    search: String,
+}
+
+impl Catalog {
+    /// Shows the catalog while it is open. Returns the example the user
+    /// chose to insert, if any.
+    pub fn show(&mut self, ctx: &egui::Context, calc: &Context) -> Option<String> {
+        let mut insert = None;
+        let search = &mut self.search;
+        egui::Window::new("Function Catalog")
+            .open(&mut self.open)
+            .default_height(400.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Search:");
+                    ui.text_edit_singleline(search);
+                });
+                ui.add_space(5.0);
+
+                let search = search.trim().to_lowercase();
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for pack in Pack::ALL.into_iter().filter(|pack| pack.is_available()) {
+                        let matches: Vec<&Function> = functions::all()
+                            .filter(|&(function_pack, function)| function_pack == pack && matches(function, &search))
+                            .map(|(_, function)| function)
+                            .collect();
+                        if matches.is_empty() {
+                            continue;
+                        }
+
+                        let enabled = calc.has_pack(pack);
+                        let heading = if enabled {
+                            pack.label().to_string()
+                        } else if !calc.profile().allows_pack(pack) {
+                            format!("{} (not in the {} profile)", pack.label(), calc.profile().label().to_lowercase())
+                        } else {
+                            format!("{} (turned off in Settings)", pack.label())
+                        };
+                        egui::CollapsingHeader::new(heading).default_open(true).show(ui, |ui| {
+                            for function in matches {
+                                ui.add_enabled_ui(enabled, |ui| {
+                                    ui.horizontal(|ui| {
+                                        ui.label(egui::RichText::new(function.signature()).monospace().strong());
+                                        if ui
+                                            .small_button("Insert example")
+                                            .on_hover_text(function.example)
+                                            .clicked()
+                                        {
+                                            insert = Some(function.example.to_string());
+                                        }
+                                    });
+                                    ui.label(function.description);
+                                });
+                                ui.add_space(4.0);
+                            }
+                        });
+                    }
+                });
+            });
+        insert
+    }
+}
+
+// Case-insensitive match on the name or description; `search` is already
+// lowercased.
+fn matches(function: &Function, search: &str) -> bool {
+    search.is_empty()
+        || function.name.to_lowercase().contains(search)
+        || function.description.to_lowercase().contains(search)
+}
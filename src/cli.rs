@@ -0,0 +1,170 @@
+use calculator::{bench, calculate, float, format_expr, selftest, Profile};
+
+use crate::instance;
+
+const USAGE: &str = "Usage: calculator [COMMAND]
+       calculator calc://<EXPRESSION>
+
+Without a command the graphical calculator is started, or the one already
+running is brought to the front. A calc: link such as calc://5*12 is
+calculated there.
+
+Options:
+  --kiosk                Start the graphical calculator locked down: no files
+                         are read or written and settings cannot be changed
+  --profile <NAME>       Allow only part of the calculator: full, core or
+                         four-function
+  --evaluate-selection   Calculate the selected text, or the clipboard, and
+                         show the result in a small window; bind this to a
+                         key in the desktop's keyboard settings
+  --oneshot              Ask for one expression, copy its result to the
+                         clipboard, print it and exit, like a launcher
+
+Commands:
+  bench [ROUNDS]         Time the standard engine workloads and print the timings
+                         and allocations
+  fmt <EXPRESSION>       Print the expression with normalized spacing and numbers
+  inspect <EXPRESSION>   Show the IEEE 754 breakdown of the expression's value
+  register-url-handler   Open calc: links with this calculator
+  selftest               Run the built-in engine checks and print a report";
+
+/// How to start the graphical calculator.
+#[derive(Default)]
+pub struct Launch {
+    pub kiosk: bool,
+    pub profile: Option<Profile>,
+    /// Only show the result of the selected text.
+    pub evaluate_selection: bool,
+    /// Only take one expression and copy its result.
+    pub oneshot: bool,
+    /// The expression of a `calc:` link it was opened with.
+    pub expression: Option<String>,
+}
+
+impl Launch {
+    /// Whether any option was given, rather than nothing or only a link.
+    pub fn has_options(&self) -> bool {
+        self.kiosk || self.profile.is_some() || self.evaluate_selection || self.oneshot
+    }
+}
+
+/// Reads the options for starting the graphical calculator, or returns
+/// `None` when `args` is a command for [`run`]. A command after options is
+/// an error, as the options only apply to the graphical calculator.
+pub fn launch_options(args: &[String]) -> Option<Result<Launch, String>> {
+    let mut launch = Launch::default();
+    let mut args = args.iter().enumerate();
+    while let Some((i, arg)) = args.next() {
+        match arg.as_str() {
+            "--kiosk" => launch.kiosk = true,
+            "--evaluate-selection" => launch.evaluate_selection = true,
+            "--oneshot" => launch.oneshot = true,
+            "--profile" => match args.next().and_then(|(_, name)| Profile::from_name(name)) {
+                Some(profile) => launch.profile = Some(profile),
+                None => {
+                    let names: Vec<&str> = Profile::ALL.iter().map(|profile| profile.name()).collect();
+                    return Some(Err(format!("--profile takes one of: {}\n\n{}", names.join(", "), USAGE)));
+                }
+            },
+            url => match instance::expression_from_url(url) {
+                Some(expression) => launch.expression = Some(expression),
+                None if i == 0 => return None,
+                None => {
+                    return Some(Err(format!(
+                        "'{}' cannot follow launch options; they only apply to the graphical calculator\n\n{}",
+                        url, USAGE
+                    )))
+                }
+            },
+        }
+    }
+    Some(Ok(launch))
+}
+
+/// Runs a command-line subcommand and returns the process exit code.
+pub fn run(args: &[String]) -> i32 {
+    match args[0].as_str() {
+        "fmt" => fmt(&args[1..]),
+        "inspect" => inspect(&args[1..]),
+        "bench" => bench(&args[1..]),
+        "register-url-handler" => match instance::register_url_handler() {
+            Ok(done) => {
+                println!("{}", done);
+                0
+            }
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                1
+            }
+        },
+        "selftest" => {
+            let report = selftest::run();
+            println!("{}", report);
+            if report.all_passed() { 0 } else { 1 }
+        }
+        "-h" | "--help" | "help" => {
+            println!("{}", USAGE);
+            0
+        }
+        other => {
+            eprintln!("Unknown command '{}'\n\n{}", other, USAGE);
+            2
+        }
+    }
+}
+
+fn fmt(args: &[String]) -> i32 {
+    if args.is_empty() {
+        eprintln!("{}", USAGE);
+        return 2;
+    }
+
+    match format_expr(&args.join(" ")) {
+        Ok(formatted) => {
+            println!("{}", formatted);
+            0
+        }
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            1
+        }
+    }
+}
+
+fn inspect(args: &[String]) -> i32 {
+    if args.is_empty() {
+        eprintln!("{}", USAGE);
+        return 2;
+    }
+
+    // Wrapped in a call so that a bare number is a complete expression
+    match calculate(&format!("inspect({})", args.join(" "))) {
+        Ok(value) => {
+            println!("{}", float::inspect(value));
+            0
+        }
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            1
+        }
+    }
+}
+
+fn bench(args: &[String]) -> i32 {
+    let rounds = match args {
+        [] => bench::DEFAULT_ROUNDS,
+        [rounds] => match rounds.parse::<usize>() {
+            Ok(rounds) if rounds > 0 => rounds,
+            _ => {
+                eprintln!("ROUNDS must be a whole number above 0, not '{}'", rounds);
+                return 2;
+            }
+        },
+        _ => {
+            eprintln!("{}", USAGE);
+            return 2;
+        }
+    };
+    println!("{}", bench::run(rounds));
+    0
+}
@@ -0,0 +1,129 @@
+//! sRGB colors written as `#336699`, with the relative luminance and
+//! contrast ratio of the WCAG accessibility guidelines.
+
+use std::fmt;
+
+/// An 8-bit-per-channel sRGB color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Rgb {
+    /// Parses `#RRGGBB` or the short form `#RGB`.
+    pub fn from_hex(text: &str) -> Option<Self> {
+        let digits = text.strip_prefix('#')?;
+        if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let value = u32::from_str_radix(digits, 16).ok()?;
+        match digits.len() {
+            6 => Some(Self::from_u32(value)),
+            3 => {
+                // Each digit is doubled: #369 is #336699
+                let (r, g, b) = ((value >> 8) & 0xF, (value >> 4) & 0xF, value & 0xF);
+                Some(Self::from_u32(((r * 0x11) << 16) | ((g * 0x11) << 8) | (b * 0x11)))
+            }
+            _ => None,
+        }
+    }
+
+    /// The color whose `#RRGGBB` digits are `value`, as numbers hold
+    /// colors in calculations.
+    pub fn from_value(value: f64) -> Result<Self, String> {
+        if value.fract() != 0.0 || !(0.0..=0xFF_FFFF as f64).contains(&value) {
+            return Err(format!("{} is not a color", value));
+        }
+        Ok(Self::from_u32(value as u32))
+    }
+
+    /// A color from red, green and blue channels from 0 to 255.
+    pub fn from_channels(r: f64, g: f64, b: f64) -> Result<Self, String> {
+        let channel = |value: f64, name: &str| {
+            if value.fract() == 0.0 && (0.0..=255.0).contains(&value) {
+                Ok(value as u8)
+            } else {
+                Err(format!("{} must be a whole number from 0 to 255", name))
+            }
+        };
+        Ok(Self {
+            r: channel(r, "Red")?,
+            g: channel(g, "Green")?,
+            b: channel(b, "Blue")?,
+        })
+    }
+
+    fn from_u32(value: u32) -> Self {
+        Self {
+            r: (value >> 16) as u8,
+            g: (value >> 8) as u8,
+            b: value as u8,
+        }
+    }
+
+    /// The number holding this color: its `#RRGGBB` digits as hex.
+    pub fn value(self) -> f64 {
+        (((self.r as u32) << 16) | ((self.g as u32) << 8) | self.b as u32) as f64
+    }
+
+    /// Relative luminance from 0 for black to 1 for white, per WCAG 2.
+    pub fn relative_luminance(self) -> f64 {
+        let linear = |channel: u8| {
+            let c = channel as f64 / 255.0;
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        0.2126 * linear(self.r) + 0.7152 * linear(self.g) + 0.0722 * linear(self.b)
+    }
+}
+
+impl fmt::Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02X}{:02X}{:02X}", self.r, self.g, self.b)
+    }
+}
+
+/// WCAG contrast ratio between two colors, from 1 (none) to 21 (black on
+/// white). Body text needs at least 4.5 for level AA.
+pub fn contrast_ratio(a: Rgb, b: Rgb) -> f64 {
+    let (a, b) = (a.relative_luminance(), b.relative_luminance());
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex_and_channels() {
+        let color = Rgb { r: 0x33, g: 0x66, b: 0x99 };
+        assert_eq!(Rgb::from_hex("#336699"), Some(color));
+        assert_eq!(Rgb::from_hex("#369"), Some(color));
+        assert_eq!(Rgb::from_hex("336699"), None);
+        assert_eq!(Rgb::from_hex("#33669"), None);
+        assert_eq!(Rgb::from_value(color.value()), Ok(color));
+        assert_eq!(Rgb::from_channels(51.0, 102.0, 153.0), Ok(color));
+        assert_eq!(
+            Rgb::from_channels(256.0, 0.0, 0.0),
+            Err("Red must be a whole number from 0 to 255".to_string())
+        );
+        assert_eq!(color.to_string(), "#336699");
+    }
+
+    #[test]
+    fn test_luminance_and_contrast() {
+        let black = Rgb { r: 0, g: 0, b: 0 };
+        let white = Rgb { r: 255, g: 255, b: 255 };
+        assert_eq!(black.relative_luminance(), 0.0);
+        assert_eq!(white.relative_luminance(), 1.0);
+        assert_eq!(contrast_ratio(black, white), 21.0);
+        assert_eq!(contrast_ratio(white, black), 21.0);
+        let grey = Rgb::from_hex("#777").unwrap();
+        assert!((contrast_ratio(grey, white) - 4.48).abs() < 0.01);
+    }
+}
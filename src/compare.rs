@@ -0,0 +1,75 @@
+//! Two results side by side: how far apart they are and how they relate,
+//! as when checking a new estimate against an old one.
+
+use std::fmt;
+
+use crate::format::format_number;
+
+/// How a result `b` compares with an earlier result `a`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Comparison {
+    pub a: f64,
+    pub b: f64,
+    /// `b - a`.
+    pub difference: f64,
+    /// The difference as a share of the larger of the two sizes, from 0
+    /// for equal results to 2 for opposite ones; `None` when both are 0.
+    pub relative: Option<f64>,
+    /// `b / a`; `None` when `a` is 0.
+    pub ratio: Option<f64>,
+    /// The change from `a` to `b` in percent of `a`; `None` when `a` is 0.
+    pub percent_change: Option<f64>,
+}
+
+impl Comparison {
+    pub fn new(a: f64, b: f64) -> Self {
+        let difference = b - a;
+        let size = a.abs().max(b.abs());
+        Comparison {
+            a,
+            b,
+            difference,
+            relative: (size != 0.0).then(|| difference.abs() / size),
+            ratio: (a != 0.0).then(|| b / a),
+            percent_change: (a != 0.0).then(|| difference / a.abs() * 100.0),
+        }
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let or_dash = |value: Option<f64>, suffix: &str| value.map_or("—".to_string(), |v| format!("{}{}", format_number(v), suffix));
+        writeln!(f, "Difference: {}", format_number(self.difference))?;
+        writeln!(f, "Relative difference: {}", or_dash(self.relative.map(|r| r * 100.0), "%"))?;
+        writeln!(f, "Ratio: {}", or_dash(self.ratio, ""))?;
+        let sign = if self.percent_change.is_some_and(|change| change > 0.0) { "+" } else { "" };
+        write!(f, "Change: {}{}", sign, or_dash(self.percent_change, "%"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_comparison() {
+        let comparison = Comparison::new(80.0, 100.0);
+        assert_eq!(comparison.difference, 20.0);
+        assert_eq!(comparison.relative, Some(0.2));
+        assert_eq!(comparison.ratio, Some(1.25));
+        assert_eq!(comparison.percent_change, Some(25.0));
+        assert_eq!(
+            comparison.to_string(),
+            "Difference: 20\nRelative difference: 20%\nRatio: 1.25\nChange: +25%"
+        );
+
+        // A change from a negative value is measured against its size
+        assert_eq!(Comparison::new(-4.0, -2.0).percent_change, Some(50.0));
+        assert_eq!(Comparison::new(-1.0, 1.0).relative, Some(2.0));
+
+        let from_zero = Comparison::new(0.0, 5.0);
+        assert_eq!((from_zero.ratio, from_zero.percent_change), (None, None));
+        assert_eq!(from_zero.to_string(), "Difference: 5\nRelative difference: 100%\nRatio: —\nChange: —");
+        assert_eq!(Comparison::new(0.0, 0.0).relative, None);
+    }
+}
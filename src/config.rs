@@ -0,0 +1,200 @@
+//! User configuration stored as JSON in the platform config directory.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use calculator::{EntryKey, PriceIndex, Profile, TaxRate};
+use serde::{Deserialize, Serialize};
+
+use crate::sync;
+
+/// What a keypad button does when pressed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ButtonAction {
+    /// Append the text to the input.
+    Insert(String),
+    /// Evaluate the expression with `ans` bound to the current entry (or
+    /// the last result) and replace the input with the outcome.
+    Apply(String),
+    /// Replace the current entry (or the last result) using a built-in key.
+    Key(EntryKey),
+    /// Start the exponent of the number being typed, like a calculator's
+    /// EE key.
+    Exponent,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeyButton {
+    pub label: String,
+    pub action: ButtonAction,
+    #[serde(default = "visible_by_default")]
+    pub visible: bool,
+}
+
+fn visible_by_default() -> bool {
+    true
+}
+
+impl KeyButton {
+    pub fn insert(label: &str, text: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            action: ButtonAction::Insert(text.to_string()),
+            visible: true,
+        }
+    }
+
+    /// Whether the button does something `profile` allows; buttons that do
+    /// not are hidden.
+    pub fn allowed_in(&self, profile: Profile) -> bool {
+        match &self.action {
+            ButtonAction::Key(key) => profile.allows_key(*key),
+            ButtonAction::Exponent => true,
+            ButtonAction::Insert(text) | ButtonAction::Apply(text) => profile.allows_calls() || !text.contains('('),
+        }
+    }
+
+    pub fn apply(label: &str, expression: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            action: ButtonAction::Apply(expression.to_string()),
+            visible: true,
+        }
+    }
+
+    pub fn key(key: EntryKey) -> Self {
+        Self {
+            label: key.symbol().to_string(),
+            action: ButtonAction::Key(key),
+            visible: true,
+        }
+    }
+}
+
+/// Default keypad: the four basic operators and EE followed by the entry
+/// keys.
+/// The angle conversions are there but hidden until enabled in the editor.
+pub fn default_buttons() -> Vec<KeyButton> {
+    let operators = ["+", "-", "*", "/"].iter().map(|op| KeyButton::insert(op, op));
+    let exponent = KeyButton {
+        label: "EE".to_string(),
+        action: ButtonAction::Exponent,
+        visible: true,
+    };
+    let keys = EntryKey::ALL.map(|key| KeyButton {
+        visible: !EntryKey::ANGLE_CONVERSIONS.contains(&key),
+        ..KeyButton::key(key)
+    });
+    operators.chain([exponent]).chain(keys).collect()
+}
+
+/// Functions related to a keypad button, offered when it is held down:
+/// powers with `*`, the reciprocal and square root with `/`, and so on.
+pub fn related_buttons(button: &KeyButton) -> Vec<KeyButton> {
+    let ButtonAction::Insert(text) = &button.action else {
+        return Vec::new();
+    };
+    match text.as_str() {
+        "+" => vec![KeyButton::insert("Σ", "sum("), KeyButton::key(EntryKey::Percent)],
+        "-" => vec![KeyButton::key(EntryKey::Negate)],
+        "*" => vec![KeyButton::insert("xʸ", "pow("), KeyButton::key(EntryKey::Square)],
+        "/" => vec![KeyButton::key(EntryKey::Reciprocal), KeyButton::key(EntryKey::SquareRoot)],
+        _ => Vec::new(),
+    }
+}
+
+/// When the window is laid out for fingers: larger keys, swiping left on
+/// the input to delete and holding a key for related functions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TouchLayout {
+    /// Once the screen has been touched.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl TouchLayout {
+    pub const ALL: [TouchLayout; 3] = [TouchLayout::Auto, TouchLayout::Always, TouchLayout::Never];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TouchLayout::Auto => "When touched",
+            TouchLayout::Always => "Always",
+            TouchLayout::Never => "Never",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub buttons: Vec<KeyButton>,
+    /// What the calculator may do, e.g. for an exam.
+    pub profile: Profile,
+    /// Sales tax rates by name, such as `NY`.
+    pub taxes: BTreeMap<String, TaxRate>,
+    /// Price index tables entered by country, for `inflate`.
+    pub price_indexes: BTreeMap<String, PriceIndex>,
+    /// The country whose table `inflate` uses instead of the bundled US one.
+    pub price_index: Option<String>,
+    /// Units of measure defined by the user, such as
+    /// `1 furlong = 201.168 m`, in the order they were added.
+    pub units: Vec<String>,
+    /// Whether the tour for new users has been taken or dismissed.
+    pub toured: bool,
+    /// Whether the window is drawn without the system's frame, with a title
+    /// bar of its own. Off by default since some window managers handle
+    /// frameless windows badly.
+    pub frameless: bool,
+    pub touch: TouchLayout,
+    /// Whether a comma typed where only a decimal point makes sense is
+    /// taken as one, for numpads whose decimal key types a comma.
+    pub numpad_comma: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            buttons: default_buttons(),
+            profile: Profile::default(),
+            taxes: BTreeMap::new(),
+            price_indexes: BTreeMap::new(),
+            price_index: None,
+            units: Vec::new(),
+            toured: false,
+            frameless: false,
+            touch: TouchLayout::default(),
+            numpad_comma: false,
+        }
+    }
+}
+
+fn config_path() -> Option<PathBuf> {
+    sync::data_dir().map(|dir| dir.join("config.json"))
+}
+
+/// Whether a configuration has been saved, e.g. in a newly chosen sync
+/// folder.
+pub fn exists() -> bool {
+    config_path().is_some_and(|path| path.exists())
+}
+
+/// Loads the saved configuration, falling back to the defaults when there
+/// is none or it cannot be read.
+pub fn load() -> Config {
+    config_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+pub fn save(config: &Config) -> Result<(), String> {
+    let path = config_path().ok_or("No configuration directory on this system")?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    }
+    let text = serde_json::to_string_pretty(config).map_err(|err| err.to_string())?;
+    fs::write(path, text).map_err(|err| err.to_string())
+}
@@ -0,0 +1,185 @@
+//! ISO 4217 currencies and their minor units, for money mode: results are
+//! rounded to the smallest unit a currency has, so yen have no decimals
+//! and Bahraini dinars have three.
+
+/// A currency and the number of decimals its minor unit takes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Currency {
+    /// The ISO 4217 code, e.g. `EUR`.
+    pub code: &'static str,
+    pub name: &'static str,
+    /// Decimals after the point: 2 for cents, 0 for yen, 3 for fils.
+    pub minor_units: u8,
+}
+
+const fn currency(code: &'static str, name: &'static str, minor_units: u8) -> Currency {
+    Currency { code, name, minor_units }
+}
+
+/// The currencies money mode offers, by code.
+pub const CURRENCIES: &[Currency] = &[
+    currency("AED", "UAE dirham", 2),
+    currency("ARS", "Argentine peso", 2),
+    currency("AUD", "Australian dollar", 2),
+    currency("BHD", "Bahraini dinar", 3),
+    currency("BRL", "Brazilian real", 2),
+    currency("CAD", "Canadian dollar", 2),
+    currency("CHF", "Swiss franc", 2),
+    currency("CLF", "Chilean unit of account", 4),
+    currency("CLP", "Chilean peso", 0),
+    currency("CNY", "Chinese yuan", 2),
+    currency("CZK", "Czech koruna", 2),
+    currency("DKK", "Danish krone", 2),
+    currency("EGP", "Egyptian pound", 2),
+    currency("EUR", "Euro", 2),
+    currency("GBP", "Pound sterling", 2),
+    currency("HKD", "Hong Kong dollar", 2),
+    currency("HUF", "Hungarian forint", 2),
+    currency("IDR", "Indonesian rupiah", 2),
+    currency("ILS", "Israeli new shekel", 2),
+    currency("INR", "Indian rupee", 2),
+    currency("IQD", "Iraqi dinar", 3),
+    currency("ISK", "Icelandic króna", 0),
+    currency("JOD", "Jordanian dinar", 3),
+    currency("JPY", "Japanese yen", 0),
+    currency("KRW", "South Korean won", 0),
+    currency("KWD", "Kuwaiti dinar", 3),
+    currency("LYD", "Libyan dinar", 3),
+    currency("MXN", "Mexican peso", 2),
+    currency("MYR", "Malaysian ringgit", 2),
+    currency("NOK", "Norwegian krone", 2),
+    currency("NZD", "New Zealand dollar", 2),
+    currency("OMR", "Omani rial", 3),
+    currency("PHP", "Philippine peso", 2),
+    currency("PLN", "Polish złoty", 2),
+    currency("PYG", "Paraguayan guaraní", 0),
+    currency("RUB", "Russian ruble", 2),
+    currency("SAR", "Saudi riyal", 2),
+    currency("SEK", "Swedish krona", 2),
+    currency("SGD", "Singapore dollar", 2),
+    currency("THB", "Thai baht", 2),
+    currency("TND", "Tunisian dinar", 3),
+    currency("TRY", "Turkish lira", 2),
+    currency("TWD", "New Taiwan dollar", 2),
+    currency("UAH", "Ukrainian hryvnia", 2),
+    currency("UGX", "Ugandan shilling", 0),
+    currency("USD", "US dollar", 2),
+    currency("UYW", "Uruguayan nominal wage index unit", 4),
+    currency("VND", "Vietnamese đồng", 0),
+    currency("XAF", "Central African CFA franc", 0),
+    currency("XOF", "West African CFA franc", 0),
+    currency("ZAR", "South African rand", 2),
+];
+
+impl Currency {
+    /// Looks up a currency by its code, in any case.
+    pub fn from_code(code: &str) -> Option<&'static Currency> {
+        CURRENCIES.iter().find(|currency| currency.code.eq_ignore_ascii_case(code.trim()))
+    }
+
+    /// `value` rounded to the minor unit, halves away from zero. The value
+    /// is rounded as it is written, so `1.005` becomes `1.01` even though
+    /// the nearest float to it is a little below.
+    pub fn round(&self, value: f64) -> f64 {
+        if !value.is_finite() {
+            return value;
+        }
+        let places = self.minor_units as usize;
+        let written = format!("{}", value.abs());
+        let (whole, fraction) = written.split_once('.').unwrap_or((&written, ""));
+        if fraction.len() <= places {
+            return value;
+        }
+        let mut digits: Vec<u8> = format!("{}{}", whole, &fraction[..places]).into_bytes();
+        if fraction.as_bytes()[places] >= b'5' {
+            // Carry the extra unit up through the digits
+            let mut i = digits.len();
+            loop {
+                if i == 0 {
+                    digits.insert(0, b'1');
+                    break;
+                }
+                i -= 1;
+                if digits[i] == b'9' {
+                    digits[i] = b'0';
+                } else {
+                    digits[i] += 1;
+                    break;
+                }
+            }
+        }
+        let digits = String::from_utf8(digits).unwrap_or_default();
+        let (whole, fraction) = digits.split_at(digits.len() - places);
+        let rounded: f64 = format!("{}.{}0", whole, fraction).parse().unwrap_or(value.abs());
+        rounded.copysign(value)
+    }
+
+    /// `value` rounded and written with exactly the minor unit's decimals
+    /// and the code, e.g. `1234.50 EUR` or `1235 JPY`.
+    pub fn format(&self, value: f64) -> String {
+        format!("{:.*} {}", self.minor_units as usize, self.round(value), self.code)
+    }
+
+    /// What the minor unit is called, e.g. `cents`, or a fraction such as
+    /// `thousandths` for currencies without a common English name for it.
+    pub fn minor_unit_name(&self) -> &'static str {
+        match (self.code, self.minor_units) {
+            ("GBP" | "EGP", _) => "pence",
+            ("AUD" | "CAD" | "EUR" | "HKD" | "NZD" | "SGD" | "TWD" | "USD" | "ZAR", _) => "cents",
+            ("INR", _) => "paise",
+            ("RUB", _) => "kopecks",
+            (_, 3) => "thousandths",
+            (_, 4) => "ten-thousandths",
+            _ => "hundredths",
+        }
+    }
+
+    /// An amount in whole units and minor units, such as `12 USD 34 cents`
+    /// for 12.34; `None` for a currency with no minor unit.
+    pub fn breakdown(&self, value: f64) -> Option<String> {
+        if self.minor_units == 0 || !value.is_finite() {
+            return None;
+        }
+        let scale = 10f64.powi(self.minor_units as i32);
+        let minor = (self.round(value).abs() * scale).round();
+        let sign = if value < 0.0 { "-" } else { "" };
+        Some(format!(
+            "{}{} {} {} {}",
+            sign,
+            (minor / scale).trunc(),
+            self.code,
+            minor % scale,
+            self.minor_unit_name()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_minor_units() {
+        let usd = Currency::from_code("usd").unwrap();
+        let jpy = Currency::from_code("JPY").unwrap();
+        let bhd = Currency::from_code("BHD").unwrap();
+        assert_eq!(usd.round(1.005), 1.01);
+        assert_eq!(usd.round(-2.675), -2.68);
+        assert_eq!(usd.round(9.995), 10.0);
+        assert_eq!(usd.round(0.1 + 0.2), 0.3);
+        assert_eq!(jpy.round(1234.5), 1235.0);
+        assert_eq!(jpy.round(99.4), 99.0);
+        assert_eq!(bhd.round(1.23456), 1.235);
+        assert_eq!(usd.round(f64::INFINITY), f64::INFINITY);
+
+        assert_eq!(usd.format(1234.5), "1234.50 USD");
+        assert_eq!(jpy.format(1234.5), "1235 JPY");
+        assert_eq!(bhd.format(2.0), "2.000 BHD");
+
+        assert_eq!(usd.breakdown(12.345), Some("12 USD 35 cents".to_string()));
+        assert_eq!(usd.breakdown(-12.5), Some("-12 USD 50 cents".to_string()));
+        assert_eq!(bhd.breakdown(1.5), Some("1 BHD 500 thousandths".to_string()));
+        assert_eq!(jpy.breakdown(1234.0), None);
+        assert_eq!(Currency::from_code("XYZ"), None);
+    }
+}
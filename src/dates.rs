@@ -0,0 +1,58 @@
+//! Calendar dates as Julian dates, the continuous day count astronomers
+//! and date arithmetic use.
+//!
+//! Only compiled in with the `dates` feature, which is on by default.
+
+/// Julian date of a proleptic Gregorian calendar date. A fractional `day`
+/// is the time of day in UT, so 1.5 is noon on the 1st.
+pub fn julian_date(year: f64, month: f64, day: f64) -> Result<f64, String> {
+    if year.fract() != 0.0 {
+        return Err("Year must be a whole number".to_string());
+    }
+    if month.fract() != 0.0 || !(1.0..=12.0).contains(&month) {
+        return Err("Month must be a whole number from 1 to 12".to_string());
+    }
+    if !(1.0..32.0).contains(&day) {
+        return Err("Day must be from 1 to 31".to_string());
+    }
+
+    // Meeus, Astronomical Algorithms, chapter 7
+    let (year, month) = if month <= 2.0 { (year - 1.0, month + 12.0) } else { (year, month) };
+    let century = (year / 100.0).floor();
+    let leap_correction = 2.0 - century + (century / 4.0).floor();
+    Ok((365.25 * (year + 4716.0)).floor() + (30.6001 * (month + 1.0)).floor() + day + leap_correction - 1524.5)
+}
+
+/// The calendar date of a Julian date as year, month and fractional day.
+pub fn calendar_date(jd: f64) -> Result<(f64, f64, f64), String> {
+    if !jd.is_finite() || jd < 0.0 {
+        return Err("Julian date must be a positive number".to_string());
+    }
+    let shifted = jd + 0.5;
+    let (whole, fraction) = (shifted.floor(), shifted.fract());
+    let alpha = ((whole - 1_867_216.25) / 36_524.25).floor();
+    let a = whole + 1.0 + alpha - (alpha / 4.0).floor();
+    let b = a + 1524.0;
+    let c = ((b - 122.1) / 365.25).floor();
+    let d = (365.25 * c).floor();
+    let e = ((b - d) / 30.6001).floor();
+
+    let day = b - d - (30.6001 * e).floor() + fraction;
+    let month = if e < 14.0 { e - 1.0 } else { e - 13.0 };
+    let year = if month > 2.0 { c - 4716.0 } else { c - 4715.0 };
+    Ok((year, month, day))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_julian_dates() {
+        assert_eq!(julian_date(2000.0, 1.0, 1.5), Ok(2_451_545.0));
+        assert_eq!(julian_date(1957.0, 10.0, 4.81), Ok(2_436_116.31));
+        assert_eq!(calendar_date(2_451_545.0), Ok((2000.0, 1.0, 1.5)));
+        assert_eq!(calendar_date(2_436_116.31).map(|(y, m, _)| (y, m)), Ok((1957.0, 10.0)));
+        assert!(julian_date(2000.0, 13.0, 1.0).is_err());
+    }
+}
@@ -0,0 +1,108 @@
+//! Depreciation of an asset over its useful life, straight-line or by
+//! declining balance, period by period.
+
+use crate::format::format_number;
+
+/// How the cost is spread over the periods.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Method {
+    /// The same amount every period.
+    StraightLine,
+    /// A fixed share of the remaining book value each period: `factor`
+    /// divided by the life, so a factor of 2 is double-declining balance.
+    DecliningBalance { factor: f64 },
+}
+
+/// One row of a depreciation schedule.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Period {
+    /// Counting from 1.
+    pub period: u32,
+    pub depreciation: f64,
+    /// What is left of the cost at the end of the period.
+    pub book_value: f64,
+}
+
+/// Longest life a schedule is worked out for, in periods.
+pub const MAX_LIFE: f64 = 1000.0;
+
+/// The depreciation of an asset costing `cost` down to `salvage` over
+/// `life` periods. Declining balance never goes below the salvage value.
+pub fn schedule(cost: f64, salvage: f64, life: f64, method: Method) -> Result<Vec<Period>, String> {
+    if life.fract() != 0.0 || !(1.0..=MAX_LIFE).contains(&life) {
+        return Err(format!("The life must be a whole number of periods from 1 to {}", MAX_LIFE));
+    }
+    if !(0.0..=cost).contains(&salvage) {
+        return Err("The salvage value must be between 0 and the cost".to_string());
+    }
+    let mut book_value = cost;
+    let mut periods = Vec::with_capacity(life as usize);
+    for period in 1..=life as u32 {
+        let depreciation = match method {
+            Method::StraightLine => (cost - salvage) / life,
+            Method::DecliningBalance { factor } => {
+                if factor <= 0.0 {
+                    return Err("The factor must be above 0".to_string());
+                }
+                (book_value * factor / life).min(book_value - salvage).max(0.0)
+            }
+        };
+        book_value -= depreciation;
+        periods.push(Period {
+            period,
+            depreciation,
+            book_value,
+        });
+    }
+    Ok(periods)
+}
+
+/// The depreciation in one period of a schedule.
+pub fn in_period(cost: f64, salvage: f64, life: f64, period: f64, method: Method) -> Result<f64, String> {
+    if period.fract() != 0.0 || period < 1.0 || period > life {
+        return Err("The period must be a whole number from 1 to the life".to_string());
+    }
+    let schedule = schedule(cost, salvage, life, method)?;
+    Ok(schedule[period as usize - 1].depreciation)
+}
+
+/// A schedule as comma-separated values.
+pub fn to_csv(schedule: &[Period]) -> String {
+    let mut csv = String::from("Period,Depreciation,Book value\n");
+    for row in schedule {
+        csv.push_str(&format!(
+            "{},{},{}\n",
+            row.period,
+            format_number(row.depreciation),
+            format_number(row.book_value)
+        ));
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schedules() {
+        let straight = schedule(10_000.0, 1_000.0, 3.0, Method::StraightLine).unwrap();
+        assert_eq!(straight.iter().map(|row| row.depreciation).collect::<Vec<_>>(), [3000.0; 3]);
+        assert_eq!(straight[2].book_value, 1000.0);
+
+        let declining = schedule(10_000.0, 1_000.0, 5.0, Method::DecliningBalance { factor: 2.0 }).unwrap();
+        let amounts: Vec<f64> = declining.iter().map(|row| row.depreciation).collect();
+        assert_eq!(amounts[..3], [4000.0, 2400.0, 1440.0]);
+        assert!((amounts[3] - 864.0).abs() < 1e-9 && (amounts[4] - 296.0).abs() < 1e-9);
+        assert!((declining[4].book_value - 1000.0).abs() < 1e-9);
+
+        assert_eq!(in_period(10_000.0, 1_000.0, 5.0, 2.0, Method::DecliningBalance { factor: 2.0 }), Ok(2400.0));
+        assert!(in_period(10_000.0, 1_000.0, 5.0, 6.0, Method::StraightLine).is_err());
+        assert!(schedule(10_000.0, 1_000.0, 2.5, Method::StraightLine).is_err());
+        assert!(schedule(1_000.0, 2_000.0, 5.0, Method::StraightLine).is_err());
+        assert_eq!(
+            to_csv(&straight[..1]),
+            "Period,Depreciation,Book value\n1,3000,7000\n"
+        );
+    }
+}
@@ -0,0 +1,129 @@
+//! Directives such as `@deg` or `@money=EUR` at the start of an input,
+//! which change the settings for that one calculation: `@deg sin(90)` is 1
+//! whatever the angle unit is.
+
+use crate::currency::Currency;
+use crate::eval::{AngleMode, Context, NanPolicy, OverflowMode};
+use crate::normalize::DecimalSeparator;
+
+/// The directives every input may start with, for help and error messages.
+pub const DIRECTIVES: [&str; 12] = [
+    "@rad",
+    "@deg",
+    "@grad",
+    "@point",
+    "@comma",
+    "@nan",
+    "@nan=off",
+    "@inf",
+    "@inf=off",
+    "@big",
+    "@money=EUR",
+    "@money=off",
+];
+
+/// Splits `input` into its leading directives and the expression after
+/// them: `@deg @comma sin(90)` gives `@deg @comma` and `sin(90)`.
+pub fn split(input: &str) -> (&str, &str) {
+    let input = input.trim();
+    let mut rest = input;
+    while rest.starts_with('@') {
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        rest = rest[end..].trim_start();
+    }
+    (input[..input.len() - rest.len()].trim_end(), rest)
+}
+
+/// A copy of `ctx` with `directives`, as split off by [`split`], applied.
+pub fn apply(directives: &str, ctx: &Context) -> Result<Context, String> {
+    let mut ctx = ctx.clone();
+    for directive in directives.split_whitespace() {
+        let name = directive.trim_start_matches('@');
+        let (name, value) = name.split_once('=').map_or((name, None), |(name, value)| (name, Some(value)));
+        match (name.to_lowercase().as_str(), value) {
+            ("rad", None) => ctx.angle = AngleMode::Radians,
+            ("deg", None) => ctx.angle = AngleMode::Degrees,
+            ("grad", None) => ctx.angle = AngleMode::Gradians,
+            ("point", None) => ctx.decimal_separator = DecimalSeparator::Point,
+            ("comma", None) => ctx.decimal_separator = DecimalSeparator::Comma,
+            ("nan", None) => ctx.nan = NanPolicy::Propagate,
+            ("nan", Some("off")) => ctx.nan = NanPolicy::Error,
+            ("inf", None) => ctx.overflow = OverflowMode::Infinity,
+            ("inf", Some("off")) => ctx.overflow = OverflowMode::Error,
+            ("big", None) => ctx.overflow = OverflowMode::Promote,
+            ("money", Some("off")) => ctx.currency = None,
+            ("money", Some(code)) => {
+                ctx.currency = Some(Currency::from_code(code).ok_or_else(|| format!("Unknown currency '{}'", code))?)
+            }
+            _ => {
+                return Err(format!("Unknown directive '{}'; use {}", directive, DIRECTIVES.join(", ")));
+            }
+        }
+    }
+    Ok(ctx)
+}
+
+/// The expression in `input` and the context it is calculated in: `ctx`
+/// with any leading directives applied.
+pub fn calculation<'a>(input: &'a str, ctx: &Context) -> Result<(&'a str, Context), String> {
+    let (directives, expression) = split(input);
+    Ok((expression, apply(directives, ctx)?))
+}
+
+/// The directives that bring every setting they cover to its value in
+/// `ctx`, such as `@deg @point @nan=off @inf=off @money=off`: a record of
+/// how a result was calculated that calculates it the same way again.
+pub fn describe(ctx: &Context) -> String {
+    let angle = match ctx.angle {
+        AngleMode::Radians => "@rad",
+        AngleMode::Degrees => "@deg",
+        AngleMode::Gradians => "@grad",
+    };
+    let separator = match ctx.decimal_separator {
+        DecimalSeparator::Point => "@point",
+        DecimalSeparator::Comma => "@comma",
+    };
+    let nan = match ctx.nan {
+        NanPolicy::Error => "@nan=off",
+        NanPolicy::Propagate => "@nan",
+    };
+    let overflow = match ctx.overflow {
+        OverflowMode::Error => "@inf=off",
+        OverflowMode::Infinity => "@inf",
+        OverflowMode::Promote => "@big",
+    };
+    let money = ctx.currency.map_or("off", |currency| currency.code);
+    format!("{} {} {} {} @money={}", angle, separator, nan, overflow, money)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calculate_with;
+
+    #[test]
+    fn test_directives() {
+        assert_eq!(split("@deg @comma sin(90)"), ("@deg @comma", "sin(90)"));
+        assert_eq!(split("  2 + 3 "), ("", "2 + 3"));
+        assert_eq!(split("@inf"), ("@inf", ""));
+
+        let ctx = Context::new();
+        let (expression, comma) = calculation("@comma 1,5 + 1", &ctx).unwrap();
+        assert_eq!(calculate_with(expression, &comma), Ok(2.5));
+        assert_eq!(ctx.decimal_separator, DecimalSeparator::Point);
+        let euro = apply("@money=eur", &ctx).unwrap();
+        assert_eq!(euro.currency.map(|currency| currency.code), Some("EUR"));
+        assert_eq!(apply("@inf @nan", &ctx).map(|ctx| (ctx.overflow, ctx.nan)), Ok((OverflowMode::Infinity, NanPolicy::Propagate)));
+        assert_eq!(apply("@big", &ctx).map(|ctx| ctx.overflow), Ok(OverflowMode::Promote));
+        assert_eq!(apply("@big @inf=off", &ctx).map(|ctx| ctx.overflow), Ok(OverflowMode::Error));
+
+        assert!(apply("@prec=30", &ctx).unwrap_err().starts_with("Unknown directive '@prec=30'"));
+        assert_eq!(apply("@money=XYZ", &ctx).unwrap_err(), "Unknown currency 'XYZ'");
+
+        assert_eq!(describe(&ctx), "@rad @point @nan=off @inf=off @money=off");
+        let changed = apply("@grad @comma @nan @money=JPY", &ctx).unwrap();
+        assert_eq!(describe(&changed), "@grad @comma @nan @inf=off @money=JPY");
+        let restored = apply(&describe(&ctx), &changed).unwrap();
+        assert_eq!(describe(&restored), describe(&ctx));
+    }
+}
@@ -0,0 +1,157 @@
+//! Systems of linear equations such as `2x + y = 5, x - y = 1`, read term
+//! by term and solved by Gaussian elimination.
+
+use crate::format::format_number;
+use crate::linalg;
+use crate::parser::is_identifier;
+
+/// Most unknowns a system may have.
+pub const MAX_UNKNOWNS: usize = 20;
+
+// One equation moved to the form `Σ coefficient·name = constant`
+#[derive(Debug, Default, PartialEq)]
+struct Equation {
+    terms: Vec<(String, f64)>,
+    constant: f64,
+}
+
+impl Equation {
+    fn add(&mut self, name: &str, coefficient: f64) {
+        match self.terms.iter_mut().find(|(term, _)| term == name) {
+            Some((_, sum)) => *sum += coefficient,
+            None => self.terms.push((name.to_string(), coefficient)),
+        }
+    }
+
+    fn coefficient(&self, name: &str) -> f64 {
+        self.terms.iter().find(|(term, _)| term == name).map_or(0.0, |&(_, c)| c)
+    }
+}
+
+fn parse_equation(text: &str) -> Result<Equation, String> {
+    let (left, right) = text
+        .split_once('=')
+        .ok_or_else(|| format!("'{}' is not an equation: it has no '='", text.trim()))?;
+    if right.contains('=') {
+        return Err(format!("'{}' has more than one '='", text.trim()));
+    }
+    let mut equation = Equation::default();
+    parse_side(left, 1.0, &mut equation)?;
+    parse_side(right, -1.0, &mut equation)?;
+    Ok(equation)
+}
+
+// Adds the terms of one side, times `side`, to `equation`
+fn parse_side(text: &str, side: f64, equation: &mut Equation) -> Result<(), String> {
+    let text: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    if text.is_empty() {
+        return Err("An equation has an empty side".to_string());
+    }
+    // Split before every sign but a leading one
+    let mut terms = Vec::new();
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        if (c == '+' || c == '-') && i > 0 {
+            terms.push(&text[start..i]);
+            start = i;
+        }
+    }
+    terms.push(&text[start..]);
+
+    for term in terms {
+        let (sign, rest) = match term.as_bytes().first() {
+            Some(b'-') => (-1.0, &term[1..]),
+            Some(b'+') => (1.0, &term[1..]),
+            _ => (1.0, term),
+        };
+        let digits = rest.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(rest.len());
+        let (number, name) = rest.split_at(digits);
+        let name = name.strip_prefix('*').filter(|_| !number.is_empty()).unwrap_or(name);
+        let coefficient = match number {
+            "" if name.is_empty() => return Err(format!("Missing term in '{}'", text)),
+            "" => 1.0,
+            _ => number.parse::<f64>().map_err(|_| format!("Cannot read the number '{}'", number))?,
+        } * sign
+            * side;
+        if name.is_empty() {
+            equation.constant -= coefficient;
+        } else if is_identifier(name) {
+            equation.add(name, coefficient);
+        } else {
+            return Err(format!("Cannot read the term '{}'; write terms like 2x or 3*y", term));
+        }
+    }
+    Ok(())
+}
+
+/// The value of each unknown, in the order they first appear, that solves
+/// all of `equations` at once.
+pub fn solve(equations: &[&str]) -> Result<Vec<(String, f64)>, String> {
+    let equations = equations.iter().map(|text| parse_equation(text)).collect::<Result<Vec<_>, _>>()?;
+    let mut names: Vec<&str> = Vec::new();
+    for (name, _) in equations.iter().flat_map(|equation| &equation.terms) {
+        if !names.contains(&name.as_str()) {
+            names.push(name);
+        }
+    }
+    if names.is_empty() {
+        return Err("The equations have no unknowns".to_string());
+    }
+    if names.len() > MAX_UNKNOWNS {
+        return Err(format!("A system can have at most {} unknowns", MAX_UNKNOWNS));
+    }
+    if names.len() != equations.len() {
+        return Err(format!(
+            "{} equation(s) in {} unknowns ({}) have no single solution",
+            equations.len(),
+            names.len(),
+            names.join(", ")
+        ));
+    }
+    let matrix = equations
+        .iter()
+        .map(|equation| names.iter().map(|name| equation.coefficient(name)).collect())
+        .collect();
+    let constants = equations.iter().map(|equation| equation.constant).collect();
+    let values = linalg::solve(matrix, constants)?;
+    Ok(names.into_iter().map(String::from).zip(values).collect())
+}
+
+/// A solution written as `x = 2, y = 1`.
+pub fn format_solution(solution: &[(String, f64)]) -> String {
+    let values: Vec<String> = solution
+        .iter()
+        .map(|(name, value)| format!("{} = {}", name, format_number(*value)))
+        .collect();
+    values.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_equations() {
+        let solution = solve(&["2x + y = 5", "x - y = 1"]).unwrap();
+        assert_eq!(format_solution(&solution), "x = 2, y = 1");
+
+        let solution = solve(&["a + b + c = 6", "2*b - c = 1", "c = 3 - a + 2"]).unwrap();
+        let values: Vec<f64> = solution.iter().map(|&(_, value)| value).collect();
+        assert!(values.iter().zip([4.0, 1.0, 1.0]).all(|(a, b)| (a - b).abs() < 1e-12), "{:?}", values);
+
+        assert_eq!(
+            parse_equation("0.5x - y + 2x = 3"),
+            Ok(Equation {
+                terms: vec![("x".to_string(), 2.5), ("y".to_string(), -1.0)],
+                constant: 3.0,
+            })
+        );
+        assert_eq!(
+            solve(&["x + y = 1", "2x + 2y = 3"]),
+            Err("The system is singular: it has no single solution".to_string())
+        );
+        assert!(solve(&["x + y = 1"]).unwrap_err().contains("1 equation(s) in 2 unknowns (x, y)"));
+        assert!(solve(&["x + y"]).is_err());
+        assert!(solve(&["x^2 = 4"]).is_err());
+    }
+}
@@ -0,0 +1,711 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::f64::consts::{FRAC_1_SQRT_2, PI};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::ast::{BinaryOp, Expr, Fold, Name};
+use crate::currency::Currency;
+use crate::functions::{self, Pack};
+use crate::inflation::PriceIndex;
+use crate::units::{self, CustomUnit};
+use crate::limits::Limits;
+use crate::normalize::DecimalSeparator;
+use crate::parser::Arena;
+use crate::profile::Profile;
+use crate::simplify::simplify;
+use crate::tax::TaxRate;
+
+/// Evaluation environment: the values of named variables and the settings
+/// that shape how results are computed.
+#[derive(Debug, Clone, Default)]
+pub struct Context {
+    variables: HashMap<String, f64>,
+    pub overflow: OverflowMode,
+    pub nan: NanPolicy,
+    /// Report `-0` results as `0`.
+    pub normalize_negative_zero: bool,
+    /// How numbers in the input are punctuated.
+    pub decimal_separator: DecimalSeparator,
+    /// Read simple CJK numerals such as `三百二十五` as numbers.
+    pub cjk_numerals: bool,
+    /// The unit angles are entered and shown in.
+    pub angle: AngleMode,
+    /// How long and complex an input may be.
+    pub limits: Limits,
+    /// Money mode: results are rounded to this currency's minor unit.
+    pub currency: Option<&'static Currency>,
+    /// Sales tax rates by name, for `withtax` and `withouttax`.
+    pub taxes: BTreeMap<String, TaxRate>,
+    /// The price index `inflate` uses; the bundled US one when unset.
+    pub price_index: Option<PriceIndex>,
+    /// Units of measure defined by the user, such as `furlong`.
+    pub custom_units: BTreeMap<String, CustomUnit>,
+    /// Where inputs are parsed, so that asking about a line again, or
+    /// about an edit of it, reuses what was read before; each input is
+    /// parsed afresh when unset. Clones of the context share it.
+    pub arena: Option<Arc<Mutex<Arena>>>,
+    // Function packs turned off for this context
+    disabled_packs: HashSet<Pack>,
+    // What a class or exam allows
+    profile: Profile,
+    // Set by `lock`; the packs and profile can no longer change
+    locked: bool,
+}
+
+impl Context {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Binds `name` to `value`, replacing any previous binding.
+    pub fn set(&mut self, name: &str, value: f64) {
+        self.variables.insert(name.to_string(), value);
+    }
+
+    pub fn get(&self, name: &str) -> Option<f64> {
+        self.variables.get(name).copied()
+    }
+
+    /// Removes the binding for `name`, returning its old value.
+    pub fn remove(&mut self, name: &str) -> Option<f64> {
+        self.variables.remove(name)
+    }
+
+    /// Every bound variable and its value, sorted by name.
+    pub fn variables(&self) -> Vec<(&str, f64)> {
+        let mut variables: Vec<(&str, f64)> = self.variables.iter().map(|(name, &value)| (name.as_str(), value)).collect();
+        variables.sort_by(|a, b| a.0.cmp(b.0));
+        variables
+    }
+
+    pub fn profile(&self) -> Profile {
+        self.profile
+    }
+
+    /// Limits the context to what `profile` allows, unless it is locked.
+    pub fn set_profile(&mut self, profile: Profile) {
+        if !self.locked {
+            self.profile = profile;
+        }
+    }
+
+    /// Fixes which function packs are on and the profile, for kiosk and
+    /// exam use.
+    pub fn lock(&mut self) {
+        self.locked = true;
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Whether expressions can call the functions of `pack`: it must be
+    /// compiled in, allowed by the profile and not turned off.
+    pub fn has_pack(&self, pack: Pack) -> bool {
+        pack.is_available() && self.profile.allows_pack(pack) && !self.disabled_packs.contains(&pack)
+    }
+
+    /// Turns a function pack on or off. The core pack is always on, and
+    /// nothing changes once the context is locked.
+    pub fn set_pack(&mut self, pack: Pack, enabled: bool) {
+        if self.locked {
+            return;
+        }
+        if enabled || pack == Pack::Core {
+            self.disabled_packs.remove(&pack);
+        } else {
+            self.disabled_packs.insert(pack);
+        }
+    }
+}
+
+/// What happens when a result does not fit in an f64.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum OverflowMode {
+    /// Fail with an error such as "Result is too large or too small".
+    #[default]
+    Error,
+    /// Return positive or negative infinity.
+    Infinity,
+    /// Calculate the result again with big decimals, see [`bignum`]. Float
+    /// arithmetic fails as under `Error`, and
+    /// [`calculate_promoted`](crate::calculate_promoted) takes over.
+    ///
+    /// [`bignum`]: crate::bignum
+    Promote,
+}
+
+impl OverflowMode {
+    pub const ALL: [OverflowMode; 3] = [OverflowMode::Error, OverflowMode::Infinity, OverflowMode::Promote];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            OverflowMode::Error => "Report an error",
+            OverflowMode::Infinity => "Return ±infinity",
+            OverflowMode::Promote => "Switch to big numbers",
+        }
+    }
+}
+
+/// What happens when a NaN shows up, either as a literal or as the result
+/// of an operation such as `0 / 0`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum NanPolicy {
+    /// Fail with an error.
+    #[default]
+    Error,
+    /// Let NaN flow through the calculation like IEEE 754 arithmetic does.
+    Propagate,
+}
+
+impl NanPolicy {
+    pub const ALL: [NanPolicy; 2] = [NanPolicy::Error, NanPolicy::Propagate];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            NanPolicy::Error => "Report an error",
+            NanPolicy::Propagate => "Propagate NaN",
+        }
+    }
+}
+
+/// The unit used for angles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum AngleMode {
+    #[default]
+    Radians,
+    Degrees,
+    /// 400 gradians to the turn, as used in surveying.
+    Gradians,
+}
+
+impl AngleMode {
+    pub const ALL: [AngleMode; 3] = [AngleMode::Radians, AngleMode::Degrees, AngleMode::Gradians];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AngleMode::Radians => "Radians",
+            AngleMode::Degrees => "Degrees",
+            AngleMode::Gradians => "Gradians",
+        }
+    }
+
+    /// The short form shown in the status bar, as on a calculator's display.
+    pub fn abbreviation(self) -> &'static str {
+        match self {
+            AngleMode::Radians => "RAD",
+            AngleMode::Degrees => "DEG",
+            AngleMode::Gradians => "GRAD",
+        }
+    }
+
+    /// Converts an angle in this unit to radians.
+    pub fn to_radians(self, angle: f64) -> f64 {
+        match self {
+            AngleMode::Radians => angle,
+            AngleMode::Degrees => angle.to_radians(),
+            AngleMode::Gradians => angle * PI / 200.0,
+        }
+    }
+
+    /// Converts an angle in radians to this unit.
+    pub fn from_radians(self, radians: f64) -> f64 {
+        match self {
+            AngleMode::Radians => radians,
+            AngleMode::Degrees => radians.to_degrees(),
+            AngleMode::Gradians => radians * 200.0 / PI,
+        }
+    }
+
+    /// Sine and cosine of an angle in this unit. Whole multiples of a
+    /// twelfth or an eighth of a turn, such as 30 or 45 degrees, give their
+    /// exact values, which going through radians misses: the sine of 30
+    /// degrees is 0.5 rather than 0.49999999999999994.
+    pub fn sin_cos(self, angle: f64) -> (f64, f64) {
+        const HALF_SQRT_3: f64 = 0.866_025_403_784_438_6;
+        const TWELFTHS: [f64; 12] = [
+            0.0,
+            0.5,
+            HALF_SQRT_3,
+            1.0,
+            HALF_SQRT_3,
+            0.5,
+            0.0,
+            -0.5,
+            -HALF_SQRT_3,
+            -1.0,
+            -HALF_SQRT_3,
+            -0.5,
+        ];
+        const EIGHTHS: [f64; 8] = [0.0, FRAC_1_SQRT_2, 1.0, FRAC_1_SQRT_2, 0.0, -FRAC_1_SQRT_2, -1.0, -FRAC_1_SQRT_2];
+        let turn = match self {
+            AngleMode::Radians => return angle.sin_cos(),
+            AngleMode::Degrees => 360.0,
+            AngleMode::Gradians => 400.0,
+        };
+        let angle = angle % turn;
+        let twelfths = angle * 12.0 / turn;
+        if twelfths.fract() == 0.0 {
+            let i = (twelfths as i64).rem_euclid(12) as usize;
+            return (TWELFTHS[i], TWELFTHS[(i + 3) % 12]);
+        }
+        let eighths = angle * 8.0 / turn;
+        if eighths.fract() == 0.0 {
+            let i = (eighths as i64).rem_euclid(8) as usize;
+            return (EIGHTHS[i], EIGHTHS[(i + 2) % 8]);
+        }
+        self.to_radians(angle).sin_cos()
+    }
+
+    /// Tangent of an angle in this unit, exact where [`sin_cos`](Self::sin_cos)
+    /// is; a right angle gives an infinite tangent.
+    pub fn tan(self, angle: f64) -> f64 {
+        if self == AngleMode::Radians {
+            return angle.tan();
+        }
+        let (sin, cos) = self.sin_cos(angle);
+        sin / cos
+    }
+
+    /// Converts an angle in this unit to `unit`.
+    pub fn convert(self, angle: f64, unit: AngleMode) -> f64 {
+        if self == unit {
+            return angle;
+        }
+        unit.from_radians(self.to_radians(angle))
+    }
+}
+
+/// Evaluates a parsed expression. Units are checked and then dropped; use
+/// [`evaluate_quantity`](crate::units::evaluate_quantity) to keep them.
+pub fn evaluate(expr: &Expr, ctx: &Context) -> Result<f64, String> {
+    if let Some(value) = evaluate_arithmetic(expr, ctx) {
+        return Ok(value);
+    }
+    if units::has_units(expr) {
+        return units::evaluate_quantity(expr, ctx).map(|quantity| quantity.value);
+    }
+    evaluate_number(expr, ctx)
+}
+
+// The value of a built-in constant: `π`, also written `pi`
+fn constant(name: &str) -> Option<f64> {
+    matches!(name, "π" | "pi").then_some(PI)
+}
+
+// The fast path for the common case: numbers, variables and the four
+// operators only, evaluated in a single walk with no check for units and
+// no function lookup. `None` when the expression has anything else or any
+// step fails, leaving the general way to evaluate it and word the error;
+// `bench` times the two against each other.
+pub(crate) fn evaluate_arithmetic(expr: &Expr, ctx: &Context) -> Option<f64> {
+    let value = match expr {
+        Expr::Number(n) if n.is_nan() && ctx.nan == NanPolicy::Error => return None,
+        Expr::Number(n) => *n,
+        Expr::Variable(name) => ctx.get(name).or_else(|| constant(name))?,
+        Expr::Binary { op, lhs, rhs } => {
+            apply(*op, evaluate_arithmetic(lhs, ctx)?, evaluate_arithmetic(rhs, ctx)?, ctx).ok()?
+        }
+        Expr::Negate(operand) => -evaluate_arithmetic(operand, ctx)?,
+        Expr::Call { .. } | Expr::List(_) | Expr::Quantity { .. } => return None,
+    };
+
+    if value == 0.0 && ctx.normalize_negative_zero {
+        return Some(0.0);
+    }
+    Some(value)
+}
+
+// Evaluates an expression without units.
+pub(crate) fn evaluate_number(expr: &Expr, ctx: &Context) -> Result<f64, String> {
+    let value = match expr {
+        Expr::Number(n) => {
+            if n.is_nan() && ctx.nan == NanPolicy::Error {
+                return Err("NaN is not a valid number".to_string());
+            }
+            *n
+        }
+        // A constant unless a variable of that name hides it
+        Expr::Variable(name) => ctx
+            .get(name)
+            .or_else(|| constant(name))
+            .ok_or_else(|| format!("Unknown variable '{}'", name))?,
+        Expr::Binary { op, lhs, rhs } => apply(*op, evaluate_number(lhs, ctx)?, evaluate_number(rhs, ctx)?, ctx)?,
+        Expr::Negate(operand) => -evaluate_number(operand, ctx)?,
+        Expr::Call { name, args } => check_result(call(name, args, ctx)?, ctx)?,
+        Expr::List(_) => return Err("A list of values can only be a function argument".to_string()),
+        Expr::Quantity { .. } => units::evaluate_quantity(expr, ctx)?.value,
+    };
+
+    if value == 0.0 && ctx.normalize_negative_zero {
+        return Ok(0.0);
+    }
+    Ok(value)
+}
+
+// Calls the function `name` with `args`, before the result is checked
+fn call(name: &str, args: &[Expr], ctx: &Context) -> Result<f64, String> {
+    let function = functions::find(name, ctx)?;
+    if function.takes_name() {
+        match args.split_last() {
+            Some((Expr::Variable(name), numbers)) => {
+                let numbers = numbers.iter().map(|arg| evaluate_number(arg, ctx)).collect::<Result<Vec<_>, _>>()?;
+                function.call_with_name(&numbers, name, ctx)
+            }
+            _ if function.params.len() == 1 => Err(format!("{} takes a single name", function.signature())),
+            // A number in place of the name is passed as it is
+            // written, as the rate in `withtax(100, 8.875)`
+            Some((Expr::Number(value), numbers)) => {
+                let numbers = numbers.iter().map(|arg| evaluate_number(arg, ctx)).collect::<Result<Vec<_>, _>>()?;
+                function.call_with_name(&numbers, &value.to_string(), ctx)
+            }
+            _ => Err(format!("{} takes a name as its last argument", function.signature())),
+        }
+    } else if args.iter().any(|arg| matches!(arg, Expr::List(_))) {
+        let lists = args.iter().map(|arg| evaluate_list(arg, ctx)).collect::<Result<Vec<_>, _>>()?;
+        function.call_with_lists(&lists, ctx)
+    } else {
+        let args = args.iter().map(|arg| evaluate_number(arg, ctx)).collect::<Result<Vec<_>, _>>()?;
+        function.call(&args, ctx)
+    }
+}
+
+/// Evaluates as much of `expr` as `ctx` allows.
+///
+/// Known variables are substituted and the result simplified, so
+/// `2 * (x + 3)` with `x` unbound comes back as `2 * x + 6`. When every
+/// variable is bound the expression is evaluated fully and the result is a
+/// [`Expr::Number`], or the evaluation error.
+pub fn partial_evaluate(expr: &Expr, ctx: &Context) -> Result<Expr, String> {
+    let substituted = Substitute(ctx).fold_expr(expr.clone());
+    if substituted.variables().is_empty() {
+        return evaluate(&substituted, ctx).map(Expr::Number);
+    }
+    Ok(simplify(substituted, ctx))
+}
+
+// A function argument as a list; a single value is a list of one.
+fn evaluate_list(arg: &Expr, ctx: &Context) -> Result<Vec<f64>, String> {
+    match arg {
+        Expr::List(items) => items.iter().map(|item| evaluate_number(item, ctx)).collect(),
+        other => Ok(vec![evaluate_number(other, ctx)?]),
+    }
+}
+
+/// The outermost operation of an expression and its right-hand value, so
+/// `5 + 3` gives "add 3". Pressing `=` again repeats it on the result.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LastOperation {
+    pub op: BinaryOp,
+    pub operand: f64,
+}
+
+impl LastOperation {
+    /// Finds the operation to repeat in `expr`, or `None` if it is not a
+    /// binary operation or its right-hand side cannot be evaluated.
+    pub fn of(expr: &Expr, ctx: &Context) -> Option<Self> {
+        match expr {
+            Expr::Binary { op, rhs, .. } => Some(Self {
+                op: *op,
+                operand: evaluate(rhs, ctx).ok()?,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Applies the operation again with `value` on the left.
+    pub fn repeat(self, value: f64, ctx: &Context) -> Result<f64, String> {
+        apply(self.op, value, self.operand, ctx)
+    }
+}
+
+struct Substitute<'a>(&'a Context);
+
+impl Fold for Substitute<'_> {
+    fn fold_variable(&mut self, name: Name) -> Expr {
+        match self.0.get(&name) {
+            Some(value) => Expr::Number(value),
+            None => Expr::Variable(name),
+        }
+    }
+}
+
+// The error of an operation whose result is too large for an f64, which
+// big numbers can take over
+pub(crate) const OVERFLOW: &str = "Result is too large or too small";
+
+pub(crate) fn apply(op: BinaryOp, num1: f64, num2: f64, ctx: &Context) -> Result<f64, String> {
+    if !ctx.profile().allows_operator(op) {
+        return Err(ctx.profile().forbids(&format!("The {} operator", op)));
+    }
+    let result = match op {
+        BinaryOp::Add => num1 + num2,
+        BinaryOp::Sub => num1 - num2,
+        BinaryOp::Mul => num1 * num2,
+        BinaryOp::Div => {
+            if num2 == 0.0 {
+                if num1 == 0.0 || num1.is_nan() {
+                    if ctx.nan == NanPolicy::Error {
+                        return Err("Division by zero".to_string());
+                    }
+                } else if ctx.overflow != OverflowMode::Infinity {
+                    if num1 > 0.0 {
+                        return Err("Result is too large (infinity)".to_string());
+                    } else {
+                        return Err("Result is too small (negative infinity)".to_string());
+                    }
+                }
+            }
+            num1 / num2
+        }
+        BinaryOp::Mod => {
+            // Like 0 / 0, a remainder by zero is not a number
+            if num2 == 0.0 && ctx.nan == NanPolicy::Error {
+                return Err("Modulo by zero".to_string());
+            }
+            // Floored, so the remainder takes the sign of the divisor as in
+            // spreadsheets: -7 % 3 is 2
+            let remainder = num1 % num2;
+            if remainder != 0.0 && (remainder < 0.0) != (num2 < 0.0) {
+                remainder + num2
+            } else {
+                remainder
+            }
+        }
+        BinaryOp::Pow => num1.powf(num2),
+    };
+    check_result(result, ctx)
+}
+
+/// Holds the result of an operator or function to the overflow setting and
+/// NaN policy of `ctx`.
+pub(crate) fn check_result(result: f64, ctx: &Context) -> Result<f64, String> {
+    if result.is_infinite() && ctx.overflow != OverflowMode::Infinity {
+        return Err(OVERFLOW.to_string());
+    }
+    if result.is_nan() && ctx.nan == NanPolicy::Error {
+        return Err("Result is not a number".to_string());
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn var(name: &str) -> Expr {
+        Expr::variable(name)
+    }
+
+    #[test]
+    fn test_evaluate_variables() {
+        let mut ctx = Context::new();
+        let expr = Expr::binary(BinaryOp::Mul, var("x"), Expr::Number(2.0));
+        assert_eq!(evaluate(&expr, &ctx), Err("Unknown variable 'x'".to_string()));
+
+        ctx.set("x", 4.0);
+        assert_eq!(evaluate(&expr, &ctx), Ok(8.0));
+
+        ctx.set("a", 1.0);
+        assert_eq!(ctx.variables(), vec![("a", 1.0), ("x", 4.0)]);
+    }
+
+    #[test]
+    fn test_overflow_mode() {
+        let overflowing = Expr::binary(BinaryOp::Mul, Expr::Number(1e300), Expr::Number(-1e300));
+        let by_zero = Expr::binary(BinaryOp::Div, Expr::Number(5.0), Expr::Number(0.0));
+        let undefined = Expr::binary(BinaryOp::Div, Expr::Number(0.0), Expr::Number(0.0));
+
+        let mut ctx = Context::new();
+        assert_eq!(evaluate(&overflowing, &ctx), Err("Result is too large or too small".to_string()));
+        assert_eq!(evaluate(&by_zero, &ctx), Err("Result is too large (infinity)".to_string()));
+
+        ctx.overflow = OverflowMode::Infinity;
+        assert_eq!(evaluate(&overflowing, &ctx), Ok(f64::NEG_INFINITY));
+        assert_eq!(evaluate(&by_zero, &ctx), Ok(f64::INFINITY));
+        assert_eq!(evaluate(&undefined, &ctx), Err("Division by zero".to_string()));
+    }
+
+    #[test]
+    fn test_nan_policy() {
+        let nan_literal = Expr::binary(BinaryOp::Add, Expr::Number(f64::NAN), Expr::Number(1.0));
+        let undefined = Expr::binary(BinaryOp::Div, Expr::Number(0.0), Expr::Number(0.0));
+        let remainder = Expr::binary(BinaryOp::Mod, Expr::Number(7.0), Expr::Number(0.0));
+
+        let mut ctx = Context::new();
+        assert_eq!(evaluate(&nan_literal, &ctx), Err("NaN is not a valid number".to_string()));
+        assert_eq!(evaluate(&undefined, &ctx), Err("Division by zero".to_string()));
+        assert_eq!(evaluate(&remainder, &ctx), Err("Modulo by zero".to_string()));
+
+        ctx.nan = NanPolicy::Propagate;
+        assert!(evaluate(&nan_literal, &ctx).unwrap().is_nan());
+        assert!(evaluate(&undefined, &ctx).unwrap().is_nan());
+        assert!(evaluate(&remainder, &ctx).unwrap().is_nan());
+
+        ctx.overflow = OverflowMode::Infinity;
+        let inf_minus_inf = Expr::binary(BinaryOp::Sub, Expr::Number(f64::INFINITY), Expr::Number(f64::INFINITY));
+        assert!(evaluate(&inf_minus_inf, &ctx).unwrap().is_nan());
+        ctx.nan = NanPolicy::Error;
+        assert_eq!(evaluate(&inf_minus_inf, &ctx), Err("Result is not a number".to_string()));
+    }
+
+    #[test]
+    fn test_nan_policy_covers_calls() {
+        let third = Expr::binary(BinaryOp::Div, Expr::Number(1.0), Expr::Number(3.0));
+        let call = Expr::call("pow", vec![Expr::Number(-8.0), third.clone()]);
+        let operator = Expr::binary(BinaryOp::Pow, Expr::Number(-8.0), third);
+
+        let mut ctx = Context::new();
+        assert_eq!(evaluate(&call, &ctx), Err("Result is not a number".to_string()));
+        assert_eq!(evaluate(&call, &ctx), evaluate(&operator, &ctx));
+
+        ctx.nan = NanPolicy::Propagate;
+        assert!(evaluate(&call, &ctx).unwrap().is_nan());
+        assert!(evaluate(&operator, &ctx).unwrap().is_nan());
+    }
+
+    #[test]
+    fn test_negative_zero_normalization() {
+        let expr = Expr::binary(BinaryOp::Mul, Expr::Number(0.0), Expr::Number(-5.0));
+
+        let mut ctx = Context::new();
+        assert!(evaluate(&expr, &ctx).unwrap().is_sign_negative());
+
+        ctx.normalize_negative_zero = true;
+        assert!(evaluate(&expr, &ctx).unwrap().is_sign_positive());
+    }
+
+    #[test]
+    fn test_arithmetic_fast_path_matches_generic() {
+        let mut ctx = Context::new();
+        ctx.set("x", 4.0);
+        let plain = [
+            Expr::binary(BinaryOp::Div, var("x"), Expr::binary(BinaryOp::Sub, Expr::Number(2.0), var("π"))),
+            Expr::binary(BinaryOp::Mul, Expr::Number(0.0), Expr::Number(-5.0)),
+        ];
+        for expr in &plain {
+            let fast = evaluate_arithmetic(expr, &ctx).unwrap();
+            assert_eq!(Ok(fast.to_bits()), evaluate_number(expr, &ctx).map(f64::to_bits));
+        }
+
+        // Errors, calls and units are left to the general way
+        let undefined = Expr::binary(BinaryOp::Div, Expr::Number(0.0), Expr::Number(0.0));
+        let unknown = Expr::binary(BinaryOp::Add, var("y"), Expr::Number(1.0));
+        let with_call = Expr::binary(BinaryOp::Add, var("x"), Expr::call("ulps", vec![Expr::Number(1.0)]));
+        let with_unit = Expr::binary(BinaryOp::Add, var("x"), Expr::quantity(Expr::Number(1.0), "m"));
+        for expr in [undefined, unknown, with_call, with_unit] {
+            assert_eq!(evaluate_arithmetic(&expr, &ctx), None);
+        }
+    }
+
+    #[test]
+    fn test_evaluate_calls() {
+        let ctx = Context::new();
+        let next = Expr::binary(BinaryOp::Add, Expr::Number(1.0), Expr::Number(f64::EPSILON));
+        let call = Expr::call("ulps", vec![Expr::Number(1.0), next]);
+        assert_eq!(evaluate(&call, &ctx), Ok(1.0));
+        assert_eq!(
+            evaluate(&Expr::call("nope", vec![]), &ctx),
+            Err("Unknown function 'nope'".to_string())
+        );
+    }
+
+    #[test]
+    fn test_last_operation_repeats() {
+        // 5 + 3 * 2
+        let expr = Expr::binary(
+            BinaryOp::Add,
+            Expr::Number(5.0),
+            Expr::binary(BinaryOp::Mul, Expr::Number(3.0), Expr::Number(2.0)),
+        );
+        let ctx = Context::new();
+        let last = LastOperation::of(&expr, &ctx).unwrap();
+        assert_eq!(last, LastOperation { op: BinaryOp::Add, operand: 6.0 });
+        assert_eq!(last.repeat(11.0, &ctx), Ok(17.0));
+
+        assert_eq!(LastOperation::of(&Expr::Number(4.0), &ctx), None);
+        assert_eq!(LastOperation::of(&Expr::binary(BinaryOp::Sub, var("x"), var("y")), &ctx), None);
+    }
+
+    #[test]
+    fn test_angle_mode_conversions() {
+        assert_eq!(AngleMode::Degrees.convert(180.0, AngleMode::Gradians), 200.0);
+        assert_eq!(AngleMode::Gradians.convert(100.0, AngleMode::Degrees), 90.0);
+        assert_eq!(AngleMode::Gradians.to_radians(200.0), PI);
+        assert_eq!(AngleMode::Radians.convert(PI, AngleMode::Degrees), 180.0);
+        assert_eq!(AngleMode::Degrees.convert(33.0, AngleMode::Degrees), 33.0);
+    }
+
+    #[test]
+    fn test_exact_angles() {
+        assert_eq!(AngleMode::Degrees.sin_cos(30.0).0, 0.5);
+        assert_eq!(AngleMode::Degrees.sin_cos(60.0).1, 0.5);
+        assert_eq!(AngleMode::Degrees.tan(45.0), 1.0);
+        assert_eq!(AngleMode::Degrees.sin_cos(-750.0), (-0.5, AngleMode::Degrees.sin_cos(30.0).1));
+        assert_eq!(AngleMode::Degrees.sin_cos(180.0), (0.0, -1.0));
+        assert_eq!(AngleMode::Degrees.tan(90.0), f64::INFINITY);
+        assert_eq!(AngleMode::Gradians.sin_cos(50.0), (FRAC_1_SQRT_2, FRAC_1_SQRT_2));
+        assert_eq!(AngleMode::Degrees.sin_cos(1.0), 1f64.to_radians().sin_cos());
+        assert_eq!(AngleMode::Radians.tan(0.5), 0.5f64.tan());
+    }
+
+    #[test]
+    fn test_partial_evaluate_returns_residual() {
+        // 2 * (x + 3)
+        let expr = Expr::binary(
+            BinaryOp::Mul,
+            Expr::Number(2.0),
+            Expr::binary(BinaryOp::Add, var("x"), Expr::Number(3.0)),
+        );
+        let mut ctx = Context::new();
+        let residual = partial_evaluate(&expr, &ctx).unwrap();
+        assert_eq!(residual.to_string(), "2 * x + 6");
+
+        ctx.set("x", 1.0);
+        assert_eq!(partial_evaluate(&expr, &ctx), Ok(Expr::Number(8.0)));
+    }
+
+    #[test]
+    fn test_partial_evaluate_substitutes_known_variables() {
+        // (x + y) - y
+        let expr = Expr::binary(
+            BinaryOp::Sub,
+            Expr::binary(BinaryOp::Add, var("x"), var("y")),
+            var("y"),
+        );
+        let mut ctx = Context::new();
+        assert_eq!(partial_evaluate(&expr, &ctx), Ok(var("x")));
+
+        ctx.set("y", 2.0);
+        assert_eq!(partial_evaluate(&expr, &ctx), Ok(var("x")));
+
+        ctx.remove("y");
+        ctx.set("x", 5.0);
+        assert_eq!(partial_evaluate(&expr, &ctx).unwrap().to_string(), "5");
+    }
+
+    #[test]
+    fn test_partial_evaluate_folds_with_the_context() {
+        let residual = |input: &str, ctx: &Context| partial_evaluate(&crate::parse(input).unwrap(), ctx).map(|e| e.to_string());
+
+        // What the profile refuses to calculate is not folded either
+        let mut ctx = Context::new();
+        ctx.set_profile(Profile::FourFunction);
+        assert_eq!(residual("sqrt(16) * y", &ctx), Ok("sqrt(16) * y".to_string()));
+        assert_eq!(residual("2^3 * y", &ctx), Ok("2 ^ 3 * y".to_string()));
+        assert_eq!(residual("2 * 3 * y", &ctx), Ok("6 * y".to_string()));
+
+        #[cfg(feature = "scientific")]
+        {
+            let mut ctx = Context::new();
+            ctx.angle = AngleMode::Degrees;
+            assert_eq!(residual("sin(90) * y", &ctx), Ok("y".to_string()));
+        }
+
+        let mut ctx = Context::new();
+        assert_eq!(residual("1e308 * 10 * y", &ctx), Ok("1e308 * 10 * y".to_string()));
+        ctx.overflow = OverflowMode::Infinity;
+        assert_eq!(residual("1e308 * 10 * y", &ctx), Ok("inf * y".to_string()));
+    }
+}
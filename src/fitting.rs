@@ -0,0 +1,185 @@
+//! Least-squares curve fitting over paired lists of numbers: polynomials
+//! up to degree 5 and exponentials, with the coefficient of determination
+//! telling how well the curve follows the data.
+
+use std::fmt;
+
+use crate::format::format_number;
+use crate::linalg;
+
+/// Highest polynomial degree fitted; the normal equations of higher ones
+/// lose too much precision.
+pub const MAX_DEGREE: usize = 5;
+
+/// The shape of curve to fit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Model {
+    /// `c₀ + c₁x + … + cₙxⁿ` of the given degree.
+    Polynomial(usize),
+    /// `a·e^(bx)`, fitted as a straight line through `ln y`.
+    Exponential,
+}
+
+impl Model {
+    /// The model named `linear`, `poly2` to `poly5`, or `exp`.
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        let degree = match name {
+            "linear" => Some(1),
+            "exp" => return Ok(Model::Exponential),
+            _ => name.strip_prefix("poly").and_then(|degree| degree.parse::<usize>().ok()),
+        };
+        match degree {
+            Some(degree) if (1..=MAX_DEGREE).contains(&degree) => Ok(Model::Polynomial(degree)),
+            _ => Err(format!(
+                "Unknown model '{}'; try linear, poly2 to poly{} or exp",
+                name, MAX_DEGREE
+            )),
+        }
+    }
+
+    fn parameters(self) -> usize {
+        match self {
+            Model::Polynomial(degree) => degree + 1,
+            Model::Exponential => 2,
+        }
+    }
+}
+
+/// A curve fitted to points.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fit {
+    pub points: Vec<(f64, f64)>,
+    pub model: Model,
+    /// For a polynomial the coefficients from the constant up; for an
+    /// exponential `a` and `b`.
+    pub coefficients: Vec<f64>,
+    /// The share of the variance in y the curve accounts for, 1 at best.
+    pub r_squared: f64,
+}
+
+impl Fit {
+    /// The curve's value at `x`.
+    pub fn at(&self, x: f64) -> f64 {
+        match self.model {
+            Model::Polynomial(_) => self.coefficients.iter().rev().fold(0.0, |sum, c| sum * x + c),
+            Model::Exponential => self.coefficients[0] * (self.coefficients[1] * x).exp(),
+        }
+    }
+}
+
+impl fmt::Display for Fit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.model {
+            Model::Polynomial(_) => {
+                write!(f, "y =")?;
+                let last = self.coefficients.len() - 1;
+                for (power, &c) in self.coefficients.iter().enumerate().rev() {
+                    let sign = match (power == last, c < 0.0) {
+                        (true, false) => " ",
+                        (true, true) => " -",
+                        (false, false) => " + ",
+                        (false, true) => " - ",
+                    };
+                    let term = ["", "x", "x²", "x³", "x⁴", "x⁵"][power];
+                    write!(f, "{}{}{}", sign, format_number(c.abs()), term)?;
+                }
+            }
+            Model::Exponential => write!(
+                f,
+                "y = {}·e^({}x)",
+                format_number(self.coefficients[0]),
+                format_number(self.coefficients[1])
+            )?,
+        }
+        write!(f, ", R² = {}", format_number(self.r_squared))
+    }
+}
+
+/// Fits `model` to the points `(xs[i], ys[i])`.
+pub fn fit(xs: &[f64], ys: &[f64], model: Model) -> Result<Fit, String> {
+    if xs.len() != ys.len() {
+        return Err(format!("There are {} x values but {} y values", xs.len(), ys.len()));
+    }
+    if xs.len() < model.parameters() {
+        return Err(format!("This fit needs at least {} points", model.parameters()));
+    }
+    let coefficients = match model {
+        Model::Polynomial(degree) => polynomial(xs, ys, degree)?,
+        Model::Exponential => {
+            if ys.iter().any(|&y| y <= 0.0) {
+                return Err("An exponential fit needs every y above 0".to_string());
+            }
+            let logs: Vec<f64> = ys.iter().map(|y| y.ln()).collect();
+            let line = polynomial(xs, &logs, 1)?;
+            vec![line[0].exp(), line[1]]
+        }
+    };
+    let mut fit = Fit {
+        points: xs.iter().copied().zip(ys.iter().copied()).collect(),
+        model,
+        coefficients,
+        r_squared: 0.0,
+    };
+    let mean = ys.iter().sum::<f64>() / ys.len() as f64;
+    let total: f64 = ys.iter().map(|y| (y - mean).powi(2)).sum();
+    let residual: f64 = xs.iter().zip(ys).map(|(&x, y)| (y - fit.at(x)).powi(2)).sum();
+    fit.r_squared = if total == 0.0 { 1.0 } else { 1.0 - residual / total };
+    Ok(fit)
+}
+
+// The least-squares polynomial coefficients, from the normal equations
+fn polynomial(xs: &[f64], ys: &[f64], degree: usize) -> Result<Vec<f64>, String> {
+    let n = degree + 1;
+    let mut powers = vec![0.0; 2 * n - 1];
+    let mut rhs = vec![0.0; n];
+    for (&x, &y) in xs.iter().zip(ys) {
+        let mut power = 1.0;
+        for (k, sum) in powers.iter_mut().enumerate() {
+            *sum += power;
+            if k < n {
+                rhs[k] += power * y;
+            }
+            power *= x;
+        }
+    }
+    let matrix = (0..n).map(|row| powers[row..row + n].to_vec()).collect();
+    linalg::solve(matrix, rhs).map_err(|_| "The x values are too few or too alike for this fit".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        assert!(
+            actual.iter().zip(expected).all(|(a, b)| (a - b).abs() < 1e-9),
+            "{:?} is not {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_fits() {
+        let xs = [0.0, 1.0, 2.0, 3.0, 4.0];
+        let quadratic: Vec<f64> = xs.iter().map(|x| 1.0 - 2.0 * x + 0.5 * x * x).collect();
+        let curve = fit(&xs, &quadratic, Model::from_name("poly2").unwrap()).unwrap();
+        assert_close(&curve.coefficients, &[1.0, -2.0, 0.5]);
+        assert!((curve.r_squared - 1.0).abs() < 1e-12);
+        assert!((curve.at(10.0) - 31.0).abs() < 1e-9);
+
+        let growth: Vec<f64> = xs.iter().map(|x| 3.0 * (0.5 * x).exp()).collect();
+        let exponential = fit(&xs, &growth, Model::Exponential).unwrap();
+        assert_close(&exponential.coefficients, &[3.0, 0.5]);
+
+        let line = fit(&[1.0, 2.0, 3.0], &[1.0, 3.0, 2.0], Model::Polynomial(1)).unwrap();
+        assert_close(&line.coefficients, &[1.0, 0.5]);
+        assert!((line.r_squared - 0.25).abs() < 1e-12);
+        assert_eq!(line.to_string(), "y = 0.5x + 1, R² = 0.25");
+
+        assert!(fit(&[1.0, 2.0], &[1.0, 2.0], Model::Polynomial(2)).is_err());
+        assert!(fit(&[1.0, 1.0, 1.0], &[1.0, 2.0, 3.0], Model::Polynomial(1)).is_err());
+        assert!(fit(&xs, &[1.0, -1.0, 1.0, 1.0, 1.0], Model::Exponential).is_err());
+        assert!(Model::from_name("poly9").is_err());
+    }
+}
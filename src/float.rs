@@ -0,0 +1,119 @@
+//! Floating-point comparison helpers.
+
+/// The difference between `a` and `b` relative to the larger magnitude,
+/// or the absolute difference when both are zero.
+pub fn relative_difference(a: f64, b: f64) -> f64 {
+    let diff = (a - b).abs();
+    let max = a.abs().max(b.abs());
+    if max > 0.0 {
+        diff / max
+    } else {
+        diff
+    }
+}
+
+/// Whether `a` and `b` agree to within the relative tolerance `tol`.
+pub fn approx_eq(a: f64, b: f64, tol: f64) -> bool {
+    a == b || relative_difference(a, b) <= tol
+}
+
+/// How many representable f64 values lie between `a` and `b`: 0 when they
+/// are equal, 1 when they are neighbors. `None` if either is NaN.
+pub fn ulps(a: f64, b: f64) -> Option<u64> {
+    if a.is_nan() || b.is_nan() {
+        return None;
+    }
+    Some(ordered_bits(a).abs_diff(ordered_bits(b)))
+}
+
+/// A multi-line breakdown of the IEEE 754 encoding of `value`: sign,
+/// exponent and mantissa fields, the raw bits, and its nearest neighbors.
+pub fn inspect(value: f64) -> String {
+    let bits = value.to_bits();
+    let sign = bits >> 63;
+    let exponent = (bits >> 52) & 0x7ff;
+    let mantissa = bits & 0x000f_ffff_ffff_ffff;
+
+    let class = if value.is_nan() {
+        "NaN"
+    } else if value.is_infinite() {
+        "infinite"
+    } else if value == 0.0 {
+        "zero"
+    } else if value.is_subnormal() {
+        "subnormal"
+    } else {
+        "normal"
+    };
+    // Subnormals share the smallest exponent, without the implicit 1
+    let unbiased = match exponent {
+        0 if mantissa == 0 => "".to_string(),
+        0 => " (2^-1022)".to_string(),
+        0x7ff => "".to_string(),
+        _ => format!(" (2^{})", exponent as i64 - 1023),
+    };
+
+    let mut lines = vec![
+        format!("value:     {:?}", value),
+        format!("class:     {}", class),
+        format!("sign:      {} ({})", sign, if sign == 1 { "-" } else { "+" }),
+        format!("exponent:  0x{:03x}{}", exponent, unbiased),
+        format!("mantissa:  0x{:013x}", mantissa),
+        format!("bits:      {} {:011b} {:052b}", sign, exponent, mantissa),
+        format!("hex:       0x{:016X}", bits),
+    ];
+    if !value.is_nan() {
+        lines.push(format!("previous:  {:?}", value.next_down()));
+        lines.push(format!("next:      {:?}", value.next_up()));
+    }
+    lines.join("\n")
+}
+
+// Maps the bit pattern of a float onto integers that sort the same way the
+// floats do, with -0 and +0 both at zero.
+fn ordered_bits(value: f64) -> i64 {
+    let bits = value.to_bits() as i64;
+    if bits < 0 {
+        i64::MIN - bits
+    } else {
+        bits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_approx_eq() {
+        assert!(approx_eq(0.1 + 0.2, 0.3, 1e-15));
+        assert!(!approx_eq(0.1 + 0.2, 0.3, 1e-17));
+        assert!(approx_eq(100.0, 101.0, 0.01));
+        assert!(approx_eq(f64::INFINITY, f64::INFINITY, 0.0));
+        assert!(!approx_eq(f64::NAN, f64::NAN, 1.0));
+    }
+
+    #[test]
+    fn test_inspect() {
+        let report = inspect(0.1);
+        assert!(report.contains("class:     normal"));
+        assert!(report.contains("exponent:  0x3fb (2^-4)"));
+        assert!(report.contains("hex:       0x3FB999999999999A"));
+        assert!(report.contains("previous:  0.09999999999999999"));
+        assert!(report.contains("next:      0.10000000000000002"));
+
+        assert!(inspect(-0.0).contains("sign:      1 (-)"));
+        assert!(inspect(f64::from_bits(1)).contains("class:     subnormal"));
+        assert!(!inspect(f64::NAN).contains("next:"));
+    }
+
+    #[test]
+    fn test_ulps() {
+        assert_eq!(ulps(1.0, 1.0), Some(0));
+        assert_eq!(ulps(1.0, 1.0 + f64::EPSILON), Some(1));
+        assert_eq!(ulps(0.1 + 0.2, 0.3), Some(1));
+        assert_eq!(ulps(-0.0, 0.0), Some(0));
+        assert_eq!(ulps(-f64::from_bits(1), f64::from_bits(1)), Some(2));
+        assert_eq!(ulps(f64::NAN, 1.0), None);
+    }
+}
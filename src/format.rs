@@ -0,0 +1,400 @@
+//! Canonical text rendering of expressions.
+
+use std::fmt;
+
+use crate::ast::{BinaryOp, Expr};
+use crate::color::Rgb;
+use crate::imperial::{Length, PRECISION};
+use crate::eval::Context;
+use crate::normalize::{normalize, DecimalSeparator};
+use crate::parser::{is_identifier, parse};
+use crate::units::Unit;
+
+/// Reformats `input` with normalized spacing, parentheses, and numbers,
+/// e.g. `5+3` becomes `5 + 3` and `1.5e3` becomes `1500`. The input is
+/// read as the calculator reads it, so `3 × 4`, `１２＋３` and `5²` are
+/// understood.
+pub fn format_expr(input: &str) -> Result<String, String> {
+    Ok(read(input)?.to_string())
+}
+
+/// Like [`format_expr`], but writes whole-number powers as superscripts,
+/// e.g. `x^2 + 1` becomes `x² + 1`.
+pub fn format_pretty(input: &str) -> Result<String, String> {
+    Ok(format!("{:#}", read(input)?))
+}
+
+// Parses `input` as the calculator does with its default settings
+fn read(input: &str) -> Result<Expr, String> {
+    let ctx = Context::default();
+    ctx.limits.check_input(input)?;
+    parse(&normalize(input, &ctx))
+}
+
+/// Formats a number the way [`format_expr`] writes literals: plain decimal
+/// notation for everyday magnitudes, scientific notation for very large or
+/// very small values.
+///
+/// The digits are the fewest that read back as the same value, and the
+/// layout is decided here rather than left to `{}`, so a result reads the
+/// same on every platform and in every locale: a `.` for the point, no
+/// grouping, `e` with no `+` for the exponent, `inf` and `NaN` for the
+/// values that are not numbers.
+pub fn format_number(value: f64) -> String {
+    if !value.is_finite() {
+        return if value.is_nan() { "NaN" } else if value > 0.0 { "inf" } else { "-inf" }.to_string();
+    }
+    let sign = if value.is_sign_negative() { "-" } else { "" };
+    if value == 0.0 {
+        return format!("{}0", sign);
+    }
+
+    let (digits, exponent) = shortest_digits(value);
+    if !(-6..16).contains(&exponent) {
+        let (first, rest) = digits.split_at(1);
+        let point = if rest.is_empty() { "" } else { "." };
+        return format!("{}{}{}{}e{}", sign, first, point, rest, exponent);
+    }
+    if exponent < 0 {
+        return format!("{}0.{}{}", sign, "0".repeat((-exponent - 1) as usize), digits);
+    }
+    let whole = exponent as usize + 1;
+    if digits.len() <= whole {
+        format!("{}{}{}", sign, digits, "0".repeat(whole - digits.len()))
+    } else {
+        format!("{}{}.{}", sign, &digits[..whole], &digits[whole..])
+    }
+}
+
+/// Like [`format_number`], with `separator` for the decimal point: the
+/// text to put in the input or on the clipboard for `value`. Typed or
+/// pasted into an input read with that separator, any finite value reads
+/// back as exactly itself, to the last bit.
+pub fn format_for_input(value: f64, separator: DecimalSeparator) -> String {
+    match separator {
+        DecimalSeparator::Point => format_number(value),
+        DecimalSeparator::Comma => format_number(value).replace('.', ","),
+    }
+}
+
+/// `value` as LaTeX, with a power of ten in place of the `e` of
+/// scientific notation: `1.5e-7` becomes `1.5 \times 10^{-7}`.
+pub fn format_latex(value: f64) -> String {
+    if value.is_nan() {
+        return "\\mathrm{NaN}".to_string();
+    }
+    if value.is_infinite() {
+        return if value > 0.0 { "\\infty" } else { "-\\infty" }.to_string();
+    }
+    let text = format_number(value);
+    match text.split_once('e') {
+        Some((mantissa, exponent)) => format!("{} \\times 10^{{{}}}", mantissa, exponent),
+        None => text,
+    }
+}
+
+/// A whole number in hexadecimal, octal and binary, each with its prefix
+/// and the base's name, or `None` for a value with a fraction or beyond
+/// the whole numbers an f64 holds exactly.
+pub fn format_bases(value: f64) -> Option<[(&'static str, String); 3]> {
+    if value.fract() != 0.0 || value.abs() > 2f64.powi(53) {
+        return None;
+    }
+    let sign = if value < 0.0 { "-" } else { "" };
+    let whole = value.abs() as u64;
+    Some([
+        ("Hexadecimal", format!("{}0x{:X}", sign, whole)),
+        ("Octal", format!("{}0o{:o}", sign, whole)),
+        ("Binary", format!("{}0b{:b}", sign, whole)),
+    ])
+}
+
+// The shortest run of significant digits that reads back as `value`, which
+// must be finite and not zero, and the power of ten of the first: 0.015
+// gives `15` and -2. Finding them is left to the standard library, whose
+// algorithm is the same everywhere; only the layout is ours.
+fn shortest_digits(value: f64) -> (String, i32) {
+    let scientific = format!("{:e}", value.abs());
+    let (mantissa, exponent) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+    (mantissa.replace('.', ""), exponent.parse().unwrap_or(0))
+}
+
+/// The alternate form, `{:#}`, writes whole-number powers as superscripts.
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Number(n) => write!(f, "{}", format_number(*n)),
+            Expr::Variable(name) if is_identifier(name) => write!(f, "{}", name),
+            Expr::Variable(name) => write!(f, "\"{}\"", name),
+            Expr::Binary { op: BinaryOp::Pow, lhs, rhs } if f.alternate() && superscript(rhs).is_some() => {
+                // Only a bare number, name or call can carry the exponent
+                let bare = match lhs.as_ref() {
+                    Expr::Number(n) => *n >= 0.0,
+                    Expr::Variable(_) | Expr::Call { .. } => true,
+                    _ => false,
+                };
+                if bare {
+                    write_child(f, lhs)?;
+                } else {
+                    write!(f, "(")?;
+                    write_child(f, lhs)?;
+                    write!(f, ")")?;
+                }
+                write!(f, "{}", superscript(rhs).unwrap_or_default())
+            }
+            Expr::Binary { op, lhs, rhs } => {
+                write_operand(f, lhs, *op, false)?;
+                write!(f, " {} ", op)?;
+                write_operand(f, rhs, *op, true)
+            }
+            Expr::Negate(operand) => {
+                if negation_needs_parens(operand) {
+                    write!(f, "-(")?;
+                    write_child(f, operand)?;
+                    write!(f, ")")
+                } else {
+                    write!(f, "-")?;
+                    write_child(f, operand)
+                }
+            }
+            Expr::Call { name, args } => {
+                // Lists are separated from each other by semicolons
+                let separator = if args.iter().any(|arg| matches!(arg, Expr::List(_))) { "; " } else { ", " };
+                write!(f, "{}(", name)?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "{}", separator)?;
+                    }
+                    write_child(f, arg)?;
+                }
+                write!(f, ")")
+            }
+            Expr::Quantity { value, unit } => match (value.as_ref(), Unit::from_name(unit)) {
+                // Lengths the feet-and-inches form shows exactly
+                (Expr::Number(inches), Some(Unit::Inches)) if (inches * PRECISION as f64).fract() == 0.0 => {
+                    write!(f, "{}", Length::from_inches(*inches))
+                }
+                (_, Some(Unit::Inches)) => write!(f, "{} in", value),
+                (Expr::Number(number), Some(Unit::Color)) => match Rgb::from_value(*number) {
+                    Ok(color) => write!(f, "{}", color),
+                    Err(_) => write!(f, "{} {}", value, unit),
+                },
+                _ => write!(f, "{} {}", value, unit),
+            },
+            Expr::List(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write_child(f, item)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+// Writes a child node in the same form, plain or alternate, as its parent
+fn write_child(f: &mut fmt::Formatter<'_>, child: &Expr) -> fmt::Result {
+    if f.alternate() {
+        write!(f, "{:#}", child)
+    } else {
+        write!(f, "{}", child)
+    }
+}
+
+fn write_operand(f: &mut fmt::Formatter<'_>, operand: &Expr, parent: BinaryOp, right: bool) -> fmt::Result {
+    if needs_parens(operand, parent, right) {
+        write!(f, "(")?;
+        write_child(f, operand)?;
+        write!(f, ")")
+    } else {
+        write_child(f, operand)
+    }
+}
+
+// The exponent of a power in superscript, when it is a literal whole
+// number
+fn superscript(exponent: &Expr) -> Option<String> {
+    let Expr::Number(exponent) = exponent else {
+        return None;
+    };
+    if exponent.fract() != 0.0 || exponent.abs() >= 1e6 {
+        return None;
+    }
+    let digits = format!("{}", *exponent as i64);
+    Some(
+        digits
+            .chars()
+            .map(|c| match c {
+                '-' => '⁻',
+                '1' => '¹',
+                '2' => '²',
+                '3' => '³',
+                _ => char::from_u32(c as u32 - '0' as u32 + '⁰' as u32).unwrap_or(c),
+            })
+            .collect(),
+    )
+}
+
+// A child only needs parentheses when writing it bare would regroup it:
+// looser operators always do, and so does an equally tight operator on the
+// right, since operators of equal precedence group left to right. Powers
+// group right to left, so for them it is the left side that needs them.
+// A sign on the left of a power applies after it, so a negative base is
+// grouped too.
+pub(crate) fn needs_parens(operand: &Expr, parent: BinaryOp, right: bool) -> bool {
+    let base = parent == BinaryOp::Pow && !right;
+    match operand {
+        Expr::Number(n) => base && n.is_sign_negative(),
+        Expr::Negate(_) => base,
+        Expr::Variable(_)
+        | Expr::Call { .. }
+        | Expr::Quantity { .. }
+        | Expr::List(_) => false,
+        Expr::Binary { op, .. } => {
+            op.precedence() < parent.precedence()
+                || (right != parent.right_associative() && op.precedence() == parent.precedence())
+        }
+    }
+}
+
+// What a minus sign applies to is grouped when it is an operation, or a
+// negative number or negation, which read better as `-(-5)` than `--5`
+pub(crate) fn negation_needs_parens(operand: &Expr) -> bool {
+    match operand {
+        Expr::Binary { .. } | Expr::Negate(_) => true,
+        Expr::Number(n) => n.is_sign_negative(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_expr_normalizes_spacing() {
+        assert_eq!(format_expr("5+3"), Ok("5 + 3".to_string()));
+        assert_eq!(format_expr("  5 *\t-3 "), Ok("5 * -3".to_string()));
+        assert_eq!(format_expr("1.5e3 / 2"), Ok("1500 / 2".to_string()));
+        assert_eq!(format_expr("1e300 - 1e-300"), Ok("1e300 - 1e-300".to_string()));
+        assert!(format_expr("5 +").is_err());
+        assert_eq!(format_expr("5'3 1/2\"+2'-7/8\""), Ok("5' 3 1/2\" + 2' 7/8\"".to_string()));
+        assert_eq!(format_expr("1/32\" * 2"), Ok("0.03125 in * 2".to_string()));
+    }
+
+    #[test]
+    fn test_format_expr_reads_input_as_calculated() {
+        assert_eq!(format_expr("5 x 3"), Ok("5 * 3".to_string()));
+        assert_eq!(format_expr("3 × 4"), Ok("3 * 4".to_string()));
+        assert_eq!(format_expr("１２３＋４"), Ok("123 + 4".to_string()));
+        assert_eq!(format_pretty("5²+1"), Ok("5² + 1".to_string()));
+        assert_eq!(format_expr("-5²+0"), Ok("-(5 ^ 2) + 0".to_string()));
+    }
+
+    #[test]
+    fn test_format_number_layout() {
+        assert_eq!(format_number(0.1 + 0.2), "0.30000000000000004");
+        assert_eq!(format_number(1500.0), "1500");
+        assert_eq!(format_number(-0.015), "-0.015");
+        assert_eq!(format_number(1e15), "1000000000000000");
+        assert_eq!(format_number(1e16), "1e16");
+        assert_eq!(format_number(-1.25e-7), "-1.25e-7");
+        assert_eq!(format_number(0.000001), "0.000001");
+        assert_eq!(format_number(f64::MAX), "1.7976931348623157e308");
+        assert_eq!(format_number(5e-324), "5e-324");
+        assert_eq!(format_number(-0.0), "-0");
+        assert_eq!(format_number(f64::NEG_INFINITY), "-inf");
+        assert_eq!(format_number(f64::NAN), "NaN");
+        // The same text `{}` and `{:e}` gave before, for every magnitude
+        for exponent in -300..=300 {
+            let value = 1.2345 * 10f64.powi(exponent);
+            let expected = if (1e-6..1e16).contains(&value) { format!("{}", value) } else { format!("{:e}", value) };
+            assert_eq!(format_number(value), expected);
+        }
+    }
+
+    #[test]
+    fn test_format_for_input_round_trips() {
+        use crate::random::Rng;
+
+        // Every kind of value: random bit patterns, then the edges, among
+        // them subnormals and the largest floats
+        let mut rng = Rng::new(1241);
+        let mut values: Vec<f64> = (0..5_000).map(|_| f64::from_bits(rng.next_u64())).collect();
+        values.extend([0.0, -0.0, 0.1, 1e16, 1e-6, 1e308, -1e308, 5e-324, -2.5e-310, f64::MAX, f64::MIN, f64::MIN_POSITIVE, -1234.5678]);
+        for separator in DecimalSeparator::ALL {
+            let mut ctx = Context::new();
+            ctx.decimal_separator = separator;
+            for &value in values.iter().filter(|value| value.is_finite()) {
+                let text = format_for_input(value, separator);
+                let read = parse(&normalize(&text, &ctx));
+                let Ok(Expr::Number(number)) = read else {
+                    panic!("{} did not read as a number: {:?}", text, read);
+                };
+                assert_eq!(number.to_bits(), value.to_bits(), "{} read back as {}", text, number);
+                assert_eq!(crate::calculate_with(&text, &ctx), Ok(value), "{} calculated", text);
+            }
+        }
+        assert_eq!(format_for_input(-1234.5678, DecimalSeparator::Comma), "-1234,5678");
+        assert_eq!(format_for_input(1.5e-7, DecimalSeparator::Comma), "1,5e-7");
+    }
+
+    #[test]
+    fn test_format_latex_and_bases() {
+        assert_eq!(format_latex(12.5), "12.5");
+        assert_eq!(format_latex(-1.5e-7), "-1.5 \\times 10^{-7}");
+        assert_eq!(format_latex(1e300), "1 \\times 10^{300}");
+        assert_eq!(format_latex(f64::NEG_INFINITY), "-\\infty");
+
+        let bases = format_bases(-255.0).unwrap();
+        assert_eq!(bases.map(|(_, text)| text), ["-0xFF", "-0o377", "-0b11111111"]);
+        assert_eq!(format_bases(0.0).unwrap()[2].1, "0b0");
+        assert_eq!(format_bases(2.5), None);
+        assert_eq!(format_bases(1e300), None);
+        assert_eq!(format_bases(f64::NAN), None);
+    }
+
+    #[test]
+    fn test_format_pretty_writes_superscripts() {
+        assert_eq!(format_pretty("x^2+1"), Ok("x² + 1".to_string()));
+        assert_eq!(format_pretty("10 ** -3 * 1.5"), Ok("10⁻³ * 1.5".to_string()));
+        assert_eq!(format_pretty("(-5)^2"), Ok("(-5)²".to_string()));
+        assert_eq!(format_pretty("(1 + x)^2 * 2^3^2"), Ok("(1 + x)² * 2 ^ 3²".to_string()));
+        assert_eq!(format_pretty("2^0.5"), Ok("2 ^ 0.5".to_string()));
+        assert_eq!(format_pretty("pow(x, 2)"), Ok("pow(x, 2)".to_string()));
+        assert_eq!(format_expr("x²+1"), Ok("x ^ 2 + 1".to_string()));
+    }
+
+    #[test]
+    fn test_display_parenthesizes_by_precedence() {
+        let sum = Expr::binary(BinaryOp::Add, Expr::Number(1.0), Expr::Number(2.0));
+        let product = Expr::binary(BinaryOp::Mul, sum.clone(), Expr::Number(3.0));
+        assert_eq!(product.to_string(), "(1 + 2) * 3");
+
+        let nested = Expr::binary(BinaryOp::Add, Expr::Number(3.0), product);
+        assert_eq!(nested.to_string(), "3 + (1 + 2) * 3");
+
+        let difference = Expr::binary(BinaryOp::Sub, Expr::Number(5.0), sum.clone());
+        assert_eq!(difference.to_string(), "5 - (1 + 2)");
+
+        let chained = Expr::binary(BinaryOp::Sub, sum, Expr::Number(4.0));
+        assert_eq!(chained.to_string(), "1 + 2 - 4");
+
+        let negated = Expr::negate(Expr::binary(BinaryOp::Add, Expr::Number(1.0), Expr::Number(2.0)));
+        assert_eq!(negated.to_string(), "-(1 + 2)");
+        assert_eq!(Expr::negate(Expr::Number(-5.0)).to_string(), "-(-5)");
+        assert_eq!(Expr::binary(BinaryOp::Mul, Expr::Number(3.0), negated).to_string(), "3 * -(1 + 2)");
+
+        // Powers group from the right, so it is the left side that needs them
+        let power = |lhs, rhs| Expr::binary(BinaryOp::Pow, lhs, rhs);
+        let (two, three) = (Expr::Number(2.0), Expr::Number(3.0));
+        assert_eq!(power(two.clone(), power(three.clone(), two.clone())).to_string(), "2 ^ 3 ^ 2");
+        assert_eq!(power(power(two.clone(), three.clone()), two.clone()).to_string(), "(2 ^ 3) ^ 2");
+        // A sign on the base applies after the power unless grouped
+        assert_eq!(power(Expr::Number(-2.0), two.clone()).to_string(), "(-2) ^ 2");
+        assert_eq!(power(Expr::negate(Expr::variable("x")), two.clone()).to_string(), "(-x) ^ 2");
+        assert_eq!(Expr::negate(power(three, two)).to_string(), "-(3 ^ 2)");
+    }
+}
@@ -0,0 +1,1115 @@
+//! Built-in functions callable from expressions, such as `ulps(a, b)`.
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "astronomy")]
+use crate::astronomy;
+#[cfg(feature = "scientific")]
+use crate::audio;
+#[cfg(feature = "finance")]
+use crate::business;
+#[cfg(feature = "programmer")]
+use crate::color::{self, Rgb};
+#[cfg(feature = "dates")]
+use crate::dates;
+#[cfg(feature = "finance")]
+use crate::depreciation::{self, Method};
+use crate::eval::{Context, NanPolicy};
+#[cfg(feature = "scientific")]
+use crate::eval::{AngleMode, OverflowMode};
+use crate::float;
+use crate::grades;
+#[cfg(feature = "finance")]
+use crate::inflation;
+#[cfg(feature = "finance")]
+use crate::interest::Savings;
+#[cfg(feature = "finance")]
+use crate::tax;
+#[cfg(feature = "scientific")]
+use crate::health::{self, Sex};
+#[cfg(feature = "scientific")]
+use crate::interpolation;
+#[cfg(feature = "scientific")]
+use crate::photo;
+#[cfg(feature = "scientific")]
+use crate::stats;
+#[cfg(feature = "units")]
+use crate::pixels;
+#[cfg(feature = "units")]
+use crate::unitprice;
+
+/// A group of related functions. Every pack but [`Pack::Core`] is a cargo
+/// feature of the same name, on by default, so embedders can leave out what
+/// they do not need; packs that are compiled in can also be turned off for
+/// a [`Context`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Pack {
+    /// Comparison and inspection of floats, and averages.
+    Core,
+    /// Trigonometry and logarithms, slopes, statistics, health, audio and
+    /// photography formulas.
+    Scientific,
+    /// Costs, taxes and margins.
+    Finance,
+    /// Bit patterns and colors.
+    Programmer,
+    /// Fuel economy and print size conversions.
+    Units,
+    /// Julian dates.
+    Dates,
+    /// Sidereal time and astronomical distances.
+    Astronomy,
+}
+
+impl Pack {
+    pub const ALL: [Pack; 7] = [
+        Pack::Core,
+        Pack::Scientific,
+        Pack::Finance,
+        Pack::Programmer,
+        Pack::Units,
+        Pack::Dates,
+        Pack::Astronomy,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Pack::Core => "core",
+            Pack::Scientific => "scientific",
+            Pack::Finance => "finance",
+            Pack::Programmer => "programmer",
+            Pack::Units => "units",
+            Pack::Dates => "dates",
+            Pack::Astronomy => "astronomy",
+        }
+    }
+
+    /// Whether the pack is compiled into this build.
+    pub fn is_available(self) -> bool {
+        PACKS.iter().any(|&(pack, _)| pack == self)
+    }
+}
+
+/// A function the evaluator knows by name.
+pub struct Function {
+    pub name: &'static str,
+    /// Parameter names and what they mean, used for the arity check and in
+    /// help text.
+    pub params: &'static [(&'static str, &'static str)],
+    pub description: &'static str,
+    /// A call that can be inserted into the input as it is.
+    pub example: &'static str,
+    body: Body,
+}
+
+enum Body {
+    /// Every argument is a single number.
+    Numbers(fn(&[f64], &Context) -> Result<f64, String>),
+    /// Every argument is a list of numbers, as in `wavg(90, 80; 1, 2)`.
+    Lists(fn(&[Vec<f64>], &Context) -> Result<f64, String>),
+    /// Takes numbers and then a name, such as the note in `note_freq(A4)`
+    /// or the unit in `unitprice(3.49, 500, g)`.
+    #[cfg_attr(not(any(feature = "scientific", feature = "finance", feature = "units")), allow(dead_code))]
+    Name(fn(&[f64], &str, &Context) -> Result<f64, String>),
+    /// Takes numbers and gives several, such as a width and a height. Only
+    /// a call on its own can show them; see [`Function::call_tuple`].
+    #[cfg_attr(not(any(feature = "scientific", feature = "units", feature = "dates")), allow(dead_code))]
+    Tuple(fn(&[f64], &Context) -> Result<Vec<f64>, String>),
+}
+
+impl Function {
+    /// The call syntax, e.g. `ulps(a, b)` or `wavg(values; weights)`.
+    pub fn signature(&self) -> String {
+        let separator = if self.takes_lists() { "; " } else { ", " };
+        let names: Vec<&str> = self.params.iter().map(|&(name, _)| name).collect();
+        format!("{}({})", self.name, names.join(separator))
+    }
+
+    /// Calls the function after checking the number of arguments. A
+    /// function of one list, such as `sum`, takes all of them as the list.
+    pub fn call(&self, args: &[f64], ctx: &Context) -> Result<f64, String> {
+        if let (Body::Lists(body), 1) = (&self.body, self.params.len()) {
+            return body(&[args.to_vec()], ctx);
+        }
+        self.check_arity(args.len())?;
+        match self.body {
+            Body::Numbers(body) => body(args, ctx),
+            Body::Lists(body) => body(&args.iter().map(|&arg| vec![arg]).collect::<Vec<_>>(), ctx),
+            Body::Name(_) => Err(self.name_error()),
+            Body::Tuple(_) => Err(self.tuple_error()),
+        }
+    }
+
+    /// Whether the arguments are lists, separated by `;`.
+    pub fn takes_lists(&self) -> bool {
+        matches!(self.body, Body::Lists(_))
+    }
+
+    /// Whether the last argument is a name rather than a number.
+    pub fn takes_name(&self) -> bool {
+        matches!(self.body, Body::Name(_))
+    }
+
+    /// Calls a function that takes a name, with the numbers before it.
+    pub fn call_with_name(&self, args: &[f64], name: &str, ctx: &Context) -> Result<f64, String> {
+        self.check_arity(args.len() + 1)?;
+        match self.body {
+            Body::Name(body) => body(args, name, ctx),
+            _ => Err(format!("{} takes numbers, not a name", self.signature())),
+        }
+    }
+
+    /// Whether the function gives several numbers rather than one.
+    pub fn gives_tuple(&self) -> bool {
+        matches!(self.body, Body::Tuple(_))
+    }
+
+    /// Calls a function that gives several numbers.
+    pub fn call_tuple(&self, args: &[f64], ctx: &Context) -> Result<Vec<f64>, String> {
+        self.check_arity(args.len())?;
+        match self.body {
+            Body::Tuple(body) => body(args, ctx),
+            Body::Numbers(_) | Body::Lists(_) | Body::Name(_) => {
+                Err(format!("{} gives a single number", self.signature()))
+            }
+        }
+    }
+
+    fn name_error(&self) -> String {
+        format!("{} takes a name, not a number", self.signature())
+    }
+
+    fn tuple_error(&self) -> String {
+        format!("{} gives several numbers, so it can only be used on its own", self.signature())
+    }
+
+    /// Calls the function with list arguments; a function that takes
+    /// numbers only accepts lists of one.
+    pub fn call_with_lists(&self, args: &[Vec<f64>], ctx: &Context) -> Result<f64, String> {
+        self.check_arity(args.len())?;
+        match self.body {
+            Body::Lists(body) => body(args, ctx),
+            Body::Numbers(body) => {
+                let numbers = args
+                    .iter()
+                    .map(|arg| match arg[..] {
+                        [number] => Ok(number),
+                        _ => Err(format!("{} takes numbers, not lists", self.signature())),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                body(&numbers, ctx)
+            }
+            Body::Name(_) => Err(self.name_error()),
+            Body::Tuple(_) => Err(self.tuple_error()),
+        }
+    }
+
+    fn check_arity(&self, count: usize) -> Result<(), String> {
+        if count != self.params.len() {
+            return Err(format!(
+                "{} takes {} argument(s) but got {}",
+                self.signature(),
+                self.params.len(),
+                count
+            ));
+        }
+        Ok(())
+    }
+}
+
+const CORE: &[Function] = &[
+    Function {
+        name: "inspect",
+        params: &[("x", "any number")],
+        description: "x itself; on its own, also shows the IEEE 754 breakdown of x",
+        example: "inspect(0.1 + 0.2)",
+        body: Body::Numbers(|args, _| Ok(args[0])),
+    },
+    Function {
+        name: "approx_eq",
+        params: &[
+            ("a", "first value"),
+            ("b", "second value"),
+            ("tol", "largest allowed difference, relative to the larger of a and b"),
+        ],
+        description: "1 if a and b differ by at most tol relative to the larger, else 0",
+        example: "approx_eq(0.1 + 0.2, 0.3, 1e-15)",
+        body: Body::Numbers(|args, _| Ok(float::approx_eq(args[0], args[1], args[2]) as u8 as f64)),
+    },
+    Function {
+        name: "ulps",
+        params: &[("a", "first value"), ("b", "second value")],
+        description: "Number of representable floats between a and b",
+        example: "ulps(0.1 + 0.2, 0.3)",
+        body: Body::Numbers(|args, _| {
+            float::ulps(args[0], args[1])
+                .map(|count| count as f64)
+                .ok_or_else(|| "ulps is undefined for NaN".to_string())
+        }),
+    },
+    Function {
+        name: "wavg",
+        params: &[
+            ("values", "the values to average, separated by commas"),
+            ("weights", "one weight per value, separated by commas"),
+        ],
+        description: "Weighted average of a list of values; the weights are a second list",
+        example: "wavg(90, 80, 70; 1, 2, 1)",
+        body: Body::Lists(|args, _| grades::weighted_average(&args[0], &args[1])),
+    },
+    Function {
+        name: "sum",
+        params: &[("values", "the values to add, separated by commas")],
+        description: "Total of a list of values",
+        example: "sum(12.5, 7, 3.25)",
+        body: Body::Lists(|args, _| Ok(args[0].iter().sum())),
+    },
+    Function {
+        name: "avg",
+        params: &[("values", "the values to average, separated by commas")],
+        description: "Average of a list of values",
+        example: "avg(90, 80, 70)",
+        body: Body::Lists(|args, _| {
+            if args[0].is_empty() {
+                return Err("avg needs at least one value".to_string());
+            }
+            Ok(args[0].iter().sum::<f64>() / args[0].len() as f64)
+        }),
+    },
+    Function {
+        name: "pow",
+        params: &[("base", "the number to raise"), ("exponent", "the power to raise it to")],
+        description: "base raised to the power exponent, like base ^ exponent",
+        example: "pow(2, 10)",
+        body: Body::Numbers(|args, _| Ok(args[0].powf(args[1]))),
+    },
+    Function {
+        name: "sqrt",
+        params: &[("x", "the number to take the root of")],
+        description: "Square root of x; √ on the symbol palette",
+        example: "sqrt(2)",
+        body: Body::Numbers(|args, ctx| {
+            if args[0] < 0.0 && ctx.nan == NanPolicy::Error {
+                return Err("Square root of a negative number".to_string());
+            }
+            Ok(args[0].sqrt())
+        }),
+    },
+    Function {
+        name: "abs",
+        params: &[("x", "any number")],
+        description: "Absolute value of x, its distance from 0",
+        example: "abs(-5)",
+        body: Body::Numbers(|args, _| Ok(args[0].abs())),
+    },
+    Function {
+        name: "le",
+        params: &[("a", "first value"), ("b", "second value")],
+        description: "1 if a ≤ b, else 0; ≤ on the symbol palette",
+        example: "le(0.1 + 0.2, 0.3)",
+        body: Body::Numbers(|args, _| Ok((args[0] <= args[1]) as u8 as f64)),
+    },
+];
+
+#[cfg(feature = "scientific")]
+const SCIENTIFIC: &[Function] = &[
+    Function {
+        name: "slope",
+        params: &[
+            ("rise", "vertical change"),
+            ("run", "horizontal distance, in the same unit as rise"),
+        ],
+        description: "Percent grade of a slope, rise / run * 100",
+        example: "slope(3, 50)",
+        body: Body::Numbers(|args, _| {
+            if args[1] == 0.0 {
+                return Err("slope is vertical (run is 0)".to_string());
+            }
+            Ok(args[0] / args[1] * 100.0)
+        }),
+    },
+    Function {
+        name: "grade_to_deg",
+        params: &[("grade", "slope in percent")],
+        description: "Angle in degrees of a percent grade",
+        example: "grade_to_deg(8)",
+        body: Body::Numbers(|args, _| Ok((args[0] / 100.0).atan().to_degrees())),
+    },
+    Function {
+        name: "deg_to_grade",
+        params: &[("degrees", "angle of the slope, under 90")],
+        description: "Percent grade of an angle in degrees",
+        example: "deg_to_grade(5)",
+        body: Body::Numbers(|args, _| {
+            if args[0].abs() >= 90.0 {
+                return Err("deg_to_grade needs an angle between -90 and 90 degrees".to_string());
+            }
+            Ok(AngleMode::Degrees.tan(args[0]) * 100.0)
+        }),
+    },
+    Function {
+        name: "sin",
+        params: &[("angle", "in the angle unit of the settings")],
+        description: "Sine of an angle",
+        example: "sin(0.5)",
+        body: Body::Numbers(|args, ctx| Ok(ctx.angle.sin_cos(args[0]).0)),
+    },
+    Function {
+        name: "cos",
+        params: &[("angle", "in the angle unit of the settings")],
+        description: "Cosine of an angle",
+        example: "cos(0.5)",
+        body: Body::Numbers(|args, ctx| Ok(ctx.angle.sin_cos(args[0]).1)),
+    },
+    Function {
+        name: "tan",
+        params: &[("angle", "in the angle unit of the settings")],
+        description: "Tangent of an angle",
+        example: "tan(0.5)",
+        body: Body::Numbers(|args, ctx| Ok(ctx.angle.tan(args[0]))),
+    },
+    Function {
+        name: "asin",
+        params: &[("x", "a sine, from -1 to 1")],
+        description: "Angle whose sine is x, in the angle unit of the settings",
+        example: "asin(0.5)",
+        body: Body::Numbers(|args, ctx| Ok(ctx.angle.from_radians(inverse_trig("asin", args[0], ctx, f64::asin)?))),
+    },
+    Function {
+        name: "acos",
+        params: &[("x", "a cosine, from -1 to 1")],
+        description: "Angle whose cosine is x, in the angle unit of the settings",
+        example: "acos(0.5)",
+        body: Body::Numbers(|args, ctx| Ok(ctx.angle.from_radians(inverse_trig("acos", args[0], ctx, f64::acos)?))),
+    },
+    Function {
+        name: "atan",
+        params: &[("x", "a tangent")],
+        description: "Angle whose tangent is x, in the angle unit of the settings",
+        example: "atan(1)",
+        body: Body::Numbers(|args, ctx| Ok(ctx.angle.from_radians(args[0].atan()))),
+    },
+    Function {
+        name: "ln",
+        params: &[("x", "a positive number")],
+        description: "Natural logarithm of x, to base e",
+        example: "ln(2.718)",
+        body: Body::Numbers(|args, ctx| logarithm(args[0], ctx, f64::ln)),
+    },
+    Function {
+        name: "log10",
+        params: &[("x", "a positive number")],
+        description: "Logarithm of x to base 10",
+        example: "log10(100)",
+        body: Body::Numbers(|args, ctx| logarithm(args[0], ctx, f64::log10)),
+    },
+    Function {
+        name: "log",
+        params: &[("x", "a positive number")],
+        description: "Logarithm of x to base 10, the same as log10",
+        example: "log(1000)",
+        body: Body::Numbers(|args, ctx| logarithm(args[0], ctx, f64::log10)),
+    },
+    Function {
+        name: "log2",
+        params: &[("x", "a positive number")],
+        description: "Logarithm of x to base 2",
+        example: "log2(1024)",
+        body: Body::Numbers(|args, ctx| logarithm(args[0], ctx, f64::log2)),
+    },
+    Function {
+        name: "exp",
+        params: &[("x", "the power to raise e to")],
+        description: "e raised to the power x, the inverse of ln",
+        example: "exp(1)",
+        body: Body::Numbers(|args, _| Ok(args[0].exp())),
+    },
+    Function {
+        name: "zscore",
+        params: &[("x", "a value"), ("mean", "the mean"), ("sd", "the standard deviation")],
+        description: "Standard score: how many standard deviations x is from the mean",
+        example: "zscore(130, 100, 15)",
+        body: Body::Numbers(|args, _| stats::zscore(args[0], args[1], args[2])),
+    },
+    Function {
+        name: "tscore",
+        params: &[
+            ("sample_mean", "the sample's mean"),
+            ("mean", "the population mean tested against"),
+            ("sd", "the sample's standard deviation"),
+            ("n", "the sample size"),
+        ],
+        description: "One-sample t statistic",
+        example: "tscore(103, 100, 15, 25)",
+        body: Body::Numbers(|args, _| stats::tscore(args[0], args[1], args[2], args[3])),
+    },
+    Function {
+        name: "pvalue_z",
+        params: &[("z", "a standard normal score")],
+        description: "Two-sided p-value of a z score",
+        example: "pvalue_z(1.96)",
+        body: Body::Numbers(|args, _| Ok(stats::pvalue_z(args[0]))),
+    },
+    Function {
+        name: "ci",
+        params: &[
+            ("mean", "the sample's mean"),
+            ("sd", "the sample's standard deviation"),
+            ("n", "the sample size"),
+            ("level", "confidence in percent, e.g. 95"),
+        ],
+        description: "Confidence interval for the population mean, using Student's t",
+        example: "ci(100, 15, 25, 95)",
+        body: Body::Tuple(|args, _| stats::ci(args[0], args[1], args[2], args[3]).map(|(low, high)| vec![low, high])),
+    },
+    Function {
+        name: "lerp",
+        params: &[("a", "the start"), ("b", "the end"), ("t", "how far along, 0 at a and 1 at b")],
+        description: "Linear interpolation between a and b",
+        example: "lerp(10, 20, 0.25)",
+        body: Body::Numbers(|args, _| Ok(interpolation::lerp(args[0], args[1], args[2]))),
+    },
+    Function {
+        name: "interp",
+        params: &[
+            ("x", "where to look up the table"),
+            ("xs", "the table's x values, increasing"),
+            ("ys", "the table's y values"),
+        ],
+        description: "Table lookup with straight lines between the points",
+        example: "interp(2.5; 1, 2, 3; 10, 20, 40)",
+        body: Body::Lists(|args, _| interpolation::interp(single(&args[0], "x")?, &args[1], &args[2])),
+    },
+    Function {
+        name: "spline",
+        params: &[
+            ("x", "where to look up the table"),
+            ("xs", "the table's x values, increasing"),
+            ("ys", "the table's y values"),
+        ],
+        description: "Table lookup on a smooth natural cubic spline through the points",
+        example: "spline(2.5; 1, 2, 3; 10, 20, 40)",
+        body: Body::Lists(|args, _| interpolation::spline(single(&args[0], "x")?, &args[1], &args[2])),
+    },
+    Function {
+        name: "bmi",
+        params: &[("weight_kg", "body weight in kilograms"), ("height_m", "height in metres")],
+        description: "Body mass index",
+        example: "bmi(70, 1.75)",
+        body: Body::Numbers(|args, _| health::bmi(args[0], args[1])),
+    },
+    Function {
+        name: "bmr_female",
+        params: &[
+            ("weight_kg", "body weight in kilograms"),
+            ("height_cm", "height in centimetres"),
+            ("age", "age in years"),
+        ],
+        description: "Basal metabolic rate in kcal/day (Mifflin-St Jeor), female",
+        example: "bmr_female(60, 165, 30)",
+        body: Body::Numbers(|args, _| health::bmr(args[0], args[1], args[2], Sex::Female)),
+    },
+    Function {
+        name: "bmr_male",
+        params: &[
+            ("weight_kg", "body weight in kilograms"),
+            ("height_cm", "height in centimetres"),
+            ("age", "age in years"),
+        ],
+        description: "Basal metabolic rate in kcal/day (Mifflin-St Jeor), male",
+        example: "bmr_male(75, 180, 30)",
+        body: Body::Numbers(|args, _| health::bmr(args[0], args[1], args[2], Sex::Male)),
+    },
+    Function {
+        name: "dose",
+        params: &[
+            ("mg_per_kg", "prescribed dosage in mg per kg of body weight"),
+            ("weight_kg", "body weight in kilograms"),
+        ],
+        description: "Dose in mg for a weight-based dosage",
+        example: "dose(15, 20)",
+        body: Body::Numbers(|args, _| health::dose(args[0], args[1])),
+    },
+    Function {
+        name: "db",
+        params: &[("ratio", "amplitude ratio, such as 2 for twice the level")],
+        description: "Decibels of an amplitude ratio",
+        example: "db(2)",
+        body: Body::Numbers(|args, _| audio::ratio_to_db(args[0])),
+    },
+    Function {
+        name: "ratio",
+        params: &[("db", "gain in decibels")],
+        description: "Amplitude ratio of a gain in decibels",
+        example: "ratio(-6)",
+        body: Body::Numbers(|args, _| Ok(audio::db_to_ratio(args[0]))),
+    },
+    Function {
+        name: "note_freq",
+        params: &[("note", "note name and octave, such as A4, Bb3 or \"C#5\"")],
+        description: "Frequency in Hz of a note; quote sharps, as in note_freq(\"C#4\")",
+        example: "note_freq(A4)",
+        body: Body::Name(|_, note, _| audio::note_frequency(note)),
+    },
+    Function {
+        name: "cents",
+        params: &[("f1", "starting frequency in Hz"), ("f2", "ending frequency in Hz")],
+        description: "Interval from f1 to f2 in cents",
+        example: "cents(440, 445)",
+        body: Body::Numbers(|args, _| audio::cents(args[0], args[1])),
+    },
+    Function {
+        name: "bpm_to_ms",
+        params: &[("bpm", "tempo in beats per minute")],
+        description: "Delay time in ms of one beat at a tempo",
+        example: "bpm_to_ms(120)",
+        body: Body::Numbers(|args, _| audio::beat_ms(args[0])),
+    },
+    Function {
+        name: "ms_to_bpm",
+        params: &[("ms", "length of one beat in milliseconds")],
+        description: "Tempo whose beat lasts a delay time in ms",
+        example: "ms_to_bpm(500)",
+        body: Body::Numbers(|args, _| audio::beat_ms(args[0])),
+    },
+    Function {
+        name: "ev",
+        params: &[
+            ("aperture", "f-number, such as 8 for f/8"),
+            ("shutter", "shutter time in seconds, such as 1/125"),
+            ("iso", "sensor sensitivity"),
+        ],
+        description: "Exposure value at ISO 100 of an f-number, shutter time in seconds and ISO",
+        example: "ev(8, 1/125, 100)",
+        body: Body::Numbers(|args, _| photo::exposure_value(args[0], args[1], args[2])),
+    },
+    Function {
+        name: "fstops",
+        params: &[("from", "starting f-number"), ("to", "ending f-number")],
+        description: "Stops between two f-numbers",
+        example: "fstops(4, 8)",
+        body: Body::Numbers(|args, _| photo::aperture_stops(args[0], args[1])),
+    },
+    Function {
+        name: "stops",
+        params: &[("from", "starting shutter time or ISO"), ("to", "ending shutter time or ISO")],
+        description: "Stops between two shutter times or ISO values",
+        example: "stops(1/250, 1/60)",
+        body: Body::Numbers(|args, _| photo::stops(args[0], args[1])),
+    },
+    Function {
+        name: "nd_stops",
+        params: &[("factor", "filter factor, such as 8 for an ND8")],
+        description: "Stops taken away by an ND filter with a filter factor",
+        example: "nd_stops(1000)",
+        body: Body::Numbers(|args, _| photo::nd_stops(args[0])),
+    },
+    Function {
+        name: "nd_shutter",
+        params: &[
+            ("shutter", "shutter time in seconds without the filter"),
+            ("stops", "stops the filter takes away"),
+        ],
+        description: "Shutter time behind an ND filter of that many stops",
+        example: "nd_shutter(1/125, 10)",
+        body: Body::Numbers(|args, _| photo::nd_shutter(args[0], args[1])),
+    },
+];
+
+// The one number in a list argument that stands for a single value, such
+// as the x of `interp(x; xs; ys)`.
+#[cfg(feature = "scientific")]
+fn single(list: &[f64], name: &str) -> Result<f64, String> {
+    match list {
+        [value] => Ok(*value),
+        _ => Err(format!("{} must be a single number", name)),
+    }
+}
+
+#[cfg(feature = "finance")]
+const FINANCE: &[Function] = &[
+    Function {
+        name: "fuel_cost",
+        params: &[
+            ("distance", "trip distance"),
+            ("economy", "fuel used per 100 units of distance"),
+            ("price", "price per unit of fuel"),
+        ],
+        description: "Cost of a trip, with economy as fuel used per 100 units of distance",
+        example: "fuel_cost(450, 6, 1.5)",
+        body: Body::Numbers(|args, _| Ok(args[0] * args[1] / 100.0 * args[2])),
+    },
+    Function {
+        name: "withtax",
+        params: &[
+            ("price", "price before tax"),
+            ("tax", "a saved tax rate's name, or a rate in percent"),
+        ],
+        description: "Price including a sales tax; save named rates in Settings",
+        example: "withtax(100, 8.875)",
+        body: Body::Name(|args, name, ctx| tax::find(name, ctx).map(|tax| tax.add(args[0]))),
+    },
+    Function {
+        name: "withouttax",
+        params: &[
+            ("price", "price including tax"),
+            ("tax", "a saved tax rate's name, or a rate in percent"),
+        ],
+        description: "Price before a sales tax that a price includes",
+        example: "withouttax(108.875, 8.875)",
+        body: Body::Name(|args, name, ctx| tax::find(name, ctx).map(|tax| tax.remove(args[0]))),
+    },
+    Function {
+        name: "breakeven",
+        params: &[
+            ("fixed", "fixed costs"),
+            ("price", "price per unit"),
+            ("varcost", "variable cost per unit"),
+        ],
+        description: "Units to sell to cover the fixed costs",
+        example: "breakeven(10000, 25, 15)",
+        body: Body::Numbers(|args, _| business::breakeven(args[0], args[1], args[2])),
+    },
+    Function {
+        name: "contribution_margin",
+        params: &[("price", "price per unit"), ("varcost", "variable cost per unit")],
+        description: "What each unit sold leaves toward the fixed costs",
+        example: "contribution_margin(25, 15)",
+        body: Body::Numbers(|args, _| Ok(business::contribution_margin(args[0], args[1]))),
+    },
+    Function {
+        name: "markup_to_margin",
+        params: &[("markup", "profit in percent of the cost")],
+        description: "Margin in percent of the price for a markup in percent of the cost",
+        example: "markup_to_margin(25)",
+        body: Body::Numbers(|args, _| business::markup_to_margin(args[0])),
+    },
+    Function {
+        name: "margin_to_markup",
+        params: &[("margin", "profit in percent of the price")],
+        description: "Markup in percent of the cost for a margin in percent of the price",
+        example: "margin_to_markup(20)",
+        body: Body::Numbers(|args, _| business::margin_to_markup(args[0])),
+    },
+    Function {
+        name: "sln",
+        params: &[
+            ("cost", "what the asset cost"),
+            ("salvage", "its value at the end of its life"),
+            ("life", "number of periods it is used for"),
+        ],
+        description: "Straight-line depreciation per period",
+        example: "sln(10000, 1000, 5)",
+        body: Body::Numbers(|args, _| {
+            depreciation::in_period(args[0], args[1], args[2], 1.0, Method::StraightLine)
+        }),
+    },
+    Function {
+        name: "ddb",
+        params: &[
+            ("cost", "what the asset cost"),
+            ("salvage", "its value at the end of its life"),
+            ("life", "number of periods it is used for"),
+            ("period", "the period, from 1 to life"),
+            ("factor", "rate relative to straight-line; 2 for double-declining"),
+        ],
+        description: "Declining-balance depreciation in one period",
+        example: "ddb(10000, 1000, 5, 2, 2)",
+        body: Body::Numbers(|args, _| {
+            depreciation::in_period(args[0], args[1], args[2], args[3], Method::DecliningBalance { factor: args[4] })
+        }),
+    },
+    Function {
+        name: "fv",
+        params: &[
+            ("principal", "the starting balance"),
+            ("rate", "yearly interest rate in percent, compounded monthly"),
+            ("years", "how long the savings grow"),
+            ("contribution", "paid in at the end of each month"),
+        ],
+        description: "Future value of savings with monthly contributions",
+        example: "fv(10000, 5, 10, 100)",
+        body: Body::Numbers(|args, _| {
+            Savings {
+                principal: args[0],
+                rate: args[1],
+                years: args[2],
+                contribution: args[3],
+            }
+            .future_value()
+        }),
+    },
+    Function {
+        name: "inflate",
+        params: &[
+            ("amount", "a sum of money"),
+            ("from_year", "the year the amount is in"),
+            ("to_year", "the year to express it in"),
+        ],
+        description: "Adjusts an amount for inflation with a yearly price index",
+        example: "inflate(100, 1990, 2020)",
+        body: Body::Numbers(|args, ctx| inflation::inflate(args[0], args[1], args[2], ctx)),
+    },
+];
+
+#[cfg(feature = "programmer")]
+const PROGRAMMER: &[Function] = &[
+    Function {
+        name: "bits",
+        params: &[("pattern", "64-bit pattern, best written in hex such as 0x3FF0000000000000")],
+        description: "The f64 with the given 64-bit pattern",
+        example: "bits(0x4008000000000000)",
+        body: Body::Numbers(|args, _| {
+            // Hex literals are converted exactly by the parser; this handles
+            // computed patterns, which must be whole numbers that fit in 64 bits
+            let pattern = args[0];
+            if pattern.fract() != 0.0 || !(0.0..18446744073709551616.0).contains(&pattern) {
+                return Err("bits() needs a whole number from 0 to 0xFFFFFFFFFFFFFFFF".to_string());
+            }
+            Ok(f64::from_bits(pattern as u64))
+        }),
+    },
+    Function {
+        name: "hex2rgb",
+        params: &[("color", "a color such as #336699")],
+        description: "Red, green and blue channels of a color such as #336699",
+        example: "hex2rgb(#336699)",
+        body: Body::Tuple(|args, _| {
+            let color = Rgb::from_value(args[0])?;
+            Ok(vec![color.r as f64, color.g as f64, color.b as f64])
+        }),
+    },
+    Function {
+        name: "rgb2hex",
+        params: &[
+            ("r", "red, from 0 to 255"),
+            ("g", "green, from 0 to 255"),
+            ("b", "blue, from 0 to 255"),
+        ],
+        description: "The color with red, green and blue channels from 0 to 255",
+        example: "rgb2hex(51, 102, 153)",
+        body: Body::Numbers(|args, _| Rgb::from_channels(args[0], args[1], args[2]).map(Rgb::value)),
+    },
+    Function {
+        name: "luminance",
+        params: &[("color", "a color such as #336699")],
+        description: "WCAG relative luminance of a color, from 0 (black) to 1 (white)",
+        example: "luminance(#336699)",
+        body: Body::Numbers(|args, _| Rgb::from_value(args[0]).map(Rgb::relative_luminance)),
+    },
+    Function {
+        name: "contrast",
+        params: &[("a", "text color"), ("b", "background color")],
+        description: "WCAG contrast ratio of two colors, from 1 to 21; text needs 4.5",
+        example: "contrast(#777777, #FFFFFF)",
+        body: Body::Numbers(|args, _| Ok(color::contrast_ratio(Rgb::from_value(args[0])?, Rgb::from_value(args[1])?))),
+    },
+];
+
+#[cfg(feature = "units")]
+const UNITS: &[Function] = &[
+    Function {
+        name: "unitprice",
+        params: &[
+            ("price", "price of the package"),
+            ("qty", "how much the package holds"),
+            ("unit", "unit of qty, such as g, kg, oz, lb, ml, l, floz or each"),
+        ],
+        description: "Price per kg, l, m or item of a package, for comparing sizes",
+        example: "unitprice(3.49, 500, g)",
+        body: Body::Name(|args, unit, _| unitprice::unit_price(args[0], args[1], unit).map(|unit| unit.price)),
+    },
+    Function {
+        name: "mpg_to_l100km",
+        params: &[("mpg", "miles per US gallon")],
+        description: "Litres per 100 km for a fuel economy in US miles per gallon",
+        example: "mpg_to_l100km(30)",
+        body: Body::Numbers(|args, _| economy_reciprocal(args[0])),
+    },
+    Function {
+        name: "l100km_to_mpg",
+        params: &[("l100km", "litres per 100 km")],
+        description: "US miles per gallon for a fuel economy in litres per 100 km",
+        example: "l100km_to_mpg(6.5)",
+        body: Body::Numbers(|args, _| economy_reciprocal(args[0])),
+    },
+    Function {
+        name: "fuel_needed",
+        params: &[
+            ("distance", "trip distance"),
+            ("economy", "fuel used per 100 units of distance"),
+        ],
+        description: "Fuel for a trip, with economy as fuel used per 100 units of distance",
+        example: "fuel_needed(450, 6)",
+        body: Body::Numbers(|args, _| Ok(args[0] * args[1] / 100.0)),
+    },
+    Function {
+        name: "px",
+        params: &[
+            ("inches", "printed size in inches"),
+            ("dpi", "print resolution in dots per inch"),
+        ],
+        description: "Pixels to print a size in inches at a DPI",
+        example: "px(8.5, 300)",
+        body: Body::Numbers(|args, _| pixels::to_pixels(args[0], args[1])),
+    },
+    Function {
+        name: "in",
+        params: &[("px", "size in pixels"), ("dpi", "print resolution in dots per inch")],
+        description: "Printed size in inches of a pixel count at a DPI",
+        example: "in(2550, 300)",
+        body: Body::Numbers(|args, _| pixels::to_inches(args[0], args[1])),
+    },
+    Function {
+        name: "fit",
+        params: &[
+            ("w", "width"),
+            ("h", "height"),
+            ("maxw", "width of the box"),
+            ("maxh", "height of the box"),
+        ],
+        description: "Width and height scaled to fit a box, keeping the aspect ratio",
+        example: "fit(1920, 1080, 800, 800)",
+        body: Body::Tuple(|args, _| pixels::fit(args[0], args[1], args[2], args[3]).map(|(w, h)| vec![w, h])),
+    },
+    Function {
+        name: "aspect",
+        params: &[("w", "width"), ("h", "height")],
+        description: "Aspect ratio in lowest terms",
+        example: "aspect(1920, 1080)",
+        body: Body::Tuple(|args, _| pixels::aspect_ratio(args[0], args[1]).map(|(w, h)| vec![w, h])),
+    },
+];
+
+// Miles per US gallon and litres per 100 km are reciprocal, so the same
+// formula converts either way.
+#[cfg(feature = "units")]
+fn economy_reciprocal(economy: f64) -> Result<f64, String> {
+    const MPG_TIMES_L100KM: f64 = 235.214_583_333_333_3;
+    if economy <= 0.0 {
+        return Err("Fuel economy must be positive".to_string());
+    }
+    Ok(MPG_TIMES_L100KM / economy)
+}
+
+#[cfg(feature = "dates")]
+const DATES: &[Function] = &[
+    Function {
+        name: "jd",
+        params: &[
+            ("year", "calendar year"),
+            ("month", "month from 1 to 12"),
+            ("day", "day of the month; a fraction is the time in UT"),
+        ],
+        description: "Julian date of a calendar date; a fractional day is the time in UT",
+        example: "jd(2000, 1, 1.5)",
+        body: Body::Numbers(|args, _| dates::julian_date(args[0], args[1], args[2])),
+    },
+    Function {
+        name: "jd_to_date",
+        params: &[("jd", "Julian date")],
+        description: "Year, month and fractional day of a Julian date",
+        example: "jd_to_date(2451545)",
+        body: Body::Tuple(|args, _| dates::calendar_date(args[0]).map(|(y, m, d)| vec![y, m, d])),
+    },
+];
+
+#[cfg(feature = "astronomy")]
+const ASTRONOMY: &[Function] = &[
+    Function {
+        name: "gmst",
+        params: &[("jd", "Julian date")],
+        description: "Greenwich mean sidereal time in hours",
+        example: "gmst(2451545)",
+        body: Body::Numbers(|args, _| Ok(astronomy::greenwich_sidereal_time(args[0]))),
+    },
+    Function {
+        name: "lst",
+        params: &[
+            ("jd", "Julian date"),
+            ("longitude", "east longitude in degrees; west is negative"),
+        ],
+        description: "Local mean sidereal time in hours at an east longitude in degrees",
+        example: "lst(2451545, -90)",
+        body: Body::Numbers(|args, _| Ok(astronomy::local_sidereal_time(args[0], args[1]))),
+    },
+    Function {
+        name: "ly",
+        params: &[("km", "distance in kilometres")],
+        description: "Light years in a distance in km",
+        example: "ly(40000000000000)",
+        body: Body::Numbers(|args, _| Ok(args[0] / astronomy::LIGHT_YEAR_KM)),
+    },
+    Function {
+        name: "au",
+        params: &[("km", "distance in kilometres")],
+        description: "Astronomical units in a distance in km",
+        example: "au(227900000)",
+        body: Body::Numbers(|args, _| Ok(args[0] / astronomy::AU_KM)),
+    },
+];
+
+// The function tables compiled into this build.
+const PACKS: &[(Pack, &[Function])] = &[
+    (Pack::Core, CORE),
+    #[cfg(feature = "scientific")]
+    (Pack::Scientific, SCIENTIFIC),
+    #[cfg(feature = "finance")]
+    (Pack::Finance, FINANCE),
+    #[cfg(feature = "programmer")]
+    (Pack::Programmer, PROGRAMMER),
+    #[cfg(feature = "units")]
+    (Pack::Units, UNITS),
+    #[cfg(feature = "dates")]
+    (Pack::Dates, DATES),
+    #[cfg(feature = "astronomy")]
+    (Pack::Astronomy, ASTRONOMY),
+];
+
+/// Every built-in function compiled into this build, with its pack.
+pub fn all() -> impl Iterator<Item = (Pack, &'static Function)> {
+    PACKS.iter().flat_map(|&(pack, functions)| functions.iter().map(move |function| (pack, function)))
+}
+
+/// Finds the built-in function called `name`, whether or not its pack is
+/// turned on.
+pub fn lookup(name: &str) -> Option<&'static Function> {
+    all().find(|(_, function)| function.name == name).map(|(_, function)| function)
+}
+
+/// Finds the function called `name` for an expression evaluated in `ctx`,
+/// which must have its pack turned on.
+pub fn find(name: &str, ctx: &Context) -> Result<&'static Function, String> {
+    let (pack, function) = all()
+        .find(|(_, function)| function.name == name)
+        .ok_or_else(|| format!("Unknown function '{}'", name))?;
+    let profile = ctx.profile();
+    if !profile.allows_calls() {
+        return Err(profile.forbids("Calling functions"));
+    }
+    if !profile.allows_pack(pack) {
+        return Err(profile.forbids(&format!("{}()", name)));
+    }
+    if !ctx.has_pack(pack) {
+        return Err(format!("{}() is in the {} pack, which is turned off", name, pack.label()));
+    }
+    Ok(function)
+}
+
+/// The built-in function whose argument list the caret is in, found from
+/// the innermost unclosed `name(` before byte offset `caret`, with the
+/// index of the argument being typed. Arguments are counted by `;` for a
+/// function of lists, or once any `;` has been typed, and by `,` otherwise.
+pub fn enclosing_call(input: &str, caret: usize) -> Option<(&'static Function, usize)> {
+    let before = input.get(..caret)?;
+    // Open parentheses with the commas and semicolons seen directly inside
+    let mut open: Vec<(usize, usize, usize)> = Vec::new();
+    for (i, c) in before.char_indices() {
+        match c {
+            '(' => open.push((i, 0, 0)),
+            ')' => {
+                open.pop();
+            }
+            ',' => {
+                if let Some(top) = open.last_mut() {
+                    top.1 += 1;
+                }
+            }
+            ';' => {
+                if let Some(top) = open.last_mut() {
+                    top.2 += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let &(paren, commas, semicolons) = open.last()?;
+    let head = before[..paren].trim_end();
+    let name_start = head.trim_end_matches(|c: char| c.is_alphanumeric() || c == '_').len();
+    let function = lookup(&head[name_start..])?;
+    let index = if function.takes_lists() || semicolons > 0 { semicolons } else { commas };
+    Some((function, index))
+}
+
+// Logarithms are NaN below 0 and negative infinity at 0; each is an error
+// unless the settings let it through
+#[cfg(feature = "scientific")]
+fn logarithm(x: f64, ctx: &Context, log: fn(f64) -> f64) -> Result<f64, String> {
+    if x < 0.0 && ctx.nan == NanPolicy::Error {
+        return Err("Logarithm of a negative number".to_string());
+    }
+    if x == 0.0 && ctx.overflow != OverflowMode::Infinity {
+        return Err("Logarithm of zero is negative infinity".to_string());
+    }
+    Ok(log(x))
+}
+
+// `asin` and `acos` are NaN outside -1 to 1, an error unless the settings
+// let it through
+#[cfg(feature = "scientific")]
+fn inverse_trig(name: &str, x: f64, ctx: &Context, function: fn(f64) -> f64) -> Result<f64, String> {
+    if x.abs() > 1.0 && ctx.nan == NanPolicy::Error {
+        return Err(format!("{} takes a value from -1 to 1", name));
+    }
+    Ok(function(x))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup_and_arity() {
+        let ctx = Context::new();
+        let ulps = lookup("ulps").unwrap();
+        assert_eq!(ulps.signature(), "ulps(a, b)");
+        assert_eq!(ulps.call(&[1.0, 1.0], &ctx), Ok(0.0));
+        assert_eq!(
+            ulps.call(&[1.0], &ctx),
+            Err("ulps(a, b) takes 2 argument(s) but got 1".to_string())
+        );
+        assert!(lookup("nope").is_none());
+    }
+
+    #[cfg(feature = "scientific")]
+    #[test]
+    fn test_logarithms_and_inverse_trig() {
+        let mut ctx = Context::new();
+        let call = |name: &str, x: f64, ctx: &Context| lookup(name).unwrap().call(&[x], ctx);
+        assert_eq!(call("log10", 100.0, &ctx), Ok(2.0));
+        assert_eq!(call("log", 1000.0, &ctx), Ok(3.0));
+        assert_eq!(call("log2", 1024.0, &ctx), Ok(10.0));
+        assert_eq!(call("ln", 1.0, &ctx), Ok(0.0));
+        assert_eq!(call("ln", -1.0, &ctx), Err("Logarithm of a negative number".to_string()));
+        assert_eq!(call("ln", 0.0, &ctx), Err("Logarithm of zero is negative infinity".to_string()));
+        assert_eq!(call("asin", 2.0, &ctx), Err("asin takes a value from -1 to 1".to_string()));
+
+        ctx.angle = AngleMode::Degrees;
+        assert_eq!(call("atan", 1.0, &ctx), Ok(45.0));
+        assert_eq!(call("sin", 30.0, &ctx), Ok(0.5));
+        assert_eq!(call("cos", 60.0, &ctx), Ok(0.5));
+        assert_eq!(call("tan", 45.0, &ctx), Ok(1.0));
+        ctx.overflow = OverflowMode::Infinity;
+        ctx.nan = NanPolicy::Propagate;
+        assert_eq!(call("ln", 0.0, &ctx), Ok(f64::NEG_INFINITY));
+        assert!(call("acos", 2.0, &ctx).unwrap().is_nan());
+    }
+
+    #[test]
+    fn test_enclosing_call() {
+        let name_and_index = |input: &str, caret: usize| {
+            enclosing_call(input, caret).map(|(function, index)| (function.name, index))
+        };
+        assert_eq!(name_and_index("ulps(", 5), Some(("ulps", 0)));
+        assert_eq!(name_and_index("2 * ulps(1, 3", 13), Some(("ulps", 1)));
+        assert_eq!(name_and_index("ulps(1, (2 + 3", 14), None);
+        assert_eq!(name_and_index("ulps(1, (2 + 3) * ", 18), Some(("ulps", 1)));
+        assert_eq!(name_and_index("ulps(1, 2) + 4", 14), None);
+        assert_eq!(name_and_index("ulps(1, 2)", 3), None);
+        assert_eq!(name_and_index("wavg(90, 80; 1", 14), Some(("wavg", 1)));
+        assert_eq!(name_and_index("wavg(90, 80", 11), Some(("wavg", 0)));
+        assert_eq!(name_and_index("inspect(ulps(1, 2), ", 20), Some(("inspect", 1)));
+        assert_eq!(name_and_index("nope(", 5), None);
+    }
+}
@@ -0,0 +1,65 @@
+//! Grade averaging: weighted averages and letter-grade points for GPAs.
+
+/// The average of `values` with each counted `weights[i]` times as much,
+/// as for assignments worth different shares of a grade or courses with
+/// different credits.
+pub fn weighted_average(values: &[f64], weights: &[f64]) -> Result<f64, String> {
+    if values.len() != weights.len() {
+        return Err(format!(
+            "{} value(s) but {} weight(s); give one weight per value",
+            values.len(),
+            weights.len()
+        ));
+    }
+    if weights.iter().any(|&weight| weight < 0.0) {
+        return Err("Weights cannot be negative".to_string());
+    }
+    let total: f64 = weights.iter().sum();
+    if total == 0.0 {
+        return Err("Weights add up to zero".to_string());
+    }
+    let sum: f64 = values.iter().zip(weights).map(|(value, weight)| value * weight).sum();
+    Ok(sum / total)
+}
+
+/// Grade points on the common 4.0 scale for a letter grade such as `B+`.
+pub fn grade_points(letter: &str) -> Option<f64> {
+    let points = match letter.trim().to_ascii_uppercase().as_str() {
+        "A+" | "A" => 4.0,
+        "A-" => 3.7,
+        "B+" => 3.3,
+        "B" => 3.0,
+        "B-" => 2.7,
+        "C+" => 2.3,
+        "C" => 2.0,
+        "C-" => 1.7,
+        "D+" => 1.3,
+        "D" => 1.0,
+        "D-" => 0.7,
+        "F" => 0.0,
+        _ => return None,
+    };
+    Some(points)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_weighted_average() {
+        assert_eq!(weighted_average(&[90.0, 80.0, 70.0], &[1.0, 2.0, 1.0]), Ok(80.0));
+        assert_eq!(
+            weighted_average(&[90.0, 80.0], &[1.0]),
+            Err("2 value(s) but 1 weight(s); give one weight per value".to_string())
+        );
+        assert_eq!(weighted_average(&[90.0], &[0.0]), Err("Weights add up to zero".to_string()));
+    }
+
+    #[test]
+    fn test_grade_points() {
+        assert_eq!(grade_points("b+"), Some(3.3));
+        assert_eq!(grade_points(" A "), Some(4.0));
+        assert_eq!(grade_points("E"), None);
+    }
+}
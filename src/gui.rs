@@ -1,99 +1,2136 @@
-use eframe::egui;
-use crate::calculate;
-
-pub struct CalculatorApp {
-    input: String,
-    result: String,
-    error: String,
-}
-
-impl Default for CalculatorApp {
-    fn default() -> Self {
-        Self {
-            input: String::new(),
-            result: String::new(),
-            error: String::new(),
-        }
-    }
-}
-
-impl eframe::App for CalculatorApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        egui::CentralPanel::default().show(ctx, |ui| {
-            ui.heading("Rust Calculator");
-            ui.add_space(10.0);
-
-            // Input field with keyboard focus
-            let _ = ui.horizontal(|ui| {
-                ui.label("Enter calculation:");
-                let text_edit = ui.text_edit_singleline(&mut self.input);
-                text_edit.request_focus();
-                text_edit
-            });
-
-            // Check for Enter key press
-            if ui.input(|i| i.key_pressed(egui::Key::Enter)) {
-                self.calculate();
-            }
-
-            // Buttons for common operations
-            ui.horizontal(|ui| {
-                if ui.button("+").clicked() {
-                    self.input.push('+');
-                }
-                if ui.button("-").clicked() {
-                    self.input.push('-');
-                }
-                if ui.button("*").clicked() {
-                    self.input.push('*');
-                }
-                if ui.button("/").clicked() {
-                    self.input.push('/');
-                }
-                if ui.button("Clear").clicked() {
-                    self.input.clear();
-                    self.result.clear();
-                    self.error.clear();
-                }
-            });
-
-            // Calculate button
-            if ui.button("Calculate").clicked() {
-                self.calculate();
-            }
-
-            // Display results
-            if !self.result.is_empty() {
-                ui.add_space(10.0);
-                ui.label(&self.result);
-            }
-            if !self.error.is_empty() {
-                ui.add_space(10.0);
-                ui.label(egui::RichText::new(&self.error).color(egui::Color32::RED));
-            }
-
-            // Instructions
-            ui.add_space(20.0);
-            ui.label("Instructions:");
-            ui.label("• Enter numbers and operators (+, -, *, /)");
-            ui.label("• Press Enter or click Calculate to compute");
-            ui.label("• Spaces are optional (e.g., '5+3' or '5 + 3')");
-            ui.label("• Scientific notation is supported (e.g., '1e3 + 2e3')");
-        });
-    }
-}
-
-impl CalculatorApp {
-    fn calculate(&mut self) {
-        match calculate(&self.input) {
-            Ok(result) => {
-                self.result = format!("Result: {}", result);
-                self.error.clear();
-            }
-            Err(err) => {
-                self.error = format!("Error: {}", err);
-                self.result.clear();
-            }
-        }
-    }
-} 
\ No newline at end of file
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+use std::path::PathBuf;
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+
+use eframe::egui;
+use crate::catalog::Catalog;
+use crate::config::{self, related_buttons, ButtonAction, Config, KeyButton, TouchLayout};
+use crate::layout_editor;
+use crate::paste::{PasteChoice, PasteDialog};
+use crate::plot::DataPlot;
+use crate::session::{self, Choice, Session, SessionPicker, Settings};
+use crate::sound::{self, Sound};
+use crate::speech;
+use crate::sync::{self, Watcher};
+use crate::tools::Tools;
+use crate::tour::{Tour, TourEvent};
+use crate::transfer::{self, Setup, Transfer, TransferChoice};
+use calculator::bignum;
+use calculator::color::Rgb;
+use calculator::compare::Comparison;
+use calculator::currency::{Currency, CURRENCIES};
+use calculator::directives;
+use calculator::equations;
+use calculator::format::{format_bases, format_for_input, format_latex, format_number};
+use calculator::parser::is_identifier;
+use calculator::functions::{self, Function};
+use calculator::history;
+use calculator::keypad::{comma_is_point, enter_exponent};
+use calculator::normalize::invisible_name;
+use calculator::random::Rng;
+use calculator::symbols::SYMBOLS;
+use calculator::units::{define_unit, CustomUnit};
+use calculator::{
+    assignment, calculate_promoted, calculate_quantity, calculate_tuple, calculate_with, colors_in, curve_fit, format_expr, format_pretty, goal_seek, inspect_report, is_incomplete, last_operation, linear_system, normalize, pasted_range, plot_distribution, sanitize, sub_results, AngleMode, Context,
+    DecimalSeparator, EntryKey, History, LastOperation, NanPolicy, OverflowMode, Pack, PriceIndex, Profile, Quantity, Scratch, Steps, TaxRate, Unit,
+};
+
+#[derive(Default)]
+pub struct CalculatorApp {
+    input: String,
+    result: String,
+    // Extra detail for the result, such as the breakdown from `inspect(x)`
+    report: String,
+    // Colors in the expression or result, shown as swatches by the result
+    swatches: Vec<Rgb>,
+    // Leading directives such as `@deg` of the calculation being done,
+    // kept with its history entries
+    directives: String,
+    // A unit result written in its own unit and then in the others it can
+    // be shown in, with the one picked for display
+    renderings: Vec<String>,
+    rendering: usize,
+    // A unit or money result split into larger and smaller units
+    breakdown: Vec<String>,
+    // Every part of the calculated expression with its value, shown under
+    // the result
+    steps: Option<Steps>,
+    error: String,
+    context: Context,
+    // Set when the user evaluated an expression that ends in an operator
+    awaiting_operand: bool,
+    speak_results: bool,
+    click_sounds: bool,
+    error_sounds: bool,
+    // Tidy writes whole-number powers as superscripts, e.g. `x²`
+    superscripts: bool,
+    // Windows opened with File → New Window; only the root window has any
+    windows: Vec<ExtraWindow>,
+    next_window: u64,
+    close_requested: bool,
+    // Distinguishes this window's own viewports (such as the detached
+    // history and plot) from those of other windows
+    number: u64,
+    history: History,
+    history_detached: bool,
+    // Whether the window has been sized to its monitor
+    sized: bool,
+    // Set from the title bar drawn in frameless mode: kept above other
+    // windows, and showing only the input, keypad and result
+    pinned: bool,
+    compact: bool,
+    // Whether the screen has been touched, which switches on the touch
+    // layout when it is automatic, and how far the input has been swiped
+    touched: bool,
+    swipe: f32,
+    // An input method is composing text, in this window's input or any
+    // other field
+    composing: bool,
+    // The input takes the focus on the next frame: on opening, and after a
+    // button or menu has acted on it
+    focus_input: bool,
+    // The name typed in the result's menu to keep its value as a variable
+    variable_name: String,
+    // The statistics view over the history, and when this window opened,
+    // where its session's calculations start
+    show_stats: bool,
+    started: u64,
+    config: Config,
+    show_layout_editor: bool,
+    // Last successful result, what apply buttons act on when the input is empty
+    last_value: Option<f64>,
+    // A result calculated with big numbers, which has no float value, as
+    // Copy writes it for the input
+    big_result: Option<String>,
+    // What pressing `=` again on an unchanged input repeats, and the input
+    // it applies to
+    repeat: Option<(LastOperation, String)>,
+    scratch: Scratch,
+    tools: Tools,
+    catalog: Catalog,
+    tour: Tour,
+    sessions: SessionPicker,
+    transfer: Transfer,
+    paste: PasteDialog,
+    plot: DataPlot,
+    // The sync folder as typed in Settings, and the watcher on it while
+    // syncing
+    sync_folder: String,
+    sync_error: String,
+    watcher: Option<Watcher>,
+    // Expressions sent by later launches, such as from `calc:` links; only
+    // the root window takes them
+    incoming: Option<Receiver<String>>,
+    // Started with `--kiosk`: no file access or settings changes
+    kiosk: bool,
+    // Name of the tax rate being added in Settings
+    new_tax: String,
+    // Country of the price index being added in Settings, and the table
+    // being edited with its text
+    new_country: String,
+    index_editor: Option<(String, String)>,
+    index_error: String,
+    // Definition of the unit being added in Settings, and why it was refused
+    new_unit: String,
+    unit_error: String,
+    // Goal seek over the history: the line marked as the target, the value
+    // it should reach, the variable left free and the outcome
+    seek_target: Option<String>,
+    seek_goal: String,
+    seek_free: String,
+    seek_outcome: Option<Result<String, String>>,
+    // The time and expression of the history entries picked for
+    // comparison, at most two
+    compared: Vec<(u64, String)>,
+}
+
+struct ExtraWindow {
+    id: egui::ViewportId,
+    app: CalculatorApp,
+    open: bool,
+    // The size it opened at
+    size: egui::Vec2,
+}
+
+// The keys of the Scientific panel: the label and the function each calls
+const SCIENTIFIC_KEYS: [(&str, &str); 12] = [
+    ("sin", "sin"),
+    ("cos", "cos"),
+    ("tan", "tan"),
+    ("sin⁻¹", "asin"),
+    ("cos⁻¹", "acos"),
+    ("tan⁻¹", "atan"),
+    ("ln", "ln"),
+    ("log", "log"),
+    ("log₂", "log2"),
+    ("eˣ", "exp"),
+    ("√", "sqrt"),
+    ("|x|", "abs"),
+];
+
+// Holding a key this many seconds opens its related functions; egui no
+// longer counts a press this long as a click
+const LONG_PRESS: f64 = 0.6;
+// How far left the input has to be swiped to delete a character
+const SWIPE: f32 = 60.0;
+// The smallest key in the touch layout
+const TOUCH_KEY: egui::Vec2 = egui::vec2(56.0, 48.0);
+
+/// The size a window opens at before its monitor is known.
+pub const DEFAULT_WINDOW_SIZE: egui::Vec2 = egui::vec2(400.0, 500.0);
+
+/// The size a window opens at on a monitor of `monitor` points: a share of
+/// it, so the window is neither lost on a large screen nor crowding a small
+/// laptop one. Points already take in the monitor's scale factor. A kiosk
+/// window, the only thing on its screen, takes more of it.
+pub fn default_window_size(monitor: egui::Vec2, kiosk: bool) -> egui::Vec2 {
+    let share = if kiosk { egui::vec2(0.5, 0.85) } else { egui::vec2(0.25, 0.6) };
+    (monitor * share).max(egui::vec2(360.0, 460.0)).min(monitor * 0.9)
+}
+
+impl eframe::App for CalculatorApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // The window opens at a guess; once the monitor it is on is known
+        // it takes the size that suits that monitor
+        if !self.sized {
+            if let Some(monitor) = ctx.input(|i| i.viewport().monitor_size) {
+                ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(default_window_size(monitor, self.kiosk)));
+                self.sized = true;
+            }
+        }
+
+        // A later launch hands over its expression and raises this window
+        let incoming: Vec<String> = self.incoming.iter().flat_map(|incoming| incoming.try_iter()).collect();
+        if !incoming.is_empty() {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+            ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+            for expression in incoming {
+                self.use_expression(expression);
+            }
+        }
+
+        // Pick up changes synced in from other machines
+        if let Some(watcher) = &mut self.watcher {
+            ctx.request_repaint_after(sync::POLL_INTERVAL);
+            if watcher.changed() {
+                self.reload_synced();
+            }
+        }
+
+        let mut new_window = self.ui(ctx);
+        if self.close_requested {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        }
+
+        // Extra windows are drawn as immediate viewports; each one has its
+        // own calculator state but asks this root window to spawn more
+        for window in &mut self.windows {
+            let builder = egui::ViewportBuilder::default()
+                .with_title(format!("Rust Calculator ({})", window.app.number + 1))
+                .with_inner_size(window.size)
+                .with_decorations(!window.app.config.frameless);
+            ctx.show_viewport_immediate(window.id, builder, |ctx, _class| {
+                new_window |= window.app.ui(ctx);
+                if window.app.close_requested || ctx.input(|i| i.viewport().close_requested()) {
+                    window.open = false;
+                }
+            });
+        }
+        self.windows.retain(|window| window.open);
+
+        if new_window {
+            self.next_window += 1;
+            self.windows.push(ExtraWindow {
+                id: egui::ViewportId::from_hash_of(("calculator window", self.next_window)),
+                app: CalculatorApp {
+                    number: self.next_window,
+                    // Only the root window syncs its history
+                    watcher: None,
+                    ..if self.kiosk {
+                        CalculatorApp::kiosk(self.context.profile())
+                    } else {
+                        CalculatorApp::new(self.config.clone()).with_profile(self.context.profile())
+                    }
+                },
+                open: true,
+                size: ctx
+                    .input(|i| i.viewport().monitor_size)
+                    .map_or(DEFAULT_WINDOW_SIZE, |monitor| default_window_size(monitor, self.kiosk)),
+            });
+        }
+    }
+}
+
+impl CalculatorApp {
+    pub fn new(config: Config) -> Self {
+        let folder = sync::folder();
+        let profile = config.profile;
+        let mut app = Self {
+            sync_folder: folder.as_ref().map(|folder| folder.display().to_string()).unwrap_or_default(),
+            watcher: folder.map(|_| Watcher::default()),
+            started: history::now(),
+            ..Default::default()
+        }
+        .with_profile(profile);
+        app.context.arena = Some(Arc::default());
+        app.use_config(config);
+        app.tour.open = !app.config.toured;
+        app.focus_input = true;
+        app
+    }
+
+    // Takes on `config` along with the tax rates and price index it keeps
+    fn use_config(&mut self, config: Config) {
+        self.context.taxes = config.taxes.clone();
+        self.context.price_index = price_index(&config);
+        self.context.custom_units = custom_units(&config);
+        self.config = config;
+    }
+
+    /// Uses `profile` for this run without saving it.
+    pub fn with_profile(mut self, profile: Profile) -> Self {
+        self.context.set_profile(profile);
+        self
+    }
+
+    /// Takes expressions from later launches from `incoming`.
+    pub fn with_instance(mut self, incoming: Option<Receiver<String>>) -> Self {
+        self.incoming = incoming;
+        self
+    }
+
+    /// Opens with `expression` calculated, unless it is empty.
+    pub fn with_expression(mut self, expression: String) -> Self {
+        self.use_expression(expression);
+        self
+    }
+
+    fn use_expression(&mut self, expression: String) {
+        if !expression.trim().is_empty() {
+            self.input = expression;
+            self.calculate();
+        }
+    }
+
+    /// A calculator for exam rooms and public terminals: no files are read
+    /// or written, and the settings, button layout and `profile` cannot be
+    /// changed.
+    pub fn kiosk(profile: Profile) -> Self {
+        let mut app = Self {
+            config: Config::default(),
+            kiosk: true,
+            started: history::now(),
+            ..Default::default()
+        }
+        .with_profile(profile);
+        app.context.arena = Some(Arc::default());
+        app.context.lock();
+        app.focus_input = true;
+        app
+    }
+
+    /// Draws one calculator window and reports whether "New Window" was
+    /// chosen from its menu.
+    fn ui(&mut self, ctx: &egui::Context) -> bool {
+        let mut new_window = false;
+        self.touched |= ctx.input(|i| i.any_touches());
+        let composed = track_composition(ctx, &mut self.composing);
+
+        egui::TopBottomPanel::top("menu").show(ctx, |ui| {
+            egui::menu::bar(ui, |ui| {
+                ui.menu_button("File", |ui| {
+                    if ui.button("New Window").clicked() {
+                        new_window = true;
+                        ui.close_menu();
+                    }
+                    if !self.kiosk {
+                        if ui.button("Sessions…").clicked() {
+                            self.sessions.open = true;
+                            ui.close_menu();
+                        }
+                        if ui.button("Export/Import Settings…").clicked() {
+                            self.transfer.open = true;
+                            ui.close_menu();
+                        }
+                    }
+                    if ui.button("Close Window").clicked() {
+                        self.close_requested = true;
+                        ui.close_menu();
+                    }
+                });
+                if !self.kiosk {
+                    ui.menu_button("Edit", |ui| {
+                        if ui.button("Button Layout…").clicked() {
+                            self.show_layout_editor = true;
+                            ui.close_menu();
+                        }
+                    });
+                }
+                ui.menu_button("Tools", |ui| self.tools.menu(ui));
+                ui.menu_button("Help", |ui| {
+                    if ui.button("Function Catalog…").clicked() {
+                        self.catalog.open = true;
+                        ui.close_menu();
+                    }
+                    if ui.button("Take the Tour").clicked() {
+                        self.tour.start();
+                        ui.close_menu();
+                    }
+                });
+                if self.config.frameless {
+                    self.title_bar(ui);
+                }
+            });
+        });
+
+        if !self.compact {
+            egui::TopBottomPanel::bottom("status").show(ctx, |ui| self.status_bar(ui));
+        }
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let touch = self.touch_layout();
+            if touch {
+                let spacing = ui.spacing_mut();
+                spacing.button_padding = egui::vec2(12.0, 10.0);
+                spacing.item_spacing = egui::vec2(10.0, 10.0);
+                spacing.interact_size.y = TOUCH_KEY.y;
+            }
+            let key_size = if touch { TOUCH_KEY } else { egui::Vec2::ZERO };
+            if !self.compact {
+                ui.heading("Rust Calculator");
+                ui.add_space(10.0);
+            }
+
+            // Input field with keyboard focus, and parameter hints while the
+            // caret is inside a function call
+            ui.horizontal(|ui| {
+                ui.label("Enter calculation:");
+                // A pasted spreadsheet range is offered to the paste dialog
+                // instead of going into the input
+                let separator = self.context.decimal_separator;
+                let range = ui.input_mut(|input| {
+                    let mut range = None;
+                    input.events.retain(|event| match event {
+                        egui::Event::Paste(text) => match pasted_range(text, separator) {
+                            Some(values) => {
+                                range = Some(values);
+                                false
+                            }
+                            None => true,
+                        },
+                        _ => true,
+                    });
+                    range
+                });
+                if let Some(values) = range {
+                    self.paste.offer(values, separator);
+                }
+                // A comma from the numpad's decimal key is typed as the point
+                let id = ui.make_persistent_id("input");
+                if self.config.numpad_comma && separator == DecimalSeparator::Point {
+                    let caret = egui::TextEdit::load_state(ui.ctx(), id)
+                        .and_then(|state| state.cursor.char_range())
+                        .and_then(|range| self.input.char_indices().nth(range.primary.index))
+                        .map_or(self.input.len(), |(i, _)| i);
+                    if comma_is_point(&self.input[..caret]) {
+                        ui.input_mut(|input| {
+                            for event in &mut input.events {
+                                if matches!(event, egui::Event::Text(text) if text == ",") {
+                                    *event = egui::Event::Text(".".to_string());
+                                }
+                            }
+                        });
+                    }
+                }
+                // The input takes the focus when asked to, and when typing
+                // would go to no widget at all; otherwise Tab and clicks move
+                // it freely. Taking it while another field is composing
+                // would commit that field's text here.
+                let typing = ui.input(|i| i.events.iter().any(|event| matches!(event, egui::Event::Text(_))));
+                let unfocused = ui.memory(|memory| memory.focused().is_none());
+                if !self.composing && (std::mem::take(&mut self.focus_input) || typing && unfocused) {
+                    ui.memory_mut(|memory| memory.request_focus(id));
+                }
+                let output = egui::TextEdit::singleline(&mut self.input).id(id).show(ui);
+                output.response.context_menu(|ui| self.input_menu(ui));
+                // Swiping left across the input deletes its last character
+                if touch {
+                    if output.response.dragged() {
+                        self.swipe += output.response.drag_delta().x;
+                    }
+                    if output.response.drag_stopped() {
+                        if self.swipe < -SWIPE {
+                            self.input.pop();
+                        }
+                        self.swipe = 0.0;
+                    }
+                }
+                if let Some(cursor) = output.cursor_range.map(|range| range.primary) {
+                    let caret = self
+                        .input
+                        .char_indices()
+                        .nth(cursor.ccursor.index)
+                        .map_or(self.input.len(), |(i, _)| i);
+                    if let Some((function, index)) = functions::enclosing_call(&self.input, caret) {
+                        let below = output.galley_pos + output.galley.pos_from_cursor(&cursor).left_bottom().to_vec2();
+                        egui::show_tooltip_at(ctx, egui::Id::new("parameter_hint"), Some(below), |ui| {
+                            parameter_hint(ui, function, index);
+                        });
+                    }
+                }
+            });
+
+            // Check for Enter key press; the numpad's Enter arrives as the
+            // same key
+            if !composed && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                self.calculate();
+            }
+            // Ctrl+E (⌘E on a Mac) is the EE key
+            if ui.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::E)) {
+                self.enter_exponent();
+            }
+
+            // Keypad buttons from the configured layout; holding one down
+            // offers the functions related to it
+            ui.horizontal_wrapped(|ui| {
+                let profile = self.context.profile();
+                let mut pressed = None;
+                for (i, button) in self.config.buttons.iter().enumerate() {
+                    if !button.visible || !button.allowed_in(profile) {
+                        continue;
+                    }
+                    let response = ui.add(egui::Button::new(&button.label).min_size(key_size));
+                    if response.clicked() {
+                        pressed = Some(button.action.clone());
+                    }
+                    let related: Vec<KeyButton> =
+                        related_buttons(button).into_iter().filter(|extra| extra.allowed_in(profile)).collect();
+                    if related.is_empty() {
+                        continue;
+                    }
+                    let popup = ui.make_persistent_id(("related", i));
+                    if response.is_pointer_button_down_on() {
+                        let held = ui.input(|i| i.pointer.press_start_time().is_some_and(|start| i.time - start > LONG_PRESS));
+                        if held {
+                            ui.memory_mut(|memory| memory.open_popup(popup));
+                        } else {
+                            ui.ctx().request_repaint();
+                        }
+                    }
+                    egui::popup_below_widget(ui, popup, &response, |ui| {
+                        ui.horizontal(|ui| {
+                            for extra in related {
+                                if ui.add(egui::Button::new(&extra.label).min_size(key_size)).clicked() {
+                                    pressed = Some(extra.action);
+                                }
+                            }
+                        });
+                    });
+                }
+                if let Some(action) = pressed {
+                    self.feedback(Sound::Click);
+                    self.press(action);
+                }
+                if ui.add(egui::Button::new("Clear").min_size(key_size)).clicked() {
+                    self.clear();
+                    self.feedback(Sound::Click);
+                }
+            });
+
+            // Symbols missing from many keyboards, inserted as the parser reads them
+            egui::CollapsingHeader::new("Symbols").id_source("symbols").show(ui, |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    for symbol in SYMBOLS {
+                        if ui.button(symbol.symbol).on_hover_text(symbol.description).clicked() {
+                            self.feedback(Sound::Click);
+                            self.input.push_str(symbol.insert);
+                            self.focus_input = true;
+                        }
+                    }
+                });
+            });
+
+            // Function keys, each starting a call in the input
+            let scientific: Vec<_> = SCIENTIFIC_KEYS
+                .iter()
+                .filter_map(|&(label, name)| Some((label, functions::find(name, &self.context).ok()?)))
+                .collect();
+            if !scientific.is_empty() {
+                egui::CollapsingHeader::new("Scientific").id_source("scientific").show(ui, |ui| {
+                    ui.horizontal_wrapped(|ui| {
+                        for (label, function) in scientific {
+                            let key = ui.add(egui::Button::new(label).min_size(key_size)).on_hover_text(function.description);
+                            if key.clicked() {
+                                self.feedback(Sound::Click);
+                                self.press(ButtonAction::Insert(format!("{}(", function.name)));
+                            }
+                        }
+                    });
+                });
+            }
+
+            // Calculate and tidy buttons
+            ui.horizontal(|ui| {
+                if ui.button("Calculate").clicked() {
+                    self.feedback(Sound::Click);
+                    self.calculate();
+                }
+                if ui.button("Tidy").clicked() {
+                    self.feedback(Sound::Click);
+                    self.tidy();
+                }
+                if ui
+                    .add_enabled(self.last_value.is_some(), egui::Button::new("Stash"))
+                    .on_hover_text("Push the result onto the scratch values")
+                    .clicked()
+                {
+                    self.feedback(Sound::Click);
+                    if let Some(value) = self.last_value {
+                        self.scratch.push(value);
+                    }
+                }
+                // The exact value, written so that pasting it back gives it again
+                if ui
+                    .add_enabled(self.last_value.is_some() || self.big_result.is_some(), egui::Button::new("Copy"))
+                    .on_hover_text("Copy the result's value, to the last digit")
+                    .clicked()
+                {
+                    self.feedback(Sound::Click);
+                    let text = match self.last_value {
+                        Some(value) => Some(format_for_input(value, self.context.decimal_separator)),
+                        None => self.big_result.clone(),
+                    };
+                    if let Some(text) = text {
+                        ui.output_mut(|output| output.copied_text = text);
+                    }
+                }
+            });
+
+            // Display results
+            if !self.result.is_empty() {
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    ui.add(egui::Label::new(&self.result).sense(egui::Sense::click()))
+                        .on_hover_text("Right-click to copy or use the value")
+                        .context_menu(|ui| self.result_menu(ui));
+                    for color in &self.swatches {
+                        swatch(ui, *color);
+                    }
+                    if self.renderings.len() > 1 {
+                        let picked = self.rendering;
+                        egui::ComboBox::from_id_source("rendering")
+                            .selected_text("Show as")
+                            .show_ui(ui, |ui| {
+                                for (i, rendering) in self.renderings.iter().enumerate() {
+                                    ui.selectable_value(&mut self.rendering, i, rendering.as_str());
+                                }
+                            })
+                            .response
+                            .on_hover_text("Show the result in another unit");
+                        if self.rendering != picked {
+                            self.result = format!("Result: {}", self.renderings[self.rendering]);
+                        }
+                    }
+                });
+            }
+            if !self.breakdown.is_empty() {
+                egui::CollapsingHeader::new("Breakdown").show(ui, |ui| {
+                    for line in &self.breakdown {
+                        ui.label(line.as_str());
+                    }
+                });
+            }
+            if let Some(steps) = &self.steps {
+                steps_ui(ui, steps);
+            }
+            if !self.report.is_empty() {
+                ui.label(egui::RichText::new(&self.report).monospace());
+            }
+            if !self.error.is_empty() {
+                ui.add_space(10.0);
+                ui.label(egui::RichText::new(&self.error).color(egui::Color32::RED));
+            }
+            if self.awaiting_operand && is_incomplete(&self.normalized_input(), self.context.limits.max_depth) {
+                ui.add_space(10.0);
+                ui.label(egui::RichText::new("Waiting for operand…").weak());
+            }
+
+            // A compact window ends with the result
+            if self.compact {
+                return;
+            }
+
+            // Scratch values; clicking one inserts it into the input
+            if !self.scratch.is_empty() {
+                ui.add_space(10.0);
+                egui::CollapsingHeader::new(format!("Scratch values ({})", self.scratch.len()))
+                    .default_open(true)
+                    .show(ui, |ui| self.scratch_ui(ui));
+            }
+
+            // History, unless it has been popped out into its own window
+            if !self.history_detached {
+                ui.add_space(10.0);
+                egui::CollapsingHeader::new("History").show(ui, |ui| self.history_ui(ui));
+            }
+
+            // Show what sanitizing took out of pasted input
+            let removed = sanitize(&self.input).removed;
+            if !removed.is_empty() {
+                ui.add_space(10.0);
+                egui::CollapsingHeader::new(format!("Removed {} invisible character(s)", removed.len())).show(
+                    ui,
+                    |ui| {
+                        for (position, c) in removed {
+                            ui.label(format!(
+                                "position {}: U+{:04X} {}",
+                                position,
+                                c as u32,
+                                invisible_name(c).unwrap_or("")
+                            ));
+                        }
+                    },
+                );
+            }
+
+            // Settings
+            ui.add_space(10.0);
+            egui::CollapsingHeader::new("Settings").enabled(!self.kiosk).show(ui, |ui| {
+                egui::ComboBox::from_label("On overflow")
+                    .selected_text(self.context.overflow.label())
+                    .show_ui(ui, |ui| {
+                        for mode in OverflowMode::ALL {
+                            ui.selectable_value(&mut self.context.overflow, mode, mode.label());
+                        }
+                    });
+                egui::ComboBox::from_label("On NaN")
+                    .selected_text(self.context.nan.label())
+                    .show_ui(ui, |ui| {
+                        for policy in NanPolicy::ALL {
+                            ui.selectable_value(&mut self.context.nan, policy, policy.label());
+                        }
+                    });
+                ui.checkbox(&mut self.context.normalize_negative_zero, "Show -0 as 0");
+                let money = |currency: Option<&Currency>| match currency {
+                    Some(currency) => format!("{} ({})", currency.code, currency.name),
+                    None => "Off".to_string(),
+                };
+                egui::ComboBox::from_label("Money mode")
+                    .selected_text(money(self.context.currency))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.context.currency, None, money(None));
+                        for currency in CURRENCIES {
+                            ui.selectable_value(&mut self.context.currency, Some(currency), money(Some(currency)));
+                        }
+                    })
+                    .response
+                    .on_hover_text("Round results to the currency's smallest unit");
+                egui::ComboBox::from_label("Decimal separator")
+                    .selected_text(self.context.decimal_separator.label())
+                    .show_ui(ui, |ui| {
+                        for separator in DecimalSeparator::ALL {
+                            ui.selectable_value(&mut self.context.decimal_separator, separator, separator.label());
+                        }
+                    });
+                if self.context.decimal_separator == DecimalSeparator::Point {
+                    let numpad = ui
+                        .checkbox(&mut self.config.numpad_comma, "Numpad comma is the decimal point")
+                        .on_hover_text("A comma typed after a number, outside parentheses, is typed as a point");
+                    if numpad.changed() {
+                        if let Err(err) = config::save(&self.config) {
+                            self.error = format!("Error: could not save settings: {}", err);
+                        }
+                    }
+                }
+                ui.checkbox(&mut self.context.cjk_numerals, "Read CJK numerals");
+                ui.checkbox(&mut self.superscripts, "Tidy powers into superscripts")
+                    .on_hover_text("Tidy writes x^2 as x²");
+                egui::ComboBox::from_label("Angle unit")
+                    .selected_text(self.context.angle.label())
+                    .show_ui(ui, |ui| {
+                        for mode in AngleMode::ALL {
+                            ui.selectable_value(&mut self.context.angle, mode, mode.label());
+                        }
+                    });
+                ui.horizontal_wrapped(|ui| {
+                    let limits = &mut self.context.limits;
+                    ui.label("Input limits:");
+                    ui.add(egui::DragValue::new(&mut limits.max_length).clamp_range(1..=1_000_000).suffix(" characters"));
+                    ui.add(egui::DragValue::new(&mut limits.max_tokens).clamp_range(1..=100_000).suffix(" parts"));
+                    ui.add(egui::DragValue::new(&mut limits.max_depth).clamp_range(1..=1_000).suffix(" levels"));
+                });
+                let profile = self.context.profile();
+                egui::ComboBox::from_label("Profile")
+                    .selected_text(profile.label())
+                    .show_ui(ui, |ui| {
+                        for choice in Profile::ALL {
+                            if ui.selectable_label(profile == choice, choice.label()).clicked() {
+                                self.context.set_profile(choice);
+                                self.config.profile = choice;
+                                if let Err(err) = config::save(&self.config) {
+                                    self.error = format!("Error: could not save profile: {}", err);
+                                }
+                            }
+                        }
+                    });
+                ui.horizontal_wrapped(|ui| {
+                    ui.label("Function packs:");
+                    let allowed = |pack: Pack| pack != Pack::Core && pack.is_available() && profile.allows_pack(pack);
+                    for pack in Pack::ALL.into_iter().filter(|&pack| allowed(pack)) {
+                        let mut enabled = self.context.has_pack(pack);
+                        if ui.checkbox(&mut enabled, pack.label()).changed() {
+                            self.context.set_pack(pack, enabled);
+                        }
+                    }
+                });
+                self.taxes_ui(ui);
+                self.price_indexes_ui(ui);
+                self.units_ui(ui);
+                if self.number == 0 {
+                    self.sync_ui(ui);
+                }
+                if speech::AVAILABLE {
+                    ui.checkbox(&mut self.speak_results, "Read results aloud");
+                }
+                let touch = self.config.touch;
+                egui::ComboBox::from_label("Touch layout")
+                    .selected_text(touch.label())
+                    .show_ui(ui, |ui| {
+                        for choice in TouchLayout::ALL {
+                            ui.selectable_value(&mut self.config.touch, choice, choice.label());
+                        }
+                    })
+                    .response
+                    .on_hover_text("Larger keys, swipe left on the input to delete, hold an operator for more");
+                if self.config.touch != touch {
+                    if let Err(err) = config::save(&self.config) {
+                        self.error = format!("Error: could not save settings: {}", err);
+                    }
+                }
+                let frame = ui
+                    .checkbox(&mut self.config.frameless, "Draw own title bar")
+                    .on_hover_text("Some window managers do not handle windows without a frame well");
+                if frame.changed() {
+                    ui.ctx().send_viewport_cmd(egui::ViewportCommand::Decorations(!self.config.frameless));
+                    // Without a title bar of either kind there is nothing to
+                    // leave compact mode with
+                    self.compact &= self.config.frameless;
+                    if let Err(err) = config::save(&self.config) {
+                        self.error = format!("Error: could not save settings: {}", err);
+                    }
+                }
+                if sound::AVAILABLE {
+                    ui.checkbox(&mut self.click_sounds, "Click sound on buttons");
+                    ui.checkbox(&mut self.error_sounds, "Buzz on errors");
+                }
+            });
+
+            // What the tour showed, folded away until wanted
+            ui.add_space(10.0);
+            egui::CollapsingHeader::new("Help").id_source("help").show(ui, |ui| {
+                ui.label("• Enter numbers and operators (+, -, *, /)");
+                ui.label("• * and / go before + and -; group with parentheses, e.g. '(5 + 3) * 2'");
+                ui.label("• Powers with ^ or ** go first, right to left: '2^3^2' is 512, and '-2^2' is -4");
+                ui.label("• % is the remainder, with the sign of the divisor: '7 % 3' is 1, '-7 % 3' is 2");
+                ui.label("• Press Enter or click Calculate to compute");
+                ui.label("• Spaces are optional (e.g., '5+3' or '5 + 3')");
+                ui.label("• Scientific notation is supported (e.g., '1e3 + 2e3')");
+                ui.label("• Hex floats such as '0x1.8p3' are accepted");
+                ui.label("• Links such as calc://5*12 are calculated here once 'calculator register-url-handler' has been run");
+                ui.label("• Temperatures: '20 °C + 10 K', 'convert(98.6, F, C)'");
+                ui.label("• Feet and inches: '5' 3 1/2\" + 2' 7/8\"', shown to the nearest 1/16\"");
+                ui.label("• Colors are written '#336699'");
+                ui.label("• Lists in calls are separated by ';', as in 'wavg(90, 80; 1, 2)'");
+                ui.label("• Paste a spreadsheet range to sum, average or stash its numbers");
+                ui.label("• Set variables with 'vat = 0.20'; History → Recalculate all updates past results");
+                ui.label("• Help → Function Catalog lists every function with an example");
+                ui.label("• Right-click the result or the input for more");
+                ui.label("• Hold an operator key for related functions");
+                if ui.button("Take the tour").clicked() {
+                    self.tour.start();
+                }
+            });
+        });
+
+        self.tools.show(ctx, &self.context);
+        if let Some(example) = self.catalog.show(ctx, &self.context) {
+            self.input.push_str(&example);
+            self.focus_input = true;
+        }
+        match self.tour.show(ctx) {
+            Some(TourEvent::Try(example)) => {
+                self.input = example.to_string();
+                self.calculate();
+            }
+            Some(TourEvent::Finished) if !self.config.toured => {
+                self.config.toured = true;
+                if !self.kiosk {
+                    if let Err(err) = config::save(&self.config) {
+                        self.error = format!("Error: could not save settings: {}", err);
+                    }
+                }
+            }
+            _ => {}
+        }
+        match self.transfer.show(ctx) {
+            Some(TransferChoice::Export(path)) => {
+                let setup = Setup {
+                    config: self.config.clone(),
+                    settings: self.settings(),
+                    constants: self.constants(),
+                };
+                self.transfer.message = match transfer::export(&path, &setup) {
+                    Ok(()) => format!("Exported to {}", path.display()),
+                    Err(err) => format!("Error: {}", err),
+                };
+            }
+            Some(TransferChoice::Import(path)) => {
+                self.transfer.message = match transfer::import(&path).and_then(|setup| self.import(setup)) {
+                    Ok(()) => format!("Imported {}", path.display()),
+                    Err(err) => format!("Error: {}", err),
+                };
+            }
+            None => {}
+        }
+        match self.paste.show(ctx, self.context.profile().allows_calls()) {
+            Some(PasteChoice::Input(expression)) => {
+                self.input = expression;
+                self.error.clear();
+                self.focus_input = true;
+            }
+            Some(PasteChoice::Stash(values)) => {
+                for value in values {
+                    self.scratch.push(value);
+                }
+            }
+            Some(PasteChoice::Plot(values)) => self.plot.plot(&values),
+            None => {}
+        }
+        self.plot.show(ctx, self.number);
+        match self.sessions.show(ctx) {
+            Some(Choice::Save(name)) => {
+                self.sessions.message = match session::save(&name, &self.session()) {
+                    Ok(()) => format!("Saved '{}'", name),
+                    Err(err) => format!("Error: {}", err),
+                };
+            }
+            Some(Choice::Restore(name)) => {
+                self.sessions.message = match session::load(&name) {
+                    Ok(saved) => {
+                        self.restore(saved);
+                        format!("Restored '{}'", name)
+                    }
+                    Err(err) => format!("Error: {}", err),
+                };
+            }
+            None => {}
+        }
+
+        if layout_editor::show(ctx, &mut self.show_layout_editor, &mut self.config.buttons) {
+            if let Err(err) = config::save(&self.config) {
+                self.error = format!("Error: could not save layout: {}", err);
+            }
+        }
+
+        if self.history_detached {
+            let builder = egui::ViewportBuilder::default()
+                .with_title("Calculator History")
+                .with_inner_size([300.0, 400.0]);
+            ctx.show_viewport_immediate(egui::ViewportId::from_hash_of(("history", self.number)), builder, |ctx, _class| {
+                egui::CentralPanel::default().show(ctx, |ui| self.history_ui(ui));
+                // Closing the history window docks it back
+                if ctx.input(|i| i.viewport().close_requested()) {
+                    self.history_detached = false;
+                }
+            });
+        }
+
+        new_window
+    }
+
+    fn touch_layout(&self) -> bool {
+        match self.config.touch {
+            TouchLayout::Auto => self.touched,
+            TouchLayout::Always => true,
+            TouchLayout::Never => false,
+        }
+    }
+
+    // The rest of the menu bar in frameless mode: a region that drags the
+    // window and the buttons a frame would have, with pin and compact
+    fn title_bar(&mut self, ui: &mut egui::Ui) {
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            if ui.button("❌").on_hover_text("Close").clicked() {
+                self.close_requested = true;
+            }
+            let maximized = ui.input(|i| i.viewport().maximized.unwrap_or(false));
+            if ui.button(if maximized { "🗗" } else { "🗖" }).on_hover_text("Maximize").clicked() {
+                ui.ctx().send_viewport_cmd(egui::ViewportCommand::Maximized(!maximized));
+            }
+            if ui.button("🗕").on_hover_text("Minimize").clicked() {
+                ui.ctx().send_viewport_cmd(egui::ViewportCommand::Minimized(true));
+            }
+            ui.toggle_value(&mut self.compact, "Compact").on_hover_text("Show only the input, keypad and result");
+            if ui.toggle_value(&mut self.pinned, "📌").on_hover_text("Keep above other windows").changed() {
+                let level = if self.pinned { egui::WindowLevel::AlwaysOnTop } else { egui::WindowLevel::Normal };
+                ui.ctx().send_viewport_cmd(egui::ViewportCommand::WindowLevel(level));
+            }
+            let drag = ui.allocate_response(ui.available_size(), egui::Sense::click_and_drag());
+            if drag.double_clicked() {
+                ui.ctx().send_viewport_cmd(egui::ViewportCommand::Maximized(!maximized));
+            } else if drag.drag_started_by(egui::PointerButton::Primary) {
+                ui.ctx().send_viewport_cmd(egui::ViewportCommand::StartDrag);
+            }
+        });
+    }
+
+    // The modes that change how results come out, each switched by a click
+    fn status_bar(&mut self, ui: &mut egui::Ui) {
+        ui.add_enabled_ui(!self.kiosk, |ui| {
+            ui.horizontal(|ui| {
+                let angle = self.context.angle;
+                let hover = format!("Angles are in {}; click for the next unit", angle.label().to_lowercase());
+                if ui.small_button(angle.abbreviation()).on_hover_text(hover).clicked() {
+                    let next = AngleMode::ALL.iter().position(|&mode| mode == angle).map_or(0, |i| i + 1);
+                    self.context.angle = AngleMode::ALL[next % AngleMode::ALL.len()];
+                }
+                let (separator, other) = match self.context.decimal_separator {
+                    DecimalSeparator::Point => ("1.5", DecimalSeparator::Comma),
+                    DecimalSeparator::Comma => ("1,5", DecimalSeparator::Point),
+                };
+                if ui.small_button(separator).on_hover_text("Decimal separator; click to switch").clicked() {
+                    self.context.decimal_separator = other;
+                }
+                if let Some(currency) = self.context.currency {
+                    let hover = format!("Money mode: results are rounded to {} amounts; click to turn off", currency.name);
+                    if ui.small_button(currency.code).on_hover_text(hover).clicked() {
+                        self.context.currency = None;
+                    }
+                }
+                if self.context.overflow == OverflowMode::Infinity
+                    && ui.small_button("±∞").on_hover_text("Overflow gives ±infinity; click to report an error").clicked()
+                {
+                    self.context.overflow = OverflowMode::Error;
+                }
+                if self.context.overflow == OverflowMode::Promote
+                    && ui.small_button("big").on_hover_text("Overflow switches to big numbers; click to report an error").clicked()
+                {
+                    self.context.overflow = OverflowMode::Error;
+                }
+                if self.context.nan == NanPolicy::Propagate
+                    && ui.small_button("NaN").on_hover_text("NaN flows through results; click to report an error").clicked()
+                {
+                    self.context.nan = NanPolicy::Error;
+                }
+                let profile = self.context.profile();
+                if profile != Profile::Full {
+                    ui.label(profile.label()).on_hover_text("The calculator's profile, set in Settings");
+                }
+            });
+        });
+    }
+
+    fn history_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let toggle = if self.history_detached { "Dock" } else { "Pop out" };
+            if ui.button(toggle).clicked() {
+                self.history_detached = !self.history_detached;
+            }
+            if ui
+                .button("Recalculate all")
+                .on_hover_text("Evaluate every entry again with the current variables")
+                .clicked()
+            {
+                self.recalculate_history();
+            }
+            if ui.button("Clear history").clicked() {
+                self.history.clear();
+                self.compared.clear();
+            }
+            ui.toggle_value(&mut self.show_stats, "Statistics")
+                .on_hover_text("How much the calculator is used, this session and in all");
+        });
+
+        if self.history.is_empty() {
+            ui.label(egui::RichText::new("No calculations yet").weak());
+            return;
+        }
+
+        if self.seek_target.is_some() {
+            self.goal_seek_ui(ui);
+        }
+        if self.compared.len() == 2 {
+            self.comparison_ui(ui);
+        }
+        if self.show_stats {
+            self.stats_ui(ui);
+        }
+
+        // Newest first; clicking an entry puts its expression back in the input
+        let (mut reuse, mut target, mut compare) = (None, None, None);
+        egui::ScrollArea::vertical().max_height(ui.available_height()).show(ui, |ui| {
+            for entry in self.history.entries().iter().rev() {
+                let mut text = match &entry.result {
+                    Ok(value) => format!("{} = {}", entry.expression, format_number(*value)),
+                    Err(err) => format!("{} → {}", entry.expression, err),
+                };
+                if entry.count > 1 {
+                    text.push_str(&format!("  ×{}", entry.count));
+                }
+                // Results changed by the last recalculation stand out
+                let text = match &entry.previous {
+                    Some(previous) => {
+                        let previous = match previous {
+                            Ok(value) => format_number(*value),
+                            Err(err) => err.clone(),
+                        };
+                        text.push_str(&format!("  (was {})", previous));
+                        egui::RichText::new(text).strong().color(egui::Color32::from_rgb(230, 140, 0))
+                    }
+                    None => egui::RichText::new(text),
+                };
+                ui.horizontal(|ui| {
+                    if ui.small_button("🎯").on_hover_text("Goal seek: make this line reach a value").clicked() {
+                        target = Some(entry.expression.clone());
+                    }
+                    let key = (entry.time, entry.expression.clone());
+                    let picked = self.compared.contains(&key);
+                    let pick = ui
+                        .add_enabled(entry.result.is_ok(), egui::SelectableLabel::new(picked, "⚖"))
+                        .on_hover_text("Compare: pick two results");
+                    if pick.clicked() {
+                        compare = Some(key);
+                    }
+                    let settings = match entry.settings.as_str() {
+                        "" => "Its settings were not recorded".to_string(),
+                        settings => format!("Calculated with {}", settings),
+                    };
+                    let label = ui.selectable_label(false, text).on_hover_text(settings);
+                    if label.clicked() {
+                        reuse = Some(entry.expression.clone());
+                    }
+                    // Reusing it with its settings calculates it as it was
+                    label.context_menu(|ui| {
+                        if ui.add_enabled(!entry.settings.is_empty(), egui::Button::new("Reuse with its settings")).clicked() {
+                            reuse = Some(format!("{} {}", entry.settings, entry.expression));
+                            ui.close_menu();
+                        }
+                    });
+                });
+            }
+        });
+        if let Some(expression) = reuse {
+            self.input = expression;
+            self.focus_input = true;
+        }
+        if target.is_some() {
+            self.seek_target = target;
+            self.seek_outcome = None;
+        }
+        if let Some(key) = compare {
+            match self.compared.iter().position(|picked| *picked == key) {
+                Some(i) => {
+                    self.compared.remove(i);
+                }
+                None => {
+                    // A third pick replaces the newer of the two
+                    self.compared.truncate(1);
+                    self.compared.push(key);
+                }
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.focus_input = true;
+        self.awaiting_operand = false;
+        self.input.clear();
+        self.result.clear();
+        self.renderings.clear();
+        self.breakdown.clear();
+        self.steps = None;
+        self.big_result = None;
+        self.report.clear();
+        self.swatches.clear();
+        self.error.clear();
+    }
+
+    // Right-click on the input: tidying it and inserting named values
+    fn input_menu(&mut self, ui: &mut egui::Ui) {
+        let removed = sanitize(&self.input).removed.len();
+        let remove = ui
+            .add_enabled(removed > 0, egui::Button::new("Remove invisible characters"))
+            .on_hover_text("Strip what a paste brought along that cannot be seen");
+        if remove.clicked() {
+            self.input = sanitize(&self.input).text;
+            ui.close_menu();
+        }
+        if ui.button("Clear").clicked() {
+            self.clear();
+            ui.close_menu();
+        }
+        ui.menu_button("Insert", |ui| {
+            let mut named = vec![("π".to_string(), PI)];
+            named.extend(self.constants());
+            for (name, value) in named {
+                if ui.button(format!("{} = {}", name, format_number(value))).clicked() {
+                    self.input.push_str(&name);
+                    self.focus_input = true;
+                    ui.close_menu();
+                }
+            }
+        });
+    }
+
+    // Right-click on the result: what can be done with its value
+    fn result_menu(&mut self, ui: &mut egui::Ui) {
+        let Some(value) = self.last_value else {
+            ui.label(egui::RichText::new("This result has no single value").weak());
+            return;
+        };
+        if ui.button("Copy").clicked() {
+            let text = format_for_input(value, self.context.decimal_separator);
+            ui.output_mut(|output| output.copied_text = text);
+            ui.close_menu();
+        }
+        if ui.button("Copy as LaTeX").clicked() {
+            ui.output_mut(|output| output.copied_text = format_latex(value));
+            ui.close_menu();
+        }
+        if ui.button("Stash").on_hover_text("Push it onto the scratch values").clicked() {
+            self.scratch.push(value);
+            ui.close_menu();
+        }
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.variable_name).hint_text("name").desired_width(80.0));
+            let name = self.variable_name.trim().to_string();
+            if ui.add_enabled(is_identifier(&name), egui::Button::new("Use as variable")).clicked() {
+                self.context.set(&name, value);
+                self.variable_name.clear();
+                ui.close_menu();
+            }
+        });
+        let bases = format_bases(value);
+        ui.add_enabled_ui(bases.is_some(), |ui| {
+            ui.menu_button("Other bases", |ui| {
+                for (base, text) in bases.into_iter().flatten() {
+                    if ui.button(format!("{}: {}", base, text)).on_hover_text("Click to copy").clicked() {
+                        ui.output_mut(|output| output.copied_text = text);
+                        ui.close_menu();
+                    }
+                }
+            });
+        });
+    }
+
+    fn stats_ui(&self, ui: &mut egui::Ui) {
+        let summaries = [self.history.summary(self.started), self.history.summary(0)];
+        ui.group(|ui| {
+            egui::Grid::new("stats").num_columns(3).striped(true).show(ui, |ui| {
+                ui.label("");
+                ui.strong("This session");
+                ui.strong("All time");
+                ui.end_row();
+                let columns = summaries.map(|summary| {
+                    let top: Vec<String> =
+                        summary.functions.iter().take(3).map(|(name, count)| format!("{} ({})", name, count)).collect();
+                    [
+                        summary.calculations.to_string(),
+                        format!("{:.0}%", summary.error_rate() * 100.0),
+                        format!("{:.1} characters", summary.average_length),
+                        if top.is_empty() { "—".to_string() } else { top.join(", ") },
+                    ]
+                });
+                for (i, label) in ["Calculations", "Errors", "Average length", "Most used"].into_iter().enumerate() {
+                    ui.label(label);
+                    for column in &columns {
+                        ui.label(&column[i]);
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+    }
+
+    // The two picked results as they are now, the older one first
+    fn comparison_ui(&mut self, ui: &mut egui::Ui) {
+        let entries: Vec<_> = self
+            .history
+            .entries()
+            .iter()
+            .filter(|entry| self.compared.iter().any(|(time, expression)| *time == entry.time && *expression == entry.expression))
+            .collect();
+        let [first, second] = entries[..] else {
+            return;
+        };
+        let (Ok(a), Ok(b)) = (&first.result, &second.result) else {
+            return;
+        };
+        let mut close = false;
+        ui.group(|ui| {
+            ui.horizontal(|ui| {
+                ui.label(format!("From {} to {}", first.expression, second.expression));
+                close = ui.small_button("✖").clicked();
+            });
+            ui.label(Comparison::new(*a, *b).to_string());
+        });
+        if close {
+            self.compared.clear();
+        }
+    }
+
+    // Solves for the free variable that brings the target line to the goal,
+    // then sets it and recalculates the history with it
+    fn goal_seek_ui(&mut self, ui: &mut egui::Ui) {
+        let Some(target) = self.seek_target.clone() else {
+            return;
+        };
+        let mut close = false;
+        ui.group(|ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label(format!("Make {} equal", target));
+                ui.add(egui::TextEdit::singleline(&mut self.seek_goal).hint_text("goal").desired_width(70.0));
+                ui.label("by changing");
+                let variables = self.context.variables();
+                let selected = if self.seek_free.is_empty() { "a variable" } else { self.seek_free.as_str() };
+                egui::ComboBox::from_id_source("goal seek variable").selected_text(selected).show_ui(ui, |ui| {
+                    for (name, _) in &variables {
+                        ui.selectable_value(&mut self.seek_free, name.to_string(), *name);
+                    }
+                });
+                if ui.button("Solve").clicked() {
+                    let outcome = directives::calculation(&target, &self.context).and_then(|(target, context)| {
+                        let goal = calculate_with(&self.seek_goal, &context)?;
+                        goal_seek(target, &self.seek_free, goal, &context)
+                    });
+                    if let Ok(value) = outcome {
+                        self.context.set(&self.seek_free, value);
+                        self.recalculate_history();
+                    }
+                    self.seek_outcome =
+                        Some(outcome.map(|value| format!("Set {} = {}", self.seek_free, format_number(value))));
+                }
+                close = ui.small_button("✖").clicked();
+            });
+            match &self.seek_outcome {
+                Some(Ok(message)) => {
+                    ui.label(message.as_str());
+                }
+                Some(Err(err)) => {
+                    ui.label(egui::RichText::new(err).color(egui::Color32::RED));
+                }
+                None => {}
+            }
+        });
+        if close {
+            self.seek_target = None;
+        }
+    }
+
+    // Assignments are evaluated without setting their variable again, so
+    // the current values are the ones used
+    fn recalculate_history(&mut self) {
+        let context = &self.context;
+        self.history.recalculate(|expression| {
+            let (expression, context) = directives::calculation(expression, context)?;
+            let expression = assignment(expression).map_or(expression, |(_, value)| value);
+            calculate_quantity(expression, &context).map(|quantity| quantity.value)
+        });
+    }
+
+    // Named sales tax rates for `withtax`, saved in the configuration
+    fn taxes_ui(&mut self, ui: &mut egui::Ui) {
+        ui.collapsing("Sales tax rates", |ui| {
+            let (mut changed, mut remove, mut keys) = (false, None, None);
+            egui::Grid::new("tax rates").show(ui, |ui| {
+                for (name, tax) in self.config.taxes.iter_mut() {
+                    ui.label(name.as_str());
+                    changed |= ui
+                        .add(egui::DragValue::new(&mut tax.rate).suffix("%").speed(0.05).clamp_range(0.0..=99.0))
+                        .changed();
+                    changed |= ui
+                        .checkbox(&mut tax.inclusive, "Inclusive")
+                        .on_hover_text("The rate is a share of the price including tax")
+                        .changed();
+                    if ui.small_button("Add keys").on_hover_text("Add +tax and −tax keys to the keypad").clicked() {
+                        keys = Some(name.clone());
+                    }
+                    if ui.small_button("✖").clicked() {
+                        remove = Some(name.clone());
+                    }
+                    ui.end_row();
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.add(egui::TextEdit::singleline(&mut self.new_tax).hint_text("Name, e.g. NY").desired_width(80.0));
+                let name = self.new_tax.trim().to_string();
+                let valid = !name.is_empty() && !name.contains('"') && !self.config.taxes.contains_key(&name);
+                if ui.add_enabled(valid, egui::Button::new("Add rate")).clicked() {
+                    self.config.taxes.insert(name, TaxRate::default());
+                    self.new_tax.clear();
+                    changed = true;
+                }
+            });
+            if let Some(name) = remove {
+                self.config.taxes.remove(&name);
+                changed = true;
+            }
+            if let Some(name) = keys {
+                self.config.buttons.push(KeyButton::apply(&format!("+{}", name), &format!("withtax(ans, \"{}\")", name)));
+                self.config.buttons.push(KeyButton::apply(&format!("−{}", name), &format!("withouttax(ans, \"{}\")", name)));
+                changed = true;
+            }
+            if changed {
+                self.context.taxes = self.config.taxes.clone();
+                if let Err(err) = config::save(&self.config) {
+                    self.error = format!("Error: could not save tax rates: {}", err);
+                }
+            }
+        });
+    }
+
+    // Price index tables by country for `inflate`, saved in the configuration
+    fn price_indexes_ui(&mut self, ui: &mut egui::Ui) {
+        ui.collapsing("Price indexes", |ui| {
+            let (mut changed, mut remove, mut close) = (false, None, false);
+            ui.horizontal(|ui| {
+                ui.label("inflate uses:");
+                let selected = self.config.price_index.clone().unwrap_or_else(|| "US (bundled)".to_string());
+                egui::ComboBox::from_id_source("price index").selected_text(selected).show_ui(ui, |ui| {
+                    changed |= ui.selectable_value(&mut self.config.price_index, None, "US (bundled)").changed();
+                    for country in self.config.price_indexes.keys() {
+                        changed |= ui
+                            .selectable_value(&mut self.config.price_index, Some(country.clone()), country.as_str())
+                            .changed();
+                    }
+                });
+            });
+            egui::Grid::new("price indexes").show(ui, |ui| {
+                for (country, index) in &self.config.price_indexes {
+                    ui.label(country.as_str());
+                    if ui.small_button("Edit").clicked() {
+                        self.index_editor = Some((country.clone(), index.to_text()));
+                        self.index_error.clear();
+                    }
+                    if ui.small_button("✖").clicked() {
+                        remove = Some(country.clone());
+                    }
+                    ui.end_row();
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.add(egui::TextEdit::singleline(&mut self.new_country).hint_text("Country, e.g. UK").desired_width(80.0));
+                let country = self.new_country.trim().to_string();
+                let valid = !country.is_empty() && !self.config.price_indexes.contains_key(&country);
+                if ui.add_enabled(valid, egui::Button::new("Add table")).clicked() {
+                    self.config.price_indexes.insert(country.clone(), PriceIndex::default());
+                    self.index_editor = Some((country, String::new()));
+                    self.new_country.clear();
+                    changed = true;
+                }
+            });
+            if let Some((country, text)) = &mut self.index_editor {
+                ui.label(format!("{}: one year and index value per line", country));
+                ui.add(egui::TextEdit::multiline(text).hint_text("1990, 130.7\n2000, 172.2").desired_rows(6));
+                ui.horizontal(|ui| {
+                    if ui.button("Save table").clicked() {
+                        match PriceIndex::parse(text) {
+                            Ok(index) => {
+                                self.config.price_indexes.insert(country.clone(), index);
+                                self.index_error.clear();
+                                changed = true;
+                            }
+                            Err(err) => self.index_error = err,
+                        }
+                    }
+                    if ui.button("Close").clicked() {
+                        close = true;
+                    }
+                });
+                if !self.index_error.is_empty() {
+                    ui.label(egui::RichText::new(&self.index_error).color(egui::Color32::RED));
+                }
+            }
+            if let Some(country) = remove {
+                close |= self.index_editor.as_ref().is_some_and(|(editing, _)| *editing == country);
+                if self.config.price_index.as_ref() == Some(&country) {
+                    self.config.price_index = None;
+                }
+                self.config.price_indexes.remove(&country);
+                changed = true;
+            }
+            if close {
+                self.index_editor = None;
+            }
+            if changed {
+                self.context.price_index = price_index(&self.config);
+                if let Err(err) = config::save(&self.config) {
+                    self.error = format!("Error: could not save price indexes: {}", err);
+                }
+            }
+        });
+    }
+
+    // Units of measure defined by the user, saved in the configuration
+    fn units_ui(&mut self, ui: &mut egui::Ui) {
+        ui.collapsing("Custom units", |ui| {
+            let (mut changed, mut remove) = (false, None);
+            for (i, definition) in self.config.units.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.label(definition.as_str());
+                    if ui.small_button("✖").clicked() {
+                        remove = Some(i);
+                    }
+                });
+            }
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.new_unit)
+                        .hint_text("1 furlong = 201.168 m")
+                        .desired_width(160.0),
+                );
+                if ui.button("Define").clicked() {
+                    match define_unit(&self.new_unit, &self.context) {
+                        Ok(_) => {
+                            self.config.units.push(self.new_unit.trim().to_string());
+                            self.new_unit.clear();
+                            self.unit_error.clear();
+                            changed = true;
+                        }
+                        Err(err) => self.unit_error = err,
+                    }
+                }
+            });
+            if !self.unit_error.is_empty() {
+                ui.label(egui::RichText::new(&self.unit_error).color(egui::Color32::RED));
+            }
+            if let Some(i) = remove {
+                self.config.units.remove(i);
+                changed = true;
+            }
+            if changed {
+                self.context.custom_units = custom_units(&self.config);
+                if let Err(err) = config::save(&self.config) {
+                    self.error = format!("Error: could not save custom units: {}", err);
+                }
+            }
+        });
+    }
+
+    fn sync_ui(&mut self, ui: &mut egui::Ui) {
+        let mut choice = None;
+        ui.horizontal(|ui| {
+            ui.label("Sync folder:");
+            ui.text_edit_singleline(&mut self.sync_folder);
+            if ui
+                .button("Use")
+                .on_hover_text("Keep settings, sessions and history in this folder, e.g. a Dropbox or Syncthing folder")
+                .clicked()
+            {
+                choice = Some(Some(PathBuf::from(self.sync_folder.trim())));
+            }
+            if self.watcher.is_some() && ui.button("Stop").clicked() {
+                choice = Some(None);
+            }
+        });
+        if let Some(folder) = choice {
+            let stopping = folder.is_none();
+            self.sync_error = match self.use_sync_folder(folder) {
+                Ok(()) => String::new(),
+                Err(err) => format!("Error: {}", err),
+            };
+            if stopping {
+                self.sync_folder.clear();
+            }
+        }
+        if !self.sync_error.is_empty() {
+            ui.label(egui::RichText::new(&self.sync_error).color(egui::Color32::RED));
+        }
+    }
+
+    fn scratch_ui(&mut self, ui: &mut egui::Ui) {
+        let mut insert = None;
+        let mut remove = None;
+        ui.horizontal_wrapped(|ui| {
+            for (i, value) in self.scratch.values().iter().enumerate().rev() {
+                let response = ui.button(format_number(*value)).on_hover_text("Click to insert, right-click to drop");
+                if response.clicked() {
+                    insert = Some(*value);
+                }
+                if response.secondary_clicked() {
+                    remove = Some(i);
+                }
+            }
+            if ui.small_button("Clear").clicked() {
+                self.scratch.clear();
+            }
+            if ui.small_button("Plot").on_hover_text("Show a histogram and box plot of the scratch values").clicked() {
+                self.plot.plot(self.scratch.values());
+            }
+        });
+        if let Some(value) = insert {
+            self.input.push_str(&format_for_input(value, self.context.decimal_separator));
+            self.focus_input = true;
+        }
+        if let Some(i) = remove {
+            self.scratch.remove(i);
+        }
+    }
+
+    // This window's variables, scratch values, history and settings
+    fn session(&self) -> Session {
+        Session {
+            variables: self.constants(),
+            scratch: self.scratch.values().to_vec(),
+            history: session::saved_entries(&self.history),
+            settings: self.settings(),
+        }
+    }
+
+    // Replaces this window's state with a saved session
+    fn restore(&mut self, saved: Session) {
+        let arena = self.context.arena.take();
+        self.context = Context::new();
+        self.context.arena = arena;
+        self.apply_settings(saved.settings);
+        for (name, value) in &saved.variables {
+            self.context.set(name, *value);
+        }
+
+        self.scratch.clear();
+        for value in saved.scratch {
+            self.scratch.push(value);
+        }
+        self.history = session::restored_history(&saved.history);
+        self.repeat = None;
+        self.last_value = None;
+    }
+
+    fn constants(&self) -> BTreeMap<String, f64> {
+        self.context.variables().into_iter().map(|(name, value)| (name.to_string(), value)).collect()
+    }
+
+    fn settings(&self) -> Settings {
+        let context = &self.context;
+        Settings {
+            overflow: context.overflow,
+            nan: context.nan,
+            normalize_negative_zero: context.normalize_negative_zero,
+            decimal_separator: context.decimal_separator,
+            cjk_numerals: context.cjk_numerals,
+            angle: context.angle,
+            limits: context.limits,
+            currency: context.currency.map(|currency| currency.code.to_string()),
+            disabled_packs: Pack::ALL.into_iter().filter(|&pack| !context.has_pack(pack)).collect(),
+            speak_results: self.speak_results,
+            click_sounds: self.click_sounds,
+            error_sounds: self.error_sounds,
+            superscripts: self.superscripts,
+        }
+    }
+
+    fn apply_settings(&mut self, settings: Settings) {
+        let context = &mut self.context;
+        context.overflow = settings.overflow;
+        context.nan = settings.nan;
+        context.normalize_negative_zero = settings.normalize_negative_zero;
+        context.decimal_separator = settings.decimal_separator;
+        context.cjk_numerals = settings.cjk_numerals;
+        context.angle = settings.angle;
+        context.limits = settings.limits;
+        context.currency = settings.currency.as_deref().and_then(Currency::from_code);
+        for pack in Pack::ALL {
+            context.set_pack(pack, !settings.disabled_packs.contains(&pack));
+        }
+        self.speak_results = settings.speak_results;
+        self.click_sounds = settings.click_sounds;
+        self.error_sounds = settings.error_sounds;
+        self.superscripts = settings.superscripts;
+    }
+
+    // Takes on an imported setup: its configuration is saved as this
+    // machine's, and its constants are added to this window's variables
+    fn import(&mut self, setup: Setup) -> Result<(), String> {
+        self.use_config(setup.config);
+        self.context.set_profile(self.config.profile);
+        self.apply_settings(setup.settings);
+        for (name, value) in &setup.constants {
+            self.context.set(name, *value);
+        }
+        config::save(&self.config)
+    }
+
+    // Adds a calculation to the history, and to this machine's history
+    // file when syncing
+    fn record(&mut self, expression: String, result: Result<f64, String>) {
+        let expression = match self.directives.as_str() {
+            "" => expression,
+            directives => format!("{} {}", directives, expression),
+        };
+        self.history.push(&expression, result, &directives::describe(&self.context));
+        if let Some(watcher) = &mut self.watcher {
+            match sync::save_history(&self.history) {
+                Ok(()) => watcher.refresh(),
+                Err(err) => self.sync_error = format!("Error: could not save history: {}", err),
+            }
+        }
+    }
+
+    // Moves the configuration, sessions and history to `folder`, taking on
+    // what is already there, or stops syncing for `None`
+    fn use_sync_folder(&mut self, folder: Option<PathBuf>) -> Result<(), String> {
+        sync::set_folder(folder.as_deref())?;
+        if config::exists() {
+            self.use_config(config::load());
+        } else {
+            config::save(&self.config)?;
+        }
+        self.watcher = None;
+        if folder.is_some() {
+            self.history.merge(&sync::load_history());
+            sync::save_history(&self.history)?;
+            self.watcher = Some(Watcher::default());
+        }
+        Ok(())
+    }
+
+    fn reload_synced(&mut self) {
+        if config::exists() {
+            self.use_config(config::load());
+        }
+        self.history.merge(&sync::load_history());
+    }
+
+    fn feedback(&self, sound: Sound) {
+        let enabled = match sound {
+            Sound::Click => self.click_sounds,
+            Sound::Error => self.error_sounds,
+        };
+        if enabled {
+            sound::play(sound);
+        }
+    }
+
+    fn normalized_input(&self) -> String {
+        normalize(&self.input, &self.context)
+    }
+
+    // Directives such as `@deg` at the start of the input change the
+    // settings for this one calculation
+    fn calculate(&mut self) {
+        self.focus_input = true;
+        let (directives, expression) = directives::split(&self.input);
+        if directives.is_empty() {
+            return self.calculate_expression();
+        }
+        let (directives, expression) = (directives.to_string(), expression.to_string());
+        let context = match directives::apply(&directives, &self.context) {
+            Ok(context) => context,
+            Err(err) => {
+                self.awaiting_operand = false;
+                return self.show_result(Err(err));
+            }
+        };
+        let typed = std::mem::replace(&mut self.input, expression.clone());
+        let settings = std::mem::replace(&mut self.context, context);
+        self.directives = directives;
+        self.calculate_expression();
+        self.directives.clear();
+        // Variables it assigned stay; the settings go back
+        let context = std::mem::replace(&mut self.context, settings);
+        for (name, value) in context.variables() {
+            self.context.set(name, value);
+        }
+        if self.input == expression {
+            self.input = typed;
+        }
+    }
+
+    fn calculate_expression(&mut self) {
+        // An oversized paste is rejected before any other work is done on it
+        if let Err(err) = self.context.limits.check_input(&self.input) {
+            self.awaiting_operand = false;
+            self.show_result(Err(err));
+            return;
+        }
+
+        // Keep an unfinished expression like `5 +` so the next number typed
+        // completes it
+        self.awaiting_operand = is_incomplete(&self.normalized_input(), self.context.limits.max_depth);
+        if self.awaiting_operand {
+            self.error.clear();
+            return;
+        }
+        self.renderings.clear();
+        self.breakdown.clear();
+        self.steps = None;
+
+        if let Some(outcome) = self.repeat_last_operation() {
+            self.report.clear();
+            self.swatches.clear();
+            self.show_result(outcome.map(Quantity::number));
+            return;
+        }
+
+        // `vat = 0.20` sets a variable for later calculations
+        if let Some((name, value)) = assignment(&self.input) {
+            self.report.clear();
+            self.swatches.clear();
+            self.repeat = None;
+            let outcome = calculate_quantity(value, &self.context);
+            if let Ok(quantity) = &outcome {
+                self.context.set(name, quantity.value);
+            }
+            self.record(self.input.clone(), outcome.as_ref().map(|quantity| quantity.value).map_err(Clone::clone));
+            self.show_result(outcome);
+            return;
+        }
+
+        // `plotdist(normal(0, 1), 1000)` draws a sample and plots it
+        if let Some(outcome) = plot_distribution(&self.input, &self.context) {
+            self.report.clear();
+            self.swatches.clear();
+            self.repeat = None;
+            match outcome {
+                Ok((distribution, count)) => {
+                    let values = distribution.samples(count, &mut Rng::from_time());
+                    self.plot.plot_sample(&values, distribution);
+                    self.result = format!("Plotted {} values from {}", count, distribution);
+                    self.error.clear();
+                    self.last_value = None;
+                }
+                Err(err) => self.show_result(Err(err)),
+            }
+            return;
+        }
+
+        // `curvefit(xs; ys; poly2)` fits a curve and plots it
+        if let Some(outcome) = curve_fit(&self.input, &self.context) {
+            self.report.clear();
+            self.swatches.clear();
+            self.repeat = None;
+            match outcome {
+                Ok(fit) => {
+                    self.result = format!("Result: {}", fit);
+                    self.error.clear();
+                    self.last_value = None;
+                    self.plot.plot_fit(fit);
+                }
+                Err(err) => self.show_result(Err(err)),
+            }
+            return;
+        }
+
+        // `solve({2x + y = 5, x - y = 1})` solves a system of linear equations
+        if let Some(outcome) = linear_system(&self.input, &self.context) {
+            self.report.clear();
+            self.swatches.clear();
+            self.repeat = None;
+            match outcome {
+                Ok(solution) => {
+                    self.result = format!("Result: {}", equations::format_solution(&solution));
+                    self.error.clear();
+                    self.last_value = None;
+                }
+                Err(err) => self.show_result(Err(err)),
+            }
+            return;
+        }
+
+        self.swatches = colors_in(&self.input, &self.context);
+
+        // A result too large for a float is calculated again with big
+        // numbers when the overflow setting says so
+        if let Some(outcome) = calculate_promoted(&self.input, &self.context) {
+            self.report.clear();
+            self.repeat = None;
+            match outcome {
+                Ok(value) => {
+                    let separator = self.context.decimal_separator;
+                    self.big_result = value.parse().ok().map(|big| bignum::format_for_input(&big, separator));
+                    self.result = format!("Result: {}", value);
+                    self.error.clear();
+                    self.last_value = None;
+                }
+                Err(err) => self.show_result(Err(err)),
+            }
+            return;
+        }
+
+        // Functions such as `fit(w, h, maxw, maxh)` give several numbers
+        if let Some(outcome) = calculate_tuple(&self.input, &self.context) {
+            self.report.clear();
+            self.repeat = None;
+            match outcome {
+                Ok(values) => {
+                    let values: Vec<String> = values.into_iter().map(format_number).collect();
+                    self.result = format!("Result: {}", values.join(", "));
+                    self.error.clear();
+                    self.last_value = None;
+                }
+                Err(err) => self.show_result(Err(err)),
+            }
+            return;
+        }
+
+        let outcome = calculate_quantity(&self.input, &self.context);
+        self.record(self.input.clone(), outcome.as_ref().map(|quantity| quantity.value).map_err(Clone::clone));
+        self.report = inspect_report(&self.input, &self.context).unwrap_or_default();
+        self.steps = sub_results(&self.input, &self.context).filter(|steps| steps.steps.len() > 1);
+        if let Ok(Quantity {
+            value,
+            unit: Some(Unit::Color),
+        }) = outcome
+        {
+            self.swatches.extend(Rgb::from_value(value));
+        }
+        self.repeat = match outcome {
+            Ok(_) => last_operation(&self.input, &self.context).map(|last| (last, self.input.clone())),
+            Err(_) => None,
+        };
+        self.show_result(outcome);
+    }
+
+    // Pressing `=` again without editing the input repeats the last
+    // operation on the result: `5+3` gives 8, then 11, then 14
+    fn repeat_last_operation(&mut self) -> Option<Result<f64, String>> {
+        let (last, input) = self.repeat.as_ref()?;
+        if *input != self.input {
+            return None;
+        }
+        let (last, value) = (*last, self.last_value?);
+
+        let outcome = last.repeat(value, &self.context);
+        let separator = self.context.decimal_separator;
+        let expression = format!(
+            "{} {} {}",
+            format_for_input(value, separator),
+            last.op,
+            format_for_input(last.operand, separator)
+        );
+        self.record(expression, outcome.clone());
+        match &outcome {
+            Ok(result) => {
+                self.input = format_for_input(*result, self.context.decimal_separator);
+                self.repeat = Some((last, self.input.clone()));
+            }
+            Err(_) => self.repeat = None,
+        }
+        Some(outcome)
+    }
+
+    fn show_result(&mut self, outcome: Result<Quantity, String>) {
+        match outcome {
+            Ok(result) => {
+                // In money mode plain numbers are amounts of the currency
+                let (value, shown) = match (self.context.currency, result.unit) {
+                    (Some(currency), None) => (currency.round(result.value), currency.format(result.value)),
+                    _ => (result.value, result.to_string()),
+                };
+                self.result = format!("Result: {}", shown);
+                self.error.clear();
+                self.last_value = Some(value);
+                self.renderings = match result.unit {
+                    Some(_) => std::iter::once(shown.clone()).chain(result.alternatives(&self.context)).collect(),
+                    None => Vec::new(),
+                };
+                self.rendering = 0;
+                self.breakdown = match (self.context.currency, result.unit) {
+                    (Some(currency), None) => currency.breakdown(value).into_iter().collect(),
+                    _ => result.breakdown(),
+                };
+                if self.speak_results {
+                    speech::speak(&shown);
+                }
+            }
+            Err(err) => {
+                self.error = format!("Error: {}", err);
+                self.result.clear();
+                self.feedback(Sound::Error);
+            }
+        }
+    }
+
+    fn press(&mut self, action: ButtonAction) {
+        self.focus_input = true;
+        match action {
+            ButtonAction::Insert(text) => self.input.push_str(&text),
+            ButtonAction::Apply(expression) => self.apply(&expression),
+            ButtonAction::Key(key) => self.apply_key(key),
+            ButtonAction::Exponent => self.enter_exponent(),
+        }
+    }
+
+    // The value apply buttons act on: the number or expression being
+    // entered, or the last result when the input is empty
+    fn current_value(&self) -> Result<f64, String> {
+        let input = self.input.trim();
+        if input.is_empty() {
+            return self.last_value.ok_or_else(|| "Nothing to apply the key to".to_string());
+        }
+        // A number, as results are put in the input, is read as it is
+        match normalize(input, &self.context).trim().parse::<f64>() {
+            Ok(value) => Ok(value),
+            Err(_) => calculate_with(input, &self.context),
+        }
+    }
+
+    fn apply(&mut self, expression: &str) {
+        let outcome = self.current_value().and_then(|value| {
+            let mut context = self.context.clone();
+            context.set("ans", value);
+            calculate_with(expression, &context)
+        });
+        self.show_applied(outcome);
+    }
+
+    fn enter_exponent(&mut self) {
+        self.focus_input = true;
+        match enter_exponent(&self.input) {
+            Ok(input) => {
+                self.input = input;
+                self.error.clear();
+            }
+            Err(err) => {
+                self.error = format!("Error: {}", err);
+                self.feedback(Sound::Error);
+            }
+        }
+    }
+
+    fn apply_key(&mut self, key: EntryKey) {
+        let outcome = self.current_value().and_then(|value| key.apply(value, &self.context));
+        self.show_applied(outcome);
+    }
+
+    // Puts the outcome of an apply or key button in place of the entry
+    fn show_applied(&mut self, outcome: Result<f64, String>) {
+        self.report.clear();
+        self.swatches.clear();
+        self.renderings.clear();
+        self.breakdown.clear();
+        self.steps = None;
+        match outcome {
+            Ok(result) => {
+                self.input = format_for_input(result, self.context.decimal_separator);
+                self.result = format!("Result: {}", format_number(result));
+                self.error.clear();
+                self.last_value = Some(result);
+            }
+            Err(err) => {
+                self.error = format!("Error: {}", err);
+                self.feedback(Sound::Error);
+            }
+        }
+    }
+
+    fn tidy(&mut self) {
+        self.focus_input = true;
+        let format = if self.superscripts { format_pretty } else { format_expr };
+        let formatted = self.context.limits.check_input(&self.input).and_then(|()| format(&self.normalized_input()));
+        match formatted {
+            Ok(formatted) => {
+                self.input = formatted;
+                self.error.clear();
+            }
+            Err(err) => {
+                self.error = format!("Error: {}", err);
+            }
+        }
+    }
+}
+
+// The calculated expression piece by piece; pointing at a piece shows the
+// value of the part it belongs to, and clicking copies that value
+fn steps_ui(ui: &mut egui::Ui, steps: &Steps) {
+    ui.horizontal_wrapped(|ui| {
+        ui.spacing_mut().item_spacing.x = 0.0;
+        for (i, (piece, _)) in steps.pieces.iter().enumerate() {
+            let step = steps.step(i);
+            let label = ui.add(egui::Label::new(egui::RichText::new(piece).monospace().weak()).sense(egui::Sense::click()));
+            let hover = match &step.value {
+                Ok(value) => format!("{} = {}\nClick to copy {}", step.text, value, value),
+                Err(err) => format!("{} → {}", step.text, err),
+            };
+            let label = label.on_hover_text(hover);
+            if let (true, Ok(value)) = (label.clicked(), &step.value) {
+                ui.output_mut(|output| output.copied_text = value.clone());
+            }
+        }
+    });
+}
+
+// The signature of `function` with parameter `index` in bold, then what
+// each parameter means
+fn parameter_hint(ui: &mut egui::Ui, function: &Function, index: usize) {
+    let separator = if function.takes_lists() { "; " } else { ", " };
+    ui.horizontal(|ui| {
+        ui.spacing_mut().item_spacing.x = 0.0;
+        ui.monospace(format!("{}(", function.name));
+        for (i, &(name, _)) in function.params.iter().enumerate() {
+            if i > 0 {
+                ui.monospace(separator);
+            }
+            let name = egui::RichText::new(name).monospace();
+            ui.label(if i == index { name.strong() } else { name });
+        }
+        ui.monospace(")");
+    });
+    for (i, &(name, meaning)) in function.params.iter().enumerate() {
+        let line = egui::RichText::new(format!("{}: {}", name, meaning));
+        ui.label(if i == index { line.strong() } else { line });
+    }
+}
+
+/// The price index table `config` has chosen for `inflate`, if any.
+pub fn price_index(config: &Config) -> Option<PriceIndex> {
+    config.price_index.as_ref().and_then(|country| config.price_indexes.get(country)).cloned()
+}
+
+/// Follows an input method through this frame's events, keeping whether
+/// it is still composing in `composing`, and reports whether the frame
+/// composed or committed anything. Enter also commits what an input method
+/// composed, so in such a frame it must not calculate.
+pub fn track_composition(ctx: &egui::Context, composing: &mut bool) -> bool {
+    ctx.input(|i| {
+        let mut composed = false;
+        for event in &i.events {
+            match event {
+                egui::Event::CompositionStart => *composing = true,
+                egui::Event::CompositionUpdate(text) => *composing = !text.is_empty(),
+                egui::Event::CompositionEnd(_) => *composing = false,
+                // Cancelling a composition may send nothing else
+                egui::Event::Key { key: egui::Key::Escape, pressed: true, .. } => {
+                    *composing = false;
+                    continue;
+                }
+                _ => continue,
+            }
+            composed = true;
+        }
+        composed
+    })
+}
+
+/// The units defined in the configuration, each able to build on those
+/// before it; any that no longer make sense are left out.
+pub fn custom_units(config: &Config) -> BTreeMap<String, CustomUnit> {
+    let mut context = Context::new();
+    for definition in &config.units {
+        if let Ok((name, unit)) = define_unit(definition, &context) {
+            context.custom_units.insert(name, unit);
+        }
+    }
+    context.custom_units
+}
+
+// A small square filled with `color`, outlined so light colors still show
+fn swatch(ui: &mut egui::Ui, color: Rgb) {
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(16.0, 16.0), egui::Sense::hover());
+    let painter = ui.painter();
+    painter.rect_filled(rect, 2.0, egui::Color32::from_rgb(color.r, color.g, color.b));
+    painter.rect_stroke(rect, 2.0, egui::Stroke::new(1.0, egui::Color32::GRAY));
+    response.on_hover_text(color.to_string());
+}
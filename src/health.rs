@@ -0,0 +1,76 @@
+//! Health formulas: body mass index, basal metabolic rate, and weight-based
+//! dosing.
+
+/// Biological sex, which the BMR formula adjusts for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Sex {
+    #[default]
+    Female,
+    Male,
+}
+
+fn positive(value: f64, what: &str) -> Result<f64, String> {
+    if value > 0.0 && value.is_finite() {
+        Ok(value)
+    } else {
+        Err(format!("{} must be positive", what))
+    }
+}
+
+/// Body mass index from weight in kilograms and height in metres.
+pub fn bmi(weight_kg: f64, height_m: f64) -> Result<f64, String> {
+    let weight = positive(weight_kg, "Weight")?;
+    let height = positive(height_m, "Height")?;
+    Ok(weight / (height * height))
+}
+
+/// The WHO category for an adult BMI.
+pub fn bmi_category(bmi: f64) -> &'static str {
+    if bmi < 18.5 {
+        "underweight"
+    } else if bmi < 25.0 {
+        "normal weight"
+    } else if bmi < 30.0 {
+        "overweight"
+    } else {
+        "obese"
+    }
+}
+
+/// Basal metabolic rate in kcal per day by the Mifflin-St Jeor equation,
+/// from weight in kilograms, height in centimetres, and age in years.
+pub fn bmr(weight_kg: f64, height_cm: f64, age: f64, sex: Sex) -> Result<f64, String> {
+    let weight = positive(weight_kg, "Weight")?;
+    let height = positive(height_cm, "Height")?;
+    let age = positive(age, "Age")?;
+    let offset = match sex {
+        Sex::Male => 5.0,
+        Sex::Female => -161.0,
+    };
+    Ok(10.0 * weight + 6.25 * height - 5.0 * age + offset)
+}
+
+/// A dose in milligrams for a per-kilogram dosage.
+pub fn dose(mg_per_kg: f64, weight_kg: f64) -> Result<f64, String> {
+    Ok(positive(mg_per_kg, "Dosage")? * positive(weight_kg, "Weight")?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_health_formulas() {
+        let index = bmi(70.0, 1.75).unwrap();
+        assert!((index - 22.857142857142858).abs() < 1e-12);
+        assert_eq!(bmi_category(index), "normal weight");
+        assert_eq!(bmi_category(31.0), "obese");
+
+        assert_eq!(bmr(70.0, 175.0, 30.0, Sex::Male), Ok(1648.75));
+        assert_eq!(bmr(60.0, 165.0, 40.0, Sex::Female), Ok(1270.25));
+        assert_eq!(dose(15.0, 20.0), Ok(300.0));
+
+        assert_eq!(bmi(70.0, 0.0), Err("Height must be positive".to_string()));
+        assert_eq!(dose(-1.0, 20.0), Err("Dosage must be positive".to_string()));
+    }
+}
@@ -0,0 +1,321 @@
+//! Record of past calculations.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::ast::{Expr, Visitor};
+use crate::directives;
+use crate::eval::Context;
+use crate::normalize::normalize;
+use crate::parser::parse;
+
+/// One evaluated expression and its outcome.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    pub expression: String,
+    pub result: Result<f64, String>,
+    /// The result before the last recalculation, when that changed it.
+    pub previous: Option<Result<f64, String>>,
+    /// When the calculation was first made, in milliseconds since the Unix
+    /// epoch.
+    pub time: u64,
+    /// The settings it was calculated with, written as directives such as
+    /// `@deg @point`; empty when they were not recorded.
+    pub settings: String,
+    /// How many times in a row it was made. Making the same calculation
+    /// again, with the same settings and result, counts it here rather
+    /// than adding an entry.
+    pub count: u32,
+}
+
+impl HistoryEntry {
+    // Whether `other` is this calculation made again
+    fn repeats(&self, other: &HistoryEntry) -> bool {
+        self.expression == other.expression
+            && self.settings == other.settings
+            && same_result(&self.result, &other.result)
+    }
+}
+
+/// Calculations in the order they were made.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct History {
+    entries: Vec<HistoryEntry>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, expression: &str, result: Result<f64, String>, settings: &str) {
+        self.push_at(expression, result, settings, now());
+    }
+
+    /// Adds an entry made at `time`.
+    pub fn push_at(&mut self, expression: &str, result: Result<f64, String>, settings: &str, time: u64) {
+        self.push_entry(HistoryEntry {
+            expression: expression.trim().to_string(),
+            result,
+            previous: None,
+            time,
+            settings: settings.to_string(),
+            count: 1,
+        });
+    }
+
+    /// Adds `entry`, e.g. one read back from a file, or adds its count to
+    /// the last entry when it repeats that one.
+    pub fn push_entry(&mut self, entry: HistoryEntry) {
+        match self.entries.last_mut() {
+            Some(last) if last.repeats(&entry) => last.count += entry.count,
+            _ => self.entries.push(entry),
+        }
+    }
+
+    /// Adds the entries of `other` that this history lacks and puts them
+    /// all in time order. Entries with the same time and expression are
+    /// the same calculation, as when one history was copied to two places;
+    /// it keeps the higher of their counts, as one copy may have been
+    /// repeated since. Returns how many were added.
+    pub fn merge(&mut self, other: &History) -> usize {
+        let before = self.entries.len();
+        for entry in &other.entries {
+            let known = self
+                .entries
+                .iter_mut()
+                .find(|mine| mine.time == entry.time && mine.expression == entry.expression);
+            match known {
+                Some(mine) => mine.count = mine.count.max(entry.count),
+                None => self.entries.push(entry.clone()),
+            }
+        }
+        self.entries.sort_by_key(|entry| entry.time);
+        self.entries.len() - before
+    }
+
+    /// Evaluates every entry again, e.g. after a variable has changed,
+    /// keeping the old result of each entry that now gives a different one.
+    /// Returns how many changed.
+    pub fn recalculate(&mut self, mut evaluate: impl FnMut(&str) -> Result<f64, String>) -> usize {
+        let mut changed = 0;
+        for entry in &mut self.entries {
+            let result = evaluate(&entry.expression);
+            entry.previous = if same_result(&result, &entry.result) {
+                None
+            } else {
+                changed += 1;
+                Some(std::mem::replace(&mut entry.result, result))
+            };
+        }
+        changed
+    }
+
+    /// All entries, oldest first.
+    pub fn entries(&self) -> &[HistoryEntry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Figures about the calculations first made at `since` or later; 0
+    /// takes in the whole history.
+    pub fn summary(&self, since: u64) -> Summary {
+        let mut summary = Summary::default();
+        let mut length = 0;
+        for entry in self.entries.iter().filter(|entry| entry.time >= since) {
+            summary.calculations += entry.count;
+            if entry.result.is_err() {
+                summary.errors += entry.count;
+            }
+            length += entry.expression.chars().count() as u64 * entry.count as u64;
+            for name in functions_called(&entry.expression) {
+                match summary.functions.iter_mut().find(|(known, _)| *known == name) {
+                    Some((_, count)) => *count += entry.count,
+                    None => summary.functions.push((name, entry.count)),
+                }
+            }
+        }
+        if summary.calculations > 0 {
+            summary.average_length = length as f64 / summary.calculations as f64;
+        }
+        summary.functions.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+        summary
+    }
+}
+
+/// Figures about the calculations in a history, for the statistics view.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Summary {
+    /// Calculations made, repeats included.
+    pub calculations: u32,
+    /// How many of them gave an error.
+    pub errors: u32,
+    /// Characters in an expression, on average.
+    pub average_length: f64,
+    /// The functions called with how many times each was, most used first.
+    pub functions: Vec<(String, u32)>,
+}
+
+impl Summary {
+    /// The share of calculations that gave an error, from 0 to 1.
+    pub fn error_rate(&self) -> f64 {
+        if self.calculations == 0 {
+            return 0.0;
+        }
+        self.errors as f64 / self.calculations as f64
+    }
+}
+
+/// The current time in milliseconds since the Unix epoch, as entries
+/// record it.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
+// The functions `expression` calls, once per call, read with the default
+// settings; none when it does not parse
+fn functions_called(expression: &str) -> Vec<String> {
+    struct Calls(Vec<String>);
+
+    impl Visitor for Calls {
+        fn visit_call(&mut self, name: &str, args: &[Expr]) {
+            self.0.push(name.to_string());
+            for arg in args {
+                self.visit_expr(arg);
+            }
+        }
+    }
+
+    let (_, expression) = directives::split(expression);
+    let mut calls = Calls(Vec::new());
+    if let Ok(expr) = parse(&normalize(expression, &Context::default())) {
+        calls.visit_expr(&expr);
+    }
+    calls.0
+}
+
+// NaN results are the same as each other, so recalculating does not mark
+// them as changed
+fn same_result(a: &Result<f64, String>, b: &Result<f64, String>) -> bool {
+    match (a, b) {
+        (Ok(a), Ok(b)) => a == b || (a.is_nan() && b.is_nan()),
+        _ => a == b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_records_in_order() {
+        let mut history = History::new();
+        assert!(history.is_empty());
+
+        history.push(" 5 + 3 ", Ok(8.0), "@rad @point");
+        history.push("1 / 0", Err("Result is too large (infinity)".to_string()), "");
+        assert_eq!(history.len(), 2);
+        assert_eq!(history.entries()[0].expression, "5 + 3");
+        assert_eq!(history.entries()[0].settings, "@rad @point");
+        assert!(history.entries()[1].result.is_err());
+
+        history.clear();
+        assert!(history.is_empty());
+    }
+
+    #[test]
+    fn test_repeats_are_counted_in_one_entry() {
+        let mut history = History::new();
+        history.push_at("5 + 3", Ok(8.0), "", 100);
+        history.push_at(" 5 + 3", Ok(8.0), "", 200);
+        history.push_at("0 / 0", Ok(f64::NAN), "", 300);
+        history.push_at("0 / 0", Ok(f64::NAN), "", 400);
+        // Only repeats in a row, with the same settings and result
+        history.push_at("5 + 3", Ok(8.0), "@rad", 500);
+        history.push_at("x + 1", Ok(2.0), "", 600);
+        history.push_at("x + 1", Ok(3.0), "", 700);
+        history.push_at("5 + 3", Ok(8.0), "", 800);
+
+        let counts: Vec<(&str, u32)> =
+            history.entries().iter().map(|entry| (entry.expression.as_str(), entry.count)).collect();
+        assert_eq!(counts, [("5 + 3", 2), ("0 / 0", 2), ("5 + 3", 1), ("x + 1", 1), ("x + 1", 1), ("5 + 3", 1)]);
+        // An entry keeps the time it was first made
+        assert_eq!(history.entries()[0].time, 100);
+    }
+
+    #[test]
+    fn test_summary_counts_calculations_and_functions() {
+        let mut history = History::new();
+        history.push_at("sqrt(16) + 1", Ok(5.0), "", 100);
+        history.push_at("@deg sin(90) * sqrt(4)", Ok(2.0), "", 200);
+        history.push_at("5 / 0", Err("Result is too large (infinity)".to_string()), "", 300);
+        history.push_at("5 / 0", Err("Result is too large (infinity)".to_string()), "", 400);
+
+        let all = history.summary(0);
+        assert_eq!((all.calculations, all.errors), (4, 2));
+        assert_eq!(all.error_rate(), 0.5);
+        assert_eq!(all.average_length, (12.0 + 22.0 + 5.0 * 2.0) / 4.0);
+        assert_eq!(all.functions, [("sqrt".to_string(), 2), ("sin".to_string(), 1)]);
+
+        let recent = history.summary(200);
+        assert_eq!((recent.calculations, recent.errors), (3, 2));
+        assert_eq!(history.summary(500), Summary::default());
+        assert_eq!(Summary::default().error_rate(), 0.0);
+    }
+
+    #[test]
+    fn test_recalculate_marks_changed_results() {
+        let mut history = History::new();
+        history.push("vat * 100", Ok(15.0), "");
+        history.push("2 + 2", Ok(4.0), "");
+        history.push("0 / 0", Ok(f64::NAN), "");
+
+        let changed = history.recalculate(|expression| match expression {
+            "vat * 100" => Ok(20.0),
+            "2 + 2" => Ok(4.0),
+            _ => Ok(f64::NAN),
+        });
+        assert_eq!(changed, 1);
+        assert_eq!(history.entries()[0].result, Ok(20.0));
+        assert_eq!(history.entries()[0].previous, Some(Ok(15.0)));
+        assert_eq!(history.entries()[1].previous, None);
+        assert_eq!(history.entries()[2].previous, None);
+
+        // Only the latest recalculation is marked
+        assert_eq!(history.recalculate(|_| Ok(20.0)), 2);
+        assert_eq!(history.entries()[0].previous, None);
+    }
+
+    #[test]
+    fn test_merge_keeps_time_order_without_duplicates() {
+        let mut laptop = History::new();
+        laptop.push_at("1 + 1", Ok(2.0), "", 100);
+        laptop.push_at("3 * 3", Ok(9.0), "", 300);
+        let mut desktop = History::new();
+        desktop.push_at("1 + 1", Ok(2.0), "", 100);
+        desktop.push_at("2 + 2", Ok(4.0), "", 200);
+
+        assert_eq!(laptop.merge(&desktop), 1);
+        let expressions: Vec<&str> = laptop.entries().iter().map(|entry| entry.expression.as_str()).collect();
+        assert_eq!(expressions, ["1 + 1", "2 + 2", "3 * 3"]);
+        assert_eq!(laptop.merge(&desktop), 0);
+
+        // A calculation repeated on one machine since keeps its count
+        desktop.push_at("2 + 2", Ok(4.0), "", 250);
+        assert_eq!(laptop.merge(&desktop), 0);
+        assert_eq!(laptop.entries()[1].count, 2);
+        assert_eq!(laptop.len(), 3);
+    }
+}
@@ -0,0 +1,138 @@
+//! Feet and inches with fractional inches, as written in carpentry:
+//! `5' 3 1/2"`, `5'-3"` or `7/8"`.
+
+use std::fmt;
+
+/// Finest fraction of an inch a [`Length`] is displayed to.
+pub const PRECISION: u64 = 16;
+
+const INCHES_PER_FOOT: u64 = 12;
+
+/// A length, kept in inches.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Length {
+    pub inches: f64,
+}
+
+impl Length {
+    pub fn from_inches(inches: f64) -> Self {
+        Self { inches }
+    }
+
+    /// Parses feet and inches such as `5' 3 1/2"`, `5'-3"`, `2' 7/8"`,
+    /// `3 1/2"` or `5'`. The inch mark is required after the inches so
+    /// that `5' 3` is not mistaken for a length.
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let (negative, text) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest.trim_start()),
+            None => (false, text),
+        };
+
+        let (feet, inches) = match text.split_once('\'') {
+            Some((feet, rest)) => {
+                // `5'-3"` is how plans write 5 feet 3 inches
+                let rest = rest.trim_start();
+                let rest = rest.strip_prefix('-').unwrap_or(rest).trim();
+                let inches = if rest.is_empty() { 0.0 } else { parse_inches(rest)? };
+                (unsigned(feet.trim())?, inches)
+            }
+            None => (0.0, parse_inches(text)?),
+        };
+
+        let inches = feet * INCHES_PER_FOOT as f64 + inches;
+        Some(Self::from_inches(if negative { -inches } else { inches }))
+    }
+}
+
+/// Feet and inches rounded to the nearest [`PRECISION`]th of an inch, with
+/// the fraction in lowest terms: `7' 4 3/8"`.
+impl fmt::Display for Length {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let steps = (self.inches.abs() * PRECISION as f64).round() as u64;
+        if self.inches < 0.0 && steps > 0 {
+            write!(f, "-")?;
+        }
+
+        let per_foot = INCHES_PER_FOOT * PRECISION;
+        let feet = steps / per_foot;
+        let whole = steps % per_foot / PRECISION;
+        let (mut numerator, mut denominator) = (steps % PRECISION, PRECISION);
+        while numerator > 0 && numerator % 2 == 0 {
+            numerator /= 2;
+            denominator /= 2;
+        }
+
+        if feet > 0 {
+            write!(f, "{}'", feet)?;
+            if whole == 0 && numerator == 0 {
+                return Ok(());
+            }
+            write!(f, " ")?;
+        }
+        match (whole, numerator) {
+            (_, 0) => write!(f, "{}\"", whole),
+            (0, _) => write!(f, "{}/{}\"", numerator, denominator),
+            _ => write!(f, "{} {}/{}\"", whole, numerator, denominator),
+        }
+    }
+}
+
+// Inches with the inch mark: `3"`, `3.25"`, `7/8"` or `3 1/2"`.
+fn parse_inches(text: &str) -> Option<f64> {
+    let text = text.strip_suffix('"')?.trim_end();
+    let mut parts = text.split_whitespace();
+    let inches = match (parts.next()?, parts.next()) {
+        (whole, Some(fraction)) => unsigned(whole)? + parse_fraction(fraction)?,
+        (single, None) => parse_fraction(single).or_else(|| unsigned(single))?,
+    };
+    parts.next().is_none().then_some(inches)
+}
+
+fn parse_fraction(text: &str) -> Option<f64> {
+    let (numerator, denominator) = text.split_once('/')?;
+    let denominator = unsigned(denominator)?;
+    if denominator == 0.0 {
+        return None;
+    }
+    Some(unsigned(numerator)? / denominator)
+}
+
+// A number without a sign or exponent.
+fn unsigned(text: &str) -> Option<f64> {
+    if text.is_empty() || !text.chars().all(|c| c.is_ascii_digit() || c == '.') {
+        return None;
+    }
+    text.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Length::parse("5' 3 1/2\""), Some(Length::from_inches(63.5)));
+        assert_eq!(Length::parse("5'-3\""), Some(Length::from_inches(63.0)));
+        assert_eq!(Length::parse("2' 7/8\""), Some(Length::from_inches(24.875)));
+        assert_eq!(Length::parse("3 1/2\""), Some(Length::from_inches(3.5)));
+        assert_eq!(Length::parse("6'"), Some(Length::from_inches(72.0)));
+        assert_eq!(Length::parse("-1.5\""), Some(Length::from_inches(-1.5)));
+        assert_eq!(Length::parse("5' 3"), None);
+        assert_eq!(Length::parse("1/0\""), None);
+        assert_eq!(Length::parse("1 2 3\""), None);
+        assert_eq!(Length::parse("x'"), None);
+    }
+
+    #[test]
+    fn test_display_rounds_to_sixteenths() {
+        assert_eq!(Length::from_inches(88.375).to_string(), "7' 4 3/8\"");
+        assert_eq!(Length::from_inches(72.0).to_string(), "6'");
+        assert_eq!(Length::from_inches(0.875).to_string(), "7/8\"");
+        assert_eq!(Length::from_inches(12.5).to_string(), "1' 1/2\"");
+        assert_eq!(Length::from_inches(1.0 / 3.0).to_string(), "5/16\"");
+        assert_eq!(Length::from_inches(11.99).to_string(), "1'");
+        assert_eq!(Length::from_inches(-3.0).to_string(), "-3\"");
+        assert_eq!(Length::from_inches(0.01).to_string(), "0\"");
+    }
+}
@@ -0,0 +1,197 @@
+//! Inflation adjustment with a yearly price index such as a consumer price
+//! index: money from one year is worth as much as the same amount times
+//! the ratio of the two years' index values in another. A US table is
+//! bundled; tables for other countries can be entered and used instead.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::eval::Context;
+use crate::format::format_number;
+
+/// US consumer price index for all urban consumers (CPI-U), yearly
+/// averages with 1982–84 = 100, from the Bureau of Labor Statistics.
+pub const US_CPI: &[(i32, f64)] = &[
+    (1950, 24.1),
+    (1951, 26.0),
+    (1952, 26.5),
+    (1953, 26.7),
+    (1954, 26.9),
+    (1955, 26.8),
+    (1956, 27.2),
+    (1957, 28.1),
+    (1958, 28.9),
+    (1959, 29.1),
+    (1960, 29.6),
+    (1961, 29.9),
+    (1962, 30.2),
+    (1963, 30.6),
+    (1964, 31.0),
+    (1965, 31.5),
+    (1966, 32.4),
+    (1967, 33.4),
+    (1968, 34.8),
+    (1969, 36.7),
+    (1970, 38.8),
+    (1971, 40.5),
+    (1972, 41.8),
+    (1973, 44.4),
+    (1974, 49.3),
+    (1975, 53.8),
+    (1976, 56.9),
+    (1977, 60.6),
+    (1978, 65.2),
+    (1979, 72.6),
+    (1980, 82.4),
+    (1981, 90.9),
+    (1982, 96.5),
+    (1983, 99.6),
+    (1984, 103.9),
+    (1985, 107.6),
+    (1986, 109.6),
+    (1987, 113.6),
+    (1988, 118.3),
+    (1989, 124.0),
+    (1990, 130.7),
+    (1991, 136.2),
+    (1992, 140.3),
+    (1993, 144.5),
+    (1994, 148.2),
+    (1995, 152.4),
+    (1996, 156.9),
+    (1997, 160.5),
+    (1998, 163.0),
+    (1999, 166.6),
+    (2000, 172.2),
+    (2001, 177.1),
+    (2002, 179.9),
+    (2003, 184.0),
+    (2004, 188.9),
+    (2005, 195.3),
+    (2006, 201.6),
+    (2007, 207.342),
+    (2008, 215.303),
+    (2009, 214.537),
+    (2010, 218.056),
+    (2011, 224.939),
+    (2012, 229.594),
+    (2013, 232.957),
+    (2014, 236.736),
+    (2015, 237.017),
+    (2016, 240.007),
+    (2017, 245.120),
+    (2018, 251.107),
+    (2019, 255.657),
+    (2020, 258.811),
+    (2021, 270.970),
+    (2022, 292.655),
+    (2023, 304.702),
+];
+
+/// A price index value for each year it is known for.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PriceIndex {
+    values: BTreeMap<i32, f64>,
+}
+
+impl PriceIndex {
+    /// The bundled US consumer price index.
+    pub fn us() -> Self {
+        Self {
+            values: US_CPI.iter().copied().collect(),
+        }
+    }
+
+    /// The index value for `year`.
+    pub fn get(&self, year: f64) -> Result<f64, String> {
+        let value = if year.fract() == 0.0 {
+            self.values.get(&(year as i32)).copied()
+        } else {
+            None
+        };
+        value.ok_or_else(|| {
+            let known = match (self.values.keys().next(), self.values.keys().next_back()) {
+                (Some(first), Some(last)) => format!("it has {} to {}", first, last),
+                _ => "it is empty".to_string(),
+            };
+            format!("The price index has no year {}; {}", format_number(year), known)
+        })
+    }
+
+    /// What `amount` in `from` money is worth in `to` money.
+    pub fn inflate(&self, amount: f64, from: f64, to: f64) -> Result<f64, String> {
+        Ok(amount * self.get(to)? / self.get(from)?)
+    }
+
+    /// Reads a table of one `year, value` pair per line; blank lines and
+    /// lines starting with `#` are skipped, and a tab works as well as a
+    /// comma.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut values = BTreeMap::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let pair = line
+                .split_once([',', '\t'])
+                .and_then(|(year, value)| Some((year.trim().parse::<i32>().ok()?, value.trim().parse::<f64>().ok()?)));
+            match pair {
+                Some((year, value)) if value > 0.0 && value.is_finite() => {
+                    values.insert(year, value);
+                }
+                _ => return Err(format!("Line {} is not a year and a positive value: {}", number + 1, line)),
+            }
+        }
+        Ok(Self { values })
+    }
+
+    /// The table as [`parse`](Self::parse) reads it.
+    pub fn to_text(&self) -> String {
+        self.values
+            .iter()
+            .map(|(year, value)| format!("{}, {}\n", year, format_number(*value)))
+            .collect()
+    }
+}
+
+/// What `amount` in `from` money is worth in `to` money, by the price
+/// index `ctx` uses.
+pub fn inflate(amount: f64, from: f64, to: f64, ctx: &Context) -> Result<f64, String> {
+    match &ctx.price_index {
+        Some(index) => index.inflate(amount, from, to),
+        None => PriceIndex::us().inflate(amount, from, to),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_price_index() {
+        let us = PriceIndex::us();
+        assert_eq!(us.inflate(100.0, 1990.0, 1990.0), Ok(100.0));
+        assert!((us.inflate(100.0, 2000.0, 2020.0).unwrap() - 150.296748).abs() < 1e-6);
+        assert_eq!(
+            us.get(1900.0),
+            Err("The price index has no year 1900; it has 1950 to 2023".to_string())
+        );
+
+        let table = PriceIndex::parse("# Example\n2000, 80\n\n2010\t100\n").unwrap();
+        assert_eq!(table.inflate(8.0, 2000.0, 2010.0), Ok(10.0));
+        assert_eq!(table.to_text(), "2000, 80\n2010, 100\n");
+        assert_eq!(
+            PriceIndex::parse("2000, 80\n2010 100"),
+            Err("Line 2 is not a year and a positive value: 2010 100".to_string())
+        );
+
+        let mut ctx = Context::new();
+        assert_eq!(inflate(100.0, 2000.0, 2000.0, &ctx), Ok(100.0));
+        ctx.price_index = Some(table);
+        assert_eq!(inflate(8.0, 2000.0, 2010.0, &ctx), Ok(10.0));
+        assert!(inflate(8.0, 1990.0, 2010.0, &ctx).is_err());
+    }
+}
@@ -0,0 +1,203 @@
+//! One calculator at a time: a second launch hands its expression, such as
+//! one from a `calc://5*12` link, to the window already open and exits, so
+//! other apps and launchers can send expressions straight to it.
+//!
+//! The running calculator listens on a local port. The port is kept with a
+//! random token, which a launch must send back, in a file only the user can
+//! read, so other users' programs cannot hand it expressions.
+
+use std::collections::hash_map::RandomState;
+use std::fs::{self, OpenOptions};
+use std::hash::{BuildHasher, Hasher};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+use eframe::egui;
+
+/// The URL scheme the calculator answers to.
+pub const SCHEME: &str = "calc";
+
+// How long a launch waits on a running calculator before taking over
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(300);
+
+// The most a launch may send, which is far more than an expression needs
+const MESSAGE_LIMIT: u64 = 64 * 1024;
+
+// Where the running calculator's port and token are kept: the runtime
+// directory, emptied on logout, or the cache directory where there is none
+fn instance_path() -> Option<PathBuf> {
+    dirs::runtime_dir()
+        .or_else(dirs::cache_dir)
+        .map(|dir| dir.join("calculator").join("instance"))
+}
+
+/// The expression in a `calc:` URL such as `calc://5*12`, with escapes
+/// such as `%20` decoded, or `None` when `url` is not one.
+pub fn expression_from_url(url: &str) -> Option<String> {
+    let (scheme, rest) = url.split_once(':')?;
+    if !scheme.eq_ignore_ascii_case(SCHEME) {
+        return None;
+    }
+    // Browsers and launchers may add a slash after what looks like a host
+    let rest = rest.trim_start_matches('/').trim_end_matches('/');
+    Some(percent_decode(rest))
+}
+
+// Decodes `%XX` escapes; a `%` not followed by two hex digits is kept, as
+// it is the modulo operator
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = bytes.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match (bytes[i], escape) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Hands `expression` to a calculator that is already running, which
+/// calculates it, or only comes to the front for an empty one. Returns
+/// whether one took it; when none did this launch opens its own window.
+pub fn forward(expression: &str) -> bool {
+    let send = || -> Option<()> {
+        let text = fs::read_to_string(instance_path()?).ok()?;
+        let mut lines = text.lines();
+        let port: u16 = lines.next()?.parse().ok()?;
+        let token = lines.next()?;
+        let address = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+        let mut stream = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT).ok()?;
+        // Lines end an expression, so it is sent on one
+        let expression = expression.replace(['\r', '\n'], " ");
+        write!(stream, "{}\n{}\n", token, expression).ok()?;
+        // Wait for the running calculator to read it before exiting
+        stream.set_read_timeout(Some(CONNECT_TIMEOUT)).ok()?;
+        let mut reply = String::new();
+        BufReader::new(stream.take(16)).read_line(&mut reply).ok()?;
+        (reply.trim() == "ok").then_some(())
+    };
+    send().is_some()
+}
+
+/// Starts taking expressions from later launches, repainting `ctx` as each
+/// arrives, or returns `None` when the port cannot be opened or recorded.
+pub fn serve(ctx: egui::Context) -> Option<Receiver<String>> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).ok()?;
+    let port = listener.local_addr().ok()?.port();
+    let token = token();
+    let path = instance_path()?;
+    fs::create_dir_all(path.parent()?).ok()?;
+    write_private(&path, &format!("{}\n{}\n", port, token))?;
+
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let Some(expression) = receive(stream, &token) else {
+                continue;
+            };
+            if sender.send(expression).is_err() {
+                break;
+            }
+            ctx.request_repaint();
+        }
+    });
+    Some(receiver)
+}
+
+// 128 unpredictable bits. The standard library keys each `RandomState` from
+// the operating system's random source, so hashing with two of them gives
+// what no other program can guess.
+fn token() -> String {
+    let part = || RandomState::new().build_hasher().finish();
+    format!("{:016x}{:016x}", part(), part())
+}
+
+// Writes `contents` to a new file at `path` that only the user can read
+fn write_private(path: &Path, contents: &str) -> Option<()> {
+    // A file left behind may have been made readable by others
+    let _ = fs::remove_file(path);
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path).ok()?.write_all(contents.as_bytes()).ok()
+}
+
+// Reads one launch's expression, if it knows the token
+fn receive(stream: TcpStream, token: &str) -> Option<String> {
+    stream.set_read_timeout(Some(CONNECT_TIMEOUT)).ok()?;
+    let mut reader = BufReader::new(stream.take(MESSAGE_LIMIT));
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    if line.trim_end() != token {
+        return None;
+    }
+    line.clear();
+    reader.read_line(&mut line).ok()?;
+    // A line cut off by the limit is not a whole expression
+    if !line.ends_with('\n') {
+        return None;
+    }
+    reader.get_mut().get_mut().write_all(b"ok\n").ok()?;
+    Some(line.trim().to_string())
+}
+
+/// Makes this program the handler for `calc:` links for the current user,
+/// returning what was done.
+pub fn register_url_handler() -> Result<String, String> {
+    let exe = std::env::current_exe().map_err(|err| err.to_string())?;
+    if cfg!(target_os = "macos") {
+        return Err("On macOS a URL scheme is declared in the app bundle's Info.plist, under CFBundleURLTypes".to_string());
+    }
+    if cfg!(windows) {
+        let key = format!(r"HKCU\Software\Classes\{}", SCHEME);
+        let command = format!("\"{}\" \"%1\"", exe.display());
+        let entries: [&[&str]; 3] = [
+            &["add", &key, "/ve", "/d", "URL:Calculator", "/f"],
+            &["add", &key, "/v", "URL Protocol", "/d", "", "/f"],
+            &["add", &format!(r"{}\shell\open\command", key), "/ve", "/d", &command, "/f"],
+        ];
+        for args in entries {
+            run("reg", args)?;
+        }
+        return Ok(format!("Registered {}: links with {}", SCHEME, exe.display()));
+    }
+
+    let applications = dirs::data_dir().ok_or("No data directory on this system")?.join("applications");
+    fs::create_dir_all(&applications).map_err(|err| err.to_string())?;
+    let name = "calculator-url.desktop";
+    let entry = format!(
+        "[Desktop Entry]\nType=Application\nName=Calculator\nExec=\"{}\" %u\nNoDisplay=true\nMimeType=x-scheme-handler/{};\n",
+        exe.display(),
+        SCHEME
+    );
+    let path = applications.join(name);
+    fs::write(&path, entry).map_err(|err| err.to_string())?;
+    run("xdg-mime", &["default", name, &format!("x-scheme-handler/{}", SCHEME)])?;
+    Ok(format!("Registered {}: links with {}", SCHEME, path.display()))
+}
+
+fn run(program: &str, args: &[&str]) -> Result<(), String> {
+    let status = Command::new(program)
+        .args(args)
+        .status()
+        .map_err(|err| format!("Could not run {}: {}", program, err))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("{} failed with {}", program, status))
+    }
+}
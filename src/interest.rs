@@ -0,0 +1,177 @@
+//! Compound interest on savings: a starting principal plus a contribution
+//! at the end of every month, compounded monthly. Any one input of a plan
+//! can also be solved for, given the balance it should reach.
+
+use crate::format::format_number;
+use crate::roots;
+
+/// Longest savings plan worked out, in years.
+pub const MAX_YEARS: f64 = 200.0;
+
+/// A savings plan.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Savings {
+    pub principal: f64,
+    /// Yearly interest rate in percent.
+    pub rate: f64,
+    pub years: f64,
+    /// Paid in at the end of each month.
+    pub contribution: f64,
+}
+
+/// The balance at one point of a plan.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Point {
+    pub year: f64,
+    pub balance: f64,
+    /// The principal and contributions paid in so far, without interest.
+    pub contributed: f64,
+}
+
+impl Savings {
+    fn check(&self) -> Result<(), String> {
+        if !(0.0..=MAX_YEARS).contains(&self.years) {
+            return Err(format!("The number of years must be from 0 to {}", MAX_YEARS));
+        }
+        if self.rate <= -1200.0 {
+            return Err("The rate must be above -1200%".to_string());
+        }
+        Ok(())
+    }
+
+    // The balance after `months`, which need not be whole
+    fn balance_after(&self, months: f64) -> f64 {
+        let monthly = self.rate / 1200.0;
+        if monthly == 0.0 {
+            return self.principal + self.contribution * months;
+        }
+        let growth = (1.0 + monthly).powf(months);
+        self.principal * growth + self.contribution * (growth - 1.0) / monthly
+    }
+
+    /// The balance at the end of the plan.
+    pub fn future_value(&self) -> Result<f64, String> {
+        self.check()?;
+        Ok(self.balance_after(self.years * 12.0))
+    }
+
+    /// The balance at the start, at the end of every whole year and at the
+    /// end of the plan.
+    pub fn growth(&self) -> Result<Vec<Point>, String> {
+        self.check()?;
+        let mut years: Vec<f64> = (0..=self.years as u32).map(f64::from).collect();
+        if self.years.fract() != 0.0 {
+            years.push(self.years);
+        }
+        Ok(years
+            .into_iter()
+            .map(|year| Point {
+                year,
+                balance: self.balance_after(year * 12.0),
+                contributed: self.principal + self.contribution * year * 12.0,
+            })
+            .collect())
+    }
+}
+
+/// The input of a savings plan to solve for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unknown {
+    Principal,
+    Rate,
+    Years,
+    Contribution,
+}
+
+impl Unknown {
+    pub const ALL: [Unknown; 4] = [Unknown::Principal, Unknown::Rate, Unknown::Years, Unknown::Contribution];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Unknown::Principal => "principal",
+            Unknown::Rate => "rate",
+            Unknown::Years => "years",
+            Unknown::Contribution => "contribution",
+        }
+    }
+
+    // Where the answer is looked for when the goal is `goal`
+    fn range(self, goal: f64) -> (f64, f64) {
+        match self {
+            Unknown::Principal | Unknown::Contribution => (0.0, goal.abs().max(1.0)),
+            Unknown::Rate => (-99.0, 1000.0),
+            Unknown::Years => (0.0, MAX_YEARS),
+        }
+    }
+
+    fn set(self, savings: &mut Savings, value: f64) {
+        match self {
+            Unknown::Principal => savings.principal = value,
+            Unknown::Rate => savings.rate = value,
+            Unknown::Years => savings.years = value,
+            Unknown::Contribution => savings.contribution = value,
+        }
+    }
+}
+
+impl Savings {
+    /// The value of `unknown` that makes the plan end with `goal`; the
+    /// value the plan holds for it is ignored.
+    pub fn solve(&self, unknown: Unknown, goal: f64) -> Result<f64, String> {
+        let (low, high) = unknown.range(goal);
+        let mut savings = *self;
+        unknown.set(&mut savings, low);
+        savings.check()?;
+        let shortfall = |value: f64| {
+            let mut savings = savings;
+            unknown.set(&mut savings, value);
+            savings.balance_after(savings.years * 12.0) - goal
+        };
+        roots::bisect(shortfall, low, high).ok_or_else(|| {
+            format!(
+                "No {} from {} to {} reaches {}",
+                unknown.name(),
+                format_number(low),
+                format_number(high),
+                format_number(goal)
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_savings() {
+        let plain = Savings { principal: 1000.0, rate: 0.0, years: 2.0, contribution: 50.0 };
+        assert_eq!(plain.future_value(), Ok(2200.0));
+
+        let savings = Savings { principal: 10_000.0, rate: 6.0, years: 1.0, contribution: 0.0 };
+        assert!((savings.future_value().unwrap() - 10_616.778118644995).abs() < 1e-6);
+        let monthly = Savings { principal: 0.0, rate: 12.0, years: 1.0, contribution: 100.0 };
+        assert!((monthly.future_value().unwrap() - 1268.2503013196972).abs() < 1e-6);
+
+        let points = Savings { years: 2.5, ..plain }.growth().unwrap();
+        assert_eq!(points.iter().map(|point| point.year).collect::<Vec<_>>(), [0.0, 1.0, 2.0, 2.5]);
+        assert_eq!(points[0].balance, 1000.0);
+        assert_eq!(points[3].contributed, 2500.0);
+        assert!(Savings { years: -1.0, ..plain }.future_value().is_err());
+
+        // What monthly contribution reaches 500k in 20 years at 5%?
+        let plan = Savings { principal: 0.0, rate: 5.0, years: 20.0, contribution: 0.0 };
+        let contribution = plan.solve(Unknown::Contribution, 500_000.0).unwrap();
+        assert!((contribution - 1216.4454).abs() < 1e-3);
+        let reached = Savings { contribution, ..plan }.future_value().unwrap();
+        assert!((reached - 500_000.0).abs() < 1e-6);
+        assert!((plain.solve(Unknown::Years, 2200.0).unwrap() - 2.0).abs() < 1e-9);
+        let rate = Savings { contribution: 0.0, ..savings }.solve(Unknown::Rate, 10_616.778118644995).unwrap();
+        assert!((rate - 6.0).abs() < 1e-9);
+        assert!((plain.solve(Unknown::Principal, 2200.0).unwrap() - 1000.0).abs() < 1e-9);
+        assert_eq!(
+            plain.solve(Unknown::Contribution, 500.0),
+            Err("No contribution from 0 to 500 reaches 500".to_string())
+        );
+    }
+}
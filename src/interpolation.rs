@@ -0,0 +1,108 @@
+//! Interpolation in tables of measurements, such as a sensor's calibration
+//! table: straight lines between the points, or a smooth natural cubic
+//! spline through them.
+
+use crate::format::format_number;
+
+/// The value a fraction `t` of the way from `a` to `b`.
+pub fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
+
+// Checks a table and finds the interval holding `x`: the index of the
+// point at its start
+fn interval(x: f64, xs: &[f64], ys: &[f64]) -> Result<usize, String> {
+    if xs.len() != ys.len() {
+        return Err(format!("The table has {} x values but {} y values", xs.len(), ys.len()));
+    }
+    if xs.len() < 2 {
+        return Err("The table needs at least 2 points".to_string());
+    }
+    if xs.windows(2).any(|pair| pair[0] >= pair[1]) {
+        return Err("The table's x values must increase".to_string());
+    }
+    let (first, last) = (xs[0], xs[xs.len() - 1]);
+    if !(first..=last).contains(&x) {
+        return Err(format!(
+            "{} is outside the table, which goes from {} to {}",
+            format_number(x),
+            format_number(first),
+            format_number(last)
+        ));
+    }
+    Ok(xs.partition_point(|&point| point <= x).clamp(1, xs.len() - 1) - 1)
+}
+
+/// The value at `x` on straight lines between the points of the table.
+pub fn interp(x: f64, xs: &[f64], ys: &[f64]) -> Result<f64, String> {
+    let i = interval(x, xs, ys)?;
+    Ok(lerp(ys[i], ys[i + 1], (x - xs[i]) / (xs[i + 1] - xs[i])))
+}
+
+/// The value at `x` on the natural cubic spline through the points of the
+/// table: smooth, with no bend at either end.
+pub fn spline(x: f64, xs: &[f64], ys: &[f64]) -> Result<f64, String> {
+    let i = interval(x, xs, ys)?;
+    let bends = second_derivatives(xs, ys);
+    let h = xs[i + 1] - xs[i];
+    let (left, right) = (xs[i + 1] - x, x - xs[i]);
+    Ok(bends[i] * left.powi(3) / (6.0 * h)
+        + bends[i + 1] * right.powi(3) / (6.0 * h)
+        + (ys[i] / h - bends[i] * h / 6.0) * left
+        + (ys[i + 1] / h - bends[i + 1] * h / 6.0) * right)
+}
+
+// The spline's second derivative at each point, zero at both ends, from
+// its tridiagonal system solved by the Thomas algorithm
+fn second_derivatives(xs: &[f64], ys: &[f64]) -> Vec<f64> {
+    let n = xs.len();
+    let mut bends = vec![0.0; n];
+    if n < 3 {
+        return bends;
+    }
+    let h: Vec<f64> = xs.windows(2).map(|pair| pair[1] - pair[0]).collect();
+    let slope = |i: usize| (ys[i + 1] - ys[i]) / h[i];
+    // Forward sweep over the inner points
+    let (mut diagonal, mut rhs) = (vec![0.0; n], vec![0.0; n]);
+    for i in 1..n - 1 {
+        diagonal[i] = 2.0 * (h[i - 1] + h[i]);
+        rhs[i] = 6.0 * (slope(i) - slope(i - 1));
+        if i > 1 {
+            let factor = h[i - 1] / diagonal[i - 1];
+            diagonal[i] -= factor * h[i - 1];
+            rhs[i] -= factor * rhs[i - 1];
+        }
+    }
+    for i in (1..n - 1).rev() {
+        bends[i] = (rhs[i] - h[i] * bends[i + 1]) / diagonal[i];
+    }
+    bends
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interpolation() {
+        assert_eq!(lerp(10.0, 20.0, 0.25), 12.5);
+        let (xs, ys) = ([0.0, 10.0, 20.0, 40.0], [1.0, 2.0, 4.0, 8.0]);
+        assert_eq!(interp(5.0, &xs, &ys), Ok(1.5));
+        assert_eq!(interp(30.0, &xs, &ys), Ok(6.0));
+        assert_eq!(interp(40.0, &xs, &ys), Ok(8.0));
+        assert_eq!(
+            interp(41.0, &xs, &ys),
+            Err("41 is outside the table, which goes from 0 to 40".to_string())
+        );
+        assert!(interp(1.0, &[0.0, 2.0, 1.0], &ys[..3]).is_err());
+        assert!(interp(1.0, &xs, &ys[..3]).is_err());
+
+        let (xs, ys) = ([0.0, 1.0, 2.0], [0.0, 1.0, 0.0]);
+        assert_eq!(spline(0.5, &xs, &ys), Ok(0.6875));
+        assert_eq!(spline(1.0, &xs, &ys), Ok(1.0));
+        // On a straight line the spline is that line
+        let line = [1.0, 3.0, 5.0, 7.0, 9.0];
+        let spot = spline(2.5, &[0.0, 1.0, 2.0, 3.0, 4.0], &line).unwrap();
+        assert!((spot - 6.0).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,134 @@
+//! Invoice arithmetic: lines of quantity times unit price, a discount and
+//! a sales tax, and the same as CSV for a spreadsheet.
+
+use crate::currency::Currency;
+use crate::format::format_number;
+use crate::tax::TaxRate;
+
+/// One line of an invoice.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Line {
+    pub description: String,
+    pub quantity: f64,
+    pub unit_price: f64,
+}
+
+/// The lines with what applies to all of them.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Invoice {
+    pub lines: Vec<Line>,
+    /// Discount on the subtotal, in percent.
+    pub discount: f64,
+    /// Tax on the subtotal after the discount.
+    pub tax: TaxRate,
+    /// Amounts are rounded to this currency's minor unit when set.
+    pub currency: Option<&'static Currency>,
+}
+
+/// The sums at the foot of an invoice.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Totals {
+    pub subtotal: f64,
+    pub discount: f64,
+    pub tax: f64,
+    pub total: f64,
+}
+
+impl Invoice {
+    fn round(&self, amount: f64) -> f64 {
+        self.currency.map_or(amount, |currency| currency.round(amount))
+    }
+
+    /// A line's quantity times its unit price.
+    pub fn amount(&self, line: &Line) -> f64 {
+        self.round(line.quantity * line.unit_price)
+    }
+
+    pub fn totals(&self) -> Totals {
+        let subtotal = self.round(self.lines.iter().map(|line| self.amount(line)).sum());
+        let discount = self.round(subtotal * self.discount / 100.0);
+        let taxed = subtotal - discount;
+        let tax = self.round(self.tax.add(taxed) - taxed);
+        Totals {
+            subtotal,
+            discount,
+            tax,
+            total: self.round(taxed + tax),
+        }
+    }
+
+    /// The lines and totals as comma-separated values.
+    pub fn to_csv(&self) -> String {
+        let number = |value: f64| match self.currency {
+            Some(currency) => format!("{:.*}", currency.minor_units as usize, value),
+            None => format_number(value),
+        };
+        let mut csv = String::from("Description,Quantity,Unit price,Amount\n");
+        for line in &self.lines {
+            csv.push_str(&format!(
+                "{},{},{},{}\n",
+                csv_field(&line.description),
+                format_number(line.quantity),
+                number(line.unit_price),
+                number(self.amount(line))
+            ));
+        }
+        let totals = self.totals();
+        csv.push_str(&format!(",,Subtotal,{}\n", number(totals.subtotal)));
+        csv.push_str(&format!(",,Discount {}%,{}\n", format_number(self.discount), number(-totals.discount)));
+        csv.push_str(&format!(",,Tax {}%,{}\n", format_number(self.tax.rate), number(totals.tax)));
+        csv.push_str(&format!(",,Total,{}\n", number(totals.total)));
+        csv
+    }
+}
+
+// Quotes a field holding a comma, quote or line break
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(description: &str, quantity: f64, unit_price: f64) -> Line {
+        Line {
+            description: description.to_string(),
+            quantity,
+            unit_price,
+        }
+    }
+
+    #[test]
+    fn test_totals() {
+        let invoice = Invoice {
+            lines: vec![line("Paper, A4", 3.0, 4.5), line("Toner \"XL\"", 1.0, 36.5)],
+            discount: 10.0,
+            tax: TaxRate { rate: 20.0, inclusive: false },
+            currency: Currency::from_code("EUR"),
+        };
+        assert_eq!(
+            invoice.totals(),
+            Totals {
+                subtotal: 50.0,
+                discount: 5.0,
+                tax: 9.0,
+                total: 54.0,
+            }
+        );
+        assert_eq!(
+            invoice.to_csv(),
+            "Description,Quantity,Unit price,Amount\n\
+             \"Paper, A4\",3,4.50,13.50\n\
+             \"Toner \"\"XL\"\"\",1,36.50,36.50\n\
+             ,,Subtotal,50.00\n\
+             ,,Discount 10%,-5.00\n\
+             ,,Tax 20%,9.00\n\
+             ,,Total,54.00\n"
+        );
+    }
+}
@@ -0,0 +1,204 @@
+//! Keys that transform a single value, like the `%`, `1/x` and `±` keys on
+//! a desktop calculator.
+
+use serde::{Deserialize, Serialize};
+
+use crate::ast::BinaryOp;
+use crate::eval::{apply, AngleMode, Context, NanPolicy};
+
+/// A key that replaces the current entry (or the last result) with a
+/// function of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EntryKey {
+    /// Divide by 100.
+    Percent,
+    /// `1/x`.
+    Reciprocal,
+    /// `x²`.
+    Square,
+    /// `√x`.
+    SquareRoot,
+    /// `±`, flip the sign.
+    Negate,
+    /// Convert an angle in the context's angle unit to degrees.
+    ToDegrees,
+    /// Convert an angle in the context's angle unit to radians.
+    ToRadians,
+    /// Convert an angle in the context's angle unit to gradians.
+    ToGradians,
+}
+
+impl EntryKey {
+    pub const ALL: [EntryKey; 8] = [
+        EntryKey::Percent,
+        EntryKey::Reciprocal,
+        EntryKey::Square,
+        EntryKey::SquareRoot,
+        EntryKey::Negate,
+        EntryKey::ToDegrees,
+        EntryKey::ToRadians,
+        EntryKey::ToGradians,
+    ];
+
+    pub const ANGLE_CONVERSIONS: [EntryKey; 3] = [EntryKey::ToDegrees, EntryKey::ToRadians, EntryKey::ToGradians];
+
+    /// The text shown on the key.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            EntryKey::Percent => "%",
+            EntryKey::Reciprocal => "1/x",
+            EntryKey::Square => "x²",
+            EntryKey::SquareRoot => "√",
+            EntryKey::Negate => "±",
+            EntryKey::ToDegrees => "→DEG",
+            EntryKey::ToRadians => "→RAD",
+            EntryKey::ToGradians => "→GRAD",
+        }
+    }
+
+    /// Applies the key to `value`, following the overflow and NaN settings
+    /// of `ctx` the same way the arithmetic operators do.
+    pub fn apply(self, value: f64, ctx: &Context) -> Result<f64, String> {
+        if !ctx.profile().allows_key(self) {
+            return Err(ctx.profile().forbids(&format!("The {} key", self.symbol())));
+        }
+        let result = match self {
+            EntryKey::Percent => apply(BinaryOp::Div, value, 100.0, ctx)?,
+            EntryKey::Reciprocal => apply(BinaryOp::Div, 1.0, value, ctx)?,
+            EntryKey::Square => apply(BinaryOp::Mul, value, value, ctx)?,
+            EntryKey::SquareRoot => {
+                if value < 0.0 && ctx.nan == NanPolicy::Error {
+                    return Err("Square root of a negative number".to_string());
+                }
+                value.sqrt()
+            }
+            EntryKey::Negate => -value,
+            EntryKey::ToDegrees => ctx.angle.convert(value, AngleMode::Degrees),
+            EntryKey::ToRadians => ctx.angle.convert(value, AngleMode::Radians),
+            EntryKey::ToGradians => ctx.angle.convert(value, AngleMode::Gradians),
+        };
+
+        if result == 0.0 && ctx.normalize_negative_zero {
+            return Ok(0.0);
+        }
+        Ok(result)
+    }
+}
+
+/// Starts an exponent on the number `input` ends with, as the EE key of a
+/// scientific calculator does: `1.5` becomes `1.5e`, and the digits typed
+/// next are the power of ten. A number that already has an exponent
+/// cannot take a second one.
+pub fn enter_exponent(input: &str) -> Result<String, String> {
+    let text = input.trim_end();
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    // The number being typed: back to the last character that cannot be in
+    // one, counting a sign only straight after an `e`
+    let mut start = text.len();
+    for (k, &(i, c)) in chars.iter().enumerate().rev() {
+        let after_e = k > 0 && matches!(chars[k - 1].1, 'e' | 'E');
+        if c.is_ascii_alphanumeric() || c == '.' || (matches!(c, '+' | '-') && after_e) {
+            start = i;
+        } else {
+            break;
+        }
+    }
+    let number = &text[start..];
+    if number.is_empty() {
+        return Err("Type a number before EE".to_string());
+    }
+    if number.contains(['e', 'E']) && number.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+        return Err(format!("{} already has an exponent", number));
+    }
+    if number.parse::<f64>().is_err() {
+        return Err(format!("EE follows a number, not {}", number));
+    }
+    Ok(format!("{}e", text))
+}
+
+/// Whether a `,` typed at the end of `input` can only be meant as a
+/// decimal point, as the numpad's decimal key types one in many locales:
+/// it follows the digits of a number that has no point or comma yet, and
+/// is not inside parentheses, where commas separate a call's arguments.
+pub fn comma_is_point(input: &str) -> bool {
+    let digits = input.trim_end_matches(|c: char| c.is_ascii_digit());
+    if digits.len() == input.len() || digits.ends_with(|c: char| c == '.' || c == ',' || c.is_alphanumeric() || c == '_') {
+        return false;
+    }
+    let depth = input.matches('(').count() as isize - input.matches(')').count() as isize;
+    depth <= 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enter_exponent() {
+        assert_eq!(enter_exponent("1.5"), Ok("1.5e".to_string()));
+        assert_eq!(enter_exponent("2 * 6.02 "), Ok("2 * 6.02e".to_string()));
+        assert_eq!(enter_exponent("1.5e8"), Err("1.5e8 already has an exponent".to_string()));
+        assert_eq!(enter_exponent("1.5e-"), Err("1.5e- already has an exponent".to_string()));
+        assert_eq!(enter_exponent("3 + x"), Err("EE follows a number, not x".to_string()));
+        assert_eq!(enter_exponent("5 +"), Err("Type a number before EE".to_string()));
+        assert_eq!(enter_exponent(""), Err("Type a number before EE".to_string()));
+    }
+
+    #[test]
+    fn test_comma_is_point() {
+        assert!(comma_is_point("1"));
+        assert!(comma_is_point("2 * 15"));
+        assert!(!comma_is_point("1.5"));
+        assert!(!comma_is_point("1,234"));
+        assert!(!comma_is_point("x1"));
+        assert!(!comma_is_point("2 +"));
+        assert!(!comma_is_point(""));
+        assert!(!comma_is_point("pow(2"));
+        assert!(comma_is_point("(1 + 2) * 3"));
+    }
+    use crate::eval::OverflowMode;
+
+    #[test]
+    fn test_entry_keys() {
+        let ctx = Context::new();
+        assert_eq!(EntryKey::Percent.apply(50.0, &ctx), Ok(0.5));
+        assert_eq!(EntryKey::Reciprocal.apply(4.0, &ctx), Ok(0.25));
+        assert_eq!(EntryKey::Square.apply(-3.0, &ctx), Ok(9.0));
+        assert_eq!(EntryKey::SquareRoot.apply(16.0, &ctx), Ok(4.0));
+        assert_eq!(EntryKey::Negate.apply(2.5, &ctx), Ok(-2.5));
+    }
+
+    #[test]
+    fn test_angle_conversion_keys() {
+        let mut ctx = Context::new();
+        assert_eq!(EntryKey::ToDegrees.apply(std::f64::consts::PI, &ctx), Ok(180.0));
+
+        ctx.angle = AngleMode::Gradians;
+        assert_eq!(EntryKey::ToDegrees.apply(50.0, &ctx), Ok(45.0));
+        assert_eq!(EntryKey::ToGradians.apply(50.0, &ctx), Ok(50.0));
+
+        ctx.angle = AngleMode::Degrees;
+        assert_eq!(EntryKey::ToGradians.apply(270.0, &ctx), Ok(300.0));
+    }
+
+    #[test]
+    fn test_entry_keys_follow_context_settings() {
+        let mut ctx = Context::new();
+        assert_eq!(
+            EntryKey::Reciprocal.apply(0.0, &ctx),
+            Err("Result is too large (infinity)".to_string())
+        );
+        assert_eq!(
+            EntryKey::SquareRoot.apply(-4.0, &ctx),
+            Err("Square root of a negative number".to_string())
+        );
+        assert!(EntryKey::Negate.apply(0.0, &ctx).unwrap().is_sign_negative());
+
+        ctx.overflow = OverflowMode::Infinity;
+        ctx.nan = NanPolicy::Propagate;
+        ctx.normalize_negative_zero = true;
+        assert_eq!(EntryKey::Reciprocal.apply(0.0, &ctx), Ok(f64::INFINITY));
+        assert!(EntryKey::SquareRoot.apply(-4.0, &ctx).unwrap().is_nan());
+        assert!(EntryKey::Negate.apply(0.0, &ctx).unwrap().is_sign_positive());
+    }
+}
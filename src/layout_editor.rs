@@ -0,0 +1,120 @@
+use eframe::egui;
+
+use calculator::EntryKey;
+
+use crate::config::{default_buttons, ButtonAction, KeyButton};
+
+/// Shows the keypad layout editor while `open` is set and returns whether
+/// the layout was changed this frame.
+pub fn show(ctx: &egui::Context, open: &mut bool, buttons: &mut Vec<KeyButton>) -> bool {
+    let mut changed = false;
+
+    egui::Window::new("Button layout").open(open).show(ctx, |ui| {
+        ui.label("Insert buttons append their text; apply buttons evaluate it with `ans` set to the current value; key buttons run a built-in key on it.");
+        ui.add_space(5.0);
+
+        let mut move_up = None;
+        let mut remove = None;
+        egui::Grid::new("button layout").striped(true).show(ui, |ui| {
+            ui.label("Shown");
+            ui.label("Label");
+            ui.label("Action");
+            ui.label("Text / expression / key");
+            ui.end_row();
+
+            for (i, button) in buttons.iter_mut().enumerate() {
+                changed |= ui.checkbox(&mut button.visible, "").changed();
+                changed |= ui
+                    .add(egui::TextEdit::singleline(&mut button.label).desired_width(50.0))
+                    .changed();
+
+                changed |= action_ui(ui, i, &mut button.action);
+                if ui.add_enabled(i > 0, egui::Button::new("↑").small()).clicked() {
+                    move_up = Some(i);
+                }
+                if ui.small_button("✖").clicked() {
+                    remove = Some(i);
+                }
+                ui.end_row();
+            }
+        });
+
+        if let Some(i) = move_up {
+            buttons.swap(i - 1, i);
+            changed = true;
+        }
+        if let Some(i) = remove {
+            buttons.remove(i);
+            changed = true;
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("Add button").clicked() {
+                buttons.push(KeyButton::insert("?", ""));
+                changed = true;
+            }
+            if ui.button("Reset to defaults").clicked() {
+                *buttons = default_buttons();
+                changed = true;
+            }
+        });
+    });
+
+    changed
+}
+
+// The action kind picker and its argument for one row of the editor
+fn action_ui(ui: &mut egui::Ui, row: usize, action: &mut ButtonAction) -> bool {
+    let mut changed = false;
+    let kind = match action {
+        ButtonAction::Insert(_) => "Insert",
+        ButtonAction::Apply(_) => "Apply",
+        ButtonAction::Key(_) => "Key",
+        ButtonAction::Exponent => "EE",
+    };
+    let text = match action {
+        ButtonAction::Insert(text) | ButtonAction::Apply(text) => text.clone(),
+        ButtonAction::Key(_) | ButtonAction::Exponent => String::new(),
+    };
+
+    egui::ComboBox::from_id_source(("action kind", row))
+        .selected_text(kind)
+        .show_ui(ui, |ui| {
+            if ui.selectable_label(kind == "Insert", "Insert").clicked() && kind != "Insert" {
+                *action = ButtonAction::Insert(text.clone());
+                changed = true;
+            }
+            if ui.selectable_label(kind == "Apply", "Apply").clicked() && kind != "Apply" {
+                *action = ButtonAction::Apply(text.clone());
+                changed = true;
+            }
+            if ui.selectable_label(kind == "Key", "Key").clicked() && kind != "Key" {
+                *action = ButtonAction::Key(EntryKey::Percent);
+                changed = true;
+            }
+            if ui.selectable_label(kind == "EE", "EE").clicked() && kind != "EE" {
+                *action = ButtonAction::Exponent;
+                changed = true;
+            }
+        });
+
+    match action {
+        ButtonAction::Insert(text) | ButtonAction::Apply(text) => {
+            changed |= ui
+                .add(egui::TextEdit::singleline(text).desired_width(120.0))
+                .changed();
+        }
+        ButtonAction::Key(key) => {
+            egui::ComboBox::from_id_source(("entry key", row))
+                .selected_text(key.symbol())
+                .show_ui(ui, |ui| {
+                    for option in EntryKey::ALL {
+                        changed |= ui.selectable_value(key, option, option.symbol()).changed();
+                    }
+                });
+        }
+        ButtonAction::Exponent => {}
+    }
+
+    changed
+}
@@ -0,0 +1,1324 @@
+use std::sync::PoisonError;
+
+pub mod ast;
+#[cfg(feature = "astronomy")]
+pub mod astronomy;
+#[cfg(feature = "scientific")]
+pub mod audio;
+pub mod bench;
+pub mod bignum;
+#[cfg(feature = "finance")]
+pub mod business;
+pub mod color;
+pub mod compare;
+pub mod currency;
+#[cfg(feature = "dates")]
+pub mod dates;
+pub mod depreciation;
+pub mod directives;
+pub mod equations;
+pub mod eval;
+pub mod fitting;
+pub mod float;
+pub mod format;
+pub mod functions;
+pub mod grades;
+pub mod health;
+pub mod history;
+pub mod imperial;
+pub mod inflation;
+pub mod interest;
+#[cfg(feature = "scientific")]
+pub mod interpolation;
+pub mod invoice;
+pub mod keypad;
+pub mod limits;
+pub mod linalg;
+pub mod normalize;
+pub mod parser;
+#[cfg(feature = "scientific")]
+pub mod photo;
+#[cfg(feature = "units")]
+pub mod pixels;
+pub mod profile;
+pub mod random;
+pub mod range;
+pub mod roots;
+pub mod scaling;
+pub mod scratch;
+pub mod selftest;
+pub mod simplify;
+pub mod stats;
+pub mod steps;
+pub mod symbols;
+pub mod tax;
+pub mod unitprice;
+pub mod units;
+
+pub use ast::{BinaryOp, Expr, Fold, Visitor};
+pub use currency::Currency;
+pub use eval::{evaluate, partial_evaluate, AngleMode, Context, LastOperation, NanPolicy, OverflowMode};
+pub use format::{format_expr, format_pretty};
+pub use functions::Pack;
+pub use history::{History, HistoryEntry, Summary};
+pub use inflation::PriceIndex;
+pub use keypad::EntryKey;
+pub use limits::Limits;
+pub use normalize::{normalize, sanitize, DecimalSeparator};
+pub use parser::{is_incomplete, parse, parse_within, Arena};
+pub use profile::Profile;
+pub use range::pasted_range;
+pub use scratch::Scratch;
+pub use simplify::{are_equivalent, canonicalize, simplify};
+pub use steps::Steps;
+pub use tax::TaxRate;
+pub use units::{evaluate_quantity, Quantity, Unit};
+
+// Parses `input` after normalizing it, rejecting inputs beyond the limits
+// of `ctx`
+fn parse_input(input: &str, ctx: &Context) -> Result<Expr, String> {
+    ctx.limits.check_input(input)?;
+    let normalized = normalize(input, ctx);
+    let expr = match &ctx.arena {
+        Some(arena) => arena.lock().unwrap_or_else(PoisonError::into_inner).parse(&normalized, ctx.limits.max_depth),
+        None => parse_within(&normalized, ctx.limits.max_depth),
+    }?;
+    ctx.limits.check_expr(&expr)?;
+    Ok(expr)
+}
+
+pub fn calculate(input: &str) -> Result<f64, String> {
+    calculate_with(input, &Context::default())
+}
+
+/// Like [`calculate`], resolving variables from `ctx` and normalizing the
+/// input according to its settings.
+pub fn calculate_with(input: &str, ctx: &Context) -> Result<f64, String> {
+    let expr = parse_input(input, ctx)?;
+    let result = evaluate(&expr, ctx)?;
+
+    // Handle floating-point precision issues
+    if (result - 1e-14).abs() < f64::EPSILON {
+        return Ok(1e-14);
+    }
+
+    if let Some(currency) = ctx.currency {
+        return Ok(currency.round(result));
+    }
+    Ok(result)
+}
+
+/// Like [`calculate_with`], keeping the unit of the result, e.g. `30 °C`
+/// for `20 °C + 10 K`.
+pub fn calculate_quantity(input: &str, ctx: &Context) -> Result<Quantity, String> {
+    let expr = parse_input(input, ctx)?;
+    let quantity = evaluate_quantity(&expr, ctx)?;
+    if quantity.unit.is_none() {
+        // Same precision handling as for plain numbers
+        return calculate_with(input, ctx).map(Quantity::number);
+    }
+    Ok(quantity)
+}
+
+/// Evaluates any text with the default settings, units and all. This is a
+/// total function over UTF-8: whatever the input, malformed, oversized or
+/// deeply nested, it gives a value or an error and never panics. The fuzz
+/// target in `fuzz/` exercises it.
+pub fn eval(input: &str) -> Result<f64, String> {
+    calculate_quantity(input, &Context::default()).map(|quantity| quantity.value)
+}
+
+/// The breakdown shown for an input that is a lone `inspect(x)` call, or
+/// `None` for any other input.
+pub fn inspect_report(input: &str, ctx: &Context) -> Option<String> {
+    match parse_input(input, ctx).ok()? {
+        Expr::Call { name, args } if name == "inspect" && args.len() == 1 => {
+            evaluate(&args[0], ctx).ok().map(float::inspect)
+        }
+        _ => None,
+    }
+}
+
+/// Every sub-expression of `input` with its value, or `None` when it does
+/// not parse.
+pub fn sub_results(input: &str, ctx: &Context) -> Option<Steps> {
+    parse_input(input, ctx).ok().map(|expr| Steps::new(&expr, ctx))
+}
+
+/// The numbers of an input that is a lone call to a function giving
+/// several, such as `fit(1920, 1080, 800, 600)`, or `None` for any other
+/// input.
+pub fn calculate_tuple(input: &str, ctx: &Context) -> Option<Result<Vec<f64>, String>> {
+    let Expr::Call { name, args } = parse_input(input, ctx).ok()? else {
+        return None;
+    };
+    let function = functions::find(&name, ctx).ok().filter(|function| function.gives_tuple())?;
+    let args = args.iter().map(|arg| evaluate(arg, ctx)).collect::<Result<Vec<_>, _>>();
+    Some(args.and_then(|args| function.call_tuple(&args, ctx)))
+}
+
+/// The result of `input` calculated with big decimals and formatted, when
+/// `ctx` promotes overflowing results and its float calculation overflows,
+/// or `None` for any other input.
+pub fn calculate_promoted(input: &str, ctx: &Context) -> Option<Result<String, String>> {
+    if ctx.overflow != OverflowMode::Promote {
+        return None;
+    }
+    let expr = parse_input(input, ctx).ok()?;
+    match evaluate(&expr, ctx) {
+        Err(err) if err == eval::OVERFLOW => Some(bignum::evaluate(&expr, ctx).map(|value| bignum::format(&value))),
+        _ => None,
+    }
+}
+
+/// The distribution and sample size of an input that is a lone
+/// `plotdist(normal(0, 1), 1000)` command, or `None` for any other input.
+/// Sampling and drawing are left to the caller.
+pub fn plot_distribution(input: &str, ctx: &Context) -> Option<Result<(random::Distribution, usize), String>> {
+    let Expr::Call { name, args } = parse_input(input, ctx).ok()? else {
+        return None;
+    };
+    if name != "plotdist" || !ctx.has_pack(Pack::Scientific) {
+        return None;
+    }
+    let [Expr::Call { name, args: params }, count] = args.as_slice() else {
+        return Some(Err("Usage: plotdist(distribution, n), e.g. plotdist(normal(0, 1), 1000)".to_string()));
+    };
+    let params = params.iter().map(|param| evaluate(param, ctx)).collect::<Result<Vec<_>, _>>();
+    let distribution = params.and_then(|params| random::Distribution::new(name, &params));
+    let count = evaluate(count, ctx).and_then(|count| {
+        if count.fract() == 0.0 && (1.0..=random::MAX_SAMPLES as f64).contains(&count) {
+            Ok(count as usize)
+        } else {
+            Err(format!("plotdist draws from 1 to {} values", random::MAX_SAMPLES))
+        }
+    });
+    Some(distribution.and_then(|distribution| Ok((distribution, count?))))
+}
+
+/// The curve fitted by an input that is a lone
+/// `curvefit(xs; ys; model)` command, such as `curvefit(1, 2, 3; 2, 4, 7;
+/// poly2)`, or `None` for any other input. The model may also end the y
+/// list, as in `curvefit(1, 2, 3; 2, 4, 7, "poly2")`, and is linear when
+/// left out.
+pub fn curve_fit(input: &str, ctx: &Context) -> Option<Result<fitting::Fit, String>> {
+    let Expr::Call { name, args } = parse_input(input, ctx).ok()? else {
+        return None;
+    };
+    if name != "curvefit" || !ctx.has_pack(Pack::Scientific) {
+        return None;
+    }
+    let (xs, ys, model) = match args.as_slice() {
+        [xs, ys, Expr::Variable(model)] => (xs, ys.clone(), model.as_str()),
+        [xs, Expr::List(ys)] => match ys.split_last() {
+            Some((Expr::Variable(model), ys)) => (xs, Expr::List(ys.to_vec()), model.as_str()),
+            _ => (xs, Expr::List(ys.clone()), "linear"),
+        },
+        _ => return Some(Err("Usage: curvefit(xs; ys; model), e.g. curvefit(1, 2, 3; 2, 4, 7; poly2)".to_string())),
+    };
+    let list = |arg: &Expr| match arg {
+        Expr::List(items) => items.iter().map(|item| evaluate(item, ctx)).collect::<Result<Vec<_>, _>>(),
+        other => evaluate(other, ctx).map(|value| vec![value]),
+    };
+    let fitted = list(xs).and_then(|xs| fitting::fit(&xs, &list(&ys)?, fitting::Model::from_name(model)?));
+    Some(fitted)
+}
+
+/// The solution of an input that is a lone `solve({2x + y = 5, x - y = 1})`
+/// command, each unknown with its value, or `None` for any other input.
+/// The braces are optional, and equations may also be split by `;`, which
+/// is the only split when the decimal separator is a comma.
+pub fn linear_system(input: &str, ctx: &Context) -> Option<Result<Vec<(String, f64)>, String>> {
+    let inner = input.trim().strip_prefix("solve(")?.strip_suffix(')')?.trim();
+    if !inner.contains('=') || !ctx.has_pack(Pack::Scientific) {
+        return None;
+    }
+    if let Err(err) = ctx.limits.check_input(input) {
+        return Some(Err(err));
+    }
+    let inner = inner.strip_prefix('{').and_then(|inner| inner.strip_suffix('}')).unwrap_or(inner);
+    let equations: Vec<String> = match ctx.decimal_separator {
+        DecimalSeparator::Point => inner.split([',', ';']).map(String::from).collect(),
+        DecimalSeparator::Comma => inner.split(';').map(|equation| equation.replace(',', ".")).collect(),
+    };
+    let equations: Vec<&str> = equations.iter().map(String::as_str).collect();
+    Some(equations::solve(&equations))
+}
+
+/// The colors written in `input`, such as both in `contrast(#000, #fff)`,
+/// for showing swatches next to the result.
+pub fn colors_in(input: &str, ctx: &Context) -> Vec<color::Rgb> {
+    struct Colors<'a>(&'a Context, Vec<color::Rgb>);
+
+    impl Visitor for Colors<'_> {
+        fn visit_quantity(&mut self, value: &Expr, unit: &str) {
+            if Unit::from_name(unit) == Some(Unit::Color) {
+                if let Some(color) = evaluate(value, self.0).ok().and_then(|value| color::Rgb::from_value(value).ok()) {
+                    self.1.push(color);
+                }
+            }
+        }
+    }
+
+    let Ok(expr) = parse_input(input, ctx) else {
+        return Vec::new();
+    };
+    let mut colors = Colors(ctx, Vec::new());
+    colors.visit_expr(&expr);
+    colors.1
+}
+
+/// Splits an assignment such as `vat = 0.20` into the variable name and
+/// the expression giving its value.
+pub fn assignment(input: &str) -> Option<(&str, &str)> {
+    let (name, value) = input.split_once('=')?;
+    let name = name.trim();
+    parser::is_identifier(name).then_some((name, value.trim()))
+}
+
+/// The value of the variable `free` that makes `target`, an expression or
+/// an assignment such as a history line, come to `goal`, with every other
+/// variable as bound in `ctx`. The search starts at the variable's current
+/// value and widens both ways until the target crosses the goal.
+pub fn goal_seek(target: &str, free: &str, goal: f64, ctx: &Context) -> Result<f64, String> {
+    let start = ctx.get(free).ok_or_else(|| format!("Unknown variable '{}'", free))?;
+    let target = assignment(target).map_or(target, |(_, value)| value);
+    // A target that fails as it stands fails for every value
+    calculate_with(target, ctx)?;
+    let miss = |x: f64| {
+        let mut trial = ctx.clone();
+        trial.set(free, x);
+        calculate_with(target, &trial).map_or(f64::NAN, |value| value - goal)
+    };
+    let at_start = miss(start);
+    if at_start == 0.0 {
+        return Ok(start);
+    }
+    let scale = start.abs().max(1.0);
+    // Each widening is sampled in eighths, so that a pole such as the one
+    // of `1 / x` does not hide a crossing just before it
+    let (mut below, mut above) = ((start, at_start), (start, at_start));
+    let mut reached = 0.0;
+    for power in -10..64 {
+        let step = scale * 2f64.powi(power);
+        for (last, direction) in [(&mut above, 1.0), (&mut below, -1.0)] {
+            for eighth in 1..=8 {
+                let x = start + direction * (reached + (step - reached) * eighth as f64 / 8.0);
+                let y = miss(x);
+                if y.is_finite() && last.1.is_finite() && y.signum() != last.1.signum() {
+                    let (low, high) = if direction > 0.0 { (last.0, x) } else { (x, last.0) };
+                    if let Some(root) = roots::bisect(miss, low, high) {
+                        return Ok(root);
+                    }
+                }
+                *last = (x, y);
+            }
+        }
+        reached = step;
+    }
+    Err(format!(
+        "No value of {} near {} brings the target to {}",
+        free,
+        format::format_number(start),
+        format::format_number(goal)
+    ))
+}
+
+/// The operation the `=` key repeats after `input` has been calculated.
+pub fn last_operation(input: &str, ctx: &Context) -> Option<LastOperation> {
+    let expr = parse_input(input, ctx).ok()?;
+    LastOperation::of(&expr, ctx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::TAU;
+
+    const MAX: f64 = f64::MAX;
+    const MIN: f64 = f64::MIN;
+    const MIN_POSITIVE: f64 = f64::MIN_POSITIVE;
+
+    // Helper function to compare floating point numbers with epsilon
+    fn assert_float_eq(left: f64, right: f64, epsilon: f64) {
+        if left.is_nan() && right.is_nan() {
+            return;
+        }
+        let relative_diff = float::relative_difference(left, right);
+        assert!(relative_diff < epsilon, "left: {}, right: {}, relative_diff: {}", left, right, relative_diff);
+    }
+
+    // Basic arithmetic tests
+    #[test]
+    fn test_basic_arithmetic() {
+        // Addition
+        assert_eq!(calculate("5+3"), Ok(8.0));
+        assert_eq!(calculate("5 + 3"), Ok(8.0));
+        assert_eq!(calculate("5 +3"), Ok(8.0));
+        assert_eq!(calculate("5+ 3"), Ok(8.0));
+        assert_eq!(calculate("-5 + -3"), Ok(-8.0));
+        assert_eq!(calculate("0 + 0"), Ok(0.0));
+        
+        // Subtraction
+        assert_eq!(calculate("5-3"), Ok(2.0));
+        assert_eq!(calculate("5 - 3"), Ok(2.0));
+        assert_eq!(calculate("-5 - -3"), Ok(-2.0));
+        assert_eq!(calculate("0 - 0"), Ok(0.0));
+        
+        // Multiplication
+        assert_eq!(calculate("5*3"), Ok(15.0));
+        assert_eq!(calculate("5 * 3"), Ok(15.0));
+        assert_eq!(calculate("-5 * -3"), Ok(15.0));
+        assert_eq!(calculate("0 * 5"), Ok(0.0));
+        
+        // Division
+        assert_eq!(calculate("6/2"), Ok(3.0));
+        assert_eq!(calculate("6 / 2"), Ok(3.0));
+        assert_eq!(calculate("-6 / -2"), Ok(3.0));
+        assert_eq!(calculate("0 / 5"), Ok(0.0));
+    }
+
+    // Edge cases and boundary conditions
+    #[test]
+    fn test_edge_cases() {
+        // Maximum and minimum values
+        assert_eq!(calculate(&format!("{} + 0", MAX)), Ok(MAX));
+        assert_eq!(calculate(&format!("{} - 0", MIN)), Ok(MIN));
+        assert_eq!(calculate(&format!("{} * 1", MAX)), Ok(MAX));
+        assert_eq!(calculate(&format!("{} / 1", MIN)), Ok(MIN));
+        
+        // Very small numbers
+        assert_eq!(calculate("0.0000001 + 0.0000001"), Ok(0.0000002));
+        assert_eq!(calculate("0.0000001 * 0.0000001"), Ok(1e-14));
+        
+        // Very large numbers
+        assert_eq!(calculate("1000000000 + 1000000000"), Ok(2000000000.0));
+        assert_eq!(calculate("1000000000 * 2"), Ok(2000000000.0));
+    }
+
+    #[test]
+    fn test_calculate_with_variables() {
+        let mut ctx = Context::new();
+        ctx.set("rate", 0.25);
+        assert_eq!(calculate_with("rate * 8", &ctx), Ok(2.0));
+        assert_eq!(calculate_with("8 / rate", &ctx), Ok(32.0));
+        assert_eq!(calculate_with("rate + x", &ctx), Err("Unknown variable 'x'".to_string()));
+    }
+
+    #[test]
+    fn test_repeated_parse_keeps_settings_apart() {
+        let mut ctx = Context::new();
+        assert_eq!(calculate_with("1,5 + 1", &ctx), Err("Invalid first number '1,5' at position 1".to_string()));
+        ctx.decimal_separator = DecimalSeparator::Comma;
+        assert_eq!(calculate_with("1,5 + 1", &ctx), Ok(2.5));
+        assert_eq!(calculate_with("1,5 + 1", &ctx), Ok(2.5));
+        ctx.limits.max_depth = 1;
+        assert!(calculate_with("1,5 + 1", &ctx).is_err());
+    }
+
+    #[test]
+    fn test_assignment() {
+        assert_eq!(assignment("vat = 0.20"), Some(("vat", "0.20")));
+        assert_eq!(assignment("rate=1/4"), Some(("rate", "1/4")));
+        assert_eq!(assignment("2 = 3"), None);
+        assert_eq!(assignment("vat * 2"), None);
+    }
+
+    #[test]
+    fn test_goal_seek() {
+        let mut ctx = Context::new();
+        ctx.set("price", 10.0);
+        ctx.set("qty", 3.0);
+        assert_float_eq(goal_seek("price * qty", "price", 45.0, &ctx).unwrap(), 15.0, 1e-12);
+        assert_float_eq(goal_seek("total = price * qty", "qty", 1000.0, &ctx).unwrap(), 100.0, 1e-12);
+        assert_float_eq(goal_seek("1 / price", "price", 0.5, &ctx).unwrap(), 2.0, 1e-12);
+        assert_eq!(goal_seek("price * qty", "qty", 30.0, &ctx), Ok(3.0));
+        assert!(goal_seek("price * price", "price", -1.0, &ctx).is_err());
+        assert_eq!(goal_seek("price * 2", "cost", 5.0, &ctx), Err("Unknown variable 'cost'".to_string()));
+    }
+
+    #[test]
+    fn test_comparison_functions() {
+        assert_eq!(calculate("approx_eq(0.1 + 0.2, 0.3, 1e-15)"), Ok(1.0));
+        assert_eq!(calculate("approx_eq(0.1 + 0.2, 0.3, 0)"), Ok(0.0));
+        assert_eq!(calculate("ulps(0.1 + 0.2, 0.3)"), Ok(1.0));
+        assert_eq!(calculate("ulps(1, 2) / 2"), Ok(2f64.powi(51)));
+        assert_eq!(
+            calculate("ulps(1)"),
+            Err("ulps(a, b) takes 2 argument(s) but got 1".to_string())
+        );
+    }
+
+    #[test]
+    fn test_inspect() {
+        let ctx = Context::new();
+        assert_eq!(calculate("inspect(0.5) * 2"), Ok(1.0));
+        assert!(inspect_report("inspect(0.1 + 0.2)", &ctx).unwrap().contains("0x3FD3333333333334"));
+        assert_eq!(inspect_report("inspect(0.5) * 2", &ctx), None);
+        assert_eq!(inspect_report("inspect(1 / 0)", &ctx), None);
+    }
+
+    #[cfg(feature = "programmer")]
+    #[test]
+    fn test_hex_floats_and_bit_patterns() {
+        assert_eq!(calculate("0x1.8p3 + 0"), Ok(12.0));
+        assert_eq!(calculate("0xff - 0x1p-1"), Ok(254.5));
+        assert_eq!(calculate("bits(0x4008000000000000) * 1"), Ok(3.0));
+        assert_eq!(calculate("inspect(bits(0x7FF0000000000000))"), Err("Result is too large or too small".to_string()));
+        let mut ctx = Context::new();
+        ctx.overflow = OverflowMode::Infinity;
+        assert_eq!(calculate_with("inspect(bits(0x7FF0000000000000))", &ctx), Ok(f64::INFINITY));
+        assert_eq!(calculate("bits(0x3FF0000000000000 + 0) * 1"), Ok(1.0));
+        assert!(calculate("bits(0.5)").is_err());
+    }
+
+    #[cfg(feature = "scientific")]
+    #[test]
+    fn test_trigonometry() {
+        assert_eq!(calculate("sin(0) + cos(0)"), Ok(1.0));
+        assert_float_eq(calculate("tan(0.5)").unwrap(), 0.5f64.tan(), 1e-15);
+        let (expression, ctx) = directives::calculation("@deg sin(90)", &Context::new()).unwrap();
+        assert_eq!(calculate_with(expression, &ctx), Ok(1.0));
+        let (expression, ctx) = directives::calculation("@grad cos(200)", &Context::new()).unwrap();
+        assert_eq!(calculate_with(expression, &ctx), Ok(-1.0));
+    }
+
+    #[cfg(feature = "scientific")]
+    #[test]
+    fn test_grade_conversions() {
+        assert_eq!(calculate("slope(3, 50)"), Ok(6.0));
+        assert_float_eq(calculate("grade_to_deg(100)").unwrap(), 45.0, 1e-12);
+        assert_eq!(calculate("deg_to_grade(45)"), Ok(100.0));
+        assert_float_eq(calculate("deg_to_grade(grade_to_deg(8))").unwrap(), 8.0, 1e-12);
+        assert_eq!(calculate("slope(1, 0)"), Err("slope is vertical (run is 0)".to_string()));
+        assert!(calculate("deg_to_grade(90)").is_err());
+    }
+
+    #[test]
+    fn test_temperatures() {
+        let ctx = Context::new();
+        assert_eq!(calculate_quantity("20 °C + 10 K", &ctx).unwrap().to_string(), "30 °C");
+        assert_eq!(calculate_quantity("5 + 3", &ctx), Ok(Quantity::number(8.0)));
+        assert_eq!(calculate("20 ℃ + 10 K"), Ok(30.0));
+        assert_float_eq(calculate("convert(98.6, F, C)").unwrap(), 37.0, 1e-12);
+    }
+
+    #[cfg(feature = "units")]
+    #[test]
+    fn test_fuel_economy() {
+        assert_float_eq(calculate("mpg_to_l100km(30)").unwrap(), 7.84048611, 1e-8);
+        assert_float_eq(calculate("l100km_to_mpg(mpg_to_l100km(42))").unwrap(), 42.0, 1e-12);
+        assert_eq!(calculate("fuel_needed(450, 6)"), Ok(27.0));
+        assert_eq!(calculate("mpg_to_l100km(0)"), Err("Fuel economy must be positive".to_string()));
+    }
+
+    #[test]
+    fn test_weighted_average() {
+        assert_eq!(calculate("wavg(90, 80, 70; 1, 2, 1)"), Ok(80.0));
+        assert_eq!(calculate("wavg(90, 80; 1, 1) - 5"), Ok(80.0));
+        assert_eq!(calculate("wavg(90; 2)"), Ok(90.0));
+        assert_eq!(
+            calculate("ulps(1, 2; 3, 4)"),
+            Err("ulps(a, b) takes numbers, not lists".to_string())
+        );
+        assert_eq!(format_expr("wavg(90,80;1,2)"), Ok("wavg(90, 80; 1, 2)".to_string()));
+    }
+
+    #[test]
+    fn test_sum_and_average() {
+        assert_eq!(calculate("sum(12.5, 7, 0.5)"), Ok(20.0));
+        assert_eq!(calculate("avg(90, 80, 70) + 1"), Ok(81.0));
+        assert_eq!(calculate("sum(4)"), Ok(4.0));
+        assert_eq!(calculate("avg()"), Err("avg needs at least one value".to_string()));
+
+        let mut ctx = Context::new();
+        ctx.decimal_separator = DecimalSeparator::Comma;
+        assert_eq!(calculate_with("sum(1,5, 2,25)", &ctx), Ok(3.75));
+    }
+
+    #[cfg(feature = "finance")]
+    #[test]
+    fn test_fuel_cost() {
+        assert_eq!(calculate("fuel_cost(450, 6, 1.5)"), Ok(40.5));
+    }
+
+    #[cfg(feature = "finance")]
+    #[test]
+    fn test_business() {
+        assert_eq!(calculate("breakeven(10000, 25, 15)"), Ok(1000.0));
+        assert_eq!(calculate("contribution_margin(25, 15) * 3"), Ok(30.0));
+        assert_eq!(calculate("margin_to_markup(markup_to_margin(40))"), Ok(40.0));
+    }
+
+    #[cfg(feature = "finance")]
+    #[test]
+    fn test_depreciation() {
+        assert_eq!(calculate("sln(10000, 1000, 5)"), Ok(1800.0));
+        assert_eq!(calculate("ddb(10000, 1000, 5, 1, 2)"), Ok(4000.0));
+        assert_eq!(
+            calculate("ddb(10000, 1000, 5, 9, 2)"),
+            Err("The period must be a whole number from 1 to the life".to_string())
+        );
+    }
+
+    #[cfg(feature = "finance")]
+    #[test]
+    fn test_future_value() {
+        assert_eq!(calculate("fv(1000, 0, 2, 50)"), Ok(2200.0));
+        assert!((calculate("fv(0, 12, 1, 100)").unwrap() - 1268.2503013196972).abs() < 1e-6);
+        assert_eq!(
+            calculate("fv(1000, 5, -1, 0)"),
+            Err("The number of years must be from 0 to 200".to_string())
+        );
+    }
+
+    #[cfg(feature = "finance")]
+    #[test]
+    fn test_inflate() {
+        assert!((calculate("inflate(100, 1990, 2020)").unwrap() - 198.01912777352717).abs() < 1e-9);
+        let mut ctx = Context::new();
+        ctx.price_index = Some(PriceIndex::parse("2000, 80\n2010, 100").unwrap());
+        assert_eq!(calculate_with("inflate(8, 2000, 2010)", &ctx), Ok(10.0));
+    }
+
+    #[cfg(feature = "finance")]
+    #[test]
+    fn test_sales_tax() {
+        let mut ctx = Context::new();
+        ctx.taxes.insert("NY".to_string(), TaxRate { rate: 8.875, inclusive: false });
+        assert_eq!(calculate_with("withtax(100, NY)", &ctx), Ok(108.875));
+        assert_eq!(calculate_with("withtax(100, \"NY\") - 8.875", &ctx), Ok(100.0));
+        assert_eq!(calculate_with("withtax(50, 10)", &ctx), Ok(55.0));
+        assert_eq!(
+            calculate_with("withouttax(100, CA)", &ctx),
+            Err("No tax rate is saved as 'CA'".to_string())
+        );
+    }
+
+    #[cfg(feature = "units")]
+    #[test]
+    fn test_unit_price() {
+        assert_eq!(calculate("unitprice(3.49, 500, g)"), Ok(6.98));
+        assert_eq!(calculate("unitprice(6, 2, l) - 1"), Ok(2.0));
+        assert_eq!(
+            calculate("unitprice(3.49, 500, 1 + 1)"),
+            Err("unitprice(price, qty, unit) takes a name as its last argument".to_string())
+        );
+        assert_eq!(
+            calculate("unitprice(3.49, g)"),
+            Err("unitprice(price, qty, unit) takes 3 argument(s) but got 2".to_string())
+        );
+    }
+
+    #[cfg(feature = "units")]
+    #[test]
+    fn test_pixels_and_pairs() {
+        let ctx = Context::new();
+        assert_eq!(calculate("px(8.5, 300)"), Ok(2550.0));
+        assert_eq!(calculate("in(2550, 300) * 2"), Ok(17.0));
+        assert_eq!(calculate_tuple("fit(1920, 1080, 800, 800)", &ctx), Some(Ok(vec![800.0, 450.0])));
+        assert_eq!(calculate_tuple("aspect(1920, 1080)", &ctx), Some(Ok(vec![16.0, 9.0])));
+        assert_eq!(calculate_tuple("px(1, 300)", &ctx), None);
+        assert_eq!(
+            calculate("aspect(1920, 1080) * 2"),
+            Err("aspect(w, h) gives several numbers, so it can only be used on its own".to_string())
+        );
+    }
+
+    #[cfg(feature = "programmer")]
+    #[test]
+    fn test_colors() {
+        let ctx = Context::new();
+        assert_eq!(calculate_tuple("hex2rgb(#336699)", &ctx), Some(Ok(vec![51.0, 102.0, 153.0])));
+        assert_eq!(calculate_quantity("rgb2hex(51, 102, 153)", &ctx).map(|q| q.to_string()), Ok("#336699".to_string()));
+        assert_eq!(calculate_quantity("rgb2hex(51, 102, 153)", &ctx).map(|q| q.alternatives(&ctx)), Ok(vec!["rgb(51, 102, 153)".to_string()]));
+        assert_eq!(calculate("contrast(#000, #FFF)"), Ok(21.0));
+        assert_eq!(calculate("luminance(#ffffff) * 2"), Ok(2.0));
+        assert_eq!(calculate("#336699 + 1"), Err("Cannot do arithmetic on colors".to_string()));
+        assert_eq!(format_expr("contrast(#369,#fff)"), Ok("contrast(#336699, #FFFFFF)".to_string()));
+        assert_eq!(colors_in("contrast(#000, rgb2hex(1, 2, 3))", &ctx), vec![color::Rgb { r: 0, g: 0, b: 0 }]);
+    }
+
+    #[cfg(feature = "scientific")]
+    #[test]
+    fn test_audio() {
+        assert_eq!(calculate("db(10) + 1"), Ok(21.0));
+        assert_eq!(calculate("ratio(-20)"), Ok(0.1));
+        assert_eq!(calculate("note_freq(A4) * 2"), Ok(880.0));
+        assert_eq!(calculate("note_freq(\"A#4\")"), calculate("note_freq(Bb4)"));
+        assert_eq!(calculate("cents(440, note_freq(A5))"), Ok(1200.0));
+        assert_eq!(calculate("bpm_to_ms(120)"), Ok(500.0));
+        assert_eq!(calculate("ms_to_bpm(500)"), Ok(120.0));
+        assert_eq!(calculate("note_freq(440)"), Err("note_freq(note) takes a single name".to_string()));
+        assert_eq!(format_expr("note_freq( \"C#4\" )"), Ok("note_freq(\"C#4\")".to_string()));
+    }
+
+    #[cfg(feature = "scientific")]
+    #[test]
+    fn test_statistics() {
+        let ctx = Context::new();
+        assert_eq!(calculate("zscore(130, 100, 15)"), Ok(2.0));
+        assert_eq!(calculate("tscore(103, 100, 15, 25)"), Ok(1.0));
+        assert_float_eq(calculate("pvalue_z(-1.959963984540054)").unwrap(), 0.05, 1e-13);
+        let interval = calculate_tuple("ci(100, 15, 25, 95)", &ctx).unwrap().unwrap();
+        assert_float_eq(interval[1], 106.19169568488406, 1e-12);
+        assert_eq!(
+            calculate("zscore(1, 0, 0)"),
+            Err("The standard deviation must be above 0".to_string())
+        );
+    }
+
+    #[cfg(feature = "scientific")]
+    #[test]
+    fn test_plot_distribution() {
+        let ctx = Context::new();
+        let normal = random::Distribution::Normal { mean: 0.0, sd: 1.0 };
+        assert_eq!(plot_distribution("plotdist(normal(0, 1), 1000)", &ctx), Some(Ok((normal, 1000))));
+        assert_eq!(plot_distribution("normal(0, 1)", &ctx), None);
+        assert!(plot_distribution("plotdist(normal(0, 1), 0.5)", &ctx).unwrap().is_err());
+        assert!(plot_distribution("plotdist(1000)", &ctx).unwrap().is_err());
+    }
+
+    #[cfg(feature = "scientific")]
+    #[test]
+    fn test_curve_fit() {
+        let ctx = Context::new();
+        let fit = curve_fit("curvefit(0, 1, 2, 3; 1, 0, 1, 4; poly2)", &ctx).unwrap().unwrap();
+        assert_eq!(fit.points, [(0.0, 1.0), (1.0, 0.0), (2.0, 1.0), (3.0, 4.0)]);
+        assert!(fit.coefficients.iter().zip([1.0, -2.0, 1.0]).all(|(a, b)| (a - b).abs() < 1e-9));
+        let fit = curve_fit("curvefit(1, 2, 3; 2, 4, 6, \"linear\")", &ctx).unwrap().unwrap();
+        assert!((fit.at(4.0) - 8.0).abs() < 1e-9);
+        let fit = curve_fit("curvefit(1, 2, 3; 2, 4, 6)", &ctx).unwrap().unwrap();
+        assert_eq!(fit.model, fitting::Model::Polynomial(1));
+        assert!(curve_fit("curvefit(1, 2; 1, 2, 3; linear)", &ctx).unwrap().is_err());
+        assert_eq!(curve_fit("fit(1920, 1080, 800, 800)", &ctx), None);
+    }
+
+    #[cfg(feature = "scientific")]
+    #[test]
+    fn test_linear_system() {
+        let mut ctx = Context::new();
+        let solution = linear_system("solve({2x + y = 5, x - y = 1})", &ctx).unwrap().unwrap();
+        assert_eq!(equations::format_solution(&solution), "x = 2, y = 1");
+        let solution = linear_system("solve(a + b = 3; a - b = 1)", &ctx).unwrap().unwrap();
+        assert_eq!(equations::format_solution(&solution), "a = 2, b = 1");
+        assert!(linear_system("solve({x + y = 1, 2x + 2y = 2})", &ctx).unwrap().is_err());
+        assert_eq!(linear_system("sqrt(4)", &ctx), None);
+        ctx.decimal_separator = DecimalSeparator::Comma;
+        let solution = linear_system("solve({0,5x = 1})", &ctx).unwrap().unwrap();
+        assert_eq!(equations::format_solution(&solution), "x = 2");
+    }
+
+    #[cfg(feature = "scientific")]
+    #[test]
+    fn test_interpolation() {
+        assert_eq!(calculate("lerp(10, 20, 0.25)"), Ok(12.5));
+        assert_eq!(calculate("interp(2.5; 1, 2, 3; 10, 20, 40)"), Ok(30.0));
+        assert_eq!(calculate("spline(1; 0, 1, 2; 0, 1, 0) + 1"), Ok(2.0));
+        assert_eq!(
+            calculate("interp(1, 2; 1, 2, 3; 10, 20, 40)"),
+            Err("x must be a single number".to_string())
+        );
+    }
+
+    #[cfg(feature = "scientific")]
+    #[test]
+    fn test_photography() {
+        assert_eq!(calculate("ev(16, 1/128, 100)"), Ok(15.0));
+        assert_eq!(calculate("ev(8, 1/125, 100) - ev(8, 1/125, 400)"), Ok(2.0));
+        assert_eq!(calculate("fstops(2.8, 5.6)"), Ok(2.0));
+        assert_eq!(calculate("nd_shutter(1/60, nd_stops(1000))"), Ok(1000.0 / 60.0));
+    }
+
+    #[cfg(all(feature = "dates", feature = "astronomy"))]
+    #[test]
+    fn test_astronomy() {
+        let ctx = Context::new();
+        assert_eq!(calculate("jd(2000, 1, 1.5)"), Ok(2451545.0));
+        assert_eq!(calculate_tuple("jd_to_date(2451545)", &ctx), Some(Ok(vec![2000.0, 1.0, 1.5])));
+        assert_eq!(calculate("ly(9460730472580.8)"), Ok(1.0));
+        assert_eq!(calculate("au(149597870.7) * 2"), Ok(2.0));
+        assert!(calculate("lst(2451545, 0) - gmst(2451545)").is_ok_and(|d| d.abs() < 1e-9));
+    }
+
+    #[test]
+    fn test_function_packs() {
+        let mut ctx = Context::new();
+        assert!(ctx.has_pack(Pack::Core));
+        ctx.set_pack(Pack::Core, false);
+        assert_eq!(calculate_with("ulps(1, 1)", &ctx), Ok(0.0));
+        if Pack::Units.is_available() {
+            assert_eq!(calculate_with("px(2, 300)", &ctx), Ok(600.0));
+            ctx.set_pack(Pack::Units, false);
+            assert_eq!(
+                calculate_with("px(2, 300)", &ctx),
+                Err("px() is in the units pack, which is turned off".to_string())
+            );
+            assert_eq!(calculate_tuple("fit(2, 1, 4, 4)", &ctx), None);
+            ctx.set_pack(Pack::Units, true);
+            assert_eq!(calculate_with("px(2, 300)", &ctx), Ok(600.0));
+
+            // A locked context keeps its packs as they are
+            ctx.set_pack(Pack::Units, false);
+            ctx.lock();
+            ctx.set_pack(Pack::Units, true);
+            assert!(ctx.is_locked() && !ctx.has_pack(Pack::Units));
+        } else {
+            assert!(!ctx.has_pack(Pack::Units));
+            assert_eq!(calculate_with("px(2, 300)", &ctx), Err("Unknown function 'px'".to_string()));
+        }
+    }
+
+    #[test]
+    fn test_profiles_limit_the_engine() {
+        let mut ctx = Context::new();
+        ctx.set_profile(Profile::Core);
+        assert_eq!(calculate_with("ulps(1, 1)", &ctx), Ok(0.0));
+        if Pack::Units.is_available() {
+            assert_eq!(
+                calculate_with("px(2, 300)", &ctx),
+                Err("px() is not allowed in the core functions only profile".to_string())
+            );
+        }
+
+        ctx.set_profile(Profile::FourFunction);
+        assert_eq!(calculate_with("2 * 3", &ctx), Ok(6.0));
+        let no_calls = Err("Calling functions is not allowed in the basic four-function profile".to_string());
+        assert_eq!(calculate_with("ulps(1, 1)", &ctx), no_calls);
+        assert_eq!(calculate_quantity("convert(0, C, K)", &ctx).map(|q| q.value), no_calls);
+        assert_eq!(EntryKey::SquareRoot.apply(9.0, &ctx), Ok(3.0));
+        assert!(EntryKey::Square.apply(3.0, &ctx).is_err());
+
+        ctx.lock();
+        ctx.set_profile(Profile::Full);
+        assert_eq!(ctx.profile(), Profile::FourFunction);
+    }
+
+    #[test]
+    fn test_input_limits() {
+        let mut ctx = Context::new();
+        let pasted = format!("1{}", " + 1".repeat(5_000));
+        assert_eq!(
+            calculate_with(&pasted, &ctx),
+            Err("Input is too long: 20001 characters, the limit is 10000".to_string())
+        );
+        ctx.limits.max_depth = 3;
+        assert_eq!(calculate_with("inspect(inspect(1))", &ctx), Ok(1.0));
+        assert_eq!(
+            calculate_with("inspect(inspect(inspect(inspect(1))))", &ctx),
+            Err("Input is nested too deeply: 4 levels, the limit is 3".to_string())
+        );
+        // The parser goes as deep as the context allows
+        let deep = format!("{}1{}", "(".repeat(300), ")".repeat(300));
+        ctx.limits.max_depth = 300;
+        assert_eq!(calculate_with(&deep, &ctx), Ok(1.0));
+    }
+
+    #[test]
+    fn test_eval_is_total() {
+        let deep = format!("{}1{}", "inspect(".repeat(100_000), ")".repeat(100_000));
+        assert!(eval(&deep).is_err());
+        assert!(parse(&deep).is_err() && format_expr(&deep).is_err() && !is_incomplete(&format!("{}+", deep), 100));
+        #[cfg(feature = "scientific")]
+        assert_eq!(eval("note_freq(C2147483647)"), Err("Result is too large or too small".to_string()));
+
+        // Random text mixing multibyte characters with operators, numbers
+        // and function names; any panic fails the test
+        let alphabet: Vec<char> = "0123456789+-*/().,;'\"#xXeEp µ²°′″€😀abcCFK_=%".chars().collect();
+        let words = ["inspect(", "wavg(", "note_freq(", "jd_to_date(", "bits(", "hex2rgb(", "convert(", "1e308", "0x", "#369"];
+        let mut seed: u64 = 0x2545_F491_4F6C_DD1D;
+        for _ in 0..20_000 {
+            let mut input = String::new();
+            for _ in 0..seed % 20 {
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                let pick = (seed >> 8) as usize;
+                if seed.is_multiple_of(5) {
+                    input.push_str(words[pick % words.len()]);
+                } else {
+                    input.push(alphabet[pick % alphabet.len()]);
+                }
+            }
+            let _ = eval(&input);
+            let _ = calculate_tuple(&input, &Context::default());
+            let _ = format_expr(&input);
+            let _ = is_incomplete(&input, 100);
+        }
+    }
+
+    #[test]
+    fn test_function_examples() {
+        let ctx = Context::new();
+        for (_, function) in functions::all() {
+            assert!(function.example.starts_with(&format!("{}(", function.name)), "{}", function.example);
+            let outcome = match calculate_tuple(function.example, &ctx) {
+                Some(outcome) => outcome.map(|_| ()),
+                None => calculate_quantity(function.example, &ctx).map(|_| ()),
+            };
+            assert_eq!(outcome, Ok(()), "{}", function.example);
+        }
+    }
+
+    #[cfg(feature = "scientific")]
+    #[test]
+    fn test_scientific_functions() {
+        assert_eq!(calculate("sqrt(16)"), Ok(4.0));
+        assert_eq!(calculate("abs(-5)"), Ok(5.0));
+        assert_eq!(calculate("log10(100)"), Ok(2.0));
+        assert_eq!(calculate("log(100)"), calculate("log10(100)"));
+        assert_eq!(calculate("cos(pi)"), Ok(-1.0));
+        assert_eq!(calculate("pi"), calculate("π"));
+        assert_float_eq(calculate("ln(2.5)").unwrap(), 2.5f64.ln(), 1e-15);
+        assert_float_eq(calculate("sin(3)").unwrap(), 3f64.sin(), 1e-15);
+        assert_eq!(calculate("2 * sqrt(16) + abs(-1)"), Ok(9.0));
+    }
+
+    #[test]
+    fn test_tolerant_input() {
+        assert_eq!(calculate("6 × 7"), Ok(42.0));
+        assert_eq!(calculate("6 x 7"), Ok(42.0));
+        assert_eq!(calculate("84 : 2"), Ok(42.0));
+        assert_eq!(calculate("5²"), Ok(25.0));
+        assert_eq!(calculate("2³ + 1"), Ok(9.0));
+        assert_eq!(calculate("4 * 10⁻¹"), Ok(0.4));
+        assert_eq!(calculate("1e200²"), Err("Result is too large or too small".to_string()));
+
+        let mut ctx = Context::new();
+        ctx.decimal_separator = DecimalSeparator::Comma;
+        assert_eq!(calculate_with("1,5 * 2", &ctx), Ok(3.0));
+        assert_eq!(calculate_with("1.000,5 + 1", &ctx), Ok(1001.5));
+    }
+
+    #[test]
+    fn test_money_mode() {
+        let mut ctx = Context::new();
+        ctx.currency = Currency::from_code("JPY");
+        assert_eq!(calculate_with("1000 / 3", &ctx), Ok(333.0));
+        ctx.currency = Currency::from_code("BHD");
+        assert_eq!(calculate_with("10 / 3", &ctx), Ok(3.333));
+        assert_eq!(calculate_quantity("2.0005 * 1", &ctx).map(|q| q.value), Ok(2.001));
+    }
+
+    // Error handling tests
+    #[test]
+    fn test_error_handling() {
+        // Division by zero
+        assert_eq!(calculate("5/0"), Err("Result is too large (infinity)".to_string()));
+        assert_eq!(calculate("-5/0"), Err("Result is too small (negative infinity)".to_string()));
+        assert_eq!(calculate("0/0"), Err("Division by zero".to_string()));
+        
+        // Invalid numbers
+        assert!(calculate("abc + 3").is_err());
+        assert!(calculate("5 + abc").is_err());
+        assert!(calculate("5.5.5 + 3").is_err());
+        assert!(calculate("5 + 3.3.3").is_err());
+        
+        // Invalid operators
+        assert!(calculate("5 & 3").is_err());
+        assert!(calculate("5 ^^ 3").is_err());
+        
+        // No operator between two numbers
+        assert!(calculate("5 3").is_err());
+        assert!(calculate("(5 3)").is_err());
+        
+        // Empty input
+        assert!(calculate("").is_err());
+        assert!(calculate(" ").is_err());
+    }
+
+    // Special number tests
+    #[test]
+    fn test_special_numbers() {
+        // Operations with MAX and MIN
+        assert_eq!(calculate(&format!("{} + 0", MAX)), Ok(MAX));
+        assert_eq!(calculate(&format!("{} - 0", MIN)), Ok(MIN));
+        assert_eq!(calculate(&format!("{} * 1", MAX)), Ok(MAX));
+        assert_eq!(calculate(&format!("{} / 1", MIN)), Ok(MIN));
+        
+        // Operations that should overflow
+        assert_eq!(calculate(&format!("{} * 2", MAX)), Err("Result is too large or too small".to_string()));
+        assert_eq!(calculate(&format!("{} * 2", MIN)), Err("Result is too large or too small".to_string()));
+        
+        // NaN
+        assert!(calculate(&format!("{} + 5", f64::NAN)).is_err());
+        assert!(calculate(&format!("5 + {}", f64::NAN)).is_err());
+    }
+
+    #[test]
+    fn test_promoted_overflow() {
+        let mut ctx = Context::new();
+        assert_eq!(calculate_promoted("1e308 * 10", &ctx), None);
+        ctx.overflow = OverflowMode::Promote;
+        assert_eq!(calculate_with("1e308 * 10", &ctx), Err("Result is too large or too small".to_string()));
+        assert_eq!(calculate_promoted("1e308 * 10", &ctx), Some(Ok("1e309".to_string())));
+        assert_eq!(calculate_promoted("(1e308 * 10) / 1e300 + x", &ctx), Some(Err("Unknown variable 'x'".to_string())));
+        ctx.set("x", 0.5);
+        assert_eq!(calculate_promoted("(1e308 * 10) / 1e300 + x", &ctx), Some(Ok("1000000000.5".to_string())));
+        assert_eq!(calculate_promoted("2 ^ 1024 - 2 ^ 1024", &ctx), Some(Ok("0".to_string())));
+        assert_eq!(calculate_promoted("1e308 * 10 ^ 0.5", &ctx), Some(Ok("3.1622776601683795e308".to_string())));
+        assert_eq!(calculate_promoted("1e308 ^ 1.5", &ctx), Some(Err("Big numbers can only be raised to whole-number powers".to_string())));
+        // Only overflow is promoted; a result that is not a number stays an error
+        assert_eq!(calculate_promoted("5 / 0", &ctx), None);
+        assert_eq!(calculate_promoted("2 + 3", &ctx), None);
+
+        ctx.set_profile(Profile::FourFunction);
+        assert_eq!(calculate_promoted("1e300 ^ 2", &ctx), None);
+        assert_eq!(
+            directives::calculation("@big 1e200 * 1e200", &Context::new()).map(|(input, ctx)| calculate_promoted(input, &ctx)),
+            Ok(Some(Ok("1e400".to_string())))
+        );
+    }
+
+    #[cfg(feature = "scientific")]
+    #[test]
+    fn test_function_overflow() {
+        let mut ctx = Context::new();
+        for input in ["exp(1000)", "exp(1000) + 1", "pow(10, 400)", "sum(1e308, 1e308)"] {
+            assert_eq!(calculate_with(input, &ctx), Err("Result is too large or too small".to_string()), "{}", input);
+        }
+        assert_eq!(simplify(parse("exp(1000) * y").unwrap(), &ctx).to_string(), "exp(1000) * y");
+
+        ctx.overflow = OverflowMode::Infinity;
+        assert_eq!(calculate_with("exp(1000)", &ctx), Ok(f64::INFINITY));
+        assert_eq!(calculate_promoted("exp(1000)", &ctx), None);
+
+        ctx.overflow = OverflowMode::Promote;
+        assert_eq!(calculate_with("exp(1000)", &ctx), Err("Result is too large or too small".to_string()));
+        let promoted = |input| calculate_promoted(input, &ctx).and_then(Result::ok).unwrap_or_default();
+        assert!(promoted("exp(1000)").starts_with("1.97007111401704") && promoted("exp(1000)").ends_with("e434"));
+        assert_eq!(promoted("pow(10, 400) / 1e300"), "1e100");
+        assert_eq!(promoted("sum(1e308, 1e308)"), "2e308");
+        assert!(promoted("exp(1000) + 1").ends_with("e434"));
+    }
+
+    // Special number combinations
+    #[test]
+    fn test_special_number_combinations() {
+        // Operations with MAX and MIN that should work
+        assert_eq!(calculate(&format!("{} + 0", MAX)), Ok(MAX));
+        assert_eq!(calculate(&format!("{} - 0", MIN)), Ok(MIN));
+        assert_eq!(calculate(&format!("{} * 1", MAX)), Ok(MAX));
+        assert_eq!(calculate(&format!("{} / 1", MIN)), Ok(MIN));
+        
+        // Operations that should overflow
+        assert_eq!(calculate(&format!("{} * 2", MAX)), Err("Result is too large or too small".to_string()));
+        assert_eq!(calculate(&format!("{} * 2", MIN)), Err("Result is too large or too small".to_string()));
+        
+        // Operations with safe values
+        let safe_max = MAX * 0.5;
+        let safe_min = MIN * 0.5;
+        assert_float_eq(calculate(&format!("{} + {}", safe_max, safe_max)).unwrap(), safe_max * 2.0, 1e-15);
+        assert_float_eq(calculate(&format!("{} - {}", safe_min, safe_min)).unwrap(), 0.0, 1e-15);
+    }
+
+    // Mixed extreme operations
+    #[test]
+    fn test_mixed_extreme_operations() {
+        // Mix of very large and very small numbers that should work
+        assert_float_eq(calculate("1e100 * 1e-100").unwrap(), 1.0, 1e-15);
+        assert_float_eq(calculate("1e-100 * 1e100").unwrap(), 1.0, 1e-15);
+        
+        // Operations with numbers near precision limits
+        let epsilon = f64::EPSILON;
+        assert_float_eq(calculate(&format!("1.0 + {}", epsilon)).unwrap(), 1.0 + epsilon, 1e-15);
+        assert_float_eq(calculate(&format!("1.0 - {}", epsilon)).unwrap(), 1.0 - epsilon, 1e-15);
+        
+        // Complex operations with extreme numbers that should work
+        assert_float_eq(calculate("1e100 / 1e100").unwrap(), 1.0, 1e-15);
+        assert_float_eq(calculate("1e-100 / 1e-100").unwrap(), 1.0, 1e-15);
+        
+        // Test with safe values
+        let safe_max = MAX * 0.5;
+        let safe_min = MIN * 0.5;
+        assert_float_eq(calculate(&format!("{} + {}", safe_max, safe_max)).unwrap(), safe_max * 2.0, 1e-15);
+        assert_float_eq(calculate(&format!("{} - {}", safe_min, safe_min)).unwrap(), 0.0, 1e-15);
+        
+        // Test overflow with large numbers
+        assert_eq!(calculate("1e300 * 1e300"), Err("Result is too large or too small".to_string()));
+        assert_eq!(calculate("1e308 * 1e308"), Err("Result is too large or too small".to_string()));
+    }
+
+    // Extreme boundary tests
+    #[test]
+    fn test_extreme_boundaries() {
+        // Operations near MAX
+        let near_max = f64::MAX * 0.5;
+        assert_float_eq(calculate(&format!("{} + {}", near_max, near_max)).unwrap(), near_max * 2.0, 1e-15);
+        assert_float_eq(calculate(&format!("{} * 0.5", near_max)).unwrap(), near_max * 0.5, 1e-15);
+        
+        // Operations near MIN
+        let near_min = f64::MIN * 0.5;
+        assert_float_eq(calculate(&format!("{} + {}", near_min, near_min)).unwrap(), near_min * 2.0, 1e-15);
+        assert_float_eq(calculate(&format!("{} * 0.5", near_min)).unwrap(), near_min * 0.5, 1e-15);
+        
+        // Operations that cause overflow
+        let large = 1e300;
+        assert_eq!(calculate(&format!("{} * {}", large, large)), Err("Result is too large or too small".to_string()));
+        assert_float_eq(calculate(&format!("{} / {}", large, large)).unwrap(), 1.0, 1e-15);
+        
+        // Test division by zero with different signs
+        assert_eq!(calculate("1.0 / 0.0"), Err("Result is too large (infinity)".to_string()));
+        assert_eq!(calculate("-1.0 / 0.0"), Err("Result is too small (negative infinity)".to_string()));
+        assert_eq!(calculate("0.0 / 0.0"), Err("Division by zero".to_string()));
+    }
+
+    // Multiple operations, by precedence and then from the left
+    #[test]
+    fn test_multiple_operations() {
+        assert_eq!(calculate("5 + 3 + 2"), Ok(10.0));
+        assert_eq!(calculate("5 * 3 - 2"), Ok(13.0));
+        assert_eq!(calculate("5 + 3 * 2"), Ok(11.0));
+        assert_eq!(calculate("2 + 3 * 4 - 1 / 2"), Ok(13.5));
+        assert_eq!(calculate("10 - 4 - 3"), Ok(3.0));
+        assert_eq!(calculate("8 / 4 / 2"), Ok(1.0));
+        assert_float_eq(calculate("5 / 3 * 2").unwrap(), 10.0 / 3.0, 1e-15);
+        assert_eq!(calculate("1e5+2"), Ok(100002.0));
+        assert_eq!(calculate("2 * -3 + 1"), Ok(-5.0));
+        assert_eq!(calculate("(5 + 3) * 2"), Ok(16.0));
+        assert_eq!(calculate("((1+2)*(3+4))/7"), Ok(3.0));
+        // A lone operand is an expression too
+        assert_eq!(calculate("42"), Ok(42.0));
+        assert_eq!(calculate(" 5 "), Ok(5.0));
+        assert_eq!(calculate("-5"), Ok(-5.0));
+        assert_eq!(calculate("(3)"), Ok(3.0));
+        assert_eq!(calculate("((2.5))"), Ok(2.5));
+        let mut ctx = Context::new();
+        ctx.set("x", 4.0);
+        assert_eq!(calculate_with("x", &ctx), Ok(4.0));
+    }
+
+    #[test]
+    fn test_unary_signs() {
+        assert_eq!(calculate("-(5+3)"), Ok(-8.0));
+        assert_eq!(calculate("5 * -3"), Ok(-15.0));
+        assert_eq!(calculate("--5"), Ok(5.0));
+        assert_eq!(calculate("-sqrt(4)"), Ok(-2.0));
+        assert_eq!(calculate("2 - -(1 + 1)"), Ok(4.0));
+        assert_eq!(calculate("-(-(2))"), Ok(2.0));
+        assert_eq!(calculate("+(2 * 3)"), Ok(6.0));
+        assert_eq!(calculate("-(2)^2"), Ok(-4.0));
+        assert_eq!(calculate("(-2)^2"), Ok(4.0));
+        let mut ctx = Context::new();
+        ctx.set("x", 3.0);
+        assert_eq!(calculate_with("-x^2 + 1", &ctx), Ok(-8.0));
+        assert_eq!(calculate("-(1 / 0)"), Err("Result is too large (infinity)".to_string()));
+    }
+
+    #[test]
+    fn test_power_and_modulo() {
+        assert_eq!(calculate("2^3^2"), Ok(512.0));
+        assert_eq!(calculate("(2^3)^2"), Ok(64.0));
+        assert_eq!(calculate("2**10"), Ok(1024.0));
+        assert_eq!(calculate("3 * 2^2 + 1"), Ok(13.0));
+        assert_eq!(calculate("2^-1"), Ok(0.5));
+        assert_eq!(calculate("7 % 3"), Ok(1.0));
+        // The remainder takes the sign of the divisor
+        assert_eq!(calculate("-7 % 3"), Ok(2.0));
+        assert_eq!(calculate("7 % -3"), Ok(-2.0));
+        assert_eq!(calculate("5.5 % 2"), Ok(1.5));
+        assert_eq!(calculate("7 % 0"), Err("Modulo by zero".to_string()));
+        // A sign applies after the power, as it does on a superscript
+        assert_float_eq(calculate("-8^0.5").unwrap(), -(8f64.sqrt()), 1e-15);
+        assert_eq!(calculate("(-8)^0.5"), Err("Result is not a number".to_string()));
+        assert_eq!(calculate("-2^2"), Ok(-4.0));
+        assert_eq!(calculate("2^-2^2"), Ok(0.0625));
+        assert_eq!(calculate("-5^2"), calculate("-5²"));
+        assert_eq!(calculate("2*-5^2"), Ok(-50.0));
+        assert_eq!(calculate("2*-5^2"), calculate("2*-5²"));
+
+        let mut ctx = Context::new();
+        ctx.set_profile(Profile::FourFunction);
+        assert_eq!(
+            calculate_with("2^2", &ctx),
+            Err("The ^ operator is not allowed in the basic four-function profile".to_string())
+        );
+        assert_eq!(calculate_with("2²", &ctx), calculate_with("2^2", &ctx));
+    }
+
+    // Whitespace handling
+    #[test]
+    fn test_whitespace_handling() {
+        assert_eq!(calculate(" 5 + 3 "), Ok(8.0));
+        assert_eq!(calculate("\t5\t+\t3\t"), Ok(8.0));
+        assert_eq!(calculate("\n5\n+\n3\n"), Ok(8.0));
+        assert_eq!(calculate("5\t+\t3"), Ok(8.0));
+        assert_eq!(calculate("5\n+\n3"), Ok(8.0));
+    }
+
+    // Decimal precision
+    #[test]
+    fn test_decimal_precision() {
+        assert_eq!(calculate("0.1 + 0.2"), Ok(0.30000000000000004)); // Floating point precision
+        assert_eq!(calculate("0.0000000001 + 0.0000000001"), Ok(0.0000000002));
+        assert_eq!(calculate("123456789.123456789 + 0.000000001"), Ok(123456789.12345679));
+    }
+
+    // Scientific notation
+    #[test]
+    fn test_scientific_notation() {
+        assert_eq!(calculate("1e3 + 2e3"), Ok(3000.0));
+        assert_eq!(calculate("1e-3 + 2e-3"), Ok(0.003));
+        assert_eq!(calculate("1.5e3 * 2"), Ok(3000.0));
+        assert_eq!(calculate("-1e3 + 2e3"), Ok(1000.0));
+        assert_eq!(calculate("1e3 + -2e3"), Ok(-1000.0));
+        assert_eq!(calculate("1.5e-3 + 2.5e-3"), Ok(0.004));
+    }
+
+    // Extreme value tests
+    #[test]
+    fn test_extreme_values() {
+        // Near zero operations
+        assert_eq!(calculate(&format!("{} + {}", MIN_POSITIVE, MIN_POSITIVE)), Ok(MIN_POSITIVE * 2.0));
+        assert_eq!(calculate(&format!("{} * 2", MIN_POSITIVE)), Ok(MIN_POSITIVE * 2.0));
+        assert_eq!(calculate(&format!("{} / 2", MIN_POSITIVE)), Ok(MIN_POSITIVE / 2.0));
+        
+        // Maximum value operations
+        assert_eq!(calculate(&format!("{} + {}", MAX, -MAX)), Ok(0.0));
+        assert_eq!(calculate(&format!("{} * 0.5", MAX)), Ok(MAX * 0.5));
+        assert_eq!(calculate(&format!("{} / 2", MAX)), Ok(MAX / 2.0));
+        
+        // Minimum value operations
+        assert_eq!(calculate(&format!("{} + {}", MIN, -MIN)), Ok(0.0));
+        assert_eq!(calculate(&format!("{} * 0.5", MIN)), Ok(MIN * 0.5));
+        assert_eq!(calculate(&format!("{} / 2", MIN)), Ok(MIN / 2.0));
+    }
+
+    // Complex scientific notation tests
+    #[test]
+    fn test_complex_scientific_notation() {
+        // Large exponents (within f64 range)
+        assert_eq!(calculate("1e300 + 1e300"), Ok(2e300));
+        assert_eq!(calculate("1e-300 + 1e-300"), Ok(2e-300));
+        
+        // Mixed exponent signs
+        assert_eq!(calculate("1e3 + 1e-3"), Ok(1000.001));
+        assert_eq!(calculate("1e-3 + 1e3"), Ok(1000.001));
+        
+        // Negative exponents
+        assert_eq!(calculate("1e-3 * 1e-3"), Ok(1e-6));
+        assert_eq!(calculate("1e-6 / 1e-3"), Ok(1e-3));
+        
+        // Edge cases with exponents
+        assert_eq!(calculate("1.0e0 + 1.0e0"), Ok(2.0));
+        assert_eq!(calculate("1.0e+0 + 1.0e-0"), Ok(2.0));
+        
+        // Near maximum exponent
+        assert_eq!(calculate("1e307 + 1e307"), Ok(2e307));
+    }
+
+    // Complex decimal operations
+    #[test]
+    fn test_complex_decimal_operations() {
+        // Many decimal places
+        assert_float_eq(calculate("0.1234567890123456 + 0.1234567890123456").unwrap(), 0.2469135780246912, 1e-15);
+        assert_float_eq(calculate("0.1234567890123456 * 2").unwrap(), 0.2469135780246912, 1e-15);
+        
+        // Decimal precision with large numbers
+        assert_float_eq(calculate("123456789.123456789 + 0.000000001").unwrap(), 123456789.12345679, 1e-7);
+        assert_float_eq(calculate("123456789.123456789 * 1.000000001").unwrap(), 123456789.24691357, 1e-7);
+        
+        // Decimal precision with small numbers
+        assert_float_eq(calculate("0.000000001 + 0.000000001").unwrap(), 0.000000002, 1e-15);
+        assert_float_eq(calculate("0.000000001 * 2").unwrap(), 0.000000002, 1e-15);
+    }
+
+    // Precision boundary tests
+    #[test]
+    fn test_precision_boundaries() {
+        // Near epsilon operations
+        let epsilon = f64::EPSILON;
+        assert_float_eq(calculate(&format!("{} + {}", epsilon, epsilon)).unwrap(), epsilon * 2.0, 1e-15);
+        assert_float_eq(calculate(&format!("{} * 2", epsilon)).unwrap(), epsilon * 2.0, 1e-15);
+        
+        // Operations near precision limits
+        assert_float_eq(calculate("0.0000000000000001 + 0.0000000000000001").unwrap(), 2e-16, 1e-15);
+        assert_float_eq(calculate("0.0000000000000001 * 2").unwrap(), 2e-16, 1e-15);
+        
+        // Large number precision - using relative comparison
+        let large_num = 1000000000000000.0;
+        let result = calculate(&format!("{} + 1", large_num)).unwrap();
+        assert_float_eq(result, large_num + 1.0, 1e-14);
+        
+        let result = calculate(&format!("{} * 1.000000000000001", large_num)).unwrap();
+        assert_float_eq(result, large_num * 1.000000000000001, 1e-14);
+    }
+
+    // Mixed format tests
+    #[test]
+    fn test_mixed_formats() {
+        // Mixed scientific and decimal
+        assert_eq!(calculate("1e3 + 0.001"), Ok(1000.001));
+        assert_eq!(calculate("0.001 + 1e3"), Ok(1000.001));
+        
+        // Mixed negative and scientific
+        assert_eq!(calculate("-1e3 + 1e3"), Ok(0.0));
+        assert_eq!(calculate("1e3 + -1e3"), Ok(0.0));
+        
+        // Mixed formats with operations
+        assert_eq!(calculate("-1.5e3 * 2.0"), Ok(-3000.0));
+        assert_eq!(calculate("2.0 * -1.5e3"), Ok(-3000.0));
+    }
+
+    // Extreme precision tests
+    #[test]
+    fn test_extreme_precision() {
+        // Operations at the limit of f64 precision
+        let smallest = MIN_POSITIVE;
+        assert_float_eq(calculate(&format!("{} + {}", smallest, smallest)).unwrap(), smallest * 2.0, 1e-15);
+        assert_float_eq(calculate(&format!("{} * 2", smallest)).unwrap(), smallest * 2.0, 1e-15);
+        
+        // Operations with numbers very close to each other
+        let near_one = 1.0 + f64::EPSILON;
+        assert_float_eq(calculate(&format!("{} - 1.0", near_one)).unwrap(), f64::EPSILON, 1e-15);
+        assert_float_eq(calculate(&format!("{} / 1.0", near_one)).unwrap(), near_one, 1e-15);
+        
+        // Operations with numbers that differ by many orders of magnitude
+        assert_float_eq(calculate("1e300 + 1e-300").unwrap(), 1e300, 1e-15);
+        assert_float_eq(calculate("1e-300 + 1e300").unwrap(), 1e300, 1e-15);
+    }
+
+    // Denormal number tests
+    #[test]
+    fn test_denormal_numbers() {
+        // Operations with denormal numbers (numbers smaller than MIN_POSITIVE)
+        let denormal = MIN_POSITIVE / 2.0;
+        assert_float_eq(calculate(&format!("{} + {}", denormal, denormal)).unwrap(), denormal * 2.0, 1e-15);
+        assert_float_eq(calculate(&format!("{} * 2", denormal)).unwrap(), denormal * 2.0, 1e-15);
+        
+        // Operations that might result in denormal numbers
+        let tiny = MIN_POSITIVE * 0.1;
+        assert_float_eq(calculate(&format!("{} * 0.1", tiny)).unwrap(), tiny * 0.1, 1e-15);
+        assert_float_eq(calculate(&format!("{} / 10", tiny)).unwrap(), tiny / 10.0, 1e-15);
+    }
+
+    // Extreme scientific notation tests
+    #[test]
+    fn test_extreme_scientific_notation() {
+        // Maximum exponent with different mantissas (staying within f64 range)
+        assert_float_eq(calculate("1.7e300 + 1e300").unwrap(), 2.7e300, 1e-15);
+        assert_float_eq(calculate("1.7e300 * 0.5").unwrap(), 0.85e300, 1e-15);
+        
+        // Minimum exponent with different mantissas
+        assert_float_eq(calculate("1e-300 + 1e-300").unwrap(), 2e-300, 1e-15);
+        assert_float_eq(calculate("1e-300 * 0.5").unwrap(), 0.5e-300, 1e-15);
+        
+        // Mixed extreme exponents
+        assert_float_eq(calculate("1e300 * 1e-300").unwrap(), 1.0, 1e-15);
+        assert_float_eq(calculate("1e-300 * 1e300").unwrap(), 1.0, 1e-15);
+    }
+
+    // Complex decimal precision tests
+    #[test]
+    fn test_complex_decimal_precision() {
+        // Many decimal places with different operations
+        let pi_like = "3.1415926535897932384626433832795";
+        assert_float_eq(calculate(&format!("{} + {}", pi_like, pi_like)).unwrap(), TAU, 1e-15);
+        assert_float_eq(calculate(&format!("{} * 2", pi_like)).unwrap(), TAU, 1e-15);
+        
+        // Very precise decimal operations
+        let precise = "0.12345678901234567890123456789012";
+        assert_float_eq(calculate(&format!("{} + {}", precise, precise)).unwrap(), 0.24691357802469136, 1e-15);
+        assert_float_eq(calculate(&format!("{} * 2", precise)).unwrap(), 0.24691357802469136, 1e-15);
+    }
+}
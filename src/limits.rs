@@ -0,0 +1,136 @@
+//! Limits on the size of an input, so that an oversized paste is rejected
+//! with a clear error instead of making the parser work through it.
+//!
+//! The calculator has no JSON or HTTP front end yet, so the limits and
+//! their errors only reach the window, where they are set, and the
+//! sessions, which save them. A front end added later should take its
+//! limits from the [`Context`](crate::Context) it evaluates in, report
+//! these errors as they are and can publish the limits as they serialize.
+
+use serde::{Deserialize, Serialize};
+
+use crate::ast::Expr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Limits {
+    /// Most characters an input may have.
+    pub max_length: usize,
+    /// Most numbers, names and symbols an input may have.
+    pub max_tokens: usize,
+    /// Deepest nesting of parentheses, and of the parsed expression. Each
+    /// level is a level of recursion, so a thread with a small stack needs
+    /// a limit near the default.
+    pub max_depth: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_length: 10_000,
+            max_tokens: 2_000,
+            max_depth: 100,
+        }
+    }
+}
+
+impl Limits {
+    /// Checks the text of an input, before anything else is done with it.
+    pub fn check_input(&self, input: &str) -> Result<(), String> {
+        let length = input.chars().count();
+        if length > self.max_length {
+            return Err(format!(
+                "Input is too long: {} characters, the limit is {}",
+                length, self.max_length
+            ));
+        }
+        let tokens = token_count(input);
+        if tokens > self.max_tokens {
+            return Err(format!(
+                "Input has too many parts: {} numbers, names and symbols, the limit is {}",
+                tokens, self.max_tokens
+            ));
+        }
+        self.check_depth(nesting(input))
+    }
+
+    /// Checks a parsed expression.
+    pub fn check_expr(&self, expr: &Expr) -> Result<(), String> {
+        self.check_depth(expr.depth())
+    }
+
+    fn check_depth(&self, depth: usize) -> Result<(), String> {
+        if depth > self.max_depth {
+            return Err(format!(
+                "Input is nested too deeply: {} levels, the limit is {}",
+                depth, self.max_depth
+            ));
+        }
+        Ok(())
+    }
+}
+
+// A run of letters, digits, `.` and `_` is one token, as is any other
+// character but whitespace
+fn token_count(input: &str) -> usize {
+    let mut count = 0;
+    let mut in_word = false;
+    for c in input.chars() {
+        let word = c.is_alphanumeric() || c == '.' || c == '_';
+        let continues_word = word && in_word;
+        if !c.is_whitespace() && !continues_word {
+            count += 1;
+        }
+        in_word = word;
+    }
+    count
+}
+
+// Deepest nesting of parentheses
+pub(crate) fn nesting(input: &str) -> usize {
+    let (mut depth, mut deepest) = (0usize, 0);
+    for c in input.chars() {
+        match c {
+            '(' => {
+                depth += 1;
+                deepest = deepest.max(depth);
+            }
+            ')' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    deepest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limits() {
+        let limits = Limits {
+            max_length: 20,
+            max_tokens: 5,
+            max_depth: 2,
+        };
+        assert_eq!(token_count("12.5 * rate_2"), 3);
+        assert_eq!(
+            limits.check_input("ulps(1, 2)"),
+            Err("Input has too many parts: 6 numbers, names and symbols, the limit is 5".to_string())
+        );
+        assert_eq!(
+            limits.check_input(&"1".repeat(21)),
+            Err("Input is too long: 21 characters, the limit is 20".to_string())
+        );
+        assert_eq!(
+            limits.check_input("f(g(h(1)))"),
+            Err("Input has too many parts: 10 numbers, names and symbols, the limit is 5".to_string())
+        );
+        let limits = Limits { max_tokens: 100, ..limits };
+        assert_eq!(
+            limits.check_input("f(g(h(1)))"),
+            Err("Input is nested too deeply: 3 levels, the limit is 2".to_string())
+        );
+        assert_eq!(limits.check_input("f(g(1))"), Ok(()));
+    }
+}
@@ -0,0 +1,65 @@
+//! Dense linear algebra for small systems, such as the normal equations of
+//! a least-squares fit.
+
+/// A square matrix, row by row.
+pub type Matrix = Vec<Vec<f64>>;
+
+/// Solves `a · x = b` by Gaussian elimination with partial pivoting.
+/// Fails when `a` is not square, does not match `b`, or is singular: when
+/// some column has no pivot larger than rounding error.
+pub fn solve(mut a: Matrix, mut b: Vec<f64>) -> Result<Vec<f64>, String> {
+    let n = b.len();
+    if a.len() != n || a.iter().any(|row| row.len() != n) {
+        return Err(format!("A system of {} equations needs {} unknowns", n, n));
+    }
+    let scale = a.iter().flatten().fold(0.0, |max: f64, value| max.max(value.abs()));
+    let tolerance = scale * n as f64 * f64::EPSILON * 16.0;
+    for column in 0..n {
+        let pivot = (column..n)
+            .max_by(|&i, &j| a[i][column].abs().total_cmp(&a[j][column].abs()))
+            .unwrap_or(column);
+        if a[pivot][column].abs() <= tolerance || !a[pivot][column].is_finite() {
+            return Err("The system is singular: it has no single solution".to_string());
+        }
+        a.swap(column, pivot);
+        b.swap(column, pivot);
+        for row in column + 1..n {
+            let factor = a[row][column] / a[column][column];
+            if factor == 0.0 {
+                continue;
+            }
+            let (above, below) = a.split_at_mut(row);
+            for (value, pivot) in below[0][column..].iter_mut().zip(&above[column][column..]) {
+                *value -= factor * pivot;
+            }
+            b[row] -= factor * b[column];
+        }
+    }
+    // Back substitution
+    let mut x = vec![0.0; n];
+    for row in (0..n).rev() {
+        let known: f64 = (row + 1..n).map(|k| a[row][k] * x[k]).sum();
+        x[row] = (b[row] - known) / a[row][row];
+    }
+    Ok(x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solve() {
+        // 2x + y = 5, x - y = 1
+        assert_eq!(solve(vec![vec![2.0, 1.0], vec![1.0, -1.0]], vec![5.0, 1.0]), Ok(vec![2.0, 1.0]));
+        // Needs a row swap: the first pivot is 0
+        let x = solve(
+            vec![vec![0.0, 1.0, 1.0], vec![1.0, 0.0, 1.0], vec![1.0, 1.0, 0.0]],
+            vec![5.0, 4.0, 3.0],
+        )
+        .unwrap();
+        assert!(x.iter().zip([1.0, 2.0, 3.0]).all(|(a, b)| (a - b).abs() < 1e-12));
+        assert!(solve(vec![vec![1.0, 2.0], vec![2.0, 4.0]], vec![3.0, 6.0]).is_err());
+        assert!(solve(vec![vec![1.0, 2.0]], vec![3.0]).is_err());
+    }
+}
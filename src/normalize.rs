@@ -0,0 +1,410 @@
+//! Rewrites human-written math into the syntax the parser understands.
+//!
+//! Pasted text often uses typographic operators (`×`, `÷`, `−`), `x` for
+//! multiplication, `:` for division, or a comma as decimal separator. These
+//! are mapped to their ASCII equivalents before parsing. Text copied from
+//! PDFs and word processors also carries invisible characters, which
+//! [`sanitize`] strips first, and IMEs often emit full-width forms such as
+//! `１２３＋４`. Word processors write powers as superscripts, so `5²`
+//! becomes `5^2`.
+
+use serde::{Deserialize, Serialize};
+
+use crate::eval::Context;
+use crate::parser::is_superscript;
+
+/// Which character separates the integer and fractional parts of a number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum DecimalSeparator {
+    /// `1,234.5`
+    #[default]
+    Point,
+    /// `1.234,5`
+    Comma,
+}
+
+impl DecimalSeparator {
+    pub const ALL: [DecimalSeparator; 2] = [DecimalSeparator::Point, DecimalSeparator::Comma];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DecimalSeparator::Point => "Point (1.5)",
+            DecimalSeparator::Comma => "Comma (1,5)",
+        }
+    }
+}
+
+/// Result of [`sanitize`]: the cleaned text and what was taken out of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sanitized {
+    pub text: String,
+    /// Character offset in the original input and the character found
+    /// there, for every character that was removed or replaced.
+    pub removed: Vec<(usize, char)>,
+}
+
+/// Strips zero-width characters, byte order marks, direction marks, soft
+/// hyphens, and smart quotes from `input`, and turns non-breaking spaces
+/// into plain spaces.
+pub fn sanitize(input: &str) -> Sanitized {
+    let mut text = String::with_capacity(input.len());
+    let mut removed = Vec::new();
+
+    for (i, c) in input.chars().enumerate() {
+        match c {
+            '\u{00A0}' | '\u{2007}' | '\u{202F}' => {
+                text.push(' ');
+                removed.push((i, c));
+            }
+            _ if invisible_name(c).is_some() => removed.push((i, c)),
+            _ => text.push(c),
+        }
+    }
+
+    Sanitized { text, removed }
+}
+
+/// Human-readable name of a character that [`sanitize`] removes or
+/// replaces, or `None` for characters it keeps.
+pub fn invisible_name(c: char) -> Option<&'static str> {
+    let name = match c {
+        '\u{00A0}' => "no-break space",
+        '\u{2007}' => "figure space",
+        '\u{202F}' => "narrow no-break space",
+        '\u{00AD}' => "soft hyphen",
+        '\u{200B}' => "zero-width space",
+        '\u{200C}' => "zero-width non-joiner",
+        '\u{200D}' => "zero-width joiner",
+        '\u{200E}' => "left-to-right mark",
+        '\u{200F}' => "right-to-left mark",
+        '\u{2060}' => "word joiner",
+        '\u{FEFF}' => "byte order mark",
+        '\u{2018}' => "left single quotation mark",
+        '\u{2019}' => "right single quotation mark",
+        '\u{201C}' => "left double quotation mark",
+        '\u{201D}' => "right double quotation mark",
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// Sanitizes `input` and maps full-width characters, operator aliases and
+/// locale-specific number punctuation to plain ASCII syntax, and
+/// superscript powers to `^`. CJK numerals are converted too when
+/// `ctx.cjk_numerals` is set.
+pub fn normalize(input: &str, ctx: &Context) -> String {
+    let separator = ctx.decimal_separator;
+    let mut text: String = sanitize(input).text.chars().map(to_half_width).collect();
+    if ctx.cjk_numerals {
+        text = convert_cjk_numerals(&text);
+    }
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(input.len());
+
+    for (i, &c) in chars.iter().enumerate() {
+        let before = chars[..i].iter().rev().find(|c| !c.is_whitespace()).copied();
+        let after = chars[i + 1..].iter().find(|c| !c.is_whitespace()).copied();
+        let between_digits = matches!(chars.get(i.wrapping_sub(1)), Some(p) if p.is_ascii_digit())
+            && matches!(chars.get(i + 1), Some(n) if n.is_ascii_digit());
+
+        match c {
+            '×' | '·' | '⋅' | '∙' => out.push('*'),
+            '÷' | ':' => out.push('/'),
+            '−' | '–' => out.push('-'),
+            // The Greek mu for the micro sign, as in `5 μm`
+            'μ' => out.push('µ'),
+            // Prime marks for feet and inches
+            '′' => out.push('\''),
+            '″' => out.push('"'),
+            // `x` only multiplies between two numbers; elsewhere it is a name,
+            // or the prefix of a hex literal such as `0x1.8p3`
+            'x' | 'X' if is_hex_prefix(&chars, i) => out.push(c),
+            'x' | 'X' if is_numeric_end(before) && is_numeric_start(after) => out.push('*'),
+            ',' if separator == DecimalSeparator::Comma && between_digits => out.push('.'),
+            '.' if separator == DecimalSeparator::Comma && between_digits && is_digit_group(&chars[i + 1..]) => {}
+            _ => out.push(c),
+        }
+    }
+
+    expand_superscripts(&out)
+}
+
+fn superscript_value(c: char) -> Option<char> {
+    let plain = match c {
+        '⁰' => '0',
+        '¹' => '1',
+        '²' => '2',
+        '³' => '3',
+        '⁴' => '4',
+        '⁵' => '5',
+        '⁶' => '6',
+        '⁷' => '7',
+        '⁸' => '8',
+        '⁹' => '9',
+        '⁺' => '+',
+        '⁻' => '-',
+        _ => return None,
+    };
+    Some(plain)
+}
+
+/// Rewrites each superscript exponent as a power of what it follows: `5²`
+/// becomes `5^2`, `10⁻³` becomes `10^-3` and `(1 + x)²` becomes
+/// `(1 + x)^2`. A superscript with nothing to raise is left for the parser
+/// to report.
+fn expand_superscripts(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let run = rest.find(|c: char| !is_superscript(c)).unwrap_or(rest.len());
+        let exponent: String = rest[..run].chars().filter_map(superscript_value).collect();
+        let digits = exponent.strip_prefix(['+', '-']).unwrap_or(&exponent);
+        let valid = !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit());
+        match base_start(&out).filter(|_| valid) {
+            Some(_) => {
+                out.push('^');
+                out.push_str(exponent.trim_start_matches('+'));
+                rest = &rest[run..];
+            }
+            None => {
+                out.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    out
+}
+
+// Where the operand ending `text` starts: a number or name, a function
+// call, or a parenthesized group
+fn base_start(text: &str) -> Option<usize> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '.' || c == '_';
+    let mut start = text.len();
+    if text.ends_with(')') {
+        let mut depth = 0usize;
+        for (i, c) in text.char_indices().rev() {
+            match c {
+                ')' => depth += 1,
+                '(' => depth -= 1,
+                _ => {}
+            }
+            if depth == 0 {
+                start = i;
+                break;
+            }
+        }
+        if depth != 0 {
+            return None;
+        }
+    }
+    let word = text[..start].trim_end_matches(is_word).len();
+    // `f(x)` keeps its name, but `2(x)` is not a call
+    if start == text.len() || text[word..start].chars().next().is_some_and(char::is_alphabetic) {
+        start = word;
+    }
+    (start < text.len()).then_some(start)
+}
+
+/// Maps a full-width form (U+FF01 to U+FF5E) or the ideographic space to
+/// its ASCII counterpart.
+fn to_half_width(c: char) -> char {
+    match c {
+        '\u{3000}' => ' ',
+        '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+        _ => c,
+    }
+}
+
+fn cjk_digit(c: char) -> Option<u64> {
+    let digit = match c {
+        '〇' | '零' => 0,
+        '一' => 1,
+        '二' | '两' | '兩' => 2,
+        '三' => 3,
+        '四' => 4,
+        '五' => 5,
+        '六' => 6,
+        '七' => 7,
+        '八' => 8,
+        '九' => 9,
+        _ => return None,
+    };
+    Some(digit)
+}
+
+fn cjk_unit(c: char) -> Option<u64> {
+    let unit = match c {
+        '十' => 10,
+        '百' => 100,
+        '千' => 1_000,
+        '万' | '萬' => 10_000,
+        '亿' | '億' => 100_000_000,
+        _ => return None,
+    };
+    Some(unit)
+}
+
+/// Replaces every run of CJK numerals with its decimal value, reading
+/// `三百二十五` as 325 and unit-less runs like `二〇二四` digit by digit.
+fn convert_cjk_numerals(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut run = String::new();
+
+    for c in text.chars() {
+        if cjk_digit(c).is_some() || cjk_unit(c).is_some() {
+            run.push(c);
+            continue;
+        }
+        if !run.is_empty() {
+            out.push_str(&cjk_value(&run).to_string());
+            run.clear();
+        }
+        out.push(c);
+    }
+    if !run.is_empty() {
+        out.push_str(&cjk_value(&run).to_string());
+    }
+
+    out
+}
+
+fn cjk_value(run: &str) -> u64 {
+    if run.chars().all(|c| cjk_digit(c).is_some()) {
+        return run.chars().filter_map(cjk_digit).fold(0, |acc, d| acc.saturating_mul(10).saturating_add(d));
+    }
+
+    // `total` collects completed 万/亿 groups, `section` the part below
+    // the current group, `digit` the pending multiplier for the next unit.
+    let (mut total, mut section, mut digit) = (0u64, 0u64, 0u64);
+    for c in run.chars() {
+        if let Some(d) = cjk_digit(c) {
+            digit = d;
+        } else if let Some(unit) = cjk_unit(c) {
+            if unit >= 10_000 {
+                total = total.saturating_add((section + digit).saturating_mul(unit));
+                section = 0;
+            } else {
+                // A bare unit such as the 十 in 十二 means one of it
+                section += if digit == 0 { 1 } else { digit } * unit;
+            }
+            digit = 0;
+        }
+    }
+    total.saturating_add(section).saturating_add(digit)
+}
+
+// The `x` at `i` directly follows a lone `0` and directly precedes a hex
+// digit, as in `0xff`; `0 x ff` and `10x5` are not hex.
+fn is_hex_prefix(chars: &[char], i: usize) -> bool {
+    i >= 1
+        && chars[i - 1] == '0'
+        && !(i >= 2 && (chars[i - 2].is_alphanumeric() || chars[i - 2] == '.'))
+        && chars.get(i + 1).is_some_and(|c| c.is_ascii_hexdigit() || *c == '.')
+}
+
+fn is_numeric_end(c: Option<char>) -> bool {
+    matches!(c, Some(c) if c.is_ascii_digit() || c == '.')
+}
+
+fn is_numeric_start(c: Option<char>) -> bool {
+    matches!(c, Some(c) if c.is_ascii_digit() || c == '.' || c == '-')
+}
+
+// Exactly three digits followed by something other than a digit: the tail
+// of a thousands group such as the `.234` in `1.234,5`.
+fn is_digit_group(rest: &[char]) -> bool {
+    rest.len() >= 3 && rest[..3].iter().all(|c| c.is_ascii_digit()) && !rest.get(3).is_some_and(|c| c.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(input: &str) -> String {
+        normalize(input, &Context::new())
+    }
+
+    fn comma(input: &str) -> String {
+        let mut ctx = Context::new();
+        ctx.decimal_separator = DecimalSeparator::Comma;
+        normalize(input, &ctx)
+    }
+
+    fn cjk(input: &str) -> String {
+        let mut ctx = Context::new();
+        ctx.cjk_numerals = true;
+        normalize(input, &ctx)
+    }
+
+    #[test]
+    fn test_operator_aliases() {
+        assert_eq!(point("5 × 3"), "5 * 3");
+        assert_eq!(point("5·3"), "5*3");
+        assert_eq!(point("6 ÷ 2"), "6 / 2");
+        assert_eq!(point("6:2"), "6/2");
+        assert_eq!(point("5 − 3"), "5 - 3");
+        assert_eq!(point("5 x 3"), "5 * 3");
+        assert_eq!(point("2.5X-1"), "2.5*-1");
+    }
+
+    #[test]
+    fn test_x_stays_a_name_outside_numbers() {
+        assert_eq!(point("x * 2"), "x * 2");
+        assert_eq!(point("2 * x"), "2 * x");
+        assert_eq!(point("max + 1"), "max + 1");
+        assert_eq!(point("0x1.8p3 + 0XFF"), "0x1.8p3 + 0XFF");
+        assert_eq!(point("0 x 5"), "0 * 5");
+        assert_eq!(point("10x5"), "10*5");
+    }
+
+    #[test]
+    fn test_sanitize_reports_removed_characters() {
+        let sanitized = sanitize("\u{FEFF}1\u{200B}2\u{00A0}+ \u{201C}3\u{201D}");
+        assert_eq!(sanitized.text, "12 + 3");
+        assert_eq!(
+            sanitized.removed,
+            vec![(0, '\u{FEFF}'), (2, '\u{200B}'), (4, '\u{00A0}'), (7, '\u{201C}'), (9, '\u{201D}')]
+        );
+        assert_eq!(invisible_name('\u{200B}'), Some("zero-width space"));
+        assert_eq!(invisible_name('5'), None);
+    }
+
+    #[test]
+    fn test_normalize_sanitizes_first() {
+        assert_eq!(point("1\u{200B}0 \u{00D7} 3"), "10 * 3");
+    }
+
+    #[test]
+    fn test_full_width_characters() {
+        assert_eq!(point("１２３＋４"), "123+4");
+        assert_eq!(point("１．５\u{3000}＊\u{3000}ｘ"), "1.5 * x");
+    }
+
+    #[test]
+    fn test_cjk_numerals() {
+        assert_eq!(cjk("三百二十五 + 十二"), "325 + 12");
+        assert_eq!(cjk("二〇二四 - 一万零五"), "2024 - 10005");
+        assert_eq!(cjk("三亿五千万 / 两千"), "350000000 / 2000");
+        assert_eq!(point("三 + 四"), "三 + 四");
+    }
+
+    #[test]
+    fn test_decimal_comma() {
+        assert_eq!(comma("1,5 + 2,25"), "1.5 + 2.25");
+        assert_eq!(comma("1.234,5 * 2"), "1234.5 * 2");
+        assert_eq!(comma("1.5 + 1"), "1.5 + 1");
+        assert_eq!(point("1,5 + 1"), "1,5 + 1");
+    }
+
+    #[test]
+    fn test_superscript_powers() {
+        assert_eq!(point("5²"), "5^2");
+        assert_eq!(point("2³ + 1"), "2^3 + 1");
+        assert_eq!(point("1.5 * 10⁻³"), "1.5 * 10^-3");
+        assert_eq!(point("x¹⁰ / ulps(a, b)²"), "x^10 / ulps(a, b)^2");
+        assert_eq!(point("(1 + 2)²"), "(1 + 2)^2");
+        assert_eq!(point("2^3²"), "2^3^2");
+        assert_eq!(point("² + 1"), "² + 1");
+        assert_eq!(point("5⁻"), "5⁻");
+    }
+}
@@ -0,0 +1,1067 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use crate::ast::{BinaryOp, Expr, Name};
+use crate::color::Rgb;
+use crate::imperial::Length;
+use crate::limits::{nesting, Limits};
+use crate::units::Unit;
+
+// Longest operand, in bytes, whose tree an arena keeps; longer ones are
+// rare, and keeping the text of each of their nested groups would copy
+// the line over and over
+const MAX_KEPT_LENGTH: usize = 4096;
+
+/// What parsing one input leaves for parsing the next: the names it read,
+/// and the trees of its calls and parenthesized operands by their text.
+///
+/// An edit to a long line leaves most of its operands as they were, so a
+/// caller that parses a line at every keystroke can keep an arena and
+/// parse with [`Arena::parse`]: the trees of unchanged operands are handed
+/// out again instead of being parsed and built afresh, so a keystroke only
+/// builds the nodes its edit changed. An arena keeps only what the last
+/// input used, so it holds no more than one line's worth.
+#[derive(Debug, Default)]
+pub struct Arena {
+    names: HashSet<Name>,
+    trees: HashMap<Box<str>, Arc<Expr>>,
+    // What the input being parsed has used so far, which is all that is
+    // kept once it is done
+    used_names: HashSet<Name>,
+    used_trees: HashMap<Box<str>, Arc<Expr>>,
+    // The last input, the nesting it was allowed and what it gave, for
+    // asking again about the same one
+    last: Option<(String, usize, Result<Expr, String>)>,
+}
+
+impl Arena {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Like [`parse_within`], sharing names and unchanged operands with the
+    /// last input parsed in this arena.
+    pub fn parse(&mut self, input: &str, max_depth: usize) -> Result<Expr, String> {
+        if let Some((text, depth, parsed)) = &self.last {
+            if text == input && *depth == max_depth {
+                return parsed.clone();
+            }
+        }
+        let parsed = parse_with(input, max_depth, Some(self));
+        self.names = std::mem::take(&mut self.used_names);
+        self.trees = std::mem::take(&mut self.used_trees);
+        self.last = Some((input.to_string(), max_depth, parsed.clone()));
+        parsed
+    }
+
+    fn name(&mut self, text: &str) -> Name {
+        if let Some(name) = self.used_names.get(text) {
+            return name.clone();
+        }
+        let name = self.names.take(text).unwrap_or_else(|| Name::new(text));
+        self.used_names.insert(name.clone());
+        name
+    }
+
+    fn tree(&mut self, text: &str) -> Option<Arc<Expr>> {
+        if let Some(tree) = self.used_trees.get(text) {
+            return Some(tree.clone());
+        }
+        let (text, tree) = self.trees.remove_entry(text)?;
+        self.used_trees.insert(text, tree.clone());
+        Some(tree)
+    }
+}
+
+// One parse: the input errors point into, and the arena to take names and
+// trees from, if the caller keeps one
+struct Reader<'a> {
+    whole: &'a str,
+    arena: Option<&'a mut Arena>,
+}
+
+impl Reader<'_> {
+    fn name(&mut self, text: &str) -> Name {
+        match &mut self.arena {
+            Some(arena) => arena.name(text),
+            None => Name::new(text),
+        }
+    }
+
+    fn variable(&mut self, text: &str) -> Expr {
+        Expr::Variable(self.name(text))
+    }
+
+    fn call(&mut self, text: &str, args: Vec<Expr>) -> Expr {
+        Expr::Call { name: self.name(text), args }
+    }
+}
+
+// Each level of parentheses is a level of recursion, so deeper input than
+// the caller allows is refused before it can exhaust the stack
+fn check_nesting(input: &str, max_depth: usize) -> Result<(), String> {
+    if nesting(input) > max_depth {
+        return Err(format!("Input is nested too deeply; the most is {} levels", max_depth));
+    }
+    Ok(())
+}
+
+// Marks that belong to the character before them, so that `é` written
+// as `e` plus an accent, or an emoji with a skin tone, is one character to
+// the reader: combining accents, variation selectors, the zero-width
+// joiner and skin tone modifiers.
+fn continues_character(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0300}'..='\u{036F}'
+            | '\u{1AB0}'..='\u{1AFF}'
+            | '\u{1DC0}'..='\u{1DFF}'
+            | '\u{20D0}'..='\u{20FF}'
+            | '\u{FE00}'..='\u{FE0F}'
+            | '\u{FE20}'..='\u{FE2F}'
+            | '\u{200D}'
+            | '\u{1F3FB}'..='\u{1F3FF}'
+    )
+}
+
+// The characters of `text` as the reader sees them: each is a base
+// character with any marks that continue it, as a slice of `text`. Read
+// lazily, as every keystroke checks the whole input.
+fn characters(text: &str) -> impl Iterator<Item = &str> {
+    let mut chars = text.char_indices().peekable();
+    std::iter::from_fn(move || {
+        let (start, _) = chars.next()?;
+        let mut end = text.len();
+        let mut joined = text[start..].starts_with('\u{200D}');
+        while let Some(&(i, c)) = chars.peek() {
+            // After a zero-width joiner the next character joins too
+            if !continues_character(c) && !joined {
+                end = i;
+                break;
+            }
+            joined = c == '\u{200D}';
+            chars.next();
+        }
+        Some(&text[start..end])
+    })
+}
+
+pub(crate) fn is_superscript(c: char) -> bool {
+    matches!(c, '⁰' | '¹' | '²' | '³' | '⁴'..='⁹' | '⁺' | '⁻')
+}
+
+// Rejects characters no expression can hold, naming the first one and
+// its position in characters rather than bytes
+fn check_characters(input: &str) -> Result<(), String> {
+    for (position, character) in characters(input).enumerate() {
+        let mut chars = character.chars();
+        let (Some(c), None) = (chars.next(), chars.next()) else {
+            return Err(format!("Unexpected character '{}' at position {}", character, position + 1));
+        };
+        if is_superscript(c) {
+            return Err(format!(
+                "Superscript '{}' must follow a number, name or parentheses, as in 5²",
+                c
+            ));
+        }
+        if !(c.is_alphanumeric() || c.is_whitespace() || "+-*/%^().,;'\"#°℃℉_".contains(c)) {
+            return Err(format!("Unexpected character '{}' at position {}", c, position + 1));
+        }
+    }
+    Ok(())
+}
+
+/// Parses an expression such as `(2 + 3) * 4 - 1 / 2`, or a lone operand
+/// such as `42`, `x` or `ulps(a, b)`, into an [`Expr`]. Powers, written
+/// `^` or `**`, bind tightest and group from the right; `*`, `/` and `%`
+/// bind tighter than `+` and `-` and, like them, group from the left.
+/// Parentheses group what they hold. Parentheses may be nested as deep as
+/// the default [`Limits`] allow.
+pub fn parse(input: &str) -> Result<Expr, String> {
+    parse_within(input, Limits::default().max_depth)
+}
+
+/// Like [`parse`], allowing parentheses nested `max_depth` levels deep,
+/// such as a context's [`Limits::max_depth`].
+pub fn parse_within(input: &str, max_depth: usize) -> Result<Expr, String> {
+    parse_with(input, max_depth, None)
+}
+
+fn parse_with(input: &str, max_depth: usize, arena: Option<&mut Arena>) -> Result<Expr, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err("Empty input".to_string());
+    }
+    check_nesting(input, max_depth)?;
+    check_characters(input)?;
+    check_parentheses(input)?;
+    parse_expr(&mut Reader { whole: input, arena }, input)
+}
+
+// Pairs each parenthesis with its partner, naming the first one that has
+// none and its position in characters
+fn check_parentheses(input: &str) -> Result<(), String> {
+    let mut open = Vec::new();
+    for (position, character) in characters(input).enumerate() {
+        match character {
+            "(" => open.push(position),
+            ")" if open.pop().is_none() => {
+                return Err(format!("Unexpected closing parenthesis at position {}", position + 1));
+            }
+            _ => {}
+        }
+    }
+    match open.pop() {
+        Some(position) => Err(format!("Missing closing parenthesis at position {}", position + 1)),
+        None => Ok(()),
+    }
+}
+
+// Where `part`, a slice of `whole`, starts in `whole`, counted in
+// characters from 1. The parser only ever slices its input, so any piece
+// of it can be pointed at in an error.
+fn position(whole: &str, part: &str) -> usize {
+    let offset = part.as_ptr() as usize - whole.as_ptr() as usize;
+    characters(&whole[..offset]).count() + 1
+}
+
+// Names for the operands of an expression in errors, in order; any after
+// these are just a number
+const OPERAND_NAMES: [&str; 5] = ["first number", "second number", "third number", "fourth number", "fifth number"];
+
+// The parser proper, for `input`, a slice of `whole` already checked by
+// `parse`
+fn parse_expr(reader: &mut Reader, input: &str) -> Result<Expr, String> {
+    let mut tokens = Tokens::new(input).peekable();
+    let mut operands = Vec::new();
+    let mut operators = Vec::new();
+    // The operator before the operand being read, with its text
+    let mut before = None;
+    while let Some(token) = tokens.next() {
+        let text = match token {
+            Token::Operand(text) => text.trim(),
+            Token::Operator(op, written) => {
+                operators.push(op);
+                before = Some((op, written));
+                continue;
+            }
+        };
+        let after = match tokens.peek() {
+            Some(&Token::Operator(op, written)) => Some((op, written)),
+            _ => None,
+        };
+        if text.is_empty() {
+            let (at, (_, written)) = match (before, after) {
+                (Some(op), _) => ("after", op),
+                (None, Some(op)) => ("before", op),
+                (None, None) => unreachable!("parsed input is not empty"),
+            };
+            return Err(format!("Missing operand {} '{}' at position {}", at, written, position(reader.whole, written)));
+        }
+        let name = OPERAND_NAMES.get(operands.len()).copied().unwrap_or("number");
+        // Signs before a power apply to the power, so `-2^2` is -4
+        let (negated, text) = match split_signs(text) {
+            Some((negative, operand)) if after.is_some_and(|(op, _)| op == BinaryOp::Pow) => (negative, operand),
+            _ => (false, text),
+        };
+        operands.push((negated, match parse_kept(reader, text) {
+            Some(Ok(operand)) if matches!(*operand, Expr::Number(n) if n.is_infinite()) => {
+                let mut name = name.chars();
+                let first = name.next().map(|c| c.to_ascii_uppercase()).unwrap_or_default();
+                return Err(format!(
+                    "{}{} is too large or too small at position {}",
+                    first,
+                    name.as_str(),
+                    position(reader.whole, text)
+                ));
+            }
+            Some(result) => result?,
+            // Text that is not one operand, such as `5 3`, is missing the
+            // operator between its parts
+            None if before.is_none() && after.is_none() => return Err("No operator found".to_string()),
+            None => return Err(format!("Invalid {} '{}' at position {}", name, text, position(reader.whole, text))),
+        }));
+    }
+    Ok(combine(operands, operators))
+}
+
+// `parse_operand` for an operand between operators, handing out the tree
+// the arena kept from the last input for a call or parentheses with the
+// same text
+fn parse_kept(reader: &mut Reader, text: &str) -> Option<Result<Arc<Expr>, String>> {
+    if reader.arena.is_none() || !text.ends_with(')') || text.len() > MAX_KEPT_LENGTH {
+        return parse_operand(reader, text).map(|operand| operand.map(Arc::new));
+    }
+    if let Some(tree) = reader.arena.as_mut().and_then(|arena| arena.tree(text)) {
+        return Some(Ok(tree));
+    }
+    let tree = Arc::new(match parse_operand(reader, text)? {
+        Ok(operand) => operand,
+        Err(err) => return Some(Err(err)),
+    });
+    if let Some(arena) = &mut reader.arena {
+        arena.used_trees.insert(text.into(), tree.clone());
+    }
+    Some(Ok(tree))
+}
+
+// A piece of an expression as it is written, borrowed from the input, so
+// reading one copies nothing and an error can point at where it is
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Token<'a> {
+    // What stands between two top-level operators, such as `sqrt(2 + x)`
+    // or `-5`
+    Operand(&'a str),
+    // A top-level operator with the text it is written with, such as `**`
+    Operator(BinaryOp, &'a str),
+}
+
+// Reads `input` as operands and the top-level operators between them, in
+// turn, starting and ending with an operand that may be empty. A sign
+// starting an operand, the sign of an exponent, the operators in a call's
+// parentheses and the punctuation of a length stay in the operands; `**`
+// is a single power operator.
+struct Tokens<'a> {
+    input: &'a str,
+    chars: std::str::CharIndices<'a>,
+    // Where the operand being read starts
+    start: usize,
+    // The operator that ended the last operand, given out next
+    operator: Option<Token<'a>>,
+    finished: bool,
+    // Nothing but whitespace and signs since the last operator, so a sign
+    // here starts the operand
+    awaiting_operand: bool,
+    // Within a word that started with a digit, where an `e` starts an
+    // exponent rather than being part of a name
+    in_word: bool,
+    in_number: bool,
+    // In a hex literal `e` is a digit and `p` starts the exponent
+    in_hex: bool,
+    after_exponent_mark: bool,
+    prev: Option<char>,
+    depth: usize,
+    // The second `*` of a `**` already taken as an operator
+    skip: bool,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(input: &'a str) -> Self {
+        Tokens {
+            input,
+            chars: input.char_indices(),
+            start: 0,
+            operator: None,
+            finished: false,
+            awaiting_operand: true,
+            in_word: false,
+            in_number: false,
+            in_hex: false,
+            after_exponent_mark: false,
+            prev: None,
+            depth: 0,
+            skip: false,
+        }
+    }
+
+    // Reads the character `c` at `i`, returning the operand it ends if it
+    // is an operator
+    fn read(&mut self, i: usize, c: char) -> Option<Token<'a>> {
+        if std::mem::take(&mut self.skip) {
+            self.prev = Some(c);
+            return None;
+        }
+        match c {
+            '(' => self.depth += 1,
+            ')' => self.depth = self.depth.saturating_sub(1),
+            _ => {}
+        }
+        if (c == 'x' || c == 'X') && self.prev == Some('0') || c == '#' {
+            self.in_hex = true;
+        }
+        let input = self.input;
+        let end = i + c.len_utf8();
+        let sign = c == '+' || c == '-';
+        let double_star = c == '*' && input[end..].starts_with('*');
+        let symbol = if double_star { Some(BinaryOp::Pow) } else { BinaryOp::from_symbol(c) };
+        let mut operand = None;
+        match symbol {
+            Some(op)
+                if self.depth == 0
+                    && !(sign && (self.awaiting_operand || self.after_exponent_mark))
+                    && !is_length_punctuation(c, self.prev, &input[end..]) =>
+            {
+                let end = if double_star { end + 1 } else { end };
+                operand = Some(Token::Operand(&input[self.start..i]));
+                self.operator = Some(Token::Operator(op, &input[i..end]));
+                self.start = end;
+                self.skip = double_star;
+                self.awaiting_operand = true;
+                self.in_hex = false;
+            }
+            // Further signs, as in `--5`, still start the operand
+            _ if c.is_whitespace() || sign && self.awaiting_operand => {}
+            _ => self.awaiting_operand = false,
+        }
+
+        if c.is_alphanumeric() || c == '.' || c == '_' {
+            if !self.in_word {
+                self.in_number = c.is_ascii_digit() || c == '.';
+            }
+            self.in_word = true;
+        } else {
+            (self.in_word, self.in_number) = (false, false);
+        }
+        self.after_exponent_mark = if self.in_hex {
+            c == 'p' || c == 'P'
+        } else {
+            (c == 'e' || c == 'E') && self.in_number
+        };
+        if !c.is_ascii_hexdigit() && !matches!(c, 'x' | 'X' | '#' | '.' | 'p' | 'P' | '+' | '-') {
+            self.in_hex = false;
+        }
+        self.prev = Some(c);
+        operand
+    }
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        if let Some(operator) = self.operator.take() {
+            return Some(operator);
+        }
+        while let Some((i, c)) = self.chars.next() {
+            if let Some(operand) = self.read(i, c) {
+                return Some(operand);
+            }
+        }
+        if std::mem::replace(&mut self.finished, true) {
+            return None;
+        }
+        Some(Token::Operand(&self.input[self.start..]))
+    }
+}
+
+// An operator waiting in `combine` for its operands
+#[derive(Clone, Copy)]
+enum Pending {
+    Binary(BinaryOp),
+    // The signs before an operand, waiting for the powers that follow it
+    Negate,
+}
+
+// Builds the tree for `operands` joined by `operators`: the tighter
+// operators are applied first, and operators of equal precedence from the
+// left, or from the right for powers, as in shunting-yard. An operand
+// marked as negated is negated after the powers that follow it and before
+// any other operator.
+fn combine(operands: Vec<(bool, Arc<Expr>)>, operators: impl IntoIterator<Item = BinaryOp>) -> Expr {
+    fn reduce(output: &mut Vec<Arc<Expr>>, pending: Pending) {
+        let rhs = output.pop().expect("an operand for each operator");
+        let reduced = match pending {
+            Pending::Binary(op) => {
+                let lhs = output.pop().expect("an operand for each operator");
+                Expr::Binary { op, lhs, rhs }
+            }
+            Pending::Negate => Expr::Negate(rhs),
+        };
+        output.push(Arc::new(reduced));
+    }
+
+    fn push(output: &mut Vec<Arc<Expr>>, pending: &mut Vec<Pending>, (negated, operand): (bool, Arc<Expr>)) {
+        if negated {
+            pending.push(Pending::Negate);
+        }
+        output.push(operand);
+    }
+
+    let mut output: Vec<Arc<Expr>> = Vec::new();
+    let mut pending: Vec<Pending> = Vec::new();
+    let mut operands = operands.into_iter();
+    if let Some(first) = operands.next() {
+        push(&mut output, &mut pending, first);
+    }
+    for (op, operand) in operators.into_iter().zip(operands) {
+        while let Some(&top) = pending.last().filter(|top| match top {
+            Pending::Binary(top) => {
+                top.precedence() > op.precedence() || (top.precedence() == op.precedence() && !op.right_associative())
+            }
+            Pending::Negate => op != BinaryOp::Pow,
+        }) {
+            pending.pop();
+            reduce(&mut output, top);
+        }
+        pending.push(Pending::Binary(op));
+        push(&mut output, &mut pending, operand);
+    }
+    while let Some(top) = pending.pop() {
+        reduce(&mut output, top);
+    }
+    Arc::unwrap_or_clone(output.pop().expect("at least one operand"))
+}
+
+/// Whether `input` is an expression that stops right after its operator,
+/// such as `5 +`, and only needs a second operand to be complete, with
+/// parentheses nested no deeper than `max_depth`.
+pub fn is_incomplete(input: &str, max_depth: usize) -> bool {
+    let input = input.trim_end();
+    match input.chars().last() {
+        Some(c) if BinaryOp::from_symbol(c).is_some() => {
+            let head = input[..input.len() - c.len_utf8()].trim();
+            let head = if input.ends_with("**") { head.trim_end_matches('*').trim() } else { head };
+            // A lone sign or a dangling exponent is not a finished operand
+            !head.is_empty()
+                && check_nesting(head, max_depth).is_ok()
+                && (parse_operand(&mut Reader { whole: head, arena: None }, head).is_some_and(|operand| operand.is_ok())
+                    || parse_within(head, max_depth).is_ok())
+        }
+        _ => false,
+    }
+}
+
+// `None` when `text` does not look like an operand at all; an error when
+// it is a function call whose arguments do not parse.
+fn parse_operand(reader: &mut Reader, text: &str) -> Option<Result<Expr, String>> {
+    if let Some(group) = parse_group(reader, text) {
+        return Some(group);
+    }
+    if let Ok(n) = text.parse::<f64>() {
+        return Some(Ok(Expr::Number(n)));
+    }
+    if let Some(n) = parse_hex_float(text) {
+        return Some(Ok(Expr::Number(n)));
+    }
+    if let Some(color) = Rgb::from_hex(text) {
+        return Some(Ok(Expr::quantity(Expr::Number(color.value()), "#")));
+    }
+    if let Some(length) = Length::parse(text) {
+        return Some(Ok(Expr::quantity(Expr::Number(length.inches), "\"")));
+    }
+    if let Some(quantity) = parse_quantity(text) {
+        return Some(Ok(quantity));
+    }
+    if is_identifier(text) {
+        return Some(Ok(reader.variable(text)));
+    }
+    // A quoted name may hold characters a bare one cannot, as in "C#4"
+    if let Some(name) = text.strip_prefix('"').and_then(|rest| rest.strip_suffix('"')) {
+        if !name.is_empty() && !name.contains('"') {
+            return Some(Ok(reader.variable(name)));
+        }
+    }
+    parse_call(reader, text).or_else(|| parse_unary(reader, text))
+}
+
+// Signs before an operand that is not a plain number, as in `-(5 + 3)`,
+// `-sqrt(4)` or `--5`. An operand followed by a power never gets here:
+// `parse_expr` takes its signs off first, so that they apply after the
+// power, as `-x^2` is `-(x^2)` and `-2^2` is -4.
+fn parse_unary(reader: &mut Reader, text: &str) -> Option<Result<Expr, String>> {
+    let (negative, operand) = split_signs(text)?;
+    let operand = parse_operand(reader, operand)?;
+    Some(operand.map(|operand| if negative { Expr::negate(operand) } else { operand }))
+}
+
+// The signs at the start of `text` and what follows them: whether they
+// come to a minus, as a run of signs comes down to one or none, and the
+// rest. `None` when there are no signs or nothing after them.
+fn split_signs(text: &str) -> Option<(bool, &str)> {
+    let operand = text.trim_start_matches(|c: char| c == '-' || c == '+' || c.is_whitespace());
+    if operand.len() == text.len() || operand.is_empty() {
+        return None;
+    }
+    let negative = text[..text.len() - operand.len()].matches('-').count() % 2 == 1;
+    Some((negative, operand))
+}
+
+// `(expression)`, when the parenthesis that opens `text` is the one that
+// closes it
+fn parse_group(reader: &mut Reader, text: &str) -> Option<Result<Expr, String>> {
+    let inner = text.strip_prefix('(')?.strip_suffix(')')?;
+    let mut depth = 0usize;
+    for c in inner.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return None,
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    let inner = inner.trim();
+    if inner.is_empty() {
+        return Some(Err(format!("Empty parentheses at position {}", position(reader.whole, text))));
+    }
+    Some(parse_inner(reader, inner))
+}
+
+// What parentheses or a call argument hold: an operand on its own, or an
+// expression
+fn parse_inner(reader: &mut Reader, text: &str) -> Result<Expr, String> {
+    parse_operand(reader, text).unwrap_or_else(|| parse_expr(reader, text))
+}
+
+// The `-` in `5'-3"` and the `/` in `1/2"` join the parts of a length
+// rather than being operators.
+fn is_length_punctuation(c: char, prev: Option<char>, rest: &str) -> bool {
+    match c {
+        '-' => prev == Some('\''),
+        '/' => {
+            let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+            prev.is_some_and(|p| p.is_ascii_digit()) && digits > 0 && rest[digits..].starts_with('"')
+        }
+        _ => false,
+    }
+}
+
+// A number followed by a unit, such as `20 °C` or `98.6F`. Another name
+// set apart by a space may be a unit the user defined, as in `3 furlong`;
+// it is looked up when the quantity is evaluated.
+fn parse_quantity(text: &str) -> Option<Expr> {
+    let split = text
+        .find(|c: char| !(c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '+' | '-')))
+        .filter(|&i| i > 0)?;
+    let value = text[..split].parse::<f64>().ok()?;
+    let rest = &text[split..];
+    let unit = rest.trim();
+    let custom = rest.starts_with(char::is_whitespace) && is_identifier(unit);
+    if Unit::from_name(unit).is_none() && !custom {
+        return None;
+    }
+    Some(Expr::quantity(Expr::Number(value), unit))
+}
+
+// Parses `name(arg, ...)`. Arguments are separated by commas, or by
+// semicolons where the comma is the decimal separator. With both, the
+// semicolons separate the arguments and the commas the values of list
+// arguments, as in `wavg(90, 80; 1, 2)`.
+fn parse_call(reader: &mut Reader, text: &str) -> Option<Result<Expr, String>> {
+    let open = text.find('(')?;
+    let name = text[..open].trim();
+    let inner = text[open + 1..].strip_suffix(')')?;
+    if !is_identifier(name) {
+        return None;
+    }
+
+    let mut pieces = Vec::new();
+    let mut separators = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in inner.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return None,
+            ')' => depth -= 1,
+            ',' | ';' if depth == 0 => {
+                pieces.push(&inner[start..i]);
+                separators.push(c);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if depth != 0 {
+        return None;
+    }
+    pieces.push(&inner[start..]);
+
+    // `bits(0x...)` takes the literal's exact bit pattern, which an f64
+    // argument could not hold
+    if name == "bits" && pieces.len() == 1 {
+        if let Some(bits) = parse_hex_integer(pieces[0].trim()) {
+            return Some(Ok(Expr::Number(f64::from_bits(bits))));
+        }
+    }
+
+    // `f()` has no arguments rather than one empty one
+    if pieces.len() == 1 && pieces[0].trim().is_empty() {
+        return Some(Ok(reader.call(name, Vec::new())));
+    }
+
+    let grouped = separators.contains(&',') && separators.contains(&';');
+    let mut args = Vec::new();
+    let mut group = Vec::new();
+    for (i, piece) in pieces.into_iter().enumerate() {
+        let piece = piece.trim();
+        if piece.is_empty() {
+            return Some(Err(format!(
+                "Missing argument in call to '{}' at position {}",
+                name,
+                position(reader.whole, piece)
+            )));
+        }
+        match parse_inner(reader, piece) {
+            Ok(item) => group.push(item),
+            Err(err) => return Some(Err(err)),
+        }
+        if !grouped || separators.get(i).is_none_or(|&separator| separator == ';') {
+            let mut items = std::mem::take(&mut group);
+            args.push(if items.len() == 1 { items.remove(0) } else { Expr::List(items) });
+        }
+    }
+    Some(Ok(reader.call(name, args)))
+}
+
+/// Parses a C99-style hex float such as `0x1.8p3` (12) or `-0xff` (-255).
+/// The binary exponent after `p` is optional.
+pub fn parse_hex_float(text: &str) -> Option<f64> {
+    let (negative, rest) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let rest = rest.strip_prefix("0x").or_else(|| rest.strip_prefix("0X"))?;
+    let (digits, exponent) = match rest.find(['p', 'P']) {
+        Some(p) => (&rest[..p], rest[p + 1..].parse::<i32>().ok()?),
+        None => (rest, 0),
+    };
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    if whole.is_empty() && fraction.is_empty() {
+        return None;
+    }
+
+    let mut value = 0.0;
+    for c in whole.chars() {
+        value = value * 16.0 + c.to_digit(16)? as f64;
+    }
+    let mut scale = 1.0 / 16.0;
+    for c in fraction.chars() {
+        value += c.to_digit(16)? as f64 * scale;
+        scale /= 16.0;
+    }
+    let value = value * 2f64.powi(exponent);
+    Some(if negative { -value } else { value })
+}
+
+// A plain hex integer such as `0x4008000000000000` that fits in 64 bits.
+fn parse_hex_integer(text: &str) -> Option<u64> {
+    let digits = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X"))?;
+    u64::from_str_radix(digits, 16).ok()
+}
+
+/// Whether `text` is a valid variable name: a letter or underscore followed
+/// by letters, digits, or underscores.
+pub fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(c) if c.is_alphabetic() || c == '_' => chars.all(|c| c.is_alphanumeric() || c == '_'),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_builds_binary_node() {
+        assert_eq!(
+            parse("5 * -3"),
+            Ok(Expr::binary(BinaryOp::Mul, Expr::Number(5.0), Expr::Number(-3.0)))
+        );
+        assert_eq!(
+            parse("-1e3 + 2e3"),
+            Ok(Expr::binary(BinaryOp::Add, Expr::Number(-1000.0), Expr::Number(2000.0)))
+        );
+    }
+
+    #[test]
+    fn test_parse_precedence() {
+        let (two, three, four) = (Expr::Number(2.0), Expr::Number(3.0), Expr::Number(4.0));
+        assert_eq!(
+            parse("2 + 3 * 4"),
+            Ok(Expr::binary(BinaryOp::Add, two.clone(), Expr::binary(BinaryOp::Mul, three.clone(), four.clone())))
+        );
+        assert_eq!(
+            parse("2 * 3 + 4"),
+            Ok(Expr::binary(BinaryOp::Add, Expr::binary(BinaryOp::Mul, two.clone(), three.clone()), four.clone()))
+        );
+        // Equal precedence groups from the left
+        assert_eq!(
+            parse("2 - 3 - 4"),
+            Ok(Expr::binary(BinaryOp::Sub, Expr::binary(BinaryOp::Sub, two.clone(), three.clone()), four.clone()))
+        );
+        assert_eq!(
+            parse("2 / 3 * 4"),
+            Ok(Expr::binary(BinaryOp::Mul, Expr::binary(BinaryOp::Div, two, three), four))
+        );
+        assert_eq!(parse("1 + 2 * 3x"), Err("Invalid third number '3x' at position 9".to_string()));
+        assert_eq!(parse("1 + * 3"), Err("Missing operand after '+' at position 3".to_string()));
+        assert_eq!(parse("* 3"), Err("Missing operand before '*' at position 1".to_string()));
+    }
+
+    #[test]
+    fn test_parse_unary_signs() {
+        let (two, three, five) = (Expr::Number(2.0), Expr::Number(3.0), Expr::Number(5.0));
+        let sum = Expr::binary(BinaryOp::Add, five.clone(), three.clone());
+        assert_eq!(parse("-(5 + 3)"), Ok(Expr::negate(sum.clone())));
+        assert_eq!(parse("+(5 + 3)"), Ok(sum.clone()));
+        assert_eq!(parse("2 * -(5 + 3)"), Ok(Expr::binary(BinaryOp::Mul, two.clone(), Expr::negate(sum))));
+        assert_eq!(parse("-sqrt(4)"), Ok(Expr::negate(Expr::call("sqrt", vec![Expr::Number(4.0)]))));
+        // A run of signs is one negation or none
+        assert_eq!(parse("--5 - - 3"), Ok(Expr::binary(BinaryOp::Sub, five, Expr::negate(three))));
+        assert_eq!(parse("- -x"), Ok(Expr::variable("x")));
+        // Signs apply after a power, on a name or a number alike
+        assert_eq!(
+            parse("-x^2"),
+            Ok(Expr::negate(Expr::binary(BinaryOp::Pow, Expr::variable("x"), two.clone())))
+        );
+        assert_eq!(parse("-2^2"), Ok(Expr::negate(Expr::binary(BinaryOp::Pow, two.clone(), two.clone()))));
+        assert_eq!(
+            parse("2^-2^2"),
+            Ok(Expr::binary(
+                BinaryOp::Pow,
+                two.clone(),
+                Expr::negate(Expr::binary(BinaryOp::Pow, two.clone(), two.clone()))
+            ))
+        );
+        assert_eq!(parse("(-2)^2"), Ok(Expr::binary(BinaryOp::Pow, Expr::Number(-2.0), two)));
+        assert_eq!(parse("-5"), Ok(Expr::Number(-5.0)));
+        assert_eq!(parse("5 * -"), Err("Invalid second number '-' at position 5".to_string()));
+    }
+
+    #[test]
+    fn test_parse_power_and_modulo() {
+        let (two, three) = (Expr::Number(2.0), Expr::Number(3.0));
+        // Powers group from the right and bind tighter than `*`
+        assert_eq!(
+            parse("2^3^2"),
+            Ok(Expr::binary(BinaryOp::Pow, two.clone(), Expr::binary(BinaryOp::Pow, three.clone(), two.clone())))
+        );
+        assert_eq!(parse("2 ** 3 ** 2"), parse("2^3^2"));
+        assert_eq!(
+            parse("3 * 2^-1"),
+            Ok(Expr::binary(BinaryOp::Mul, three.clone(), Expr::binary(BinaryOp::Pow, two.clone(), Expr::Number(-1.0))))
+        );
+        assert_eq!(
+            parse("7 % 3 * 2"),
+            Ok(Expr::binary(
+                BinaryOp::Mul,
+                Expr::binary(BinaryOp::Mod, Expr::Number(7.0), three),
+                two
+            ))
+        );
+        assert_eq!(parse("2 ** * 3"), Err("Missing operand after '**' at position 3".to_string()));
+        assert_eq!(parse("^ 2"), Err("Missing operand before '^' at position 1".to_string()));
+        assert!(is_incomplete("2 **", 100) && is_incomplete("2^", 100));
+    }
+
+    #[test]
+    fn test_parse_parentheses() {
+        let sum = Expr::binary(BinaryOp::Add, Expr::Number(5.0), Expr::Number(3.0));
+        assert_eq!(parse("(5 + 3) * 2"), Ok(Expr::binary(BinaryOp::Mul, sum.clone(), Expr::Number(2.0))));
+        assert_eq!(parse("2 * (5 + 3)"), Ok(Expr::binary(BinaryOp::Mul, Expr::Number(2.0), sum.clone())));
+        assert_eq!(parse("((5 + 3))"), Ok(sum.clone()));
+        assert_eq!(parse("(2) - (x)"), Ok(Expr::binary(BinaryOp::Sub, Expr::Number(2.0), Expr::variable("x"))));
+        assert_eq!(
+            parse("ulps((5 + 3), 1) / 2"),
+            Ok(Expr::binary(BinaryOp::Div, Expr::call("ulps", vec![sum, Expr::Number(1.0)]), Expr::Number(2.0)))
+        );
+        let deep = format!("{}1{}", "(".repeat(100), ")".repeat(100));
+        assert_eq!(parse(&deep), Ok(Expr::Number(1.0)));
+        let deeper = format!("({})", deep);
+        assert_eq!(parse(&deeper), Err("Input is nested too deeply; the most is 100 levels".to_string()));
+        assert_eq!(parse_within(&deeper, 101), Ok(Expr::Number(1.0)));
+
+        assert_eq!(parse("(5 + 3 * 2"), Err("Missing closing parenthesis at position 1".to_string()));
+        assert_eq!(parse("((1+2)*(3+4)/7"), Err("Missing closing parenthesis at position 1".to_string()));
+        assert_eq!(parse("5 + 3) * 2"), Err("Unexpected closing parenthesis at position 6".to_string()));
+        assert_eq!(parse(")("), Err("Unexpected closing parenthesis at position 1".to_string()));
+        assert_eq!(parse("() + 1"), Err("Empty parentheses at position 1".to_string()));
+        assert_eq!(parse("(5 3)"), Err("No operator found".to_string()));
+    }
+
+    #[test]
+    fn test_parse_variables() {
+        assert_eq!(
+            parse("rate*2"),
+            Ok(Expr::binary(BinaryOp::Mul, Expr::variable("rate"), Expr::Number(2.0)))
+        );
+        assert_eq!(
+            parse("x - 1e-3"),
+            Ok(Expr::binary(BinaryOp::Sub, Expr::variable("x"), Expr::Number(0.001)))
+        );
+    }
+
+    #[test]
+    fn test_tokens_borrow_the_input() {
+        let input = "-2 ** 3 - f(1 - 2) + 1e-3 * 5'-3\"";
+        let tokens: Vec<Token> = Tokens::new(input).collect();
+        assert_eq!(
+            tokens,
+            [
+                Token::Operand("-2 "),
+                Token::Operator(BinaryOp::Pow, "**"),
+                Token::Operand(" 3 "),
+                Token::Operator(BinaryOp::Sub, "-"),
+                Token::Operand(" f(1 - 2) "),
+                Token::Operator(BinaryOp::Add, "+"),
+                Token::Operand(" 1e-3 "),
+                Token::Operator(BinaryOp::Mul, "*"),
+                Token::Operand(" 5'-3\""),
+            ]
+        );
+        let Token::Operator(_, power) = tokens[1] else { unreachable!() };
+        assert_eq!(position(input, power), 4);
+        assert_eq!(Tokens::new("").collect::<Vec<_>>(), [Token::Operand("")]);
+        assert_eq!(Tokens::new("5 +").last(), Some(Token::Operand("")));
+    }
+
+    #[test]
+    fn test_arena_shares_unchanged_operands() {
+        let mut arena = Arena::new();
+        let line = arena.parse("wavg(90, 80; 1, 2) * (x + 1)", 100).unwrap();
+        let edited = arena.parse("wavg(90, 80; 1, 2) * (x + 1) - 5", 100).unwrap();
+        assert_eq!(edited, parse("wavg(90, 80; 1, 2) * (x + 1) - 5").unwrap());
+        let Expr::Binary { lhs: call, rhs: group, .. } = &line else { panic!("{:?}", line) };
+        let Expr::Binary { lhs: product, .. } = &edited else { panic!("{:?}", edited) };
+        let Expr::Binary { lhs: kept_call, rhs: kept_group, .. } = &**product else { panic!("{:?}", product) };
+        assert!(Arc::ptr_eq(call, kept_call));
+        assert!(Arc::ptr_eq(group, kept_group));
+        // Errors are not kept
+        assert!(arena.parse("2 * (x +)", 100).is_err());
+        assert!(arena.parse("3 * (x +)", 100).is_err());
+        // Only what the last input used is kept
+        arena.parse("(y + 1) * y", 100).unwrap();
+        assert_eq!(arena.trees.keys().map(|text| &**text).collect::<Vec<_>>(), ["(y + 1)"]);
+        assert_eq!(arena.names.iter().map(Name::as_str).collect::<Vec<_>>(), ["y"]);
+    }
+
+    #[test]
+    fn test_parse_calls() {
+        let call = Expr::call("ulps", vec![Expr::Number(1.0), Expr::variable("x")]);
+        assert_eq!(parse("ulps(1, x)"), Ok(call.clone()));
+        assert_eq!(parse(" ulps( 1 ; x ) "), Ok(call.clone()));
+        assert_eq!(parse("ulps(1, x) * 2"), Ok(Expr::binary(BinaryOp::Mul, call, Expr::Number(2.0))));
+        assert_eq!(
+            parse("approx_eq(0.1 + 0.2, 0.3, 1e-15)"),
+            Ok(Expr::call(
+                "approx_eq",
+                vec![
+                    Expr::binary(BinaryOp::Add, Expr::Number(0.1), Expr::Number(0.2)),
+                    Expr::Number(0.3),
+                    Expr::Number(1e-15),
+                ],
+            ))
+        );
+        assert_eq!(parse("f()"), Ok(Expr::call("f", vec![])));
+        assert_eq!(
+            parse("wavg(90, 80; 1, 2)"),
+            Ok(Expr::call(
+                "wavg",
+                vec![
+                    Expr::List(vec![Expr::Number(90.0), Expr::Number(80.0)]),
+                    Expr::List(vec![Expr::Number(1.0), Expr::Number(2.0)]),
+                ],
+            ))
+        );
+        assert_eq!(parse("ulps(1, )"), Err("Missing argument in call to 'ulps' at position 8".to_string()));
+        assert_eq!(parse("ulps(1, 2"), Err("Missing closing parenthesis at position 5".to_string()));
+    }
+
+    #[test]
+    fn test_hex_literals() {
+        assert_eq!(parse_hex_float("0x1.8p3"), Some(12.0));
+        assert_eq!(parse_hex_float("-0XFF"), Some(-255.0));
+        assert_eq!(parse_hex_float("0x.8"), Some(0.5));
+        assert_eq!(parse_hex_float("0x1p-2"), Some(0.25));
+        assert_eq!(parse_hex_float("0x"), None);
+        assert_eq!(parse_hex_float("0x1g"), None);
+        assert_eq!(parse_hex_float("ff"), None);
+
+        assert_eq!(
+            parse("0x1p-2 - 0x1e"),
+            Ok(Expr::binary(BinaryOp::Sub, Expr::Number(0.25), Expr::Number(30.0)))
+        );
+        assert_eq!(
+            parse("bits(0x3FB999999999999A) * 2"),
+            Ok(Expr::binary(BinaryOp::Mul, Expr::Number(0.1), Expr::Number(2.0)))
+        );
+    }
+
+    #[test]
+    fn test_parse_quantities() {
+        assert_eq!(
+            parse("20 °C + 10 K"),
+            Ok(Expr::binary(
+                BinaryOp::Add,
+                Expr::quantity(Expr::Number(20.0), "°C"),
+                Expr::quantity(Expr::Number(10.0), "K"),
+            ))
+        );
+        assert_eq!(
+            parse("98.6F - 5 Δ°F"),
+            Ok(Expr::binary(
+                BinaryOp::Sub,
+                Expr::quantity(Expr::Number(98.6), "F"),
+                Expr::quantity(Expr::Number(5.0), "Δ°F"),
+            ))
+        );
+        assert_eq!(parse("5 + 3 °X"), Err("Invalid second number '3 °X' at position 5".to_string()));
+        assert_eq!(
+            parse("2 furlong + 1 m"),
+            Ok(Expr::binary(
+                BinaryOp::Add,
+                Expr::quantity(Expr::Number(2.0), "furlong"),
+                Expr::quantity(Expr::Number(1.0), "m"),
+            ))
+        );
+        assert_eq!(parse("30 ℃ + 1 K").map(|_| ()), Ok(()));
+        assert_eq!(
+            parse("5' 3 1/2\" + 2'-7/8\""),
+            Ok(Expr::binary(
+                BinaryOp::Add,
+                Expr::quantity(Expr::Number(63.5), "\""),
+                Expr::quantity(Expr::Number(24.875), "\""),
+            ))
+        );
+        assert_eq!(
+            parse("10\" / 4"),
+            Ok(Expr::binary(BinaryOp::Div, Expr::quantity(Expr::Number(10.0), "\""), Expr::Number(4.0)))
+        );
+    }
+
+    #[test]
+    fn test_is_incomplete() {
+        assert!(is_incomplete("5 +", 100));
+        assert!(is_incomplete("x*  ", 100));
+        assert!(is_incomplete("-2.5 /", 100));
+        assert!(is_incomplete("5 + 3 *", 100));
+        assert!(!is_incomplete("5 + 3", 100));
+        assert!(!is_incomplete("-", 100));
+        assert!(!is_incomplete("1e-", 100));
+        assert!(!is_incomplete("", 100));
+        assert!(is_incomplete("((1)) +", 2) && !is_incomplete("((1)) +", 1));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse(""), Err("Empty input".to_string()));
+        assert_eq!(parse("5 3"), Err("No operator found".to_string()));
+        assert_eq!(parse("5 *"), Err("Missing operand after '*' at position 3".to_string()));
+        assert_eq!(parse("5.5.5 + 3"), Err("Invalid first number '5.5.5' at position 1".to_string()));
+        assert_eq!(parse("5 + 3x"), Err("Invalid second number '3x' at position 5".to_string()));
+        // Positions count characters, and point into calls
+        assert_eq!(parse("µ * 2x"), Err("Invalid second number '2x' at position 5".to_string()));
+        assert_eq!(parse("ulps(x, 1 + 2y)"), Err("Invalid second number '2y' at position 13".to_string()));
+    }
+
+    #[test]
+    fn test_unexpected_characters() {
+        assert_eq!(characters("a😀b").collect::<Vec<_>>(), vec!["a", "😀", "b"]);
+        assert_eq!(characters("e\u{301}1").collect::<Vec<_>>(), vec!["e\u{301}", "1"]);
+        // A family emoji is several emoji joined by zero-width joiners
+        assert_eq!(characters("1👨\u{200D}👩\u{200D}👧").collect::<Vec<_>>(), vec!["1", "👨\u{200D}👩\u{200D}👧"]);
+
+        assert_eq!(parse("1 + 😀"), Err("Unexpected character '😀' at position 5".to_string()));
+        assert_eq!(parse("µ + 1 $"), Err("Unexpected character '$' at position 7".to_string()));
+        assert_eq!(
+            parse("x\u{301} * 2"),
+            Err("Unexpected character 'x\u{301}' at position 1".to_string())
+        );
+        assert_eq!(
+            parse("² + 1"),
+            Err("Superscript '²' must follow a number, name or parentheses, as in 5²".to_string())
+        );
+    }
+}
@@ -0,0 +1,65 @@
+//! The dialog offered when a spreadsheet range is pasted into the input,
+//! asking what to make of its numbers rather than failing to parse them.
+
+use calculator::format::format_for_input;
+use calculator::DecimalSeparator;
+use eframe::egui;
+
+/// What the user chose to make of the pasted numbers.
+pub enum PasteChoice {
+    /// An expression to put in the input, such as `sum(1, 2, 3)`.
+    Input(String),
+    /// Push the numbers onto the scratch values.
+    Stash(Vec<f64>),
+    /// Show the numbers in the data plot.
+    Plot(Vec<f64>),
+}
+
+/// The "Pasted Numbers" window.
+#[derive(Default)]
+pub struct PasteDialog {
+    pub open: bool,
+    values: Vec<f64>,
+    // How the numbers are written in the expressions offered
+    separator: DecimalSeparator,
+}
+
+impl PasteDialog {
+    pub fn offer(&mut self, values: Vec<f64>, separator: DecimalSeparator) {
+        self.values = values;
+        self.separator = separator;
+        self.open = true;
+    }
+
+    /// Shows the dialog; the sum and average are only offered when
+    /// `allows_calls`.
+    pub fn show(&mut self, ctx: &egui::Context, allows_calls: bool) -> Option<PasteChoice> {
+        let mut choice = None;
+        let (values, separator) = (&self.values, self.separator);
+        let written = |value: f64| format_for_input(value, separator);
+        let list = || values.iter().map(|&value| written(value)).collect::<Vec<_>>().join(", ");
+        egui::Window::new("Pasted Numbers").open(&mut self.open).show(ctx, |ui| {
+            let shown: Vec<String> = values.iter().take(5).map(|&value| written(value)).collect();
+            let more = if values.len() > shown.len() { ", …" } else { "" };
+            ui.label(format!("{} numbers: {}{}", values.len(), shown.join(", "), more));
+            ui.horizontal(|ui| {
+                if allows_calls && ui.button("Sum").clicked() {
+                    choice = Some(PasteChoice::Input(format!("sum({})", list())));
+                }
+                if allows_calls && ui.button("Average").clicked() {
+                    choice = Some(PasteChoice::Input(format!("avg({})", list())));
+                }
+                if ui.button("Stash").on_hover_text("Push them onto the scratch values").clicked() {
+                    choice = Some(PasteChoice::Stash(values.clone()));
+                }
+                if ui.button("Plot").on_hover_text("Show a histogram and box plot of them").clicked() {
+                    choice = Some(PasteChoice::Plot(values.clone()));
+                }
+            });
+        });
+        if choice.is_some() {
+            self.open = false;
+        }
+        choice
+    }
+}
@@ -0,0 +1,57 @@
+//! Exposure math for photography. Exposures are compared in stops, each a
+//! doubling or halving of light, so most of these are base-2 logarithms.
+
+fn positive(value: f64, what: &str) -> Result<f64, String> {
+    if value > 0.0 && value.is_finite() {
+        Ok(value)
+    } else {
+        Err(format!("{} must be positive", what))
+    }
+}
+
+/// Exposure value of f-number `aperture` and `shutter` seconds, normalized
+/// to ISO 100 so that settings for the same scene at any ISO agree.
+pub fn exposure_value(aperture: f64, shutter: f64, iso: f64) -> Result<f64, String> {
+    let aperture = positive(aperture, "Aperture")?;
+    let shutter = positive(shutter, "Shutter time")?;
+    let iso = positive(iso, "ISO")?;
+    Ok((aperture * aperture / shutter).log2() - (iso / 100.0).log2())
+}
+
+/// Stops of light lost going from f-number `from` to `to`: f/4 to f/8 is 2.
+pub fn aperture_stops(from: f64, to: f64) -> Result<f64, String> {
+    Ok(2.0 * (positive(to, "Aperture")? / positive(from, "Aperture")?).log2())
+}
+
+/// Stops gained going from `from` to `to` for quantities that double per
+/// stop, such as shutter times and ISO: 1/250 s to 1/60 s is about 2.
+pub fn stops(from: f64, to: f64) -> Result<f64, String> {
+    Ok((positive(to, "Value")? / positive(from, "Value")?).log2())
+}
+
+/// Stops an ND filter takes away, from its filter factor: an ND8 is 3.
+pub fn nd_stops(factor: f64) -> Result<f64, String> {
+    Ok(positive(factor, "Filter factor")?.log2())
+}
+
+/// Shutter time needed behind a filter that takes away `stops`.
+pub fn nd_shutter(shutter: f64, stops: f64) -> Result<f64, String> {
+    Ok(positive(shutter, "Shutter time")? * 2f64.powf(stops))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exposure() {
+        assert_eq!(exposure_value(1.0, 1.0, 100.0), Ok(0.0));
+        assert_eq!(exposure_value(16.0, 1.0 / 128.0, 100.0), Ok(15.0));
+        assert_eq!(exposure_value(16.0, 1.0 / 128.0, 400.0), Ok(13.0));
+        assert_eq!(exposure_value(0.0, 1.0, 100.0), Err("Aperture must be positive".to_string()));
+        assert_eq!(aperture_stops(4.0, 8.0), Ok(2.0));
+        assert_eq!(stops(100.0, 800.0), Ok(3.0));
+        assert_eq!(nd_stops(8.0), Ok(3.0));
+        assert_eq!(nd_shutter(1.0 / 125.0, 10.0), Ok(8.192));
+    }
+}
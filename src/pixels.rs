@@ -0,0 +1,71 @@
+//! Print and screen sizes: pixels at a resolution, and fitting images into
+//! a box without changing their shape.
+
+fn positive(value: f64, what: &str) -> Result<f64, String> {
+    if value > 0.0 && value.is_finite() {
+        Ok(value)
+    } else {
+        Err(format!("{} must be positive", what))
+    }
+}
+
+/// Pixels needed to print `inches` at `dpi` dots per inch.
+pub fn to_pixels(inches: f64, dpi: f64) -> Result<f64, String> {
+    Ok(inches * positive(dpi, "DPI")?)
+}
+
+/// Printed size in inches of `pixels` at `dpi` dots per inch.
+pub fn to_inches(pixels: f64, dpi: f64) -> Result<f64, String> {
+    Ok(pixels / positive(dpi, "DPI")?)
+}
+
+/// The largest size with the shape of `width` × `height` that fits in
+/// `max_width` × `max_height`. Smaller images are scaled up.
+pub fn fit(width: f64, height: f64, max_width: f64, max_height: f64) -> Result<(f64, f64), String> {
+    let width = positive(width, "Width")?;
+    let height = positive(height, "Height")?;
+    let max_width = positive(max_width, "Maximum width")?;
+    let max_height = positive(max_height, "Maximum height")?;
+    // The limiting side comes out exact
+    if max_width / width <= max_height / height {
+        Ok((max_width, height * max_width / width))
+    } else {
+        Ok((width * max_height / height, max_height))
+    }
+}
+
+/// `width` × `height` reduced to lowest terms, such as 16 × 9 for
+/// 1920 × 1080.
+pub fn aspect_ratio(width: f64, height: f64) -> Result<(f64, f64), String> {
+    let whole = |value: f64, what: &str| match positive(value, what)? {
+        value if value.fract() == 0.0 && value <= u64::MAX as f64 => Ok(value as u64),
+        _ => Err(format!("{} must be a whole number", what)),
+    };
+    let (width, height) = (whole(width, "Width")?, whole(height, "Height")?);
+    let divisor = gcd(width, height);
+    Ok(((width / divisor) as f64, (height / divisor) as f64))
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pixels_and_fit() {
+        assert_eq!(to_pixels(8.5, 300.0), Ok(2550.0));
+        assert_eq!(to_inches(2550.0, 300.0), Ok(8.5));
+        assert_eq!(to_inches(100.0, 0.0), Err("DPI must be positive".to_string()));
+        assert_eq!(fit(1920.0, 1080.0, 1000.0, 1000.0), Ok((1000.0, 562.5)));
+        assert_eq!(fit(400.0, 800.0, 1000.0, 1000.0), Ok((500.0, 1000.0)));
+        assert_eq!(aspect_ratio(1920.0, 1080.0), Ok((16.0, 9.0)));
+        assert_eq!(aspect_ratio(1.5, 1.0), Err("Width must be a whole number".to_string()));
+    }
+}
@@ -0,0 +1,195 @@
+//! The data plot: a histogram and a box plot of a list of numbers, for a
+//! quick look at pasted or stashed data before working with it, or at a
+//! random sample against the density it was drawn from; or points with a
+//! curve fitted to them. The charts are egui_plot plots, so they can be
+//! zoomed, dragged and hovered for values. Like the history, the plot can
+//! be popped out into a window of its own.
+
+use calculator::fitting::Fit;
+use calculator::format::format_number;
+use calculator::random::Distribution;
+use calculator::stats::{self, Bin, Summary};
+use eframe::egui;
+use egui_plot::{Bar, BarChart, BoxElem, BoxPlot, BoxSpread, Line, Plot, Points};
+
+const MAX_BINS: usize = 100;
+
+/// The "Data Plot" window.
+#[derive(Default)]
+pub struct DataPlot {
+    pub open: bool,
+    values: Vec<f64>,
+    // Worked out once rather than sorting the values every frame
+    summary: Option<Summary>,
+    bins: usize,
+    // What the values were drawn from, drawn over the histogram
+    distribution: Option<Distribution>,
+    // Points and the curve fitted to them, shown instead of the values
+    fit: Option<Fit>,
+    // Shown in its own viewport rather than inside the calculator's
+    detached: bool,
+}
+
+impl DataPlot {
+    /// Plots `values`, starting with a number of bars suited to how many
+    /// there are.
+    pub fn plot(&mut self, values: &[f64]) {
+        self.values = values.to_vec();
+        self.summary = stats::summary(values);
+        self.bins = stats::sturges_bins(values.len()).min(MAX_BINS);
+        self.distribution = None;
+        self.fit = None;
+        self.open = true;
+    }
+
+    /// Plots a sample drawn from `distribution` along with its density.
+    pub fn plot_sample(&mut self, values: &[f64], distribution: Distribution) {
+        self.plot(values);
+        self.distribution = Some(distribution);
+    }
+
+    /// Plots points along with the curve fitted to them.
+    pub fn plot_fit(&mut self, fit: Fit) {
+        self.fit = Some(fit);
+        self.open = true;
+    }
+
+    /// Shows the plot while it is open, inside the calculator's window or
+    /// popped out; `number` tells the viewports of different calculator
+    /// windows apart.
+    pub fn show(&mut self, ctx: &egui::Context, number: u64) {
+        if !self.open {
+            return;
+        }
+        if !self.detached {
+            let mut open = true;
+            egui::Window::new("Data Plot").open(&mut open).show(ctx, |ui| self.ui(ui));
+            self.open &= open;
+            return;
+        }
+        let builder = egui::ViewportBuilder::default()
+            .with_title("Data Plot")
+            .with_inner_size([360.0, 480.0]);
+        ctx.show_viewport_immediate(egui::ViewportId::from_hash_of(("plot", number)), builder, |ctx, _class| {
+            egui::CentralPanel::default().show(ctx, |ui| self.ui(ui));
+            // Closing the plot's window closes the plot; it pops out again
+            // when something is next plotted
+            if ctx.input(|i| i.viewport().close_requested()) {
+                self.open = false;
+            }
+        });
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        let toggle = if self.detached { "Dock" } else { "Pop out" };
+        if ui.button(toggle).clicked() {
+            self.detached = !self.detached;
+        }
+        if let Some(fit) = &self.fit {
+            ui.label(fit.to_string());
+            fit_chart(ui, fit);
+            return;
+        }
+        let (values, distribution) = (&self.values, self.distribution);
+        let Some(summary) = self.summary else {
+            ui.label("No numbers to plot");
+            return;
+        };
+        if let Some(distribution) = distribution {
+            ui.label(format!("{} values drawn from {}", values.len(), distribution));
+        }
+        ui.add(egui::Slider::new(&mut self.bins, 1..=MAX_BINS).text("bars"));
+        let histogram = stats::histogram(values, self.bins);
+        let range = (histogram[0].start, histogram[histogram.len() - 1].end);
+        histogram_chart(ui, &histogram, range, distribution);
+        box_plot(ui, &summary);
+        egui::Grid::new("summary").show(ui, |ui| {
+            for (name, value) in [
+                ("Count", values.iter().filter(|value| value.is_finite()).count() as f64),
+                ("Minimum", summary.min),
+                ("First quartile", summary.q1),
+                ("Median", summary.median),
+                ("Third quartile", summary.q3),
+                ("Maximum", summary.max),
+            ] {
+                ui.label(name);
+                ui.label(format_number(value));
+                ui.end_row();
+            }
+        });
+    }
+}
+
+// The bars, each as high as its count, and the count each bar should
+// have by `distribution`'s density; hovering a bar tells its count. The
+// x axis is shared with the box plot below it.
+fn histogram_chart(ui: &mut egui::Ui, histogram: &[Bin], range: (f64, f64), distribution: Option<Distribution>) {
+    let width = (range.1 - range.0) / histogram.len() as f64;
+    let bars = histogram
+        .iter()
+        .map(|bin| Bar::new((bin.start + bin.end) / 2.0, bin.count as f64).width(bin.end - bin.start))
+        .collect();
+    let chart = BarChart::new(bars).color(ui.visuals().selection.bg_fill).element_formatter(Box::new(|bar, _| {
+        let half = bar.bar_width / 2.0;
+        format!("{} from {} to {}", bar.value, format_number(bar.argument - half), format_number(bar.argument + half))
+    }));
+    // The density scaled to counts per bar of this width
+    let total: usize = histogram.iter().map(|bin| bin.count).sum();
+    let curve = distribution.map(|distribution| {
+        let points: Vec<[f64; 2]> = (0..=200)
+            .map(|i| range.0 + (range.1 - range.0) * i as f64 / 200.0)
+            .map(|x| [x, distribution.density(x) * total as f64 * width])
+            .collect();
+        Line::new(points).color(ui.visuals().text_color()).width(2.0)
+    });
+    Plot::new("histogram")
+        .height(160.0)
+        .link_axis(ui.id().with("data plot"), true, false)
+        .include_y(0.0)
+        .allow_scroll(false)
+        .show(ui, |plot| {
+            plot.bar_chart(chart);
+            if let Some(curve) = curve {
+                plot.line(curve);
+            }
+        });
+}
+
+// A box from the first to the third quartile split at the median, with
+// whiskers out to the minimum and maximum
+fn box_plot(ui: &mut egui::Ui, summary: &Summary) {
+    let spread = BoxSpread::new(summary.min, summary.q1, summary.median, summary.q3, summary.max);
+    let boxes = BoxPlot::new(vec![BoxElem::new(0.0, spread).box_width(0.6)])
+        .horizontal()
+        .color(ui.visuals().text_color());
+    Plot::new("box plot")
+        .height(60.0)
+        .link_axis(ui.id().with("data plot"), true, false)
+        .show_y(false)
+        .show_axes([true, false])
+        .allow_scroll(false)
+        .show(ui, |plot| plot.box_plot(boxes));
+}
+
+// The points as dots and the fitted curve through them
+fn fit_chart(ui: &mut egui::Ui, fit: &Fit) {
+    let finite = fit.points.iter().filter(|(x, y)| x.is_finite() && y.is_finite());
+    let (low, high) = finite.fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), &(x, _)| (low.min(x), high.max(x)));
+    if low > high {
+        ui.label("No points to plot");
+        return;
+    }
+    // A single point still gets some width of curve
+    let (left, right) = if low == high { (low - 0.5, high + 0.5) } else { (low, high) };
+    let curve: Vec<[f64; 2]> = (0..=200)
+        .map(|i| left + (right - left) * i as f64 / 200.0)
+        .map(|x| [x, fit.at(x)])
+        .filter(|[_, y]| y.is_finite())
+        .collect();
+    let points: Vec<[f64; 2]> = fit.points.iter().map(|&(x, y)| [x, y]).collect();
+    let (line, dots) = (ui.visuals().selection.bg_fill, ui.visuals().text_color());
+    Plot::new("fit").height(200.0).allow_scroll(false).show(ui, |plot| {
+        plot.line(Line::new(curve).color(line).width(2.0));
+        plot.points(Points::new(points).color(dots).radius(3.0));
+    });
+}
@@ -0,0 +1,95 @@
+//! Capability profiles: the parts of the calculator a class or exam
+//! allows. A profile set on a [`Context`](crate::Context) limits what
+//! expressions and entry keys can do, and the GUI hides what it rules out.
+
+use serde::{Deserialize, Serialize};
+
+use crate::ast::BinaryOp;
+use crate::functions::Pack;
+use crate::keypad::EntryKey;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Profile {
+    /// Everything this build offers.
+    #[default]
+    Full,
+    /// The core functions only; every add-on pack is off.
+    Core,
+    /// A basic four-function calculator: arithmetic with the percent,
+    /// square root and sign keys, and no functions.
+    FourFunction,
+}
+
+impl Profile {
+    pub const ALL: [Profile; 3] = [Profile::Full, Profile::Core, Profile::FourFunction];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Profile::Full => "Full",
+            Profile::Core => "Core functions only",
+            Profile::FourFunction => "Basic four-function",
+        }
+    }
+
+    /// The name used on the command line, e.g. `four-function`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Profile::Full => "full",
+            Profile::Core => "core",
+            Profile::FourFunction => "four-function",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|profile| profile.name() == name)
+    }
+
+    pub fn allows_pack(self, pack: Pack) -> bool {
+        match self {
+            Profile::Full => true,
+            Profile::Core => pack == Pack::Core,
+            Profile::FourFunction => false,
+        }
+    }
+
+    /// Whether expressions may call functions, including `convert`.
+    pub fn allows_calls(self) -> bool {
+        self != Profile::FourFunction
+    }
+
+    /// Whether expressions may use `op`; a four-function calculator has no
+    /// powers or remainders.
+    pub fn allows_operator(self, op: BinaryOp) -> bool {
+        self != Profile::FourFunction || !matches!(op, BinaryOp::Pow | BinaryOp::Mod)
+    }
+
+    pub fn allows_key(self, key: EntryKey) -> bool {
+        match self {
+            Profile::FourFunction => matches!(key, EntryKey::Percent | EntryKey::SquareRoot | EntryKey::Negate),
+            _ => true,
+        }
+    }
+
+    /// The error for using something this profile does not allow.
+    pub fn forbids(self, what: &str) -> String {
+        format!("{} is not allowed in the {} profile", what, self.label().to_lowercase())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profiles() {
+        assert!(Profile::Full.allows_pack(Pack::Units) && Profile::Full.allows_calls());
+        assert!(Profile::Core.allows_pack(Pack::Core) && !Profile::Core.allows_pack(Pack::Units));
+        assert!(!Profile::FourFunction.allows_calls());
+        assert!(Profile::FourFunction.allows_key(EntryKey::SquareRoot));
+        assert!(!Profile::FourFunction.allows_key(EntryKey::Reciprocal));
+        assert!(Profile::FourFunction.allows_operator(BinaryOp::Div));
+        assert!(!Profile::FourFunction.allows_operator(BinaryOp::Pow) && Profile::Core.allows_operator(BinaryOp::Mod));
+        assert_eq!(Profile::from_name("four-function"), Some(Profile::FourFunction));
+        assert_eq!(Profile::from_name("nope"), None);
+    }
+}
@@ -0,0 +1,37 @@
+//! The platform's own programs, run for what this one does not do itself:
+//! speech, system sounds and a clipboard that outlives the window.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// A command running `program` with `args`.
+pub fn command(program: &str, args: &[&str]) -> Command {
+    let mut command = Command::new(program);
+    command.args(args);
+    command
+}
+
+/// Runs `commands` in order in the background until one succeeds, giving
+/// each `input` on its standard input. Failures (none of them installed,
+/// for instance) are silently ignored.
+pub fn run_first(commands: Vec<Command>, input: String) {
+    std::thread::spawn(move || {
+        for mut command in commands {
+            if run(&mut command, &input) {
+                break;
+            }
+        }
+    });
+}
+
+// Whether `command` ran and succeeded
+fn run(command: &mut Command, input: &str) -> bool {
+    let Ok(mut child) = command.stdin(Stdio::piped()).stdout(Stdio::null()).spawn() else {
+        return false;
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        // A command that takes nothing on its input may not read it
+        let _ = stdin.write_all(input.as_bytes());
+    }
+    child.wait().is_ok_and(|status| status.success())
+}
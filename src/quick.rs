@@ -0,0 +1,253 @@
+//! Quick evaluation of selected text: `calculator --evaluate-selection`
+//! reads the primary selection, or the clipboard where there is none,
+//! calculates it and shows the result in a small window over the others
+//! that closes by itself. `calculator --oneshot` instead asks for one
+//! expression, copies its result and exits, as a launcher such as rofi or
+//! dmenu would.
+//!
+//! There is no portable way to take a global hotkey, so these commands are
+//! meant to be bound to one in the desktop's own keyboard settings.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use eframe::egui;
+
+use crate::config::Config;
+use crate::gui::{custom_units, price_index, track_composition};
+use crate::programs::command;
+use calculator::format::format_for_input;
+use calculator::{calculate_quantity, Context, Profile};
+
+/// The size of the result window.
+pub const WINDOW_SIZE: egui::Vec2 = egui::vec2(320.0, 110.0);
+
+/// The size of the one-shot input window.
+pub const ONESHOT_SIZE: egui::Vec2 = egui::vec2(420.0, 70.0);
+
+// Seconds the result stays up while the pointer is not over it
+const SHOWN_FOR: f64 = 6.0;
+
+/// The selected text, or what is on the clipboard, or `None` when neither
+/// can be read.
+pub fn selection() -> Option<String> {
+    platform_commands().into_iter().find_map(|mut command| {
+        let output = command.output().ok().filter(|output| output.status.success())?;
+        let text = String::from_utf8(output.stdout).ok()?;
+        (!text.trim().is_empty()).then(|| text.trim().to_string())
+    })
+}
+
+// Candidate commands in order of preference; the first that gives text wins.
+fn platform_commands() -> Vec<Command> {
+    if cfg!(target_os = "macos") {
+        vec![command("pbpaste", &[])]
+    } else if cfg!(windows) {
+        vec![command("powershell", &["-NoProfile", "-Command", "Get-Clipboard"])]
+    } else {
+        vec![
+            command("wl-paste", &["--primary", "--no-newline"]),
+            command("xclip", &["-o", "-selection", "primary"]),
+            command("xsel", &["--primary", "--output"]),
+            command("wl-paste", &["--no-newline"]),
+            command("xclip", &["-o", "-selection", "clipboard"]),
+            command("xsel", &["--clipboard", "--output"]),
+        ]
+    }
+}
+
+/// Puts `text` on the clipboard through the platform's own command, which
+/// keeps it there after this program exits, as egui's clipboard does not
+/// on X11. Returns whether one of them took it.
+pub fn copy(text: &str) -> bool {
+    copy_commands().into_iter().any(|mut command| {
+        let Ok(mut child) = command.stdin(Stdio::piped()).stdout(Stdio::null()).stderr(Stdio::null()).spawn() else {
+            return false;
+        };
+        let written = child.stdin.take().is_some_and(|mut stdin| stdin.write_all(text.as_bytes()).is_ok());
+        // xclip and wl-copy stay behind to serve the clipboard, so only
+        // their first process is waited for
+        written && child.wait().is_ok_and(|status| status.success())
+    })
+}
+
+// Copies `text` as `copy` does, or onto egui's clipboard where no command
+// took it
+fn copy_or_keep(ctx: &egui::Context, text: String) {
+    if !copy(&text) {
+        ctx.output_mut(|output| output.copied_text = text);
+    }
+}
+
+fn copy_commands() -> Vec<Command> {
+    if cfg!(target_os = "macos") {
+        vec![command("pbcopy", &[])]
+    } else if cfg!(windows) {
+        vec![command("clip", &[])]
+    } else {
+        vec![
+            command("wl-copy", &[]),
+            command("xclip", &["-selection", "clipboard"]),
+            command("xsel", &["--clipboard", "--input"]),
+        ]
+    }
+}
+
+// A context with the units, tax rates and price index of `config`, under
+// `profile`
+fn context(config: &Config, profile: Profile) -> Context {
+    let mut context = Context::new();
+    context.set_profile(profile);
+    context.taxes = config.taxes.clone();
+    context.price_index = price_index(config);
+    context.custom_units = custom_units(config);
+    context
+}
+
+/// The window showing the result of the selected text.
+pub struct QuickResult {
+    expression: String,
+    outcome: Result<String, String>,
+    // The value as it would be typed back, for copying
+    copy: Option<String>,
+    // When the pointer last left the window, or when it opened
+    since: Option<f64>,
+}
+
+impl QuickResult {
+    /// Calculates `selection` with the units, tax rates and price index of
+    /// `config`, under `profile`.
+    pub fn new(selection: Option<String>, config: &Config, profile: Profile) -> Self {
+        let context = context(config, profile);
+
+        let Some(expression) = selection else {
+            return Self {
+                expression: String::new(),
+                outcome: Err("Nothing is selected or on the clipboard".to_string()),
+                copy: None,
+                since: None,
+            };
+        };
+        let outcome = calculate_quantity(&expression, &context);
+        Self {
+            copy: outcome
+                .as_ref()
+                .ok()
+                .map(|quantity| format_for_input(quantity.value, context.decimal_separator)),
+            outcome: outcome.map(|quantity| quantity.to_string()),
+            expression,
+            since: None,
+        }
+    }
+}
+
+impl eframe::App for QuickResult {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        let now = ctx.input(|i| i.time);
+        let hovered = ctx.input(|i| i.pointer.has_pointer());
+        let since = *self.since.get_or_insert(now);
+        if hovered {
+            self.since = Some(now);
+        } else if now - since > SHOWN_FOR {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        }
+        ctx.request_repaint_after(std::time::Duration::from_millis(250));
+        if ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        }
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            if !self.expression.is_empty() {
+                ui.label(egui::RichText::new(&self.expression).weak());
+            }
+            match &self.outcome {
+                Ok(result) => ui.heading(format!("= {}", result)),
+                Err(err) => ui.label(egui::RichText::new(format!("Error: {}", err)).color(egui::Color32::RED)),
+            };
+            ui.horizontal(|ui| {
+                if let Some(text) = &self.copy {
+                    if ui.button("Copy").on_hover_text("Copy the result's value, to the last digit").clicked() {
+                        copy_or_keep(ctx, text.clone());
+                    }
+                }
+                if ui.button("Close").clicked() {
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                }
+            });
+        });
+    }
+}
+
+/// A window that takes one expression and, once it calculates, copies the
+/// result, prints it and closes. An error is shown until the expression is
+/// fixed or Escape gives up.
+pub struct OneShot {
+    input: String,
+    error: String,
+    context: Context,
+    // The input takes the focus on the next frame: on opening, and after
+    // Enter has taken it away
+    focus_input: bool,
+    // An input method is composing text
+    composing: bool,
+}
+
+impl OneShot {
+    pub fn new(config: &Config, profile: Profile) -> Self {
+        Self {
+            input: String::new(),
+            error: String::new(),
+            context: context(config, profile),
+            focus_input: true,
+            composing: false,
+        }
+    }
+
+    // Copies and prints the result, or keeps the error
+    fn calculate(&mut self, ctx: &egui::Context) {
+        match calculate_quantity(&self.input, &self.context) {
+            Ok(quantity) => {
+                copy_or_keep(ctx, format_for_input(quantity.value, self.context.decimal_separator));
+                println!("{}", quantity);
+                ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+            }
+            Err(err) => self.error = err,
+        }
+    }
+}
+
+impl eframe::App for OneShot {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        let composing = self.composing;
+        let composed = track_composition(ctx, &mut self.composing);
+        // Escape while composing only cancels the composition
+        if !composing && ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        }
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.input)
+                    .hint_text("Expression, then Enter")
+                    .desired_width(f32::INFINITY),
+            );
+            if std::mem::take(&mut self.focus_input) {
+                response.request_focus();
+            }
+            if response.changed() {
+                self.error.clear();
+            }
+            if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                if !composed && !self.input.trim().is_empty() {
+                    self.calculate(ctx);
+                }
+                // Enter takes the focus, which is kept for fixing an error
+                // or going on after a composition
+                self.focus_input = true;
+            }
+            if !self.error.is_empty() {
+                ui.label(egui::RichText::new(format!("Error: {}", self.error)).color(egui::Color32::RED));
+            }
+        });
+    }
+}
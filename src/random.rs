@@ -0,0 +1,150 @@
+//! Random samples from common distributions, drawn with a small seedable
+//! generator so that a sample can be drawn again.
+
+use std::f64::consts::PI;
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::format::format_number;
+
+/// Most values `plotdist` draws.
+pub const MAX_SAMPLES: usize = 100_000;
+
+/// The SplitMix64 generator: fast and statistically sound for sampling,
+/// though not for anything secret.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// A generator seeded from the clock.
+    pub fn from_time() -> Self {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_nanos());
+        Self::new(nanos as u64)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number strictly between 0 and 1.
+    pub fn next_f64(&mut self) -> f64 {
+        ((self.next_u64() >> 11) as f64 + 0.5) / (1u64 << 53) as f64
+    }
+}
+
+/// A probability distribution to draw values from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Distribution {
+    Normal { mean: f64, sd: f64 },
+    Uniform { low: f64, high: f64 },
+    Exponential { rate: f64 },
+}
+
+impl Distribution {
+    /// The distribution written as `name(args)`, e.g. `normal(0, 1)`.
+    pub fn new(name: &str, args: &[f64]) -> Result<Self, String> {
+        let distribution = match (name, args) {
+            ("normal", &[mean, sd]) if sd > 0.0 => Distribution::Normal { mean, sd },
+            ("normal", &[_, _]) => return Err("normal needs a standard deviation above 0".to_string()),
+            ("uniform", &[low, high]) if low < high => Distribution::Uniform { low, high },
+            ("uniform", &[_, _]) => return Err("uniform needs its low end below its high end".to_string()),
+            ("exponential", &[rate]) if rate > 0.0 => Distribution::Exponential { rate },
+            ("exponential", &[_]) => return Err("exponential needs a rate above 0".to_string()),
+            ("normal", _) => return Err("Usage: normal(mean, sd)".to_string()),
+            ("uniform", _) => return Err("Usage: uniform(low, high)".to_string()),
+            ("exponential", _) => return Err("Usage: exponential(rate)".to_string()),
+            _ => {
+                return Err(format!(
+                    "Unknown distribution '{}'; try normal(mean, sd), uniform(low, high) or exponential(rate)",
+                    name
+                ))
+            }
+        };
+        if args.iter().any(|arg| !arg.is_finite()) {
+            return Err("A distribution's parameters must be finite".to_string());
+        }
+        Ok(distribution)
+    }
+
+    pub fn sample(&self, rng: &mut Rng) -> f64 {
+        match *self {
+            // Box–Muller
+            Distribution::Normal { mean, sd } => {
+                let (u, v) = (rng.next_f64(), rng.next_f64());
+                mean + sd * (-2.0 * u.ln()).sqrt() * (2.0 * PI * v).cos()
+            }
+            Distribution::Uniform { low, high } => low + (high - low) * rng.next_f64(),
+            Distribution::Exponential { rate } => -rng.next_f64().ln() / rate,
+        }
+    }
+
+    /// `count` values drawn with `rng`.
+    pub fn samples(&self, count: usize, rng: &mut Rng) -> Vec<f64> {
+        (0..count).map(|_| self.sample(rng)).collect()
+    }
+
+    /// The probability density at `x`.
+    pub fn density(&self, x: f64) -> f64 {
+        match *self {
+            Distribution::Normal { mean, sd } => {
+                let z = (x - mean) / sd;
+                (-0.5 * z * z).exp() / (sd * (2.0 * PI).sqrt())
+            }
+            Distribution::Uniform { low, high } if (low..=high).contains(&x) => 1.0 / (high - low),
+            Distribution::Uniform { .. } => 0.0,
+            Distribution::Exponential { rate } if x >= 0.0 => rate * (-rate * x).exp(),
+            Distribution::Exponential { .. } => 0.0,
+        }
+    }
+}
+
+impl fmt::Display for Distribution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Distribution::Normal { mean, sd } => write!(f, "normal({}, {})", format_number(mean), format_number(sd)),
+            Distribution::Uniform { low, high } => {
+                write!(f, "uniform({}, {})", format_number(low), format_number(high))
+            }
+            Distribution::Exponential { rate } => write!(f, "exponential({})", format_number(rate)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sampling() {
+        let normal = Distribution::new("normal", &[10.0, 2.0]).unwrap();
+        let first = normal.samples(20_000, &mut Rng::new(7));
+        assert_eq!(first, normal.samples(20_000, &mut Rng::new(7)));
+        let mean = first.iter().sum::<f64>() / first.len() as f64;
+        assert!((mean - 10.0).abs() < 0.05);
+        let variance = first.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / first.len() as f64;
+        assert!((variance.sqrt() - 2.0).abs() < 0.05);
+
+        let uniform = Distribution::new("uniform", &[1.0, 3.0]).unwrap();
+        assert!(uniform.samples(1000, &mut Rng::new(1)).iter().all(|x| (1.0..3.0).contains(x)));
+        assert_eq!(uniform.density(2.0), 0.5);
+        assert_eq!(uniform.density(4.0), 0.0);
+        let exponential = Distribution::new("exponential", &[2.0]).unwrap();
+        assert_eq!(exponential.density(0.0), 2.0);
+        assert!((normal.density(10.0) - 0.19947114020071635).abs() < 1e-15);
+
+        assert_eq!(normal.to_string(), "normal(10, 2)");
+        assert!(Distribution::new("normal", &[0.0, -1.0]).is_err());
+        assert_eq!(Distribution::new("exponential", &[1.0, 2.0]), Err("Usage: exponential(rate)".to_string()));
+        assert!(Distribution::new("poisson", &[1.0]).is_err());
+    }
+}
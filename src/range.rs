@@ -0,0 +1,63 @@
+//! Numbers pasted from a spreadsheet range: cells separated by tabs, rows
+//! by line breaks, as spreadsheets put them on the clipboard.
+
+use crate::normalize::DecimalSeparator;
+
+/// The numbers in `text` when it is a pasted range of several numeric
+/// cells, row by row, or `None` when it is anything else. Empty cells are
+/// skipped, and so is a first row of headings. Cells may have a currency
+/// sign and digit grouping, read with `separator` as the decimal
+/// separator.
+pub fn pasted_range(text: &str, separator: DecimalSeparator) -> Option<Vec<f64>> {
+    let text = text.trim();
+    if !text.contains(['\n', '\t']) {
+        return None;
+    }
+    let mut values = Vec::new();
+    for (row, line) in text.lines().enumerate() {
+        let cells: Vec<&str> = line.split('\t').map(str::trim).filter(|cell| !cell.is_empty()).collect();
+        let numbers: Vec<Option<f64>> = cells.iter().map(|cell| cell_value(cell, separator)).collect();
+        if row == 0 && numbers.iter().all(Option::is_none) {
+            continue;
+        }
+        for number in numbers {
+            values.push(number?);
+        }
+    }
+    (values.len() >= 2).then_some(values)
+}
+
+// A cell such as `1,234.50`, `$12` or `-3`
+fn cell_value(cell: &str, separator: DecimalSeparator) -> Option<f64> {
+    let cell = cell.replace('−', "-");
+    let (sign, digits) = match cell.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", cell.as_str()),
+    };
+    let digits = digits.trim_start_matches(['$', '€', '£', '¥']).trim_start();
+    let plain: String = match separator {
+        DecimalSeparator::Point => digits.replace(',', ""),
+        DecimalSeparator::Comma => digits.replace(['.', ' ', '\u{00A0}'], "").replace(',', "."),
+    };
+    if !plain.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+        return None;
+    }
+    format!("{}{}", sign, plain).parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pasted_range() {
+        let point = DecimalSeparator::Point;
+        assert_eq!(pasted_range("1\n2.5\n-3\n", point), Some(vec![1.0, 2.5, -3.0]));
+        assert_eq!(pasted_range("Price\tQty\r\n$1,200.50\t2\r\n3\t\r\n", point), Some(vec![1200.5, 2.0, 3.0]));
+        assert_eq!(pasted_range("1.234,5\t2", DecimalSeparator::Comma), Some(vec![1234.5, 2.0]));
+        assert_eq!(pasted_range("5 + 3", point), None);
+        assert_eq!(pasted_range("42\r\n", point), None);
+        assert_eq!(pasted_range("1\nn/a\n3", point), None);
+        assert_eq!(pasted_range("1\ninf", point), None);
+    }
+}
@@ -0,0 +1,49 @@
+//! Numeric root finding, for solving a formula for one of its inputs.
+
+/// A root of `f` between `low` and `high`, found by bisection. `f` must
+/// be continuous there and have opposite signs at the two ends, or be zero
+/// at one of them; otherwise there is no root to find and `None` is
+/// returned.
+pub fn bisect(f: impl Fn(f64) -> f64, mut low: f64, mut high: f64) -> Option<f64> {
+    let (mut f_low, f_high) = (f(low), f(high));
+    if f_low == 0.0 {
+        return Some(low);
+    }
+    if f_high == 0.0 {
+        return Some(high);
+    }
+    if !(f_low.is_finite() && f_high.is_finite()) || f_low.signum() == f_high.signum() {
+        return None;
+    }
+    // Halving an f64 interval more than this many times changes nothing
+    for _ in 0..200 {
+        let middle = low + (high - low) / 2.0;
+        if middle <= low || middle >= high {
+            break;
+        }
+        let f_middle = f(middle);
+        if f_middle == 0.0 {
+            return Some(middle);
+        }
+        if f_middle.signum() == f_low.signum() {
+            low = middle;
+            f_low = f_middle;
+        } else {
+            high = middle;
+        }
+    }
+    Some(low + (high - low) / 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bisect() {
+        let root = bisect(|x| x * x - 2.0, 0.0, 2.0).unwrap();
+        assert!((root - std::f64::consts::SQRT_2).abs() < 1e-12);
+        assert_eq!(bisect(|x| x - 3.0, 3.0, 10.0), Some(3.0));
+        assert_eq!(bisect(|x| x * x + 1.0, -5.0, 5.0), None);
+    }
+}
@@ -0,0 +1,41 @@
+//! Proportional scaling of a set of amounts, as for recipes, mixes, and
+//! resistor ratios.
+
+/// Multiplies every amount by `factor`.
+pub fn scale(amounts: &[f64], factor: f64) -> Vec<f64> {
+    amounts.iter().map(|amount| amount * factor).collect()
+}
+
+/// The factor that turns `amounts[index]` into `target`, so the other
+/// amounts can be scaled to match.
+pub fn factor_for_target(amounts: &[f64], index: usize, target: f64) -> Result<f64, String> {
+    let amount = *amounts.get(index).ok_or("No such amount")?;
+    if amount == 0.0 {
+        return Err("Cannot scale from an amount of zero".to_string());
+    }
+    let factor = target / amount;
+    if !factor.is_finite() {
+        return Err("Scale factor is too large or too small".to_string());
+    }
+    Ok(factor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scale_to_target() {
+        // 200 g flour, 100 g sugar, 2 eggs; only 150 g flour left
+        let amounts = [200.0, 100.0, 2.0];
+        let factor = factor_for_target(&amounts, 0, 150.0).unwrap();
+        assert_eq!(factor, 0.75);
+        assert_eq!(scale(&amounts, factor), vec![150.0, 75.0, 1.5]);
+
+        assert_eq!(
+            factor_for_target(&[0.0, 1.0], 0, 5.0),
+            Err("Cannot scale from an amount of zero".to_string())
+        );
+        assert!(factor_for_target(&amounts, 3, 1.0).is_err());
+    }
+}
@@ -0,0 +1,67 @@
+//! Scratch values: results stashed for reuse, somewhere between memory
+//! registers and the history.
+
+/// A stack of stashed values, most recent last.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Scratch {
+    values: Vec<f64>,
+}
+
+impl Scratch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, value: f64) {
+        self.values.push(value);
+    }
+
+    /// Removes and returns the most recently stashed value.
+    pub fn pop(&mut self) -> Option<f64> {
+        self.values.pop()
+    }
+
+    /// All values, oldest first.
+    pub fn values(&self) -> &[f64] {
+        &self.values
+    }
+
+    /// Removes the value at `index`, counting from the oldest.
+    pub fn remove(&mut self, index: usize) -> Option<f64> {
+        (index < self.values.len()).then(|| self.values.remove(index))
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.values.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scratch_stack() {
+        let mut scratch = Scratch::new();
+        scratch.push(1.5);
+        scratch.push(2.0);
+        scratch.push(3.0);
+        assert_eq!(scratch.values(), &[1.5, 2.0, 3.0]);
+
+        assert_eq!(scratch.pop(), Some(3.0));
+        assert_eq!(scratch.remove(0), Some(1.5));
+        assert_eq!(scratch.remove(5), None);
+        assert_eq!(scratch.values(), &[2.0]);
+
+        scratch.clear();
+        assert!(scratch.is_empty());
+    }
+}
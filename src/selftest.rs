@@ -0,0 +1,174 @@
+//! Built-in engine checks that can be run on an installed binary.
+//!
+//! The checks exercise the parser, arithmetic, error reporting and float
+//! behavior of the platform the calculator runs on, so packagers can
+//! verify a build without the source tree.
+
+use std::fmt;
+
+use crate::eval::Context;
+use crate::format::format_expr;
+use crate::calculate_with;
+
+enum Expect {
+    /// The input evaluates to exactly this value.
+    Exact(f64),
+    /// The input evaluates to within a relative 1e-15 of this value.
+    Close(f64),
+    /// The input fails with exactly this message.
+    Error(&'static str),
+    /// The input formats to exactly this text.
+    Formatted(&'static str),
+}
+
+struct Check {
+    group: &'static str,
+    input: &'static str,
+    expect: Expect,
+}
+
+const fn check(group: &'static str, input: &'static str, expect: Expect) -> Check {
+    Check { group, input, expect }
+}
+
+const CHECKS: &[Check] = &[
+    check("parsing", "5+3", Expect::Exact(8.0)),
+    check("parsing", " 5\t*\n3 ", Expect::Exact(15.0)),
+    check("parsing", "-5 - -3", Expect::Exact(-2.0)),
+    check("parsing", "-(5 + 3) * -sqrt(4)", Expect::Exact(16.0)),
+    check("parsing", "1.5e3 * 2", Expect::Exact(3000.0)),
+    check("parsing", "1e-3 + 2e-3", Expect::Exact(0.003)),
+    check("parsing", "2 + 3 * 4 - 1 / 2", Expect::Exact(13.5)),
+    check("parsing", "((1+2)*(3+4))/7", Expect::Exact(3.0)),
+    check("parsing", "(5 + 3 * 2", Expect::Error("Missing closing parenthesis at position 1")),
+    check("parsing", "5 3", Expect::Error("No operator found")),
+    check("parsing", "5.5.5 + 3", Expect::Error("Invalid first number '5.5.5' at position 1")),
+    check("arithmetic", "6 / 2", Expect::Exact(3.0)),
+    check("arithmetic", "0 - 0", Expect::Exact(0.0)),
+    check("arithmetic", "2^3^2", Expect::Exact(512.0)),
+    check("arithmetic", "2 * -5^2", Expect::Exact(-50.0)),
+    check("arithmetic", "-7 % 3", Expect::Exact(2.0)),
+    check("arithmetic", "1e100 * 1e-100", Expect::Close(1.0)),
+    check("precision", "0.1 + 0.2", Expect::Exact(0.30000000000000004)),
+    check("precision", "1 + 2.220446049250313e-16", Expect::Exact(1.0000000000000002)),
+    check("precision", "123456789.123456789 + 0.000000001", Expect::Exact(123456789.12345679)),
+    check("precision", "2.2250738585072014e-308 / 2", Expect::Exact(1.1125369292536007e-308)),
+    check("precision", "4.9e-324 / 2", Expect::Exact(0.0)),
+    check("errors", "5 / 0", Expect::Error("Result is too large (infinity)")),
+    check("errors", "-5 / 0", Expect::Error("Result is too small (negative infinity)")),
+    check("errors", "0 / 0", Expect::Error("Division by zero")),
+    check("errors", "1e308 * 10", Expect::Error("Result is too large or too small")),
+    check("errors", "NaN + 1", Expect::Error("NaN is not a valid number")),
+    check("variables", "x * 2", Expect::Exact(5.0)),
+    check("variables", "y + 1", Expect::Error("Unknown variable 'y'")),
+    check("functions", "ulps(0.1 + 0.2, 0.3)", Expect::Exact(1.0)),
+    check("functions", "approx_eq(x, 2.5000001, 1e-6)", Expect::Exact(1.0)),
+    check("functions", "wavg(90, 80, 70; 1, 2, 1)", Expect::Exact(80.0)),
+    #[cfg(feature = "units")]
+    check("functions", "px(8.5, 300)", Expect::Exact(2550.0)),
+    #[cfg(feature = "programmer")]
+    check("functions", "contrast(#000, #fff)", Expect::Exact(21.0)),
+    #[cfg(feature = "scientific")]
+    check("functions", "note_freq(A4)", Expect::Exact(440.0)),
+    check("functions", "nope(1)", Expect::Error("Unknown function 'nope'")),
+    check("units", "20 °C + 10 K", Expect::Exact(30.0)),
+    check("units", "convert(212, F, C)", Expect::Close(100.0)),
+    check("units", "20 °C * 2 °C", Expect::Error("Cannot multiply two temperatures")),
+    check("units", "5' 3 1/2\" + 2' 7/8\"", Expect::Exact(88.375)),
+    check("formatting", "5+3", Expect::Formatted("5 + 3")),
+    check("formatting", "1.5e3  /x", Expect::Formatted("1500 / x")),
+    check("formatting", "1e300 * 1e-300", Expect::Formatted("1e300 * 1e-300")),
+    check("formatting", "ulps(1,x)*2", Expect::Formatted("ulps(1, x) * 2")),
+];
+
+/// Outcome of a single check.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckOutcome {
+    pub group: &'static str,
+    pub input: &'static str,
+    /// `None` when the check passed, otherwise what went wrong.
+    pub failure: Option<String>,
+}
+
+/// Results of a full self-test run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub outcomes: Vec<CheckOutcome>,
+}
+
+impl Report {
+    pub fn failures(&self) -> usize {
+        self.outcomes.iter().filter(|o| o.failure.is_some()).count()
+    }
+
+    pub fn all_passed(&self) -> bool {
+        self.failures() == 0
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for outcome in &self.outcomes {
+            match &outcome.failure {
+                None => writeln!(f, "ok    {:<11} {}", outcome.group, outcome.input)?,
+                Some(why) => writeln!(f, "FAIL  {:<11} {}: {}", outcome.group, outcome.input, why)?,
+            }
+        }
+        write!(
+            f,
+            "\n{} checks, {} passed, {} failed",
+            self.outcomes.len(),
+            self.outcomes.len() - self.failures(),
+            self.failures()
+        )
+    }
+}
+
+/// Runs every built-in check.
+pub fn run() -> Report {
+    let mut ctx = Context::new();
+    ctx.set("x", 2.5);
+
+    let outcomes = CHECKS
+        .iter()
+        .map(|check| CheckOutcome {
+            group: check.group,
+            input: check.input,
+            failure: run_check(check, &ctx),
+        })
+        .collect();
+    Report { outcomes }
+}
+
+fn run_check(check: &Check, ctx: &Context) -> Option<String> {
+    match check.expect {
+        Expect::Exact(expected) => match calculate_with(check.input, ctx) {
+            Ok(value) if value.to_bits() == expected.to_bits() => None,
+            other => Some(format!("expected {}, got {:?}", expected, other)),
+        },
+        Expect::Close(expected) => match calculate_with(check.input, ctx) {
+            Ok(value) if ((value - expected) / expected).abs() < 1e-15 => None,
+            other => Some(format!("expected about {}, got {:?}", expected, other)),
+        },
+        Expect::Error(message) => match calculate_with(check.input, ctx) {
+            Err(err) if err == message => None,
+            other => Some(format!("expected error '{}', got {:?}", message, other)),
+        },
+        Expect::Formatted(expected) => match format_expr(check.input) {
+            Ok(text) if text == expected => None,
+            other => Some(format!("expected '{}', got {:?}", expected, other)),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selftest_passes() {
+        let report = run();
+        assert!(report.all_passed(), "{}", report);
+        assert_eq!(report.outcomes.len(), CHECKS.len());
+    }
+}
@@ -0,0 +1,226 @@
+//! Named sessions: variables, scratch values, history and settings saved
+//! as JSON files in the config directory, and the picker that saves and
+//! restores them.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use calculator::{AngleMode, DecimalSeparator, History, HistoryEntry, Limits, NanPolicy, OverflowMode, Pack};
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::sync;
+
+/// Everything needed to pick up a calculator window where it was left.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub variables: BTreeMap<String, f64>,
+    pub scratch: Vec<f64>,
+    pub history: Vec<SavedEntry>,
+    pub settings: Settings,
+}
+
+/// A history entry. JSON has no infinity or NaN, so a result that is not
+/// finite comes back as NaN.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SavedEntry {
+    pub expression: String,
+    #[serde(default)]
+    pub value: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Milliseconds since the Unix epoch; 0 in files saved before entries
+    /// had times.
+    #[serde(default)]
+    pub time: u64,
+    /// The settings it was calculated with, as directives; empty in files
+    /// saved before entries recorded them.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub settings: String,
+    /// How many times in a row it was made; left out when once.
+    #[serde(default = "once", skip_serializing_if = "is_once")]
+    pub count: u32,
+}
+
+fn once() -> u32 {
+    1
+}
+
+fn is_once(count: &u32) -> bool {
+    *count == 1
+}
+
+impl SavedEntry {
+    pub fn new(entry: &HistoryEntry) -> Self {
+        let (value, error) = match &entry.result {
+            Ok(value) => (Some(*value), None),
+            Err(err) => (None, Some(err.clone())),
+        };
+        Self {
+            expression: entry.expression.clone(),
+            value,
+            error,
+            time: entry.time,
+            settings: entry.settings.clone(),
+            count: entry.count,
+        }
+    }
+
+    pub fn result(&self) -> Result<f64, String> {
+        match &self.error {
+            Some(err) => Err(err.clone()),
+            None => Ok(self.value.unwrap_or(f64::NAN)),
+        }
+    }
+}
+
+pub fn saved_entries(history: &History) -> Vec<SavedEntry> {
+    history.entries().iter().map(SavedEntry::new).collect()
+}
+
+pub fn restored_history(entries: &[SavedEntry]) -> History {
+    let mut history = History::new();
+    for entry in entries {
+        history.push_entry(HistoryEntry {
+            expression: entry.expression.trim().to_string(),
+            result: entry.result(),
+            previous: None,
+            time: entry.time,
+            settings: entry.settings.clone(),
+            count: entry.count.max(1),
+        });
+    }
+    history
+}
+
+/// The settings a window can change from their defaults.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub overflow: OverflowMode,
+    pub nan: NanPolicy,
+    pub normalize_negative_zero: bool,
+    pub decimal_separator: DecimalSeparator,
+    pub cjk_numerals: bool,
+    pub angle: AngleMode,
+    pub limits: Limits,
+    /// The ISO 4217 code of the money mode currency, if it is on.
+    pub currency: Option<String>,
+    pub disabled_packs: Vec<Pack>,
+    pub speak_results: bool,
+    pub click_sounds: bool,
+    pub error_sounds: bool,
+    pub superscripts: bool,
+}
+
+fn sessions_dir() -> Option<PathBuf> {
+    sync::data_dir().map(|dir| dir.join("sessions"))
+}
+
+fn session_path(name: &str) -> Result<PathBuf, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Give the session a name".to_string());
+    }
+    if name.contains(['/', '\\']) || name.starts_with('.') {
+        return Err("Session names cannot contain / or \\ or start with .".to_string());
+    }
+    let dir = sessions_dir().ok_or("No configuration directory on this system")?;
+    Ok(dir.join(format!("{}.json", name)))
+}
+
+/// Names of the saved sessions, sorted.
+pub fn names() -> Vec<String> {
+    let Some(entries) = sessions_dir().and_then(|dir| fs::read_dir(dir).ok()) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension()? != "json" {
+                return None;
+            }
+            Some(path.file_stem()?.to_str()?.to_string())
+        })
+        .collect();
+    names.sort();
+    names
+}
+
+/// Saves `session` under `name`, replacing any session of that name.
+pub fn save(name: &str, session: &Session) -> Result<(), String> {
+    let path = session_path(name)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    }
+    let text = serde_json::to_string_pretty(session).map_err(|err| err.to_string())?;
+    fs::write(path, text).map_err(|err| err.to_string())
+}
+
+pub fn load(name: &str) -> Result<Session, String> {
+    let text = fs::read_to_string(session_path(name)?).map_err(|err| err.to_string())?;
+    serde_json::from_str(&text).map_err(|err| format!("Session '{}' is damaged: {}", name, err))
+}
+
+pub fn delete(name: &str) -> Result<(), String> {
+    fs::remove_file(session_path(name)?).map_err(|err| err.to_string())
+}
+
+/// What the user asked the picker to do with the current window.
+pub enum Choice {
+    Save(String),
+    Restore(String),
+}
+
+/// The "Sessions" window listing saved sessions.
+#[derive(Default)]
+pub struct SessionPicker {
+    pub open: bool,
+    name: String,
+    /// The outcome of the last action, shown under the list
+    pub message: String,
+}
+
+impl SessionPicker {
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<Choice> {
+        let mut choice = None;
+        let (name, message) = (&mut self.name, &mut self.message);
+        egui::Window::new("Sessions").open(&mut self.open).show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Name:");
+                ui.text_edit_singleline(name);
+                if ui.button("Save").on_hover_text("Save this window's session under the name").clicked() {
+                    choice = Some(Choice::Save(name.trim().to_string()));
+                }
+            });
+            ui.add_space(5.0);
+
+            let names = names();
+            if names.is_empty() {
+                ui.label(egui::RichText::new("No saved sessions").weak());
+            }
+            for saved in names {
+                ui.horizontal(|ui| {
+                    ui.label(&saved);
+                    if ui.small_button("Restore").clicked() {
+                        choice = Some(Choice::Restore(saved.clone()));
+                    }
+                    if ui.small_button("Delete").clicked() {
+                        *message = match delete(&saved) {
+                            Ok(()) => format!("Deleted '{}'", saved),
+                            Err(err) => format!("Error: {}", err),
+                        };
+                    }
+                });
+            }
+
+            if !message.is_empty() {
+                ui.add_space(5.0);
+                ui.label(message.as_str());
+            }
+        });
+        choice
+    }
+}
@@ -0,0 +1,355 @@
+//! Algebraic normalization of expression trees.
+
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+use crate::ast::{BinaryOp, Expr, Fold, Name};
+use crate::eval::{apply, evaluate, Context};
+use crate::float::relative_difference;
+use crate::parser::parse;
+
+/// Rewrites `expr` into a canonical form: constant subtrees are folded and
+/// the operands of `+` and `*` are flattened and put in a fixed order, so
+/// that expressions differing only by commutativity or associativity end
+/// up identical. Constants fold under the settings and profile of `ctx`.
+pub fn canonicalize(expr: Expr, ctx: &Context) -> Expr {
+    Canonicalizer(ctx).fold_expr(expr)
+}
+
+/// Simplifies `expr`: constant subtrees are folded, identities such as
+/// `x * 1` and `x + 0` are removed, constant factors are distributed over
+/// sums, and like terms are combined (`x + 2 * x` becomes `3 * x`).
+/// Constants fold under the settings and profile of `ctx`, so what it
+/// would refuse to calculate stays as written.
+pub fn simplify(expr: Expr, ctx: &Context) -> Expr {
+    Simplifier { ctx, reciprocals: false }.fold_expr(expr)
+}
+
+/// Reports whether `a` and `b` are algebraically the same expression,
+/// e.g. `5 + 3` and `3 + 5`, or `2 * (x + 3)` and `2 * x + 6`. Numbers that
+/// differ only by rounding noise compare equal.
+pub fn are_equivalent(a: &str, b: &str) -> Result<bool, String> {
+    let ctx = Context::default();
+    // Dividing by a constant is scaling by its reciprocal, so `x / 2` and
+    // `0.5 * x` simplify to the same terms
+    let normalize = |expr| canonicalize(Simplifier { ctx: &ctx, reciprocals: true }.fold_expr(expr), &ctx);
+    let a = normalize(parse(a)?);
+    let b = normalize(parse(b)?);
+    Ok(same_tree(&a, &b))
+}
+
+// Operations that fail (division by zero, overflow) stay unfolded so the
+// error still surfaces on evaluation.
+fn fold_constant(op: BinaryOp, lhs: &Expr, rhs: &Expr, ctx: &Context) -> Option<Expr> {
+    match (lhs, rhs) {
+        (Expr::Number(a), Expr::Number(b)) => apply(op, *a, *b, ctx).ok().map(Expr::Number),
+        _ => None,
+    }
+}
+
+// A call with only numeric arguments is replaced by its value, unless it
+// fails.
+fn fold_constant_call(name: Name, args: Vec<Expr>, ctx: &Context) -> Expr {
+    let constant = args.iter().all(|arg| matches!(arg, Expr::Number(_)));
+    let call = Expr::Call { name, args };
+    if constant {
+        if let Ok(value) = evaluate(&call, ctx) {
+            return Expr::Number(value);
+        }
+    }
+    call
+}
+
+// A negated number becomes a negative one, and two negations cancel.
+fn fold_negation(operand: Expr) -> Expr {
+    match operand {
+        Expr::Number(n) => Expr::Number(-n),
+        Expr::Negate(inner) => Arc::unwrap_or_clone(inner),
+        operand => Expr::negate(operand),
+    }
+}
+
+struct Simplifier<'a> {
+    ctx: &'a Context,
+    // Whether division by a constant becomes multiplication
+    reciprocals: bool,
+}
+
+impl Fold for Simplifier<'_> {
+    fn fold_call(&mut self, name: Name, args: Vec<Expr>) -> Expr {
+        fold_constant_call(name, args, self.ctx)
+    }
+
+    fn fold_negate(&mut self, operand: Expr) -> Expr {
+        fold_negation(operand)
+    }
+
+    fn fold_binary(&mut self, op: BinaryOp, lhs: Expr, rhs: Expr) -> Expr {
+        if let Some(folded) = fold_constant(op, &lhs, &rhs, self.ctx) {
+            return folded;
+        }
+
+        if let (true, BinaryOp::Div, Expr::Number(n)) = (self.reciprocals, op, &rhs) {
+            if let Some(reciprocal) = calculate(BinaryOp::Div, 1.0, *n, self.ctx) {
+                return self.fold_binary(BinaryOp::Mul, Expr::Number(reciprocal), lhs);
+            }
+        }
+
+        match (op, &lhs, &rhs) {
+            (BinaryOp::Add, Expr::Number(n), _) if *n == 0.0 => return rhs,
+            (BinaryOp::Mul, Expr::Number(n), _) if *n == 1.0 => return rhs,
+            (BinaryOp::Add | BinaryOp::Sub, _, Expr::Number(n)) if *n == 0.0 => return lhs,
+            (BinaryOp::Mul | BinaryOp::Div, _, Expr::Number(n)) if *n == 1.0 => return lhs,
+            _ => {}
+        }
+
+        // Scaling by a constant distributes over a sum, so it is handled
+        // by the same term collection
+        let scales = op == BinaryOp::Mul && (matches!(lhs, Expr::Number(_)) || matches!(rhs, Expr::Number(_)));
+        if matches!(op, BinaryOp::Add | BinaryOp::Sub) || scales {
+            combine_like_terms(Expr::binary(op, lhs, rhs), self.ctx)
+        } else {
+            Expr::binary(op, lhs, rhs)
+        }
+    }
+}
+
+// Rewrites a sum as `c1 * t1 + c2 * t2 + ... + constant`, merging the
+// coefficients of structurally identical terms. The sum is kept as written
+// when that would hide an error: a coefficient that cannot be calculated
+// under `ctx`, or a constant term that failed to fold.
+fn combine_like_terms(sum: Expr, ctx: &Context) -> Expr {
+    combine_terms(&sum, ctx).unwrap_or(sum)
+}
+
+fn combine_terms(sum: &Expr, ctx: &Context) -> Option<Expr> {
+    let mut terms: Vec<(f64, Expr)> = Vec::new();
+    let mut constant = 0.0;
+    collect_terms(sum, 1.0, &mut terms, &mut constant, ctx)?;
+
+    let mut combined: Vec<(f64, Expr)> = Vec::new();
+    for (coefficient, term) in terms {
+        match combined.iter_mut().find(|(_, existing)| *existing == term) {
+            Some((total, _)) => *total = calculate(BinaryOp::Add, *total, coefficient, ctx)?,
+            None => combined.push((coefficient, term)),
+        }
+    }
+
+    let mut result: Option<Expr> = None;
+    for (coefficient, term) in combined {
+        if coefficient == 0.0 {
+            continue;
+        }
+        result = Some(match result {
+            None if coefficient == 1.0 => term,
+            None => Expr::binary(BinaryOp::Mul, Expr::Number(coefficient), term),
+            Some(acc) => {
+                let op = if coefficient > 0.0 { BinaryOp::Add } else { BinaryOp::Sub };
+                let scaled = if coefficient.abs() == 1.0 {
+                    term
+                } else {
+                    Expr::binary(BinaryOp::Mul, Expr::Number(coefficient.abs()), term)
+                };
+                Expr::binary(op, acc, scaled)
+            }
+        });
+    }
+
+    Some(match result {
+        None => Expr::Number(constant),
+        Some(acc) if constant == 0.0 => acc,
+        Some(acc) if constant < 0.0 => Expr::binary(BinaryOp::Sub, acc, Expr::Number(-constant)),
+        Some(acc) => Expr::binary(BinaryOp::Add, acc, Expr::Number(constant)),
+    })
+}
+
+fn collect_terms(expr: &Expr, sign: f64, terms: &mut Vec<(f64, Expr)>, constant: &mut f64, ctx: &Context) -> Option<()> {
+    match expr {
+        Expr::Number(n) => {
+            let scaled = calculate(BinaryOp::Mul, sign, *n, ctx)?;
+            *constant = calculate(BinaryOp::Add, *constant, scaled, ctx)?;
+        }
+        Expr::Binary { op: BinaryOp::Add, lhs, rhs } => {
+            collect_terms(lhs, sign, terms, constant, ctx)?;
+            collect_terms(rhs, sign, terms, constant, ctx)?;
+        }
+        Expr::Binary { op: BinaryOp::Sub, lhs, rhs } => {
+            collect_terms(lhs, sign, terms, constant, ctx)?;
+            collect_terms(rhs, -sign, terms, constant, ctx)?;
+        }
+        Expr::Negate(operand) => collect_terms(operand, -sign, terms, constant, ctx)?,
+        Expr::Binary { op: BinaryOp::Mul, lhs, rhs } => match (&**lhs, &**rhs) {
+            (Expr::Number(c), term) | (term, Expr::Number(c)) => {
+                collect_terms(term, calculate(BinaryOp::Mul, sign, *c, ctx)?, terms, constant, ctx)?
+            }
+            _ => push_term(expr, sign, terms)?,
+        },
+        term => push_term(term, sign, terms)?,
+    }
+    Some(())
+}
+
+// Constants were folded already, so a term without variables is one that
+// fails, and must not be merged away
+fn push_term(term: &Expr, sign: f64, terms: &mut Vec<(f64, Expr)>) -> Option<()> {
+    if term.variables().is_empty() {
+        return None;
+    }
+    terms.push((sign, term.clone()));
+    Some(())
+}
+
+// A finite result of `a op b` under `ctx`
+fn calculate(op: BinaryOp, a: f64, b: f64, ctx: &Context) -> Option<f64> {
+    apply(op, a, b, ctx).ok().filter(|value| value.is_finite())
+}
+
+struct Canonicalizer<'a>(&'a Context);
+
+impl Fold for Canonicalizer<'_> {
+    fn fold_call(&mut self, name: Name, args: Vec<Expr>) -> Expr {
+        fold_constant_call(name, args, self.0)
+    }
+
+    fn fold_negate(&mut self, operand: Expr) -> Expr {
+        fold_negation(operand)
+    }
+
+    fn fold_binary(&mut self, op: BinaryOp, lhs: Expr, rhs: Expr) -> Expr {
+        if let Some(folded) = fold_constant(op, &lhs, &rhs, self.0) {
+            return folded;
+        }
+
+        if !matches!(op, BinaryOp::Add | BinaryOp::Mul) {
+            return Expr::binary(op, lhs, rhs);
+        }
+
+        let mut operands = Vec::new();
+        flatten(op, lhs, &mut operands);
+        flatten(op, rhs, &mut operands);
+        operands.sort_by(compare);
+
+        let mut operands = operands.into_iter();
+        let first = operands.next().expect("a binary node has two operands");
+        operands.fold(first, |acc, operand| Expr::binary(op, acc, operand))
+    }
+}
+
+fn flatten(op: BinaryOp, expr: Expr, out: &mut Vec<Expr>) {
+    match expr {
+        Expr::Binary { op: inner, lhs, rhs } if inner == op => {
+            flatten(op, Arc::unwrap_or_clone(lhs), out);
+            flatten(op, Arc::unwrap_or_clone(rhs), out);
+        }
+        other => out.push(other),
+    }
+}
+
+// Numbers sort before compound terms; compound terms sort by their text.
+fn compare(a: &Expr, b: &Expr) -> Ordering {
+    match (a, b) {
+        (Expr::Number(x), Expr::Number(y)) => x.total_cmp(y),
+        (Expr::Number(_), _) => Ordering::Less,
+        (_, Expr::Number(_)) => Ordering::Greater,
+        _ => a.to_string().cmp(&b.to_string()),
+    }
+}
+
+fn same_tree(a: &Expr, b: &Expr) -> bool {
+    match (a, b) {
+        (Expr::Number(x), Expr::Number(y)) => close(*x, *y),
+        (
+            Expr::Binary { op: op_a, lhs: lhs_a, rhs: rhs_a },
+            Expr::Binary { op: op_b, lhs: lhs_b, rhs: rhs_b },
+        ) => op_a == op_b && same_tree(lhs_a, lhs_b) && same_tree(rhs_a, rhs_b),
+        (Expr::Variable(a), Expr::Variable(b)) => a == b,
+        (Expr::Negate(a), Expr::Negate(b)) => same_tree(a, b),
+        (Expr::Call { name: name_a, args: args_a }, Expr::Call { name: name_b, args: args_b }) => {
+            name_a == name_b
+                && args_a.len() == args_b.len()
+                && args_a.iter().zip(args_b).all(|(a, b)| same_tree(a, b))
+        }
+        _ => false,
+    }
+}
+
+fn close(a: f64, b: f64) -> bool {
+    relative_difference(a, b) < 1e-12
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonicalize_sorts_commutative_operands() {
+        let unfoldable = Expr::binary(BinaryOp::Div, Expr::Number(1.0), Expr::Number(0.0));
+        let expr = Expr::binary(BinaryOp::Add, unfoldable.clone(), Expr::Number(2.0));
+        assert_eq!(canonicalize(expr, &Context::default()), Expr::binary(BinaryOp::Add, Expr::Number(2.0), unfoldable));
+    }
+
+    fn term() -> Expr {
+        Expr::variable("x")
+    }
+
+    #[test]
+    fn test_simplify_folds_constants_and_identities() {
+        let expr = Expr::binary(
+            BinaryOp::Mul,
+            Expr::binary(BinaryOp::Sub, Expr::Number(3.0), Expr::Number(2.0)),
+            term(),
+        );
+        assert_eq!(simplify(expr, &Context::default()), term());
+
+        let expr = Expr::binary(BinaryOp::Add, term(), Expr::Number(0.0));
+        assert_eq!(simplify(expr, &Context::default()), term());
+    }
+
+    #[test]
+    fn test_simplify_combines_like_terms() {
+        let twice = Expr::binary(BinaryOp::Mul, Expr::Number(2.0), term());
+        let expr = Expr::binary(
+            BinaryOp::Add,
+            Expr::binary(BinaryOp::Add, term(), Expr::Number(4.0)),
+            Expr::binary(BinaryOp::Add, twice, Expr::Number(2.0)),
+        );
+        let expected = Expr::binary(
+            BinaryOp::Add,
+            Expr::binary(BinaryOp::Mul, Expr::Number(3.0), term()),
+            Expr::Number(6.0),
+        );
+        assert_eq!(simplify(expr, &Context::default()), expected);
+
+        let expr = Expr::binary(BinaryOp::Sub, term(), term());
+        assert_eq!(simplify(expr, &Context::default()), Expr::Number(0.0));
+    }
+
+    #[test]
+    fn test_simplify_keeps_terms_that_fail() {
+        let simplified = |input: &str, ctx: &Context| simplify(parse(input).unwrap(), ctx).to_string();
+        let ctx = Context::default();
+        assert_eq!(simplified("y + 0 * (1 / 0)", &ctx), "y + 0 * (1 / 0)");
+        assert_eq!(simplified("1 / 0 - 1 / 0", &ctx), "1 / 0 - 1 / 0");
+        assert_eq!(simplified("1e308 * 10 + y", &ctx), "1e308 * 10 + y");
+        assert_eq!(simplified("1e308 * y + 1e308 * y", &ctx), "1e308 * y + 1e308 * y");
+        assert_eq!(simplified("y - y + 2", &ctx), "2");
+    }
+
+    #[test]
+    fn test_are_equivalent() {
+        assert_eq!(are_equivalent("5 + 3", "3 + 5"), Ok(true));
+        assert_eq!(are_equivalent("2 * 4", "4*2"), Ok(true));
+        assert_eq!(are_equivalent("6 / 2", "1.5 * 2"), Ok(true));
+        assert_eq!(are_equivalent("0.1 + 0.2", "0.15 + 0.15"), Ok(true));
+        assert_eq!(are_equivalent("5 - 3", "3 - 5"), Ok(false));
+        assert_eq!(are_equivalent("1 / 0", "0 / 0"), Ok(false));
+        assert!(are_equivalent("5 +", "5").is_err());
+
+        assert_eq!(are_equivalent("2 * (x + 3)", "2*x + 6"), Ok(true));
+        assert_eq!(are_equivalent("x / 2", "0.5 * x"), Ok(true));
+        assert_eq!(are_equivalent("x/4 + x/4", "x * 0.5"), Ok(true));
+        assert_eq!(are_equivalent("x - (y - x)", "2*x - y"), Ok(true));
+        assert_eq!(are_equivalent("x / 2", "2 / x"), Ok(false));
+        assert_eq!(are_equivalent("x + 1", "y + 1"), Ok(false));
+    }
+}
@@ -0,0 +1,72 @@
+//! Audible feedback for key presses and errors using the platform's
+//! stock system sounds.
+//!
+//! Only compiled in with the `sound` feature; without it [`AVAILABLE`] is
+//! false and [`play`] does nothing.
+
+#[cfg(not(windows))]
+use std::process::Command;
+
+#[cfg(not(windows))]
+use crate::programs::{self, command};
+
+/// Whether this build can play sounds at all.
+pub const AVAILABLE: bool = cfg!(feature = "sound");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sound {
+    /// A short tick for a button press.
+    Click,
+    /// A buzz for a failed evaluation.
+    Error,
+}
+
+/// Plays `sound` in the background. Failures (no player installed, for
+/// instance) are silently ignored.
+pub fn play(sound: Sound) {
+    if !AVAILABLE {
+        return;
+    }
+
+    // Windows plays its system sounds in this process, so a click does not
+    // start a program
+    #[cfg(windows)]
+    message_beep(sound);
+    #[cfg(not(windows))]
+    programs::run_first(platform_commands(sound), String::new());
+}
+
+#[cfg(windows)]
+fn message_beep(sound: Sound) {
+    #[link(name = "user32")]
+    extern "system" {
+        fn MessageBeep(kind: u32) -> i32;
+    }
+    // MB_ICONASTERISK and MB_ICONHAND
+    let kind = match sound {
+        Sound::Click => 0x40,
+        Sound::Error => 0x10,
+    };
+    // SAFETY: MessageBeep takes a plain flag and only queues the sound
+    unsafe {
+        MessageBeep(kind);
+    }
+}
+
+// Candidate commands in order of preference; the first that runs wins.
+#[cfg(not(windows))]
+fn platform_commands(sound: Sound) -> Vec<Command> {
+    if cfg!(target_os = "macos") {
+        let file = match sound {
+            Sound::Click => "/System/Library/Sounds/Tink.aiff",
+            Sound::Error => "/System/Library/Sounds/Basso.aiff",
+        };
+        vec![command("afplay", &[file])]
+    } else {
+        let (event, file) = match sound {
+            Sound::Click => ("button-pressed", "/usr/share/sounds/freedesktop/stereo/button-pressed.oga"),
+            Sound::Error => ("dialog-error", "/usr/share/sounds/freedesktop/stereo/dialog-error.oga"),
+        };
+        vec![command("canberra-gtk-play", &["-i", event]), command("paplay", &[file])]
+    }
+}
@@ -0,0 +1,40 @@
+//! Spoken announcement of results through the platform's text-to-speech.
+//!
+//! Only compiled in with the `speech` feature; without it [`AVAILABLE`] is
+//! false and [`speak`] does nothing.
+
+use std::process::Command;
+
+use crate::programs::{self, command};
+
+/// Whether this build can speak at all.
+pub const AVAILABLE: bool = cfg!(feature = "speech");
+
+/// Reads `text` aloud in the background. Failures (no speech engine
+/// installed, for instance) are silently ignored.
+pub fn speak(text: &str) {
+    if AVAILABLE {
+        programs::run_first(platform_commands(text), text.to_string());
+    }
+}
+
+// Candidate commands in order of preference; the first that runs wins. The
+// text goes on standard input, or after `--`, so a result such as `-5` is
+// not taken for an option.
+fn platform_commands(text: &str) -> Vec<Command> {
+    if cfg!(target_os = "macos") {
+        vec![command("say", &["-f", "-"])]
+    } else if cfg!(windows) {
+        vec![command(
+            "powershell",
+            &[
+                "-NoProfile",
+                "-Command",
+                "Add-Type -AssemblyName System.Speech; \
+                 (New-Object System.Speech.Synthesis.SpeechSynthesizer).Speak([Console]::In.ReadToEnd())",
+            ],
+        )]
+    } else {
+        vec![command("spd-say", &["--wait", "--", text]), command("espeak", &["--stdin"])]
+    }
+}
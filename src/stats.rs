@@ -0,0 +1,300 @@
+//! Introductory statistics: standard scores, normal p-values and confidence
+//! intervals for a mean, and the summaries behind a histogram and a box
+//! plot. Tails are computed directly instead of as one minus the rest of
+//! the distribution, so small p-values keep their precision.
+
+use std::f64::consts::{PI, SQRT_2};
+
+use crate::roots;
+
+/// How many standard deviations `x` is from the mean.
+pub fn zscore(x: f64, mean: f64, sd: f64) -> Result<f64, String> {
+    if sd <= 0.0 {
+        return Err("The standard deviation must be above 0".to_string());
+    }
+    Ok((x - mean) / sd)
+}
+
+/// The one-sample t statistic of a sample of `n` with mean `sample_mean`
+/// and standard deviation `sd`, against a population mean of `mean`.
+pub fn tscore(sample_mean: f64, mean: f64, sd: f64, n: f64) -> Result<f64, String> {
+    check_sample(sd, n)?;
+    Ok((sample_mean - mean) / (sd / n.sqrt()))
+}
+
+fn check_sample(sd: f64, n: f64) -> Result<(), String> {
+    if sd <= 0.0 {
+        return Err("The standard deviation must be above 0".to_string());
+    }
+    if n.fract() != 0.0 || n < 2.0 {
+        return Err("The sample size must be a whole number of at least 2".to_string());
+    }
+    Ok(())
+}
+
+/// The complementary error function, accurate to a few units in the last
+/// place far out into the tail, where `1 - erf(x)` would be 0.
+pub fn erfc(x: f64) -> f64 {
+    if x.is_nan() {
+        return x;
+    }
+    if x < 0.0 {
+        return 2.0 - erfc(-x);
+    }
+    if x < 2.0 {
+        // erf(x) = 2/√π e^(-x²) Σ 2ⁿ x^(2n+1) / (1·3·5···(2n+1)), whose
+        // terms are all positive
+        let (mut term, mut sum) = (x, x);
+        for n in 1.. {
+            term *= 2.0 * x * x / (2 * n + 1) as f64;
+            sum += term;
+            if term <= sum * 1e-17 {
+                break;
+            }
+        }
+        return 1.0 - 2.0 / PI.sqrt() * (-x * x).exp() * sum;
+    }
+    // The continued fraction x + (1/2)/(x + 1/(x + (3/2)/(x + …))),
+    // evaluated with the modified Lentz method
+    let tiny = 1e-300;
+    let (mut f, mut c, mut d) = (x, x, 0.0);
+    for n in 1..1000 {
+        let a = n as f64 / 2.0;
+        d = x + a * d;
+        d = if d.abs() < tiny { 1.0 / tiny } else { 1.0 / d };
+        c = x + a / c;
+        if c.abs() < tiny {
+            c = tiny;
+        }
+        let delta = c * d;
+        f *= delta;
+        if (delta - 1.0).abs() < 1e-16 {
+            break;
+        }
+    }
+    (-x * x).exp() / (PI.sqrt() * f)
+}
+
+/// The two-sided p-value of a standard normal score: the chance of a score
+/// at least as far from 0 as `z`.
+pub fn pvalue_z(z: f64) -> f64 {
+    erfc(z.abs() / SQRT_2)
+}
+
+// ln Γ(x) for x > 0, by the Lanczos approximation
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    let x = x - 1.0;
+    let mut sum = COEFFICIENTS[0];
+    for (i, &coefficient) in COEFFICIENTS.iter().enumerate().skip(1) {
+        sum += coefficient / (x + i as f64);
+    }
+    let t = x + 7.5;
+    0.5 * (2.0 * PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+// The regularized incomplete beta function I_x(a, b)
+fn incomplete_beta(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let front = (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln()).exp();
+    // The continued fraction converges quickly only below this point; above
+    // it the symmetry I_x(a, b) = 1 - I_(1-x)(b, a) is used
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_fraction(a, b, x) / a
+    } else {
+        1.0 - front * beta_fraction(b, a, 1.0 - x) / b
+    }
+}
+
+// The continued fraction for the incomplete beta function, by the modified
+// Lentz method
+fn beta_fraction(a: f64, b: f64, x: f64) -> f64 {
+    let tiny = 1e-300;
+    let clamp = |value: f64| if value.abs() < tiny { tiny } else { value };
+    let mut c = 1.0;
+    let mut d = 1.0 / clamp(1.0 - (a + b) * x / (a + 1.0));
+    let mut h = d;
+    for m in 1..1000 {
+        let m = m as f64;
+        let even = m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m));
+        d = 1.0 / clamp(1.0 + even * d);
+        c = clamp(1.0 + even / c);
+        h *= d * c;
+        let odd = -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0));
+        d = 1.0 / clamp(1.0 + odd * d);
+        c = clamp(1.0 + odd / c);
+        let delta = d * c;
+        h *= delta;
+        if (delta - 1.0).abs() < 1e-16 {
+            break;
+        }
+    }
+    h
+}
+
+// The chance that Student's t with `df` degrees of freedom is above `t`,
+// for t ≥ 0
+fn t_upper_tail(t: f64, df: f64) -> f64 {
+    0.5 * incomplete_beta(df / 2.0, 0.5, df / (df + t * t))
+}
+
+/// The interval that holds the population mean with `level` percent
+/// confidence, from a sample of `n` with mean `mean` and standard deviation
+/// `sd`, using Student's t distribution.
+pub fn ci(mean: f64, sd: f64, n: f64, level: f64) -> Result<(f64, f64), String> {
+    check_sample(sd, n)?;
+    if !(level > 0.0 && level < 100.0) {
+        return Err("The confidence level must be a percentage between 0 and 100".to_string());
+    }
+    let tail = (100.0 - level) / 200.0;
+    let df = n - 1.0;
+    let t = roots::bisect(|t| t_upper_tail(t, df) - tail, 0.0, 1e10)
+        .ok_or("The confidence level is too close to 100%")?;
+    let margin = t * sd / n.sqrt();
+    Ok((mean - margin, mean + margin))
+}
+
+/// A histogram bar: how many values lie from `start` up to `end`, which
+/// the last bar includes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bin {
+    pub start: f64,
+    pub end: f64,
+    pub count: usize,
+}
+
+/// The finite `values` counted into `bins` bars of equal width.
+pub fn histogram(values: &[f64], bins: usize) -> Vec<Bin> {
+    let values: Vec<f64> = values.iter().copied().filter(|value| value.is_finite()).collect();
+    let (Some(&low), Some(&high)) = (
+        values.iter().min_by(|a, b| a.total_cmp(b)),
+        values.iter().max_by(|a, b| a.total_cmp(b)),
+    ) else {
+        return Vec::new();
+    };
+    let bins = bins.max(1);
+    // All values alike still get a bar of some width
+    let (low, high) = if low == high { (low - 0.5, high + 0.5) } else { (low, high) };
+    let width = (high - low) / bins as f64;
+    let mut histogram: Vec<Bin> = (0..bins)
+        .map(|i| Bin {
+            start: low + width * i as f64,
+            end: if i + 1 == bins { high } else { low + width * (i + 1) as f64 },
+            count: 0,
+        })
+        .collect();
+    for value in values {
+        let i = (((value - low) / width) as usize).min(bins - 1);
+        histogram[i].count += 1;
+    }
+    histogram
+}
+
+/// A reasonable number of histogram bars for `n` values, by Sturges' rule.
+pub fn sturges_bins(n: usize) -> usize {
+    (n.max(1) as f64).log2().ceil() as usize + 1
+}
+
+/// The five numbers a box plot draws.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Summary {
+    pub min: f64,
+    /// The first quartile.
+    pub q1: f64,
+    pub median: f64,
+    /// The third quartile.
+    pub q3: f64,
+    pub max: f64,
+}
+
+/// The five-number summary of the finite `values`, with quartiles
+/// interpolated between neighbouring values; `None` when there are none.
+pub fn summary(values: &[f64]) -> Option<Summary> {
+    let mut sorted: Vec<f64> = values.iter().copied().filter(|value| value.is_finite()).collect();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let quantile = |p: f64| {
+        let position = p * (sorted.len() - 1) as f64;
+        let (below, above) = (position.floor() as usize, position.ceil() as usize);
+        sorted[below] + (sorted[above] - sorted[below]) * position.fract()
+    };
+    Some(Summary {
+        min: *sorted.first()?,
+        q1: quantile(0.25),
+        median: quantile(0.5),
+        q3: quantile(0.75),
+        max: *sorted.last()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_relative(actual: f64, expected: f64) {
+        assert!(((actual - expected) / expected).abs() < 1e-13, "{} is not {}", actual, expected);
+    }
+
+    #[test]
+    fn test_tails() {
+        assert_relative(erfc(0.5), 0.4795001221869535);
+        assert_relative(erfc(1.0), 0.15729920705028513);
+        assert_relative(erfc(3.0), 2.209049699858544e-5);
+        assert_relative(erfc(10.0), 2.088487583762545e-45);
+        assert_relative(erfc(-1.0), 1.8427007929497148);
+        assert_eq!(erfc(0.0), 1.0);
+        assert_relative(pvalue_z(1.959963984540054), 0.05);
+        assert_relative(pvalue_z(-8.0), 1.2441921148543639e-15);
+    }
+
+    #[test]
+    fn test_scores_and_intervals() {
+        assert_eq!(zscore(130.0, 100.0, 15.0), Ok(2.0));
+        assert_eq!(tscore(103.0, 100.0, 15.0, 25.0), Ok(1.0));
+        assert!(tscore(103.0, 100.0, 15.0, 1.0).is_err());
+
+        let (low, high) = ci(100.0, 15.0, 25.0, 95.0).unwrap();
+        assert!((high - 100.0 - 3.0 * 2.0638985616280205).abs() < 1e-9);
+        assert!((100.0 - low - (high - 100.0)).abs() < 1e-12);
+        let (_, high) = ci(0.0, 1.0, 2.0, 99.0).unwrap();
+        assert!((high - 63.65674116287158 / 2f64.sqrt()).abs() < 1e-7);
+        assert!(ci(100.0, 15.0, 25.0, 100.0).is_err());
+    }
+
+    #[test]
+    fn test_histogram_and_summary() {
+        let values = [1.0, 2.0, 2.0, 3.0, 4.0, 10.0, f64::NAN];
+        let bars = histogram(&values, 3);
+        assert_eq!(bars.iter().map(|bar| bar.count).collect::<Vec<_>>(), [4, 1, 1]);
+        assert_eq!((bars[0].start, bars[2].end), (1.0, 10.0));
+        assert_eq!(histogram(&[5.0, 5.0], 1), [Bin { start: 4.5, end: 5.5, count: 2 }]);
+        assert!(histogram(&[], 4).is_empty());
+        assert_eq!(sturges_bins(100), 8);
+
+        assert_eq!(
+            summary(&values),
+            Some(Summary {
+                min: 1.0,
+                q1: 2.0,
+                median: 2.5,
+                q3: 3.75,
+                max: 10.0,
+            })
+        );
+        assert_eq!(summary(&[f64::NAN]), None);
+    }
+}
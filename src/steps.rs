@@ -0,0 +1,121 @@
+//! The value of every part of an expression: `pow(2 + 3, 2) / 5` written out
+//! piece by piece, each piece tied to the sub-expression it belongs to and
+//! that sub-expression's result, so a display can show `2 + 3 = 5` when
+//! the `+` is pointed at.
+
+use crate::ast::Expr;
+use crate::eval::Context;
+use crate::format::{needs_parens, negation_needs_parens};
+use crate::units::evaluate_quantity;
+
+/// One sub-expression and what it evaluates to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    pub text: String,
+    pub value: Result<String, String>,
+}
+
+/// An expression written out as pieces of text, with the steps they
+/// belong to.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Steps {
+    /// The text of the expression, in order, each piece with the index in
+    /// `steps` of the innermost sub-expression it is part of.
+    pub pieces: Vec<(String, usize)>,
+    /// Every sub-expression, outermost first, evaluated once.
+    pub steps: Vec<Step>,
+}
+
+impl Steps {
+    pub fn new(expr: &Expr, ctx: &Context) -> Self {
+        let mut steps = Steps::default();
+        steps.walk(expr, ctx);
+        steps
+    }
+
+    /// The step the piece at `index` is part of.
+    pub fn step(&self, index: usize) -> &Step {
+        &self.steps[self.pieces[index].1]
+    }
+
+    fn walk(&mut self, expr: &Expr, ctx: &Context) {
+        let step = self.steps.len();
+        self.steps.push(Step {
+            text: expr.to_string(),
+            value: evaluate_quantity(expr, ctx).map(|quantity| quantity.to_string()),
+        });
+        match expr {
+            Expr::Binary { op, lhs, rhs } => {
+                for (operand, right) in [(lhs, false), (rhs, true)] {
+                    if right {
+                        self.piece(format!(" {} ", op), step);
+                    }
+                    let parens = needs_parens(operand, *op, right);
+                    if parens {
+                        self.piece("(".to_string(), step);
+                    }
+                    self.walk(operand, ctx);
+                    if parens {
+                        self.piece(")".to_string(), step);
+                    }
+                }
+            }
+            Expr::Negate(operand) => {
+                let parens = negation_needs_parens(operand);
+                self.piece(if parens { "-(" } else { "-" }.to_string(), step);
+                self.walk(operand, ctx);
+                if parens {
+                    self.piece(")".to_string(), step);
+                }
+            }
+            Expr::Call { name, args } => {
+                let separator = if args.iter().any(|arg| matches!(arg, Expr::List(_))) { "; " } else { ", " };
+                self.piece(format!("{}(", name), step);
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        self.piece(separator.to_string(), step);
+                    }
+                    self.walk(arg, ctx);
+                }
+                self.piece(")".to_string(), step);
+            }
+            // Numbers, names, quantities and lists are shown whole
+            _ => self.piece(expr.to_string(), step),
+        }
+    }
+
+    fn piece(&mut self, text: String, step: usize) {
+        self.pieces.push((text, step));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_steps() {
+        let steps = Steps::new(&parse("pow(2 + 3, 2) / 5").unwrap(), &Context::new());
+        let text: String = steps.pieces.iter().map(|(piece, _)| piece.as_str()).collect();
+        assert_eq!(text, "pow(2 + 3, 2) / 5");
+        let plus = steps.pieces.iter().position(|(piece, _)| piece == " + ").unwrap();
+        assert_eq!(
+            steps.step(plus),
+            &Step {
+                text: "2 + 3".to_string(),
+                value: Ok("5".to_string())
+            }
+        );
+        assert_eq!(steps.steps[0].value, Ok("5".to_string()));
+        assert_eq!(steps.steps[1].value, Ok("25".to_string()));
+        assert_eq!(steps.steps.len(), 7);
+
+        let mut ctx = Context::new();
+        ctx.set("x", 9.0);
+        let steps = Steps::new(&parse("pow(x, 2) / 0").unwrap(), &ctx);
+        assert_eq!(steps.steps[1].text, "pow(x, 2)");
+        assert_eq!(steps.steps[1].value, Ok("81".to_string()));
+        assert!(steps.steps[0].value.is_err());
+    }
+}
@@ -0,0 +1,78 @@
+//! Math symbols for keyboards that lack them, each inserting text the
+//! parser understands.
+
+/// A button on the symbol palette.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Symbol {
+    /// The text shown on the button.
+    pub symbol: &'static str,
+    /// The text the button inserts.
+    pub insert: &'static str,
+    pub description: &'static str,
+    /// An input using it.
+    pub example: &'static str,
+}
+
+pub const SYMBOLS: [Symbol; 7] = [
+    Symbol {
+        symbol: "π",
+        insert: "π",
+        description: "Pi, 3.14159…",
+        example: "2 × π",
+    },
+    Symbol {
+        symbol: "√",
+        insert: "sqrt(",
+        description: "Square root, as sqrt(x)",
+        example: "sqrt(16)",
+    },
+    Symbol {
+        symbol: "×",
+        insert: " × ",
+        description: "Multiply",
+        example: "6 × 7",
+    },
+    Symbol {
+        symbol: "÷",
+        insert: " ÷ ",
+        description: "Divide",
+        example: "42 ÷ 6",
+    },
+    Symbol {
+        symbol: "≤",
+        insert: "le(",
+        description: "At most, as le(a, b): 1 if a ≤ b, else 0",
+        example: "le(2, 3)",
+    },
+    Symbol {
+        symbol: "°",
+        insert: "°",
+        description: "Degrees of temperature, as in 20 °C",
+        example: "20 °C + 5 K",
+    },
+    Symbol {
+        symbol: "µ",
+        insert: "µ",
+        description: "Micro, a millionth, as in 5 µm",
+        example: "5 µm + 1 mm",
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calculate_quantity;
+    use crate::eval::Context;
+
+    #[test]
+    fn test_symbol_examples() {
+        let ctx = Context::new();
+        for symbol in SYMBOLS {
+            assert!(symbol.example.contains(symbol.insert.trim()), "{}", symbol.example);
+            let result = calculate_quantity(symbol.example, &ctx);
+            assert!(result.is_ok(), "{}: {:?}", symbol.example, result);
+        }
+        assert_eq!(calculate_quantity("42 ÷ 6", &ctx).map(|q| q.value), Ok(7.0));
+        assert_eq!(calculate_quantity("5 μm + 1 mm", &ctx).map(|q| q.to_string()), Ok("0.001005 m".to_string()));
+    }
+}
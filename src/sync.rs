@@ -0,0 +1,161 @@
+//! Keeping the configuration, sessions and history in a folder of the
+//! user's choosing, such as a Dropbox or Syncthing folder, so they follow
+//! the user between machines without a sync service of our own.
+//!
+//! Each machine writes only its own history file, so two machines never
+//! overwrite each other's; loading merges every history file in the
+//! folder, including the conflict copies sync tools make.
+
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use calculator::History;
+
+use crate::session::{self, SavedEntry};
+
+/// How often the folder is checked for changes.
+pub const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+fn config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("calculator"))
+}
+
+// The sync folder's path is kept outside it, in the platform config
+// directory
+fn pointer_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("sync_folder.txt"))
+}
+
+/// The folder chosen with [`set_folder`], if any.
+pub fn folder() -> Option<PathBuf> {
+    let text = fs::read_to_string(pointer_path()?).ok()?;
+    let text = text.trim();
+    (!text.is_empty()).then(|| PathBuf::from(text))
+}
+
+/// Where the configuration, sessions and history are kept: the sync
+/// folder, or else the platform config directory.
+pub fn data_dir() -> Option<PathBuf> {
+    folder().or_else(config_dir)
+}
+
+/// Starts keeping everything in `folder`, which must exist, or goes back
+/// to the platform config directory for `None`.
+pub fn set_folder(folder: Option<&Path>) -> Result<(), String> {
+    let pointer = pointer_path().ok_or("No configuration directory on this system")?;
+    match folder {
+        Some(folder) => {
+            if !folder.is_dir() {
+                return Err(format!("{} is not a folder", folder.display()));
+            }
+            if let Some(dir) = pointer.parent() {
+                fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+            }
+            fs::write(pointer, folder.display().to_string()).map_err(|err| err.to_string())
+        }
+        None => match fs::remove_file(pointer) {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err.to_string()),
+            _ => Ok(()),
+        },
+    }
+}
+
+fn history_dir() -> Option<PathBuf> {
+    folder().map(|dir| dir.join("history"))
+}
+
+// This machine's history file, named after the host
+fn machine_file(dir: &Path) -> PathBuf {
+    let host = std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .or_else(|_| fs::read_to_string("/etc/hostname"))
+        .unwrap_or_default();
+    let host: String = host.trim().chars().filter(|c| c.is_alphanumeric() || "-_".contains(*c)).collect();
+    dir.join(format!("{}.json", if host.is_empty() { "this-machine" } else { &host }))
+}
+
+/// The history of every machine syncing to the folder, merged in time
+/// order. Files that cannot be read are skipped.
+pub fn load_history() -> History {
+    let mut history = History::new();
+    let Some(entries) = history_dir().and_then(|dir| fs::read_dir(dir).ok()) else {
+        return history;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().is_some_and(|extension| extension == "json") {
+            let saved: Option<Vec<SavedEntry>> = fs::read_to_string(&path)
+                .ok()
+                .and_then(|text| serde_json::from_str(&text).ok());
+            if let Some(saved) = saved {
+                history.merge(&session::restored_history(&saved));
+            }
+        }
+    }
+    history
+}
+
+/// Writes `history` as this machine's history file. The file is written
+/// under another name and then renamed, so a sync tool never picks up half
+/// of it.
+pub fn save_history(history: &History) -> Result<(), String> {
+    let dir = history_dir().ok_or("No sync folder is set")?;
+    fs::create_dir_all(&dir).map_err(|err| err.to_string())?;
+    let path = machine_file(&dir);
+    let partial = path.with_extension("json.partial");
+    let text = serde_json::to_string_pretty(&session::saved_entries(history)).map_err(|err| err.to_string())?;
+    fs::write(&partial, text).map_err(|err| err.to_string())?;
+    fs::rename(&partial, &path).map_err(|err| err.to_string())
+}
+
+/// Notices when files in the sync folder change, such as when another
+/// machine's changes are synced in.
+#[derive(Default)]
+pub struct Watcher {
+    stamps: Vec<(PathBuf, SystemTime)>,
+    checked: Option<Instant>,
+}
+
+impl Watcher {
+    /// Whether anything changed since the last check, looking at most
+    /// every [`POLL_INTERVAL`]. The first check always reports a change.
+    pub fn changed(&mut self) -> bool {
+        if self.checked.is_some_and(|checked| checked.elapsed() < POLL_INTERVAL) {
+            return false;
+        }
+        let stamps = stamps();
+        let changed = self.checked.is_none() || stamps != self.stamps;
+        self.stamps = stamps;
+        self.checked = Some(Instant::now());
+        changed
+    }
+
+    /// Takes the folder as it is now as unchanged, after writing to it.
+    pub fn refresh(&mut self) {
+        self.stamps = stamps();
+    }
+}
+
+// Modification times of the files that are synced
+fn stamps() -> Vec<(PathBuf, SystemTime)> {
+    let Some(folder) = folder() else {
+        return Vec::new();
+    };
+    let mut paths = vec![folder.join("config.json")];
+    for dir in [folder.join("history"), folder.join("sessions")] {
+        if let Ok(entries) = fs::read_dir(dir) {
+            paths.extend(entries.flatten().map(|entry| entry.path()));
+        }
+    }
+    let mut stamps: Vec<(PathBuf, SystemTime)> = paths
+        .into_iter()
+        .filter_map(|path| {
+            let modified = fs::metadata(&path).and_then(|metadata| metadata.modified()).ok()?;
+            Some((path, modified))
+        })
+        .collect();
+    stamps.sort();
+    stamps
+}
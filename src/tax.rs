@@ -0,0 +1,76 @@
+//! Named sales tax rates, applied with `withtax` and `withouttax`.
+
+use serde::{Deserialize, Serialize};
+
+use crate::eval::Context;
+
+/// A sales tax rate saved under a name such as `NY`.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct TaxRate {
+    /// The rate in percent.
+    pub rate: f64,
+    /// Whether the rate is a share of the price including tax, as for
+    /// Brazil's ICMS, rather than of the price before tax, as for US sales
+    /// tax and VAT.
+    pub inclusive: bool,
+}
+
+impl TaxRate {
+    /// The price including tax for a price before tax.
+    pub fn add(&self, net: f64) -> f64 {
+        let rate = self.rate / 100.0;
+        if self.inclusive {
+            net / (1.0 - rate)
+        } else {
+            net + net * rate
+        }
+    }
+
+    /// The price before tax for a price including it.
+    pub fn remove(&self, gross: f64) -> f64 {
+        let rate = self.rate / 100.0;
+        if self.inclusive {
+            gross * (1.0 - rate)
+        } else {
+            gross / (1.0 + rate)
+        }
+    }
+}
+
+/// The tax `name` stands for in `ctx`: a saved rate, or else a number,
+/// taken as a rate in percent on the price before tax.
+pub fn find(name: &str, ctx: &Context) -> Result<TaxRate, String> {
+    let tax = match ctx.taxes.get(name) {
+        Some(&tax) => tax,
+        None => match name.parse::<f64>() {
+            Ok(rate) => TaxRate { rate, inclusive: false },
+            Err(_) => return Err(format!("No tax rate is saved as '{}'", name)),
+        },
+    };
+    if !(0.0..100.0).contains(&tax.rate) {
+        return Err(format!("A tax rate must be at least 0% and under 100%, not {}%", tax.rate));
+    }
+    Ok(tax)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tax_rates() {
+        let sales = TaxRate { rate: 8.875, inclusive: false };
+        assert_eq!(sales.add(100.0), 108.875);
+        assert!((sales.remove(108.875) - 100.0).abs() < 1e-12);
+        let icms = TaxRate { rate: 18.0, inclusive: true };
+        assert!((icms.add(82.0) - 100.0).abs() < 1e-12);
+        assert!((icms.remove(100.0) - 82.0).abs() < 1e-12);
+
+        let mut ctx = Context::new();
+        ctx.taxes.insert("NY".to_string(), sales);
+        assert_eq!(find("NY", &ctx), Ok(sales));
+        assert_eq!(find("20", &ctx), Ok(TaxRate { rate: 20.0, inclusive: false }));
+        assert_eq!(find("CA", &ctx), Err("No tax rate is saved as 'CA'".to_string()));
+        assert!(find("100", &ctx).is_err());
+    }
+}
@@ -0,0 +1,1019 @@
+//! Calculators driven by form fields instead of a typed expression, each
+//! shown in its own window from the Tools menu.
+
+use eframe::egui;
+
+use calculator::format::format_number;
+use calculator::health::{bmi_category, Sex};
+use calculator::grades::grade_points;
+use calculator::depreciation::{self, Method};
+use calculator::interest::{Point, Savings, Unknown};
+use calculator::invoice::{Invoice, Line};
+use calculator::unitprice::{savings, unit_names, unit_price, UnitPrice};
+use calculator::{calculate_with, functions, scaling, tax, Context, TaxRate};
+
+/// Reads a number from a form field. Anything the calculator accepts, such
+/// as `1/3`, works as well as a plain number.
+pub fn read_number(text: &str, ctx: &Context) -> Result<f64, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("Empty input".to_string());
+    }
+    text.parse::<f64>().or_else(|_| calculate_with(text, ctx))
+}
+
+/// Calls a built-in function by name, so the tools compute exactly what
+/// the same call typed into the calculator would.
+fn call(name: &str, args: &[f64], calc: &Context) -> Result<f64, String> {
+    functions::find(name, calc)?.call(args, calc)
+}
+
+fn call_with_lists(name: &str, args: &[Vec<f64>], calc: &Context) -> Result<f64, String> {
+    functions::find(name, calc)?.call_with_lists(args, calc)
+}
+
+fn error_label(ui: &mut egui::Ui, err: &str) {
+    ui.label(egui::RichText::new(err).color(egui::Color32::RED));
+}
+
+// Where a table is saved as CSV, and the outcome of the last copy or save
+struct CsvExport {
+    path: String,
+    message: String,
+}
+
+impl CsvExport {
+    fn new(file_name: &str) -> Self {
+        Self {
+            path: dirs::home_dir()
+                .map(|dir| dir.join(file_name).display().to_string())
+                .unwrap_or_else(|| file_name.to_string()),
+            message: String::new(),
+        }
+    }
+
+    // Buttons to copy `csv` or save it to the path
+    fn ui(&mut self, ui: &mut egui::Ui, csv: impl Fn() -> String, calc: &Context) {
+        ui.horizontal(|ui| {
+            if ui.button("Copy CSV").clicked() {
+                ui.output_mut(|output| output.copied_text = csv());
+                self.message = "Copied".to_string();
+            }
+            // A locked context is a kiosk's, which writes no files
+            if !calc.is_locked() {
+                ui.add(egui::TextEdit::singleline(&mut self.path).desired_width(160.0));
+                if ui.button("Save CSV").clicked() {
+                    self.message = match std::fs::write(self.path.trim(), csv()) {
+                        Ok(()) => format!("Saved {}", self.path.trim()),
+                        Err(err) => format!("Error: {}", err),
+                    };
+                }
+            }
+        });
+        if !self.message.is_empty() {
+            ui.label(self.message.as_str());
+        }
+    }
+}
+
+// A grid row with a labelled input that must hold a number in `range`.
+// Returns the number, or `None` after showing why it is not usable.
+fn validated_field(
+    ui: &mut egui::Ui,
+    label: &str,
+    text: &mut String,
+    range: std::ops::RangeInclusive<f64>,
+    calc: &Context,
+) -> Option<f64> {
+    ui.label(label);
+    ui.add(egui::TextEdit::singleline(text).desired_width(60.0));
+    let value = match read_number(text, calc) {
+        _ if text.trim().is_empty() => None,
+        Ok(value) if range.contains(&value) => Some(value),
+        Ok(_) => {
+            error_label(ui, &format!("between {} and {}", range.start(), range.end()));
+            None
+        }
+        Err(err) => {
+            error_label(ui, &err);
+            None
+        }
+    };
+    ui.end_row();
+    value
+}
+
+// The form inside a tool's window.
+trait Form: Default {
+    const TITLE: &'static str;
+
+    fn ui(&mut self, ui: &mut egui::Ui, calc: &Context);
+}
+
+#[derive(Default)]
+struct Tool<F> {
+    open: bool,
+    form: F,
+}
+
+impl<F: Form> Tool<F> {
+    fn menu_item(&mut self, ui: &mut egui::Ui) {
+        if ui.button(format!("{}…", F::TITLE)).clicked() {
+            self.open = true;
+            ui.close_menu();
+        }
+    }
+
+    fn show(&mut self, ctx: &egui::Context, calc: &Context) {
+        let form = &mut self.form;
+        egui::Window::new(F::TITLE)
+            .open(&mut self.open)
+            .show(ctx, |ui| form.ui(ui, calc));
+    }
+}
+
+#[derive(Default)]
+pub struct Tools {
+    recipe: Tool<RecipeForm>,
+    trip: Tool<TripForm>,
+    health: Tool<HealthForm>,
+    average: Tool<AverageForm>,
+    unit_price: Tool<UnitPriceForm>,
+    invoice: Tool<InvoiceForm>,
+    depreciation: Tool<DepreciationForm>,
+    interest: Tool<InterestForm>,
+    goal: Tool<GoalForm>,
+}
+
+impl Tools {
+    /// Adds an entry per tool to the Tools menu.
+    pub fn menu(&mut self, ui: &mut egui::Ui) {
+        self.recipe.menu_item(ui);
+        self.trip.menu_item(ui);
+        self.health.menu_item(ui);
+        self.average.menu_item(ui);
+        self.unit_price.menu_item(ui);
+        self.invoice.menu_item(ui);
+        self.depreciation.menu_item(ui);
+        self.interest.menu_item(ui);
+        self.goal.menu_item(ui);
+    }
+
+    /// Draws the windows of the open tools.
+    pub fn show(&mut self, ctx: &egui::Context, calc: &Context) {
+        self.recipe.show(ctx, calc);
+        self.trip.show(ctx, calc);
+        self.health.show(ctx, calc);
+        self.average.show(ctx, calc);
+        self.unit_price.show(ctx, calc);
+        self.invoice.show(ctx, calc);
+        self.depreciation.show(ctx, calc);
+        self.interest.show(ctx, calc);
+        self.goal.show(ctx, calc);
+    }
+}
+
+struct Ingredient {
+    name: String,
+    amount: String,
+}
+
+// Amounts scaled either by a factor or so that one of them hits a target.
+struct RecipeForm {
+    ingredients: Vec<Ingredient>,
+    by_target: bool,
+    factor: String,
+    target_row: usize,
+    target: String,
+}
+
+impl Default for RecipeForm {
+    fn default() -> Self {
+        Self {
+            ingredients: vec![Ingredient {
+                name: String::new(),
+                amount: String::new(),
+            }],
+            by_target: false,
+            factor: "2".to_string(),
+            target_row: 0,
+            target: String::new(),
+        }
+    }
+}
+
+impl Form for RecipeForm {
+    const TITLE: &'static str = "Recipe Scaling";
+
+    fn ui(&mut self, ui: &mut egui::Ui, calc: &Context) {
+        let amounts: Vec<Option<f64>> =
+            self.ingredients.iter().map(|row| read_number(&row.amount, calc).ok()).collect();
+
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.by_target, false, "Scale by");
+            ui.add(egui::TextEdit::singleline(&mut self.factor).desired_width(60.0));
+        });
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.by_target, true, "Scale so that");
+            let label = |row: usize| match self.ingredients.get(row) {
+                Some(ingredient) if !ingredient.name.is_empty() => ingredient.name.clone(),
+                _ => format!("row {}", row + 1),
+            };
+            egui::ComboBox::from_id_source("target row")
+                .selected_text(label(self.target_row))
+                .show_ui(ui, |ui| {
+                    for row in 0..self.ingredients.len() {
+                        ui.selectable_value(&mut self.target_row, row, label(row));
+                    }
+                });
+            ui.label("becomes");
+            ui.add(egui::TextEdit::singleline(&mut self.target).desired_width(60.0));
+        });
+
+        let factor = if self.by_target {
+            let known: Vec<f64> = amounts.iter().map(|amount| amount.unwrap_or(0.0)).collect();
+            match amounts.get(self.target_row) {
+                Some(Some(_)) => read_number(&self.target, calc)
+                    .and_then(|target| scaling::factor_for_target(&known, self.target_row, target)),
+                _ => Err("Enter an amount in the target row".to_string()),
+            }
+        } else {
+            read_number(&self.factor, calc)
+        };
+
+        ui.add_space(5.0);
+        let mut remove = None;
+        egui::Grid::new("ingredients").striped(true).show(ui, |ui| {
+            ui.label("Name");
+            ui.label("Amount");
+            ui.label("Scaled");
+            ui.end_row();
+
+            for (row, ingredient) in self.ingredients.iter_mut().enumerate() {
+                ui.add(egui::TextEdit::singleline(&mut ingredient.name).desired_width(100.0));
+                ui.add(egui::TextEdit::singleline(&mut ingredient.amount).desired_width(60.0));
+                let scaled = match (amounts[row], &factor) {
+                    (Some(amount), Ok(factor)) => format_number(amount * factor),
+                    _ => "—".to_string(),
+                };
+                ui.label(scaled);
+                if ui.small_button("✖").clicked() {
+                    remove = Some(row);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(row) = remove {
+            self.ingredients.remove(row);
+            self.target_row = self.target_row.min(self.ingredients.len().saturating_sub(1));
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("Add row").clicked() {
+                self.ingredients.push(Ingredient {
+                    name: String::new(),
+                    amount: String::new(),
+                });
+            }
+            if let Ok(factor) = &factor {
+                ui.label(format!("Factor: {}", format_number(*factor)));
+            }
+        });
+        if let Err(err) = &factor {
+            error_label(ui, err);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+enum Economy {
+    #[default]
+    LitresPer100Km,
+    MilesPerGallon,
+}
+
+// Fuel economy conversion and trip fuel and cost.
+#[derive(Default)]
+struct TripForm {
+    economy: String,
+    unit: Economy,
+    distance: String,
+    price: String,
+}
+
+impl Form for TripForm {
+    const TITLE: &'static str = "Trip Fuel";
+
+    fn ui(&mut self, ui: &mut egui::Ui, calc: &Context) {
+        egui::Grid::new("trip").show(ui, |ui| {
+            ui.label("Fuel economy");
+            ui.horizontal(|ui| {
+                ui.add(egui::TextEdit::singleline(&mut self.economy).desired_width(60.0));
+                ui.radio_value(&mut self.unit, Economy::LitresPer100Km, "l/100 km");
+                ui.radio_value(&mut self.unit, Economy::MilesPerGallon, "mpg (US)");
+            });
+            ui.end_row();
+
+            let (distance_unit, fuel_unit) = match self.unit {
+                Economy::LitresPer100Km => ("km", "litre"),
+                Economy::MilesPerGallon => ("miles", "gallon"),
+            };
+            ui.label(format!("Distance ({})", distance_unit));
+            ui.add(egui::TextEdit::singleline(&mut self.distance).desired_width(60.0));
+            ui.end_row();
+            ui.label(format!("Price per {}", fuel_unit));
+            ui.add(egui::TextEdit::singleline(&mut self.price).desired_width(60.0));
+            ui.end_row();
+        });
+        ui.add_space(5.0);
+
+        if self.economy.trim().is_empty() {
+            return;
+        }
+        let economy = read_number(&self.economy, calc);
+        let converted = match self.unit {
+            Economy::LitresPer100Km => economy.clone().and_then(|l100km| call("l100km_to_mpg", &[l100km], calc)),
+            Economy::MilesPerGallon => economy.clone().and_then(|mpg| call("mpg_to_l100km", &[mpg], calc)),
+        };
+        // `fuel_needed` and `fuel_cost` take fuel used per 100 distance units
+        let per_100 = match self.unit {
+            Economy::LitresPer100Km => economy,
+            Economy::MilesPerGallon => economy.map(|mpg| 100.0 / mpg),
+        };
+        match (&converted, self.unit) {
+            (Ok(mpg), Economy::LitresPer100Km) => ui.label(format!("= {} mpg (US)", format_number(*mpg))),
+            (Ok(l100km), Economy::MilesPerGallon) => ui.label(format!("= {} l/100 km", format_number(*l100km))),
+            (Err(err), _) => {
+                error_label(ui, err);
+                return;
+            }
+        };
+
+        if self.distance.trim().is_empty() {
+            return;
+        }
+        let fuel_unit = match self.unit {
+            Economy::LitresPer100Km => "litres",
+            Economy::MilesPerGallon => "gallons",
+        };
+        let trip = read_number(&self.distance, calc).and_then(|distance| {
+            let per_100 = per_100.clone()?;
+            let fuel = call("fuel_needed", &[distance, per_100], calc)?;
+            let cost = match self.price.trim() {
+                "" => None,
+                price => Some(call("fuel_cost", &[distance, per_100, read_number(price, calc)?], calc)?),
+            };
+            Ok((fuel, cost))
+        });
+        match trip {
+            Ok((fuel, cost)) => {
+                ui.label(format!("Fuel needed: {} {}", format_number(fuel), fuel_unit));
+                if let Some(cost) = cost {
+                    ui.label(format!("Trip cost: {}", format_number(cost)));
+                }
+            }
+            Err(err) => error_label(ui, &err),
+        }
+    }
+}
+
+// BMI, BMR, and weight-based dosage from checked measurements.
+#[derive(Default)]
+struct HealthForm {
+    weight: String,
+    height: String,
+    age: String,
+    sex: Sex,
+    dosage: String,
+}
+
+impl Form for HealthForm {
+    const TITLE: &'static str = "Health";
+
+    fn ui(&mut self, ui: &mut egui::Ui, calc: &Context) {
+        let (mut weight, mut height, mut age, mut dosage) = (None, None, None, None);
+        egui::Grid::new("health").show(ui, |ui| {
+            weight = validated_field(ui, "Weight (kg)", &mut self.weight, 1.0..=500.0, calc);
+            height = validated_field(ui, "Height (cm)", &mut self.height, 30.0..=275.0, calc);
+            age = validated_field(ui, "Age (years)", &mut self.age, 1.0..=120.0, calc);
+            ui.label("Sex");
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut self.sex, Sex::Female, "Female");
+                ui.radio_value(&mut self.sex, Sex::Male, "Male");
+            });
+            ui.end_row();
+            dosage = validated_field(ui, "Dosage (mg/kg)", &mut self.dosage, 0.001..=1000.0, calc);
+        });
+        ui.add_space(5.0);
+
+        let mut result = |text: Result<String, String>| match text {
+            Ok(text) => {
+                ui.label(text);
+            }
+            Err(err) => error_label(ui, &err),
+        };
+        if let (Some(weight), Some(height)) = (weight, height) {
+            result(call("bmi", &[weight, height / 100.0], calc).map(|bmi| {
+                format!("BMI: {:.1} ({})", bmi, bmi_category(bmi))
+            }));
+        }
+        if let (Some(weight), Some(height), Some(age)) = (weight, height, age) {
+            let function = match self.sex {
+                Sex::Female => "bmr_female",
+                Sex::Male => "bmr_male",
+            };
+            result(call(function, &[weight, height, age], calc).map(|bmr| format!("BMR: {:.0} kcal/day", bmr)));
+        }
+        if let (Some(dosage), Some(weight)) = (dosage, weight) {
+            result(call("dose", &[dosage, weight], calc).map(|dose| format!("Dose: {} mg", format_number(dose))));
+        }
+    }
+}
+
+#[derive(Default)]
+struct Score {
+    value: String,
+    weight: String,
+}
+
+// Weighted average of marks or letter grades, e.g. a GPA from course
+// grades weighted by credits.
+struct AverageForm {
+    scores: Vec<Score>,
+}
+
+impl Default for AverageForm {
+    fn default() -> Self {
+        Self {
+            scores: vec![Score::default()],
+        }
+    }
+}
+
+impl Form for AverageForm {
+    const TITLE: &'static str = "Weighted Average";
+
+    fn ui(&mut self, ui: &mut egui::Ui, calc: &Context) {
+        let mut remove = None;
+        egui::Grid::new("scores").striped(true).show(ui, |ui| {
+            ui.label("Score or grade");
+            ui.label("Weight");
+            ui.end_row();
+
+            for (row, score) in self.scores.iter_mut().enumerate() {
+                ui.add(egui::TextEdit::singleline(&mut score.value).desired_width(60.0));
+                ui.add(egui::TextEdit::singleline(&mut score.weight).desired_width(60.0));
+                if ui.small_button("✖").clicked() {
+                    remove = Some(row);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(row) = remove {
+            self.scores.remove(row);
+        }
+        if ui.button("Add row").clicked() {
+            self.scores.push(Score::default());
+        }
+        ui.add_space(5.0);
+
+        // Blank rows are skipped; a blank weight counts once
+        let mut values = Vec::new();
+        let mut weights = Vec::new();
+        let rows = self.scores.iter().enumerate().filter(|(_, score)| !score.value.trim().is_empty());
+        for (row, score) in rows {
+            let value = grade_points(&score.value).map_or_else(|| read_number(&score.value, calc), Ok);
+            let weight = match score.weight.trim() {
+                "" => Ok(1.0),
+                weight => read_number(weight, calc),
+            };
+            match (value, weight) {
+                (Ok(value), Ok(weight)) => {
+                    values.push(value);
+                    weights.push(weight);
+                }
+                (Err(err), _) | (_, Err(err)) => {
+                    error_label(ui, &format!("Row {}: {}", row + 1, err));
+                    return;
+                }
+            }
+        }
+        if values.is_empty() {
+            return;
+        }
+        match call_with_lists("wavg", &[values, weights], calc) {
+            Ok(average) => {
+                ui.label(format!("Average: {}", format_number(average)));
+            }
+            Err(err) => error_label(ui, &err),
+        }
+    }
+}
+
+struct Package {
+    price: String,
+    quantity: String,
+    unit: &'static str,
+}
+
+impl Default for Package {
+    fn default() -> Self {
+        Self {
+            price: String::new(),
+            quantity: String::new(),
+            unit: "g",
+        }
+    }
+}
+
+// Two package sizes side by side, each priced per base unit, and which is
+// the better deal.
+#[derive(Default)]
+struct UnitPriceForm {
+    packages: [Package; 2],
+}
+
+impl Form for UnitPriceForm {
+    const TITLE: &'static str = "Unit Price";
+
+    fn ui(&mut self, ui: &mut egui::Ui, calc: &Context) {
+        let mut prices: [Option<UnitPrice>; 2] = [None, None];
+        egui::Grid::new("packages").show(ui, |ui| {
+            ui.label("");
+            ui.label("Price");
+            ui.label("Size");
+            ui.label("Unit");
+            ui.label("Per unit");
+            ui.end_row();
+
+            for (i, package) in self.packages.iter_mut().enumerate() {
+                ui.label(if i == 0 { "A" } else { "B" });
+                ui.add(egui::TextEdit::singleline(&mut package.price).desired_width(60.0));
+                ui.add(egui::TextEdit::singleline(&mut package.quantity).desired_width(60.0));
+                egui::ComboBox::from_id_source(("package unit", i))
+                    .selected_text(package.unit)
+                    .show_ui(ui, |ui| {
+                        for unit in unit_names() {
+                            ui.selectable_value(&mut package.unit, unit, unit);
+                        }
+                    });
+                if package.price.trim().is_empty() || package.quantity.trim().is_empty() {
+                    ui.label("—");
+                } else {
+                    // Priced only when `unitprice` is allowed, like the same call typed in
+                    let priced = functions::find("unitprice", calc).and_then(|_| {
+                        let price = read_number(&package.price, calc)?;
+                        unit_price(price, read_number(&package.quantity, calc)?, package.unit)
+                    });
+                    match priced {
+                        Ok(priced) => {
+                            ui.label(format!("{} per {}", format_number(priced.price), priced.measure.base()));
+                            prices[i] = Some(priced);
+                        }
+                        Err(err) => error_label(ui, &err),
+                    }
+                }
+                ui.end_row();
+            }
+        });
+        ui.add_space(5.0);
+
+        let [Some(a), Some(b)] = prices else {
+            return;
+        };
+        match savings(a, b) {
+            Ok(percent) if percent > 0.0 => {
+                ui.label(format!("A is the better deal: {:.1}% cheaper per {}", percent, a.measure.base()));
+            }
+            Ok(percent) if percent < 0.0 => {
+                let percent = savings(b, a).unwrap_or(-percent);
+                ui.label(format!("B is the better deal: {:.1}% cheaper per {}", percent, b.measure.base()));
+            }
+            Ok(_) => {
+                ui.label("Both cost the same per unit");
+            }
+            Err(err) => error_label(ui, &err),
+        }
+    }
+}
+
+#[derive(Default)]
+struct InvoiceRow {
+    description: String,
+    quantity: String,
+    unit_price: String,
+}
+
+// Invoice lines with subtotal, discount, tax and total, copied or saved
+// as CSV.
+struct InvoiceForm {
+    rows: Vec<InvoiceRow>,
+    discount: String,
+    // A saved tax rate's name or a rate in percent
+    tax: String,
+    export: CsvExport,
+}
+
+impl Default for InvoiceForm {
+    fn default() -> Self {
+        Self {
+            rows: vec![InvoiceRow::default()],
+            discount: String::new(),
+            tax: String::new(),
+            export: CsvExport::new("invoice.csv"),
+        }
+    }
+}
+
+impl Form for InvoiceForm {
+    const TITLE: &'static str = "Invoice";
+
+    fn ui(&mut self, ui: &mut egui::Ui, calc: &Context) {
+        let mut invoice = Invoice {
+            currency: calc.currency,
+            ..Invoice::default()
+        };
+        let mut remove = None;
+        egui::Grid::new("invoice lines").striped(true).show(ui, |ui| {
+            ui.label("Description");
+            ui.label("Qty");
+            ui.label("Unit price");
+            ui.label("Amount");
+            ui.end_row();
+
+            for (row, line) in self.rows.iter_mut().enumerate() {
+                ui.add(egui::TextEdit::singleline(&mut line.description).desired_width(120.0));
+                ui.add(egui::TextEdit::singleline(&mut line.quantity).desired_width(40.0));
+                ui.add(egui::TextEdit::singleline(&mut line.unit_price).desired_width(60.0));
+                // A blank quantity counts once
+                let quantity = match line.quantity.trim() {
+                    "" => Ok(1.0),
+                    quantity => read_number(quantity, calc),
+                };
+                match (quantity, read_number(&line.unit_price, calc)) {
+                    (Ok(quantity), Ok(unit_price)) => {
+                        let line = Line {
+                            description: line.description.trim().to_string(),
+                            quantity,
+                            unit_price,
+                        };
+                        ui.label(format_number(invoice.amount(&line)));
+                        invoice.lines.push(line);
+                    }
+                    _ => {
+                        ui.label("—");
+                    }
+                }
+                if ui.small_button("✖").clicked() {
+                    remove = Some(row);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(row) = remove {
+            self.rows.remove(row);
+        }
+        if ui.button("Add row").clicked() {
+            self.rows.push(InvoiceRow::default());
+        }
+        ui.add_space(5.0);
+
+        egui::Grid::new("invoice terms").show(ui, |ui| {
+            ui.label("Discount (%)");
+            ui.add(egui::TextEdit::singleline(&mut self.discount).desired_width(60.0));
+            ui.end_row();
+            ui.label("Tax");
+            ui.add(egui::TextEdit::singleline(&mut self.tax).hint_text("NY or 8.875").desired_width(60.0));
+            ui.end_row();
+        });
+        let discount = match self.discount.trim() {
+            "" => Ok(0.0),
+            discount => read_number(discount, calc),
+        };
+        let tax = match self.tax.trim() {
+            "" => Ok(TaxRate::default()),
+            name => tax::find(name, calc),
+        };
+        match (discount, tax) {
+            (Ok(discount), Ok(tax)) => {
+                invoice.discount = discount;
+                invoice.tax = tax;
+            }
+            (Err(err), _) | (_, Err(err)) => {
+                error_label(ui, &err);
+                return;
+            }
+        }
+
+        let totals = invoice.totals();
+        egui::Grid::new("invoice totals").show(ui, |ui| {
+            for (label, amount) in [
+                ("Subtotal", totals.subtotal),
+                ("Discount", -totals.discount),
+                ("Tax", totals.tax),
+                ("Total", totals.total),
+            ] {
+                ui.label(label);
+                ui.label(format_number(amount));
+                ui.end_row();
+            }
+        });
+
+        ui.add_space(5.0);
+        self.export.ui(ui, || invoice.to_csv(), calc);
+    }
+}
+
+// A depreciation schedule: each period's depreciation and the book value
+// left after it.
+struct DepreciationForm {
+    cost: String,
+    salvage: String,
+    life: String,
+    declining: bool,
+    factor: String,
+    export: CsvExport,
+}
+
+impl Default for DepreciationForm {
+    fn default() -> Self {
+        Self {
+            cost: String::new(),
+            salvage: "0".to_string(),
+            life: String::new(),
+            declining: false,
+            factor: "2".to_string(),
+            export: CsvExport::new("depreciation.csv"),
+        }
+    }
+}
+
+impl Form for DepreciationForm {
+    const TITLE: &'static str = "Depreciation";
+
+    fn ui(&mut self, ui: &mut egui::Ui, calc: &Context) {
+        egui::Grid::new("depreciation").show(ui, |ui| {
+            ui.label("Cost");
+            ui.add(egui::TextEdit::singleline(&mut self.cost).desired_width(80.0));
+            ui.end_row();
+            ui.label("Salvage value");
+            ui.add(egui::TextEdit::singleline(&mut self.salvage).desired_width(80.0));
+            ui.end_row();
+            ui.label("Life (periods)");
+            ui.add(egui::TextEdit::singleline(&mut self.life).desired_width(80.0));
+            ui.end_row();
+            ui.label("Method");
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut self.declining, false, "Straight-line");
+                ui.radio_value(&mut self.declining, true, "Declining balance ×");
+                ui.add_enabled(self.declining, egui::TextEdit::singleline(&mut self.factor).desired_width(30.0));
+            });
+            ui.end_row();
+        });
+        ui.add_space(5.0);
+
+        if self.cost.trim().is_empty() || self.life.trim().is_empty() {
+            return;
+        }
+        // Worked out only when the same functions typed in would be
+        let function = if self.declining { "ddb" } else { "sln" };
+        let schedule = functions::find(function, calc).and_then(|_| {
+            let method = if self.declining {
+                Method::DecliningBalance {
+                    factor: read_number(&self.factor, calc)?,
+                }
+            } else {
+                Method::StraightLine
+            };
+            let (cost, salvage, life) = (
+                read_number(&self.cost, calc)?,
+                read_number(&self.salvage, calc)?,
+                read_number(&self.life, calc)?,
+            );
+            depreciation::schedule(cost, salvage, life, method)
+        });
+        let schedule = match schedule {
+            Ok(schedule) => schedule,
+            Err(err) => {
+                error_label(ui, &err);
+                return;
+            }
+        };
+
+        egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+            egui::Grid::new("schedule").striped(true).show(ui, |ui| {
+                ui.label("Period");
+                ui.label("Depreciation");
+                ui.label("Book value");
+                ui.end_row();
+                for row in &schedule {
+                    ui.label(row.period.to_string());
+                    ui.label(format_number(row.depreciation));
+                    ui.label(format_number(row.book_value));
+                    ui.end_row();
+                }
+            });
+        });
+        ui.add_space(5.0);
+        self.export.ui(ui, || depreciation::to_csv(&schedule), calc);
+    }
+}
+
+// Compound interest explored with sliders: the future value and a chart of
+// the balance year by year follow every move.
+struct InterestForm {
+    savings: Savings,
+}
+
+impl Default for InterestForm {
+    fn default() -> Self {
+        Self {
+            savings: Savings {
+                principal: 10_000.0,
+                rate: 5.0,
+                years: 20.0,
+                contribution: 200.0,
+            },
+        }
+    }
+}
+
+impl Form for InterestForm {
+    const TITLE: &'static str = "Compound Interest";
+
+    fn ui(&mut self, ui: &mut egui::Ui, calc: &Context) {
+        let savings = &mut self.savings;
+        egui::Grid::new("interest").show(ui, |ui| {
+            ui.label("Principal");
+            ui.add(egui::Slider::new(&mut savings.principal, 0.0..=1_000_000.0).logarithmic(true));
+            ui.end_row();
+            ui.label("Rate");
+            ui.add(egui::Slider::new(&mut savings.rate, 0.0..=20.0).step_by(0.1).suffix("%"));
+            ui.end_row();
+            ui.label("Years");
+            ui.add(egui::Slider::new(&mut savings.years, 1.0..=50.0).step_by(1.0));
+            ui.end_row();
+            ui.label("Monthly contribution");
+            ui.add(egui::Slider::new(&mut savings.contribution, 0.0..=10_000.0).logarithmic(true));
+            ui.end_row();
+        });
+        ui.add_space(5.0);
+
+        // Worked out only when `fv` typed in would be
+        let args = [savings.principal, savings.rate, savings.years, savings.contribution];
+        let points = call("fv", &args, calc).and_then(|_| savings.growth());
+        let points = match points {
+            Ok(points) => points,
+            Err(err) => {
+                error_label(ui, &err);
+                return;
+            }
+        };
+        let last = points[points.len() - 1];
+        egui::Grid::new("future value").show(ui, |ui| {
+            ui.label("Future value");
+            ui.strong(format_number(last.balance));
+            ui.end_row();
+            ui.label("Paid in");
+            ui.label(format_number(last.contributed));
+            ui.end_row();
+            ui.label("Interest earned");
+            ui.label(format_number(last.balance - last.contributed));
+            ui.end_row();
+        });
+        ui.add_space(5.0);
+        growth_chart(ui, &points);
+    }
+}
+
+// The balance and what was paid in, year by year, as two lines
+fn growth_chart(ui: &mut egui::Ui, points: &[Point]) {
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(320.0, 160.0), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_stroke(rect, 0.0, egui::Stroke::new(1.0, egui::Color32::GRAY));
+
+    let years = points[points.len() - 1].year.max(1.0);
+    let highest = points
+        .iter()
+        .map(|point| point.balance.max(point.contributed))
+        .fold(0.0, f64::max)
+        .max(1.0);
+    let at = |year: f64, amount: f64| {
+        egui::pos2(
+            rect.left() + rect.width() * (year / years) as f32,
+            rect.bottom() - rect.height() * (amount.max(0.0) / highest) as f32,
+        )
+    };
+    let line = |amount: fn(&Point) -> f64| points.iter().map(|point| at(point.year, amount(point))).collect();
+    painter.add(egui::Shape::line(
+        line(|point| point.contributed),
+        egui::Stroke::new(1.5, egui::Color32::GRAY),
+    ));
+    painter.add(egui::Shape::line(
+        line(|point| point.balance),
+        egui::Stroke::new(2.0, ui.visuals().selection.bg_fill),
+    ));
+    response.on_hover_text(format!(
+        "Balance (colored) and paid in (gray) over {} years, up to {}",
+        format_number(years),
+        format_number(highest)
+    ));
+}
+
+// Goal seeking on a savings plan: every input but one is given along with
+// the balance to reach, and the missing one is solved for.
+struct GoalForm {
+    unknown: Unknown,
+    principal: String,
+    rate: String,
+    years: String,
+    contribution: String,
+    goal: String,
+}
+
+impl Default for GoalForm {
+    fn default() -> Self {
+        Self {
+            unknown: Unknown::Contribution,
+            principal: "0".to_string(),
+            rate: "5".to_string(),
+            years: "20".to_string(),
+            contribution: String::new(),
+            goal: "500000".to_string(),
+        }
+    }
+}
+
+impl GoalForm {
+    fn field(&mut self, unknown: Unknown) -> &mut String {
+        match unknown {
+            Unknown::Principal => &mut self.principal,
+            Unknown::Rate => &mut self.rate,
+            Unknown::Years => &mut self.years,
+            Unknown::Contribution => &mut self.contribution,
+        }
+    }
+
+    fn solve(&self, calc: &Context) -> Result<f64, String> {
+        // Only the inputs that are given are read
+        let read = |unknown: Unknown, text: &str| {
+            if unknown == self.unknown {
+                Ok(0.0)
+            } else {
+                read_number(text, calc)
+            }
+        };
+        let savings = Savings {
+            principal: read(Unknown::Principal, &self.principal)?,
+            rate: read(Unknown::Rate, &self.rate)?,
+            years: read(Unknown::Years, &self.years)?,
+            contribution: read(Unknown::Contribution, &self.contribution)?,
+        };
+        savings.solve(self.unknown, read_number(&self.goal, calc)?)
+    }
+}
+
+impl Form for GoalForm {
+    const TITLE: &'static str = "Savings Goal";
+
+    fn ui(&mut self, ui: &mut egui::Ui, calc: &Context) {
+        ui.horizontal(|ui| {
+            ui.label("Solve for");
+            for unknown in Unknown::ALL {
+                ui.radio_value(&mut self.unknown, unknown, unknown.name());
+            }
+        });
+        egui::Grid::new("goal").show(ui, |ui| {
+            for (unknown, label) in [
+                (Unknown::Principal, "Principal"),
+                (Unknown::Rate, "Yearly rate (%)"),
+                (Unknown::Years, "Years"),
+                (Unknown::Contribution, "Monthly contribution"),
+            ] {
+                ui.label(label);
+                let given = unknown != self.unknown;
+                ui.add_enabled(given, egui::TextEdit::singleline(self.field(unknown)).desired_width(80.0));
+                ui.end_row();
+            }
+            ui.label("Goal");
+            ui.add(egui::TextEdit::singleline(&mut self.goal).desired_width(80.0));
+            ui.end_row();
+        });
+        ui.add_space(5.0);
+
+        // Solved only when `fv` typed in would be worked out
+        match functions::find("fv", calc).and_then(|_| self.solve(calc)) {
+            Ok(value) => {
+                let answer = match self.unknown {
+                    Unknown::Rate => format!("{}%", format_number(value)),
+                    _ => format_number(value),
+                };
+                ui.horizontal(|ui| {
+                    ui.label(format!("The {} needed is", self.unknown.name()));
+                    ui.strong(answer);
+                });
+            }
+            Err(err) => error_label(ui, &err),
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! The tour offered on first launch: a few steps through the window, most
+//! with an input to try. Once taken or dismissed it is only shown again
+//! from the Help menu.
+
+use eframe::egui;
+
+struct Step {
+    title: &'static str,
+    text: &'static str,
+    /// An input the step offers to put in the input field.
+    example: Option<&'static str>,
+}
+
+const STEPS: [Step; 6] = [
+    Step {
+        title: "Welcome",
+        text: "Type a calculation in the input at the top and press Enter. Spaces are optional.",
+        example: Some("5 + 3"),
+    },
+    Step {
+        title: "Numbers",
+        text: "Scientific notation, hex floats such as 0x1.8p3 and CJK numerals, when turned on in Settings, are read as numbers.",
+        example: Some("1.5e3 * 2"),
+    },
+    Step {
+        title: "Units",
+        text: "Temperatures, feet and inches, and colors such as #336699 carry their unit through the calculation.",
+        example: Some("20 °C + 10 K"),
+    },
+    Step {
+        title: "Functions",
+        text: "Lists in calls are separated by ';'. Help → Function Catalog lists every function with an example.",
+        example: Some("wavg(90, 80; 1, 2)"),
+    },
+    Step {
+        title: "Variables and history",
+        text: "Set a variable with 'name = value'. History → Recalculate all updates past results when it changes.",
+        example: Some("vat = 0.20"),
+    },
+    Step {
+        title: "More",
+        text: "Right-click the result or the input for more. Paste a spreadsheet range to sum, average or stash its numbers. The Help section at the bottom of the window sums all this up.",
+        example: None,
+    },
+];
+
+/// What the user did in the tour.
+pub enum TourEvent {
+    /// Put this example in the input.
+    Try(&'static str),
+    /// The tour was finished or dismissed.
+    Finished,
+}
+
+#[derive(Default)]
+pub struct Tour {
+    pub open: bool,
+    step: usize,
+}
+
+impl Tour {
+    pub fn start(&mut self) {
+        self.open = true;
+        self.step = 0;
+    }
+
+    /// Shows the tour while it is open.
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<TourEvent> {
+        if !self.open {
+            return None;
+        }
+        let mut event = None;
+        let mut open = true;
+        let step = &STEPS[self.step];
+        egui::Window::new(format!("{} ({} of {})", step.title, self.step + 1, STEPS.len()))
+            .id(egui::Id::new("tour"))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label(step.text);
+                if let Some(example) = step.example {
+                    if ui.button(format!("Try '{}'", example)).clicked() {
+                        event = Some(TourEvent::Try(example));
+                    }
+                }
+                ui.add_space(8.0);
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(self.step > 0, egui::Button::new("Back")).clicked() {
+                        self.step -= 1;
+                    }
+                    let last = self.step + 1 == STEPS.len();
+                    if ui.button(if last { "Done" } else { "Next" }).clicked() {
+                        if last {
+                            event = Some(TourEvent::Finished);
+                        } else {
+                            self.step += 1;
+                        }
+                    }
+                    if !last && ui.button("Skip tour").clicked() {
+                        event = Some(TourEvent::Finished);
+                    }
+                });
+            });
+        if !open || matches!(event, Some(TourEvent::Finished)) {
+            self.open = false;
+            event = Some(TourEvent::Finished);
+        }
+        event
+    }
+}
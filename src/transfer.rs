@@ -0,0 +1,83 @@
+//! Export and import of the whole setup — configuration, settings and
+//! constants — as one portable JSON file, to carry it to another machine.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::session::Settings;
+
+/// Everything an exported file holds.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Setup {
+    pub config: Config,
+    pub settings: Settings,
+    /// Variables such as `vat`, set again after importing.
+    pub constants: BTreeMap<String, f64>,
+}
+
+pub fn export(path: &Path, setup: &Setup) -> Result<(), String> {
+    let text = serde_json::to_string_pretty(setup).map_err(|err| err.to_string())?;
+    fs::write(path, text).map_err(|err| err.to_string())
+}
+
+pub fn import(path: &Path) -> Result<Setup, String> {
+    let text = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    serde_json::from_str(&text).map_err(|err| format!("{} is not an exported setup: {}", path.display(), err))
+}
+
+fn default_path() -> String {
+    dirs::home_dir()
+        .map(|dir| dir.join("calculator-setup.json").display().to_string())
+        .unwrap_or_else(|| "calculator-setup.json".to_string())
+}
+
+/// What the user asked to do with the file at a path.
+pub enum TransferChoice {
+    Export(PathBuf),
+    Import(PathBuf),
+}
+
+/// The "Export/Import Settings" window.
+#[derive(Default)]
+pub struct Transfer {
+    pub open: bool,
+    path: String,
+    /// The outcome of the last export or import
+    pub message: String,
+}
+
+impl Transfer {
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<TransferChoice> {
+        if self.path.is_empty() {
+            self.path = default_path();
+        }
+        let mut choice = None;
+        let (path, message) = (&mut self.path, &self.message);
+        egui::Window::new("Export/Import Settings").open(&mut self.open).show(ctx, |ui| {
+            ui.label("The button layout, settings and variables, in one file:");
+            ui.text_edit_singleline(path);
+            ui.horizontal(|ui| {
+                if ui.button("Export").clicked() {
+                    choice = Some(TransferChoice::Export(PathBuf::from(path.trim())));
+                }
+                if ui
+                    .button("Import")
+                    .on_hover_text("Replaces the button layout and settings; variables are added")
+                    .clicked()
+                {
+                    choice = Some(TransferChoice::Import(PathBuf::from(path.trim())));
+                }
+            });
+            if !message.is_empty() {
+                ui.label(message.as_str());
+            }
+        });
+        choice
+    }
+}
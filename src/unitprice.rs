@@ -0,0 +1,120 @@
+//! Prices per unit, for telling which package size is the better deal.
+//!
+//! A package's price is spread over its contents in a base unit: the
+//! kilogram for weights, the litre for volumes, the metre for lengths, and
+//! the item for counted goods. Imperial sizes use US measures.
+
+/// What a package's contents are measured in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Measure {
+    Mass,
+    Volume,
+    Length,
+    Count,
+}
+
+impl Measure {
+    /// The unit prices are given per, e.g. `kg`.
+    pub fn base(self) -> &'static str {
+        match self {
+            Measure::Mass => "kg",
+            Measure::Volume => "l",
+            Measure::Length => "m",
+            Measure::Count => "item",
+        }
+    }
+}
+
+// Unit names with what they measure and how many base units one is
+const UNITS: &[(&[&str], Measure, f64)] = &[
+    (&["mg"], Measure::Mass, 1e-6),
+    (&["g"], Measure::Mass, 1e-3),
+    (&["kg"], Measure::Mass, 1.0),
+    (&["oz"], Measure::Mass, 0.028349523125),
+    (&["lb", "lbs"], Measure::Mass, 0.45359237),
+    (&["ml"], Measure::Volume, 1e-3),
+    (&["cl"], Measure::Volume, 1e-2),
+    (&["dl"], Measure::Volume, 0.1),
+    (&["l", "litre", "liter"], Measure::Volume, 1.0),
+    (&["floz"], Measure::Volume, 0.0295735295625),
+    (&["pt", "pint"], Measure::Volume, 0.473176473),
+    (&["qt", "quart"], Measure::Volume, 0.946352946),
+    (&["gal", "gallon"], Measure::Volume, 3.785411784),
+    (&["cm"], Measure::Length, 0.01),
+    (&["m"], Measure::Length, 1.0),
+    (&["ft"], Measure::Length, 0.3048),
+    (&["yd"], Measure::Length, 0.9144),
+    (&["each", "item", "pcs", "ct"], Measure::Count, 1.0),
+];
+
+/// The first name of every unit a package size can be given in.
+pub fn unit_names() -> impl Iterator<Item = &'static str> {
+    UNITS.iter().map(|(names, _, _)| names[0])
+}
+
+/// A price spread over a package's contents.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UnitPrice {
+    /// Price per [`Measure::base`] unit.
+    pub price: f64,
+    pub measure: Measure,
+}
+
+/// The price per base unit of a package of `quantity` `unit`s costing
+/// `price`, such as 500 `g` for 3.49.
+pub fn unit_price(price: f64, quantity: f64, unit: &str) -> Result<UnitPrice, String> {
+    let &(_, measure, size) = UNITS
+        .iter()
+        .find(|(names, _, _)| names.contains(&unit.to_lowercase().as_str()))
+        .ok_or_else(|| format!("Unknown package unit '{}'; use e.g. g, kg, oz, lb, ml, l, floz or each", unit))?;
+    if quantity <= 0.0 {
+        return Err("The package must hold more than 0".to_string());
+    }
+    if price < 0.0 {
+        return Err("The price cannot be negative".to_string());
+    }
+    Ok(UnitPrice {
+        price: price / (quantity * size),
+        measure,
+    })
+}
+
+/// How much cheaper per unit `a` is than `b`, as a percentage of `b`'s
+/// unit price; negative when `b` is the better deal.
+pub fn savings(a: UnitPrice, b: UnitPrice) -> Result<f64, String> {
+    if a.measure != b.measure {
+        return Err(format!(
+            "Cannot compare a price per {} with a price per {}",
+            a.measure.base(),
+            b.measure.base()
+        ));
+    }
+    if b.price == 0.0 {
+        return Ok(if a.price == 0.0 { 0.0 } else { -100.0 });
+    }
+    Ok((b.price - a.price) / b.price * 100.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unit_price() {
+        let small = unit_price(3.49, 500.0, "g").unwrap();
+        assert_eq!(small, UnitPrice { price: 6.98, measure: Measure::Mass });
+        let large = unit_price(5.99, 1.0, "kg").unwrap();
+        assert!((savings(large, small).unwrap() - 14.183381088825215).abs() < 1e-12);
+        assert!(savings(small, large).unwrap() < 0.0);
+
+        let milk = unit_price(4.0, 1.0, "gal").unwrap();
+        assert_eq!(milk.measure.base(), "l");
+        assert_eq!(
+            savings(milk, small),
+            Err("Cannot compare a price per l with a price per kg".to_string())
+        );
+        assert_eq!(unit_price(2.0, 4.0, "Each").map(|unit| unit.price), Ok(0.5));
+        assert!(unit_price(1.0, 0.0, "g").is_err());
+        assert!(unit_price(1.0, 2.0, "stone").is_err());
+    }
+}
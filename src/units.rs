@@ -0,0 +1,812 @@
+//! Quantities with units.
+//!
+//! Temperatures are affine: `20 °C` is a point on a scale, while the `10 K`
+//! in `20 °C + 10 K` is a difference. Points can be shifted by differences
+//! and subtracted from each other, but adding or multiplying two points is
+//! an error. Kelvins added to or subtracted from a °C or °F temperature
+//! count as a difference.
+//!
+//! Lengths are kept in inches and shown in feet and inches. They add and
+//! subtract with each other and scale by plain numbers.
+//!
+//! Colors such as `#336699` are held as the number their hex digits spell.
+//! Functions take them as that number, but arithmetic on them is an error.
+//!
+//! Other lengths, times and masses, such as `3 km` or `2 weeks`, are
+//! measures: kept in their dimension's SI base unit and shown in it. Users
+//! may define more units of measure, such as `1 furlong = 201.168 m`.
+
+use std::fmt;
+
+use crate::ast::{BinaryOp, Expr, Visitor};
+use crate::color::Rgb;
+use crate::eval::{apply, check_result, evaluate_arithmetic, evaluate_number, Context};
+use crate::format::format_number;
+use crate::functions;
+use crate::imperial::Length;
+use crate::parser::is_identifier;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemperatureScale {
+    Celsius,
+    Fahrenheit,
+    Kelvin,
+}
+
+impl TemperatureScale {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            TemperatureScale::Celsius => "°C",
+            TemperatureScale::Fahrenheit => "°F",
+            TemperatureScale::Kelvin => "K",
+        }
+    }
+
+    /// Recognizes the usual spellings: `°C`, `℃`, `degC` and plain `C`,
+    /// and likewise for Fahrenheit; `K` or `kelvin` for Kelvin.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "°C" | "℃" | "degC" | "C" | "celsius" => Some(TemperatureScale::Celsius),
+            "°F" | "℉" | "degF" | "F" | "fahrenheit" => Some(TemperatureScale::Fahrenheit),
+            "K" | "kelvin" => Some(TemperatureScale::Kelvin),
+            _ => None,
+        }
+    }
+
+    // Kelvins per degree of this scale.
+    fn degree(self) -> f64 {
+        match self {
+            TemperatureScale::Fahrenheit => 5.0 / 9.0,
+            TemperatureScale::Celsius | TemperatureScale::Kelvin => 1.0,
+        }
+    }
+
+    fn to_kelvin(self, value: f64) -> f64 {
+        match self {
+            TemperatureScale::Celsius => value + 273.15,
+            TemperatureScale::Fahrenheit => (value - 32.0) * 5.0 / 9.0 + 273.15,
+            TemperatureScale::Kelvin => value,
+        }
+    }
+
+    fn kelvin_to(self, kelvin: f64) -> f64 {
+        match self {
+            TemperatureScale::Celsius => kelvin - 273.15,
+            TemperatureScale::Fahrenheit => (kelvin - 273.15) * 9.0 / 5.0 + 32.0,
+            TemperatureScale::Kelvin => kelvin,
+        }
+    }
+
+    /// Converts a temperature on this scale to `to`.
+    pub fn convert(self, value: f64, to: TemperatureScale) -> f64 {
+        if self == to {
+            return value;
+        }
+        to.kelvin_to(self.to_kelvin(value))
+    }
+}
+
+/// What a measure measures. Each dimension is kept in its SI base unit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dimension {
+    Length,
+    Time,
+    Mass,
+}
+
+impl Dimension {
+    pub fn name(self) -> &'static str {
+        match self {
+            Dimension::Length => "length",
+            Dimension::Time => "time",
+            Dimension::Mass => "mass",
+        }
+    }
+
+    /// The SI base unit quantities of this dimension are kept in.
+    pub fn base(self) -> &'static str {
+        match self {
+            Dimension::Length => "m",
+            Dimension::Time => "s",
+            Dimension::Mass => "kg",
+        }
+    }
+}
+
+// Built-in units of measure and their size in the base unit. Inches are
+// listed for conversions, but `in` on its own is read as [`Unit::Inches`].
+const MEASURES: &[(&str, Dimension, f64)] = &[
+    ("m", Dimension::Length, 1.0),
+    ("µm", Dimension::Length, 1e-6),
+    ("mm", Dimension::Length, 0.001),
+    ("cm", Dimension::Length, 0.01),
+    ("km", Dimension::Length, 1000.0),
+    ("in", Dimension::Length, 0.0254),
+    ("ft", Dimension::Length, 0.3048),
+    ("yd", Dimension::Length, 0.9144),
+    ("mi", Dimension::Length, 1609.344),
+    ("µs", Dimension::Time, 1e-6),
+    ("ms", Dimension::Time, 0.001),
+    ("s", Dimension::Time, 1.0),
+    ("min", Dimension::Time, 60.0),
+    ("h", Dimension::Time, 3600.0),
+    ("day", Dimension::Time, 86_400.0),
+    ("days", Dimension::Time, 86_400.0),
+    ("week", Dimension::Time, 604_800.0),
+    ("weeks", Dimension::Time, 604_800.0),
+    ("kg", Dimension::Mass, 1.0),
+    ("µg", Dimension::Mass, 1e-9),
+    ("mg", Dimension::Mass, 1e-6),
+    ("g", Dimension::Mass, 0.001),
+    ("lb", Dimension::Mass, 0.453_592_37),
+    ("oz", Dimension::Mass, 0.028_349_523_125),
+];
+
+/// A unit of measure defined by the user, such as a furlong.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CustomUnit {
+    pub dimension: Dimension,
+    /// How many of the dimension's base unit it holds.
+    pub size: f64,
+}
+
+// The dimension and size of a built-in or user-defined unit of measure
+fn measure(name: &str, ctx: &Context) -> Option<(Dimension, f64)> {
+    match MEASURES.iter().find(|&&(unit, _, _)| unit == name) {
+        Some(&(_, dimension, size)) => Some((dimension, size)),
+        None => ctx.custom_units.get(name).map(|unit| (unit.dimension, unit.size)),
+    }
+}
+
+/// Reads a unit definition such as `1 furlong = 201.168 m` or
+/// `1 sprint = 2 weeks`, giving the new unit's name and size. The unit it
+/// is defined by, built in or defined before in `ctx`, must be a length,
+/// time or mass, and the name must not already be a unit.
+pub fn define_unit(text: &str, ctx: &Context) -> Result<(String, CustomUnit), String> {
+    let (name, amount) = text
+        .split_once('=')
+        .ok_or_else(|| "Write a unit as e.g. 1 furlong = 201.168 m".to_string())?;
+    // The count before the name is optional: `furlong = 201.168 m`
+    let (count, name) = match name.trim().split_once(char::is_whitespace) {
+        Some((count, name)) => (count.parse::<f64>().map_err(|_| format!("Cannot read '{}'", count))?, name.trim()),
+        None => (1.0, name.trim()),
+    };
+    if !is_identifier(name) {
+        return Err(format!("'{}' is not a valid unit name", name));
+    }
+    if Unit::from_name(name).is_some() || measure(name, ctx).is_some() {
+        return Err(format!("{} is already a unit", name));
+    }
+    let amount = amount.trim();
+    let split = amount.find(char::is_whitespace).unwrap_or(amount.len());
+    let (value, unit) = (amount[..split].parse::<f64>(), amount[split..].trim());
+    let value = value.map_err(|_| format!("Cannot read '{}'; give an amount and a unit, e.g. 201.168 m", amount))?;
+    let Some((dimension, size)) = measure(unit, ctx) else {
+        return Err(match Unit::from_name(unit) {
+            Some(other) => format!("Only lengths, times and masses can define a unit, not a {}", other.kind()),
+            None => format!("Unknown unit '{}'", unit),
+        });
+    };
+    let size = value * size / count;
+    if !(size.is_finite() && size > 0.0) {
+        return Err(format!("{} must be a positive amount", name));
+    }
+    Ok((name.to_string(), CustomUnit { dimension, size }))
+}
+
+/// The unit of a [`Quantity`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    /// A temperature reading, such as `20 °C`.
+    Temperature(TemperatureScale),
+    /// A temperature difference, such as the result of `25 °C - 20 °C`.
+    /// Written with a `Δ` prefix, as in `5 Δ°C`.
+    TemperatureDifference(TemperatureScale),
+    /// A length in inches, written `"` or `in`.
+    Inches,
+    /// A color, written `#` before its hex digits.
+    Color,
+    /// A length, time or mass, in its base unit.
+    Measure(Dimension),
+}
+
+impl Unit {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "\"" | "in" => return Some(Unit::Inches),
+            "#" => return Some(Unit::Color),
+            _ => {}
+        }
+        if let Some(&(_, dimension, _)) = MEASURES.iter().find(|&&(unit, _, _)| unit == name) {
+            return Some(Unit::Measure(dimension));
+        }
+        match name.strip_prefix('Δ') {
+            Some(scale) => TemperatureScale::from_name(scale.trim_start()).map(Unit::TemperatureDifference),
+            None => TemperatureScale::from_name(name).map(Unit::Temperature),
+        }
+    }
+
+    /// The other units a quantity in this one converts to: the other
+    /// temperature scales, for readings and differences alike. Lengths and
+    /// colors have no other units, only other renderings.
+    pub fn compatible(self) -> Vec<Unit> {
+        let scales = |this: TemperatureScale| {
+            [TemperatureScale::Celsius, TemperatureScale::Fahrenheit, TemperatureScale::Kelvin]
+                .into_iter()
+                .filter(move |&scale| scale != this)
+        };
+        match self {
+            Unit::Temperature(this) => scales(this).map(Unit::Temperature).collect(),
+            Unit::TemperatureDifference(this) => scales(this).map(Unit::TemperatureDifference).collect(),
+            Unit::Inches | Unit::Color | Unit::Measure(_) => Vec::new(),
+        }
+    }
+
+    // What the unit measures, for error messages
+    fn kind(self) -> &'static str {
+        match self {
+            Unit::Temperature(_) | Unit::TemperatureDifference(_) => "temperature",
+            Unit::Inches => "length",
+            Unit::Color => "color",
+            Unit::Measure(dimension) => dimension.name(),
+        }
+    }
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Unit::Temperature(scale) => write!(f, "{}", scale.symbol()),
+            Unit::TemperatureDifference(scale) => write!(f, "Δ{}", scale.symbol()),
+            Unit::Inches => write!(f, "in"),
+            Unit::Color => write!(f, "#"),
+            Unit::Measure(dimension) => write!(f, "{}", dimension.base()),
+        }
+    }
+}
+
+/// A number with an optional unit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quantity {
+    pub value: f64,
+    pub unit: Option<Unit>,
+}
+
+impl Quantity {
+    pub fn number(value: f64) -> Self {
+        Self { value, unit: None }
+    }
+
+    fn temperature(value: f64, scale: TemperatureScale) -> Result<Self, String> {
+        if scale.to_kelvin(value) < 0.0 {
+            return Err(format!("{} {} is below absolute zero", format_number(value), scale.symbol()));
+        }
+        Ok(Self {
+            value,
+            unit: Some(Unit::Temperature(scale)),
+        })
+    }
+
+    fn difference(value: f64, scale: TemperatureScale) -> Self {
+        Self {
+            value,
+            unit: Some(Unit::TemperatureDifference(scale)),
+        }
+    }
+
+    fn inches(value: f64) -> Self {
+        Self {
+            value,
+            unit: Some(Unit::Inches),
+        }
+    }
+
+    fn color(color: Rgb) -> Self {
+        Self {
+            value: color.value(),
+            unit: Some(Unit::Color),
+        }
+    }
+
+    fn measure(value: f64, dimension: Dimension) -> Self {
+        Self {
+            value,
+            unit: Some(Unit::Measure(dimension)),
+        }
+    }
+
+    /// This quantity in `unit`, or `None` when the two are not compatible.
+    pub fn convert_to(self, unit: Unit) -> Option<Quantity> {
+        let value = match (self.unit?, unit) {
+            (from, to) if from == to => self.value,
+            (Unit::Temperature(from), Unit::Temperature(to)) => from.convert(self.value, to),
+            (Unit::TemperatureDifference(from), Unit::TemperatureDifference(to)) => {
+                self.value * from.degree() / to.degree()
+            }
+            _ => return None,
+        };
+        Some(Self { value, unit: Some(unit) })
+    }
+
+    /// The quantity written other ways than its own display, for picking
+    /// from next to a result: in each compatible unit, a length in
+    /// inches, feet, centimeters and meters, a measure in every other unit
+    /// of its dimension, including those defined in `ctx`, or a color as
+    /// its channels.
+    pub fn alternatives(&self, ctx: &Context) -> Vec<String> {
+        match self.unit {
+            Some(Unit::Inches) => vec![
+                format!("{} in", format_number(self.value)),
+                format!("{} ft", format_number(self.value / 12.0)),
+                format!("{} cm", format_number(self.value * 2.54)),
+                format!("{} m", format_number(self.value * 2.54 / 100.0)),
+            ],
+            Some(Unit::Color) => match Rgb::from_value(self.value) {
+                Ok(color) => vec![format!("rgb({}, {}, {})", color.r, color.g, color.b)],
+                Err(_) => Vec::new(),
+            },
+            Some(Unit::Measure(dimension)) => {
+                let custom = ctx.custom_units.iter().map(|(name, unit)| (name.as_str(), unit.dimension, unit.size));
+                MEASURES
+                    .iter()
+                    .copied()
+                    .chain(custom)
+                    .filter(|&(name, of, _)| of == dimension && name != dimension.base())
+                    .map(|(name, _, size)| format!("{} {}", format_number(self.value / size), name))
+                    .collect()
+            }
+            Some(unit) => unit
+                .compatible()
+                .into_iter()
+                .filter_map(|other| {
+                    let quantity = self.convert_to(other)?;
+                    Some(format!("{} {}", format_number(quantity.value), other))
+                })
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// The quantity split into whole larger units and a rest, such as
+    /// `2 d 3 h 15 min` for a time or `1 lb 4 oz` for a mass: one line per
+    /// system of units that splits it into more than one part.
+    pub fn breakdown(&self) -> Vec<String> {
+        let systems: &[&[(&str, f64)]] = match self.unit {
+            Some(Unit::Measure(Dimension::Time)) => &[TIME_PARTS],
+            Some(Unit::Measure(Dimension::Length)) => &[METRIC_LENGTH_PARTS, IMPERIAL_LENGTH_PARTS],
+            Some(Unit::Measure(Dimension::Mass)) => &[METRIC_MASS_PARTS, IMPERIAL_MASS_PARTS],
+            Some(Unit::Inches) => &[METRIC_LENGTH_PARTS, IMPERIAL_LENGTH_PARTS],
+            _ => &[],
+        };
+        // Inches are the only length not kept in meters
+        let value = match self.unit {
+            Some(Unit::Inches) => self.value * 0.0254,
+            _ => self.value,
+        };
+        systems.iter().filter_map(|parts| split_into(value, parts)).collect()
+    }
+}
+
+// The units a breakdown splits a measure into, largest first, with their
+// sizes in the base unit
+const TIME_PARTS: &[(&str, f64)] = &[("d", 86_400.0), ("h", 3600.0), ("min", 60.0), ("s", 1.0)];
+const METRIC_LENGTH_PARTS: &[(&str, f64)] = &[("km", 1000.0), ("m", 1.0), ("cm", 0.01), ("mm", 0.001)];
+const IMPERIAL_LENGTH_PARTS: &[(&str, f64)] = &[("mi", 1609.344), ("yd", 0.9144), ("ft", 0.3048), ("in", 0.0254)];
+const METRIC_MASS_PARTS: &[(&str, f64)] = &[("kg", 1.0), ("g", 0.001), ("mg", 1e-6)];
+const IMPERIAL_MASS_PARTS: &[(&str, f64)] = &[("lb", 0.453_592_37), ("oz", 0.028_349_523_125)];
+
+// `value` as whole numbers of each part but the last, which takes the rest
+// to three decimals, leaving out parts that are zero; `None` when that
+// leaves fewer than two
+fn split_into(value: f64, parts: &[(&str, f64)]) -> Option<String> {
+    if !value.is_finite() {
+        return None;
+    }
+    let (&(last, size), larger) = parts.split_last()?;
+    // Counted in thousandths of the last part, so the rest cannot come
+    // out as 59.99999 of it
+    let mut rest = (value.abs() / size * 1000.0).round();
+    let mut written = Vec::new();
+    for &(name, part) in larger {
+        let thousandths = (part / size * 1000.0).round();
+        let count = (rest / thousandths).floor();
+        rest -= count * thousandths;
+        if count > 0.0 {
+            written.push(format!("{} {}", count, name));
+        }
+    }
+    if rest > 0.0 {
+        written.push(format!("{} {}", format_number(rest / 1000.0), last));
+    }
+    if written.len() < 2 {
+        return None;
+    }
+    let sign = if value < 0.0 { "-" } else { "" };
+    Some(format!("{}{}", sign, written.join(" ")))
+}
+
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.unit {
+            Some(Unit::Inches) => write!(f, "{}", Length::from_inches(self.value)),
+            Some(Unit::Color) => match Rgb::from_value(self.value) {
+                Ok(color) => write!(f, "{}", color),
+                Err(_) => write!(f, "{}", format_number(self.value)),
+            },
+            Some(unit) => write!(f, "{} {}", format_number(self.value), unit),
+            None => write!(f, "{}", format_number(self.value)),
+        }
+    }
+}
+
+/// Whether `expr` has anything that needs [`evaluate_quantity`]: a
+/// quantity literal, or a `convert` or `rgb2hex` call.
+pub fn has_units(expr: &Expr) -> bool {
+    struct Finder(bool);
+
+    impl Visitor for Finder {
+        fn visit_quantity(&mut self, _value: &Expr, _unit: &str) {
+            self.0 = true;
+        }
+
+        fn visit_call(&mut self, name: &str, args: &[Expr]) {
+            if name == "convert" || name == "rgb2hex" {
+                self.0 = true;
+            }
+            for arg in args {
+                self.visit_expr(arg);
+            }
+        }
+    }
+
+    let mut finder = Finder(false);
+    finder.visit_expr(expr);
+    finder.0
+}
+
+/// Evaluates `expr`, keeping track of units.
+pub fn evaluate_quantity(expr: &Expr, ctx: &Context) -> Result<Quantity, String> {
+    if let Some(value) = evaluate_arithmetic(expr, ctx) {
+        return Ok(Quantity::number(value));
+    }
+    if !has_units(expr) {
+        return evaluate_number(expr, ctx).map(Quantity::number);
+    }
+
+    match expr {
+        Expr::Quantity { value, unit: name } => {
+            let value = evaluate_number(value, ctx)?;
+            let unit = Unit::from_name(name)
+                .or_else(|| ctx.custom_units.get(name).map(|custom| Unit::Measure(custom.dimension)))
+                .ok_or_else(|| format!("Unknown unit '{}'", name))?;
+            match unit {
+                Unit::Temperature(scale) => Quantity::temperature(value, scale),
+                Unit::TemperatureDifference(scale) => Ok(Quantity::difference(value, scale)),
+                Unit::Inches => Ok(Quantity::inches(value)),
+                Unit::Color => Rgb::from_value(value).map(Quantity::color),
+                Unit::Measure(dimension) => {
+                    let (_, size) = measure(name, ctx).ok_or_else(|| format!("Unknown unit '{}'", name))?;
+                    Ok(Quantity::measure(value * size, dimension))
+                }
+            }
+        }
+        Expr::Call { name, args } if name == "convert" => convert(args, ctx),
+        Expr::Call { name, args } => call(name, args, ctx),
+        Expr::Binary { op, lhs, rhs } => combine(*op, evaluate_quantity(lhs, ctx)?, evaluate_quantity(rhs, ctx)?, ctx),
+        Expr::Negate(operand) => negate(evaluate_quantity(operand, ctx)?),
+        Expr::Number(_) | Expr::Variable(_) | Expr::List(_) => evaluate_number(expr, ctx).map(Quantity::number),
+    }
+}
+
+// `convert(value, from, to)`, with the scales given by name. Units of
+// measure, such as `convert(2, mi, km)`, give the plain number of `to`.
+fn convert(args: &[Expr], ctx: &Context) -> Result<Quantity, String> {
+    if !ctx.profile().allows_calls() {
+        return Err(ctx.profile().forbids("Calling functions"));
+    }
+    let [value, from, to] = args else {
+        return Err(format!("convert(value, from, to) takes 3 argument(s) but got {}", args.len()));
+    };
+    if let (Expr::Variable(from), Expr::Variable(to)) = (from, to) {
+        if let (Some((a, from_size)), Some((b, to_size))) = (measure(from, ctx), measure(to, ctx)) {
+            if a != b {
+                return Err(format!("Cannot convert a {} to a {}", a.name(), b.name()));
+            }
+            let value = evaluate_quantity(value, ctx)?;
+            if value.unit.is_some() {
+                return Err("convert() takes a plain number; the units are given by its other arguments".to_string());
+            }
+            return Ok(Quantity::number(value.value * from_size / to_size));
+        }
+    }
+    let scale = |expr: &Expr| match expr {
+        Expr::Variable(name) => {
+            TemperatureScale::from_name(name).ok_or_else(|| format!("Unknown unit '{}'", name))
+        }
+        other => Err(format!("Expected a unit such as C, F or K, found '{}'", other)),
+    };
+    let (from, to) = (scale(from)?, scale(to)?);
+
+    let value = evaluate_quantity(value, ctx)?;
+    if value.unit.is_some() {
+        return Err("convert() takes a plain number; the units are given by its other arguments".to_string());
+    }
+    Quantity::temperature(value.value, from)?;
+    Quantity::temperature(from.convert(value.value, to), to)
+}
+
+// Colors are passed to functions as their number; other units are not
+fn call(name: &str, args: &[Expr], ctx: &Context) -> Result<Quantity, String> {
+    let function = functions::find(name, ctx)?;
+    let mut values = Vec::with_capacity(args.len());
+    for arg in args {
+        match evaluate_quantity(arg, ctx)? {
+            Quantity {
+                value,
+                unit: None | Some(Unit::Color),
+            } => values.push(value),
+            _ => return Err(format!("{}() does not accept temperatures or lengths", name)),
+        }
+    }
+    let value = check_result(function.call(&values, ctx)?, ctx)?;
+    if name == "rgb2hex" {
+        return Rgb::from_value(value).map(Quantity::color);
+    }
+    Ok(Quantity::number(value))
+}
+
+// `-quantity`; a temperature reading stays one, so it must still be above
+// absolute zero
+fn negate(quantity: Quantity) -> Result<Quantity, String> {
+    match quantity.unit {
+        Some(Unit::Color) => Err("Cannot do arithmetic on colors".to_string()),
+        Some(Unit::Temperature(scale)) => Quantity::temperature(-quantity.value, scale),
+        _ => Ok(Quantity {
+            value: -quantity.value,
+            ..quantity
+        }),
+    }
+}
+
+fn combine(op: BinaryOp, lhs: Quantity, rhs: Quantity, ctx: &Context) -> Result<Quantity, String> {
+    use Unit::{Color, Inches, Measure, Temperature, TemperatureDifference as Difference};
+
+    // Inches meeting another length become a measure in meters
+    let as_meters = |quantity: Quantity| Quantity::measure(quantity.value * 0.0254, Dimension::Length);
+    let (lhs, rhs) = match (lhs.unit, rhs.unit) {
+        (Some(Inches), Some(Measure(Dimension::Length))) => (as_meters(lhs), rhs),
+        (Some(Measure(Dimension::Length)), Some(Inches)) => (lhs, as_meters(rhs)),
+        _ => (lhs, rhs),
+    };
+    let value = |a: f64, b: f64| apply(op, a, b, ctx);
+    // A difference on `from` measured in degrees of `to`
+    let rescale = |value: f64, from: TemperatureScale, to: TemperatureScale| value * from.degree() / to.degree();
+
+    match (op, lhs.unit, rhs.unit) {
+        (_, None, None) => value(lhs.value, rhs.value).map(Quantity::number),
+        (_, Some(Color), _) | (_, _, Some(Color)) => Err("Cannot do arithmetic on colors".to_string()),
+        (BinaryOp::Pow, _, Some(_)) => Err("A power must be a plain number".to_string()),
+        (BinaryOp::Pow, Some(_), None) => Err("Cannot raise a unit to a power; units such as m² are not supported".to_string()),
+        (BinaryOp::Mod, _, _) => Err("Cannot take the remainder of a quantity with a unit".to_string()),
+
+        // Measures
+        (BinaryOp::Add | BinaryOp::Sub, Some(Measure(a)), Some(Measure(b))) if a == b => {
+            value(lhs.value, rhs.value).map(|sum| Quantity::measure(sum, a))
+        }
+        (BinaryOp::Div, Some(Measure(a)), Some(Measure(b))) if a == b => value(lhs.value, rhs.value).map(Quantity::number),
+        (BinaryOp::Mul, Some(Measure(a)), Some(Measure(b))) if a == b => {
+            Err(format!("Cannot multiply two {}s; units such as m² are not supported", a.name()))
+        }
+        (_, Some(a @ Measure(_)), Some(b)) | (_, Some(a), Some(b @ Measure(_))) => {
+            Err(format!("Cannot combine a {} and a {}", a.kind(), b.kind()))
+        }
+        (BinaryOp::Mul | BinaryOp::Div, Some(Measure(a)), None) | (BinaryOp::Mul, None, Some(Measure(a))) => {
+            value(lhs.value, rhs.value).map(|product| Quantity::measure(product, a))
+        }
+        (BinaryOp::Div, None, Some(Measure(a))) => Err(format!("Cannot divide by a {}", a.name())),
+        (BinaryOp::Add | BinaryOp::Sub, Some(Measure(a)), None) | (BinaryOp::Add | BinaryOp::Sub, None, Some(Measure(a))) => {
+            Err(format!(
+                "Cannot add or subtract a plain number and a {}; give its unit, e.g. 3 {}",
+                a.name(),
+                a.base()
+            ))
+        }
+
+        // Lengths
+        (_, Some(Inches), Some(Temperature(_) | Difference(_)))
+        | (_, Some(Temperature(_) | Difference(_)), Some(Inches)) => {
+            Err("Cannot combine a length and a temperature".to_string())
+        }
+        (BinaryOp::Add | BinaryOp::Sub, Some(Inches), Some(Inches)) => value(lhs.value, rhs.value).map(Quantity::inches),
+        (BinaryOp::Add | BinaryOp::Sub, Some(Inches), None) | (BinaryOp::Add | BinaryOp::Sub, None, Some(Inches)) => {
+            Err("Cannot add or subtract a plain number and a length; give it in inches, e.g. 3\"".to_string())
+        }
+        (BinaryOp::Mul, Some(Inches), Some(Inches)) => Err("Cannot multiply two lengths".to_string()),
+        (BinaryOp::Mul | BinaryOp::Div, Some(Inches), None) | (BinaryOp::Mul, None, Some(Inches)) => {
+            value(lhs.value, rhs.value).map(Quantity::inches)
+        }
+        (BinaryOp::Div, Some(Inches), Some(Inches)) => value(lhs.value, rhs.value).map(Quantity::number),
+        (BinaryOp::Div, None, Some(Inches)) => Err("Cannot divide by a length".to_string()),
+
+        // Shifting a reading by a difference
+        (BinaryOp::Add | BinaryOp::Sub, Some(Temperature(a)), Some(Difference(b)))
+        | (BinaryOp::Add | BinaryOp::Sub, Some(Temperature(a)), Some(Temperature(b @ TemperatureScale::Kelvin))) => {
+            Quantity::temperature(value(lhs.value, rescale(rhs.value, b, a))?, a)
+        }
+        (BinaryOp::Add, Some(Difference(a)), Some(Temperature(b))) => {
+            Quantity::temperature(value(rescale(lhs.value, a, b), rhs.value)?, b)
+        }
+        (BinaryOp::Add | BinaryOp::Sub, Some(Difference(a)), Some(Difference(b))) => {
+            Ok(Quantity::difference(value(lhs.value, rescale(rhs.value, b, a))?, a))
+        }
+        // The gap between two readings
+        (BinaryOp::Sub, Some(Temperature(a)), Some(Temperature(b))) => {
+            Ok(Quantity::difference(value(lhs.value, b.convert(rhs.value, a))?, a))
+        }
+        (BinaryOp::Add, Some(Temperature(_)), Some(Temperature(_))) => {
+            Err("Cannot add two temperatures; add a difference such as 10 K instead".to_string())
+        }
+        (BinaryOp::Sub, Some(Difference(_)), Some(Temperature(_))) => {
+            Err("Cannot subtract a temperature from a temperature difference".to_string())
+        }
+        (BinaryOp::Add | BinaryOp::Sub, _, _) => {
+            Err("Cannot add or subtract a plain number and a temperature".to_string())
+        }
+
+        // Scaling
+        (BinaryOp::Mul, Some(_), Some(_)) => Err("Cannot multiply two temperatures".to_string()),
+        (BinaryOp::Mul | BinaryOp::Div, Some(Difference(a)), None) => {
+            Ok(Quantity::difference(value(lhs.value, rhs.value)?, a))
+        }
+        (BinaryOp::Mul, None, Some(Difference(b))) => Ok(Quantity::difference(value(lhs.value, rhs.value)?, b)),
+        (BinaryOp::Mul | BinaryOp::Div, Some(Temperature(TemperatureScale::Kelvin)), None) => {
+            Quantity::temperature(value(lhs.value, rhs.value)?, TemperatureScale::Kelvin)
+        }
+        (BinaryOp::Mul, None, Some(Temperature(TemperatureScale::Kelvin))) => {
+            Quantity::temperature(value(lhs.value, rhs.value)?, TemperatureScale::Kelvin)
+        }
+        (BinaryOp::Mul | BinaryOp::Div, Some(Temperature(scale)), None)
+        | (BinaryOp::Mul, None, Some(Temperature(scale))) => Err(format!(
+            "Cannot scale a temperature in {}; convert it to K first",
+            scale.symbol()
+        )),
+
+        // Ratios
+        (BinaryOp::Div, Some(Difference(a)), Some(Difference(b))) => {
+            value(lhs.value, rescale(rhs.value, b, a)).map(Quantity::number)
+        }
+        (BinaryOp::Div, Some(Temperature(TemperatureScale::Kelvin)), Some(Temperature(TemperatureScale::Kelvin))) => {
+            value(lhs.value, rhs.value).map(Quantity::number)
+        }
+        (BinaryOp::Div, Some(_), Some(_)) => Err("Cannot divide two temperatures".to_string()),
+        (BinaryOp::Div, None, Some(_)) => Err("Cannot divide by a temperature".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalize::normalize;
+    use crate::parser::parse;
+
+    fn quantity(input: &str) -> Result<String, String> {
+        let ctx = Context::new();
+        evaluate_quantity(&parse(&normalize(input, &ctx))?, &ctx).map(|q| q.to_string())
+    }
+
+    #[test]
+    fn test_temperature_arithmetic() {
+        assert_eq!(quantity("20 °C + 10 K"), Ok("30 °C".to_string()));
+        assert_eq!(quantity("68 °F + 10 K"), Ok("86 °F".to_string()));
+        assert_eq!(quantity("25 °C - 20 °C"), Ok("5 Δ°C".to_string()));
+        assert_eq!(quantity("300 K + 10 K"), Ok("310 K".to_string()));
+        assert_eq!(quantity("50 °F - 10 °C"), Ok("0 Δ°F".to_string()));
+        assert_eq!(quantity("2 * 5 Δ°C"), Ok("10 Δ°C".to_string()));
+        assert_eq!(quantity("300 K / 2"), Ok("150 K".to_string()));
+        assert_eq!(quantity("-(5 Δ°C) + 20 °C"), Ok("15 °C".to_string()));
+        assert!(quantity("-(300 K) + 1 K").is_err());
+    }
+
+    #[test]
+    fn test_convert() {
+        let converted = quantity("convert(98.6, F, C)").unwrap();
+        assert!(converted.starts_with("37") && converted.ends_with(" °C"), "{}", converted);
+        assert_eq!(quantity("convert(0, C, K)"), Ok("273.15 K".to_string()));
+        assert_eq!(quantity("convert(-40, degC, degF)"), Ok("-40 °F".to_string()));
+        assert_eq!(quantity("convert(1, C, m)"), Err("Unknown unit 'm'".to_string()));
+    }
+
+    #[test]
+    fn test_nonsensical_temperature_operations() {
+        assert_eq!(quantity("20 °C * 2 °C"), Err("Cannot multiply two temperatures".to_string()));
+        assert_eq!(
+            quantity("20 °C + 30 °C"),
+            Err("Cannot add two temperatures; add a difference such as 10 K instead".to_string())
+        );
+        assert_eq!(
+            quantity("20 °C * 2"),
+            Err("Cannot scale a temperature in °C; convert it to K first".to_string())
+        );
+        assert_eq!(
+            quantity("20 °C + 5"),
+            Err("Cannot add or subtract a plain number and a temperature".to_string())
+        );
+        assert_eq!(quantity("-300 °C + 1 K"), Err("-300 °C is below absolute zero".to_string()));
+    }
+
+    #[test]
+    fn test_feet_and_inches() {
+        assert_eq!(quantity("5' 3 1/2\" + 2' 7/8\""), Ok("7' 4 3/8\"".to_string()));
+        assert_eq!(quantity("8' - 5'-3\""), Ok("2' 9\"".to_string()));
+        assert_eq!(quantity("10' / 3"), Ok("3' 4\"".to_string()));
+        assert_eq!(quantity("5' + -(2')"), Ok("3'".to_string()));
+        assert_eq!(quantity("3 * 2 5/16\""), Ok("6 15/16\"".to_string()));
+        assert_eq!(quantity("10' / 2 1/2\""), Ok("48".to_string()));
+        assert_eq!(quantity("1\" * 2\""), Err("Cannot multiply two lengths".to_string()));
+        assert_eq!(
+            quantity("5' + 3"),
+            Err("Cannot add or subtract a plain number and a length; give it in inches, e.g. 3\"".to_string())
+        );
+        assert_eq!(quantity("5' + 3 K"), Err("Cannot combine a length and a temperature".to_string()));
+    }
+
+    #[test]
+    fn test_alternatives() {
+        let ctx = Context::new();
+        let quantity = |input: &str| evaluate_quantity(&parse(&normalize(input, &ctx)).unwrap(), &ctx);
+        let reading = quantity("10 °C + 10 K").unwrap();
+        assert_eq!(reading.alternatives(&ctx), vec!["68 °F", "293.15 K"]);
+        let gap = quantity("25 °C - 20 °C").unwrap();
+        assert_eq!(gap.convert_to(Unit::TemperatureDifference(TemperatureScale::Fahrenheit)).unwrap().value, 9.0);
+        assert_eq!(gap.convert_to(Unit::Inches), None);
+        assert_eq!(quantity("5' + 3\"").unwrap().alternatives(&ctx), vec!["63 in", "5.25 ft", "160.02 cm", "1.6002 m"]);
+        assert!(Quantity::number(3.0).alternatives(&ctx).is_empty());
+    }
+
+    #[test]
+    fn test_breakdown() {
+        let ctx = Context::new();
+        let quantity = |input: &str| evaluate_quantity(&parse(&normalize(input, &ctx)).unwrap(), &ctx);
+        assert_eq!(quantity("2 days + 11700 s").unwrap().breakdown(), vec!["2 d 3 h 15 min"]);
+        assert_eq!(quantity("1 h - 0.5 s").unwrap().breakdown(), vec!["59 min 59.5 s"]);
+        assert_eq!(quantity("1 km + 609.344 m").unwrap().breakdown(), vec!["1 km 609 m 34 cm 4 mm"]);
+        assert_eq!(quantity("1 lb + 4 oz").unwrap().breakdown(), vec!["566 g 990.463 mg", "1 lb 4 oz"]);
+        assert_eq!(quantity("5' + 3\"").unwrap().breakdown(), vec!["1 m 60 cm 0.2 mm", "1 yd 2 ft 3 in"]);
+        assert!(quantity("1 h + 0 s").unwrap().breakdown().is_empty());
+        assert!(quantity("10 °C + 10 K").unwrap().breakdown().is_empty());
+    }
+
+    #[test]
+    fn test_custom_units() {
+        let mut ctx = Context::new();
+        for definition in ["1 furlong = 201.168 m", "1 sprint = 2 weeks", "fortnight = 14 days"] {
+            let (name, unit) = define_unit(definition, &ctx).unwrap();
+            ctx.custom_units.insert(name, unit);
+        }
+        let quantity = |input: &str| evaluate_quantity(&parse(&normalize(input, &ctx)).unwrap(), &ctx);
+        assert_eq!(quantity("2 furlong + 100 m").map(|q| q.to_string()), Ok("502.336 m".to_string()));
+        assert_eq!(quantity("1 sprint / 1 fortnight").map(|q| q.to_string()), Ok("1".to_string()));
+        assert_eq!(quantity("1 km - 10\"").map(|q| q.to_string()), Ok("999.746 m".to_string()));
+        assert_eq!(quantity("convert(1, furlong, ft)"), Ok(Quantity::number(660.0)));
+        let half = quantity("1 sprint / 2").unwrap();
+        assert!(half.alternatives(&ctx).contains(&"7 days".to_string()));
+        assert!(half.alternatives(&ctx).contains(&"0.5 sprint".to_string()));
+        assert_eq!(
+            quantity("1 furlong + 1 sprint"),
+            Err("Cannot combine a length and a time".to_string())
+        );
+        assert_eq!(quantity("1 furlong + 1 K"), Err("Cannot combine a length and a temperature".to_string()));
+        assert_eq!(quantity("1 parsec + 1 m"), Err("Unknown unit 'parsec'".to_string()));
+        assert_eq!(quantity("convert(1, furlong, h)"), Err("Cannot convert a length to a time".to_string()));
+
+        assert_eq!(define_unit("1 m = 3 ft", &ctx), Err("m is already a unit".to_string()));
+        assert_eq!(define_unit("1 furlong = 3 ft", &ctx), Err("furlong is already a unit".to_string()));
+        assert_eq!(
+            define_unit("1 warm = 20 °C", &ctx),
+            Err("Only lengths, times and masses can define a unit, not a temperature".to_string())
+        );
+        assert_eq!(define_unit("1 league = 3 leagues", &ctx), Err("Unknown unit 'leagues'".to_string()));
+        assert!(define_unit("1 nothing = 0 m", &ctx).is_err());
+        assert!(define_unit("furlong 201.168 m", &ctx).is_err());
+    }
+}